@@ -0,0 +1,9 @@
+// This shader fills a node with a flat color
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    return vec4(color.rgb, color.a * in.opacity);
+}
@@ -0,0 +1,10 @@
+// This shader draws a soft round glow, fading from the center of the node to its edges
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let falloff = saturate(1.0 - 2.0 * length(in.uv - 0.5));
+    return vec4(color.rgb, color.a * falloff * falloff * in.opacity);
+}
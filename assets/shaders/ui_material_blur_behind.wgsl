@@ -0,0 +1,40 @@
+// This shader blurs the scene below the node, and draws the world grid of the scene on top of it
+#import bevy_render::view::View
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(0) @binding(2) var<uniform> scene_view: View;
+@group(0) @binding(3) var scene_color: texture_2d<f32>;
+@group(0) @binding(4) var scene_sampler: sampler;
+
+@group(1) @binding(0) var<uniform> tint: vec4<f32>;
+@group(1) @binding(1) var<uniform> blur_radius: f32;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    // The scene color covers the whole render target
+    let texel_size = 1.0 / vec2<f32>(textureDimensions(scene_color));
+    let uv = in.position.xy * texel_size;
+
+    var blurred = vec3(0.0);
+    var total = 0.0;
+    for (var x = -3; x <= 3; x += 1) {
+        for (var y = -3; y <= 3; y += 1) {
+            let offset = vec2(f32(x), f32(y)) / 3.0;
+            let weight = exp(-2.0 * dot(offset, offset));
+            let sample_uv = uv + offset * blur_radius * texel_size;
+            blurred += textureSample(scene_color, scene_sampler, sample_uv).rgb * weight;
+            total += weight;
+        }
+    }
+    blurred /= total;
+
+    // Project the fragment back to the world with the camera's matrices, so the grid stays in
+    // place as the camera moves
+    let ndc = vec2(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let world = scene_view.world_from_clip * vec4(ndc, 0.0, 1.0);
+    let cell = fract(world.xy / world.w / 100.0);
+    let line = step(0.98, max(cell.x, cell.y));
+
+    let color = mix(blurred, tint.rgb, tint.a) + vec3(line * 0.15);
+    return vec4(color, in.opacity);
+}
@@ -0,0 +1,21 @@
+// This shader fills a button with a color, brightened by its hover intensity
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+struct Hover {
+    intensity: f32,
+}
+
+// Push constants aren't supported on every backend, they fall back to a uniform on the others
+#ifdef UI_PUSH_CONSTANTS
+var<push_constant> hover: Hover;
+#else ifdef UI_PUSH_CONSTANTS_UNIFORM
+@group(3) @binding(0) var<uniform> hover: Hover;
+#endif
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let rgb = mix(color.rgb, vec3(1.0), 0.5 * hover.intensity);
+    return vec4(rgb, color.a * in.opacity);
+}
@@ -0,0 +1,17 @@
+// This shader fills a node with a color, rounding its corners if the `ROUNDED` shader def is set
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<uniform> color: vec4<f32>;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+#ifdef ROUNDED
+    let radius = 0.25 * min(in.size.x, in.size.y);
+    let q = abs((in.uv - 0.5) * in.size) - 0.5 * in.size + radius;
+    let distance = length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    let coverage = saturate(0.5 - distance);
+#else
+    let coverage = 1.0;
+#endif
+    return vec4(color.rgb, color.a * coverage * in.opacity);
+}
@@ -0,0 +1,12 @@
+// This shader colors a node from a palette too large for a uniform buffer
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var<storage, read> palette: array<vec4<f32>>;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let len = arrayLength(&palette);
+    let index = min(u32(in.uv.x * f32(len)), len - 1u);
+    let color = palette[index];
+    return vec4(color.rgb, color.a * in.opacity);
+}
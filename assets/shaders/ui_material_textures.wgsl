@@ -0,0 +1,17 @@
+// This shader multiplies a base texture by a detail texture and crops it to the alpha of a mask
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+@group(1) @binding(0) var base_texture: texture_2d<f32>;
+@group(1) @binding(1) var base_sampler: sampler;
+@group(1) @binding(2) var mask_texture: texture_2d<f32>;
+@group(1) @binding(3) var mask_sampler: sampler;
+@group(1) @binding(4) var detail_texture: texture_2d<f32>;
+@group(1) @binding(5) var detail_sampler: sampler;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let base = textureSample(base_texture, base_sampler, in.uv);
+    let mask = textureSample(mask_texture, mask_sampler, in.uv).a;
+    let detail = textureSample(detail_texture, detail_sampler, in.uv);
+    return vec4(base.rgb * detail.rgb, base.a * mask * in.opacity);
+}
@@ -463,6 +463,7 @@ pub struct PipelineCache {
     pipelines: Vec<CachedPipeline>,
     waiting_pipelines: HashSet<CachedPipelineId>,
     new_pipelines: Mutex<Vec<CachedPipeline>>,
+    requeued_pipelines: Mutex<Vec<(CachedPipelineId, PipelineDescriptor)>>,
     /// If `true`, disables asynchronous pipeline compilation.
     /// This has no effect on macOS, wasm, or without the `multi_threaded` feature.
     synchronous_pipeline_compilation: bool,
@@ -491,6 +492,7 @@ impl PipelineCache {
             layout_cache: default(),
             waiting_pipelines: default(),
             new_pipelines: default(),
+            requeued_pipelines: default(),
             pipelines: default(),
             synchronous_pipeline_compilation,
         }
@@ -498,10 +500,15 @@ impl PipelineCache {
 
     /// Get the state of a cached render pipeline.
     ///
+    /// Pipelines queued since the last [`PipelineCache::process_queue()`] are
+    /// [`CachedPipelineState::Queued`].
+    ///
     /// See [`PipelineCache::queue_render_pipeline()`].
     #[inline]
     pub fn get_render_pipeline_state(&self, id: CachedRenderPipelineId) -> &CachedPipelineState {
-        &self.pipelines[id.0].state
+        self.pipelines
+            .get(id.0)
+            .map_or(&CachedPipelineState::Queued, |pipeline| &pipeline.state)
     }
 
     /// Get the state of a cached compute pipeline.
@@ -621,6 +628,37 @@ impl PipelineCache {
         id
     }
 
+    /// Replace the descriptor of a cached render pipeline, and queue its creation again.
+    ///
+    /// The pipeline keeps its ID, so a pipeline specialized again doesn't leave its previous
+    /// version in the cache. Until the new pipeline is created, [`get_render_pipeline()`] returns
+    /// `None` for it, as when one of its shaders is modified.
+    ///
+    /// [`get_render_pipeline()`]: PipelineCache::get_render_pipeline
+    pub fn requeue_render_pipeline(
+        &self,
+        id: CachedRenderPipelineId,
+        descriptor: RenderPipelineDescriptor,
+    ) {
+        let descriptor = PipelineDescriptor::RenderPipelineDescriptor(Box::new(descriptor));
+        let mut new_pipelines = self
+            .new_pipelines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Pipelines queued since the last `process_queue` haven't started being created yet
+        let new_index = id.0.checked_sub(self.pipelines.len());
+        if let Some(new_pipeline) = new_index.and_then(|index| new_pipelines.get_mut(index)) {
+            new_pipeline.descriptor = descriptor;
+            return;
+        }
+        drop(new_pipelines);
+
+        self.requeued_pipelines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id.0, descriptor));
+    }
+
     /// Insert a compute pipeline into the cache, and queue its creation.
     ///
     /// The pipeline is always inserted and queued for creation. There is no attempt to deduplicate it with
@@ -857,6 +895,18 @@ impl PipelineCache {
                 pipelines.push(new_pipeline);
                 waiting_pipelines.insert(id);
             }
+
+            let mut requeued_pipelines = self
+                .requeued_pipelines
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            for (id, descriptor) in requeued_pipelines.drain(..) {
+                pipelines[id] = CachedPipeline {
+                    descriptor,
+                    state: CachedPipelineState::Queued,
+                };
+                waiting_pipelines.insert(id);
+            }
         }
 
         for id in waiting_pipelines {
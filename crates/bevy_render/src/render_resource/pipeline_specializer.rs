@@ -9,7 +9,9 @@ use crate::{
 };
 use bevy_ecs::system::Resource;
 use bevy_utils::hashbrown::hash_map::VacantEntry;
-use bevy_utils::{default, hashbrown::hash_map::RawEntryMut, tracing::error, Entry, HashMap};
+use bevy_utils::{
+    default, hashbrown::hash_map::RawEntryMut, tracing::error, Entry, HashMap, HashSet,
+};
 use std::{fmt::Debug, hash::Hash};
 use thiserror::Error;
 
@@ -21,11 +23,15 @@ pub trait SpecializedRenderPipeline {
 #[derive(Resource)]
 pub struct SpecializedRenderPipelines<S: SpecializedRenderPipeline> {
     cache: HashMap<S::Key, CachedRenderPipelineId>,
+    stale: HashSet<S::Key>,
 }
 
 impl<S: SpecializedRenderPipeline> Default for SpecializedRenderPipelines<S> {
     fn default() -> Self {
-        Self { cache: default() }
+        Self {
+            cache: default(),
+            stale: default(),
+        }
     }
 }
 
@@ -36,10 +42,31 @@ impl<S: SpecializedRenderPipeline> SpecializedRenderPipelines<S> {
         specialize_pipeline: &S,
         key: S::Key,
     ) -> CachedRenderPipelineId {
-        *self.cache.entry(key.clone()).or_insert_with(|| {
-            let descriptor = specialize_pipeline.specialize(key);
-            cache.queue_render_pipeline(descriptor)
-        })
+        match self.cache.entry(key.clone()) {
+            Entry::Occupied(entry) => {
+                let id = *entry.get();
+                if !self.stale.is_empty() && self.stale.remove(&key) {
+                    let descriptor = specialize_pipeline.specialize(key);
+                    cache.requeue_render_pipeline(id, descriptor);
+                }
+                id
+            }
+            Entry::Vacant(entry) => {
+                let descriptor = specialize_pipeline.specialize(key);
+                *entry.insert(cache.queue_render_pipeline(descriptor))
+            }
+        }
+    }
+
+    /// Marks every specialized pipeline as stale, so each key is specialized again the next time
+    /// it is requested.
+    ///
+    /// This is useful when the output of [`SpecializedRenderPipeline::specialize`] changed for
+    /// keys that were already specialized, for example after a shader was reloaded. The pipelines
+    /// keep their [`CachedRenderPipelineId`], their new descriptor replaces the previous one in the
+    /// [`PipelineCache`], see [`PipelineCache::requeue_render_pipeline`].
+    pub fn clear(&mut self) {
+        self.stale.extend(self.cache.keys().cloned());
     }
 }
 
@@ -1,5 +1,6 @@
 use crate::{
-    BorderRadius, ContentSize, DefaultUiCamera, Node, Outline, Style, TargetCamera, UiScale,
+    ui_viewport, BorderRadius, ContentSize, DefaultUiCamera, Node, Outline, Style, TargetCamera,
+    UiNativeViewport, UiScale,
 };
 use bevy_ecs::{
     change_detection::{DetectChanges, DetectChangesMut},
@@ -93,7 +94,7 @@ struct CameraLayoutInfo {
 pub fn ui_layout_system(
     mut buffers: Local<UiLayoutSystemBuffers>,
     primary_window: Query<(Entity, &Window), With<PrimaryWindow>>,
-    cameras: Query<(Entity, &Camera)>,
+    cameras: Query<(Entity, &Camera, Option<&UiNativeViewport>)>,
     default_ui_camera: DefaultUiCamera,
     ui_scale: Res<UiScale>,
     mut scale_factor_events: EventReader<WindowScaleFactorChanged>,
@@ -134,29 +135,31 @@ pub fn ui_layout_system(
 
     resized_windows.clear();
     resized_windows.extend(resize_events.read().map(|event| event.window));
-    let mut calculate_camera_layout_info = |camera: &Camera| {
-        let size = camera.physical_viewport_size().unwrap_or(UVec2::ZERO);
-        let scale_factor = camera.target_scaling_factor().unwrap_or(1.0);
-        let camera_target = camera
-            .target
-            .normalize(primary_window.get_single().map(|(e, _)| e).ok());
-        let resized = matches!(camera_target,
-          Some(NormalizedRenderTarget::Window(window_ref)) if resized_windows.contains(&window_ref.entity())
-        );
-        CameraLayoutInfo {
-            size,
-            resized,
-            scale_factor: scale_factor * ui_scale.0,
-            root_nodes: interned_root_nodes.pop().unwrap_or_default(),
-        }
-    };
+    let mut calculate_camera_layout_info =
+        |camera: &Camera, native_viewport: Option<&UiNativeViewport>| {
+            let size =
+                ui_viewport(camera, native_viewport).map_or(UVec2::ZERO, |(rect, _)| rect.size());
+            let scale_factor = camera.target_scaling_factor().unwrap_or(1.0);
+            let camera_target = camera
+                .target
+                .normalize(primary_window.get_single().map(|(e, _)| e).ok());
+            let resized = matches!(camera_target,
+              Some(NormalizedRenderTarget::Window(window_ref)) if resized_windows.contains(&window_ref.entity())
+            );
+            CameraLayoutInfo {
+                size,
+                resized,
+                scale_factor: scale_factor * ui_scale.0,
+                root_nodes: interned_root_nodes.pop().unwrap_or_default(),
+            }
+        };
 
     // Precalculate the layout info for each camera, so we have fast access to it for each node
     camera_layout_info.clear();
     root_node_query.iter().for_each(|(entity,target_camera)|{
         match camera_with_default(target_camera) {
             Some(camera_entity) => {
-                let Ok((_, camera)) = cameras.get(camera_entity) else {
+                let Ok((_, camera, native_viewport)) = cameras.get(camera_entity) else {
                     warn!(
                         "TargetCamera (of root UI node {entity:?}) is pointing to a camera {:?} which doesn't exist",
                         camera_entity
@@ -165,7 +168,7 @@ pub fn ui_layout_system(
                 };
                 let layout_info = camera_layout_info
                     .entry(camera_entity)
-                    .or_insert_with(|| calculate_camera_layout_info(camera));
+                    .or_insert_with(|| calculate_camera_layout_info(camera, native_viewport));
                 layout_info.root_nodes.push(entity);
             }
             None => {
@@ -221,7 +224,7 @@ pub fn ui_layout_system(
     ui_surface.remove_camera_entities(removed_components.removed_cameras.read());
 
     // update camera children
-    for (camera_id, _) in cameras.iter() {
+    for (camera_id, ..) in cameras.iter() {
         let root_nodes =
             if let Some(CameraLayoutInfo { root_nodes, .. }) = camera_layout_info.get(&camera_id) {
                 root_nodes.iter().cloned()
@@ -1077,6 +1080,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ui_scale_enlarges_node_geometry() {
+        let (mut world, mut ui_schedule) = setup_ui_test_world();
+
+        let node = world
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(40.),
+                    top: Val::Px(10.),
+                    width: Val::Px(100.),
+                    height: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+        let fill = world
+            .spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    ..default()
+                },
+                ..default()
+            })
+            .id();
+
+        let mut physical_rect = |world: &mut World, ui_scale: f32| {
+            world.resource_mut::<UiScale>().0 = ui_scale;
+            ui_schedule.run(world);
+            let transform = world.get::<GlobalTransform>(node).unwrap();
+            world
+                .get::<Node>(node)
+                .unwrap()
+                .physical_rect(transform, 1., ui_scale)
+        };
+        let baseline = physical_rect(&mut world, 1.);
+        let scaled = physical_rect(&mut world, 1.5);
+
+        assert_eq!(baseline, Rect::new(40., 10., 140., 30.));
+        assert_eq!(scaled.min, 1.5 * baseline.min);
+        assert_eq!(scaled.size(), 1.5 * baseline.size());
+
+        // The scaled UI fills the same physical area, so it has less logical space to lay out in
+        let fill_width = world.get::<Node>(fill).unwrap().size().x;
+        assert!((fill_width - WINDOW_WIDTH / 1.5).abs() < 1.);
+    }
+
     #[test]
     fn no_camera_ui() {
         let mut world = World::new();
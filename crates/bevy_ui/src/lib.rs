@@ -29,7 +29,10 @@ mod focus;
 mod geometry;
 mod layout;
 mod render;
+mod screen_cache;
 mod stack;
+#[cfg(feature = "bevy_text")]
+mod stack_debug;
 mod ui_node;
 
 pub use focus::*;
@@ -37,6 +40,9 @@ pub use geometry::*;
 pub use layout::*;
 pub use measurement::*;
 pub use render::*;
+pub use screen_cache::*;
+#[cfg(feature = "bevy_text")]
+pub use stack_debug::*;
 pub use ui_material::*;
 pub use ui_node::*;
 use widget::UiImageSize;
@@ -47,8 +53,9 @@ use widget::UiImageSize;
 pub mod prelude {
     #[doc(hidden)]
     pub use crate::{
-        geometry::*, node_bundles::*, ui_material::*, ui_node::*, widget::Button, widget::Label,
-        Interaction, UiMaterialPlugin, UiScale,
+        geometry::*, node_bundles::*, ui_color_material::ColorUiMaterial,
+        ui_color_matrix_material::ColorMatrixUiMaterial, ui_material::*, ui_node::*,
+        widget::Button, widget::Label, Interaction, UiMaterialPlugin, UiScale,
     };
     // `bevy_sprite` re-exports for texture slicing
     #[doc(hidden)]
@@ -56,10 +63,12 @@ pub mod prelude {
 }
 
 use bevy_app::prelude::*;
+use bevy_asset::AssetApp;
 use bevy_ecs::prelude::*;
 use bevy_input::InputSystem;
 use bevy_render::{
     camera::CameraUpdateSystem,
+    render_graph::RenderGraph,
     view::{check_visibility, VisibilitySystems},
     RenderApp,
 };
@@ -67,7 +76,10 @@ use bevy_transform::TransformSystem;
 use layout::ui_surface::UiSurface;
 use stack::ui_stack_system;
 pub use stack::UiStack;
-use update::{update_clipping_system, update_target_camera_system};
+use update::{
+    hide_nodes_outside_camera_layers, hide_nodes_outside_camera_viewport, update_clipping_system,
+    update_target_camera_system,
+};
 
 /// The basic plugin for Bevy UI
 #[derive(Default)]
@@ -126,8 +138,15 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<UiSurface>()
             .init_resource::<UiScale>()
+            .init_resource::<UiTheme>()
             .init_resource::<UiStack>()
             .register_type::<BackgroundColor>()
+            .register_type::<UiCornerColors>()
+            .register_type::<UiGradientTessellation>()
+            .register_type::<UiGradientInterpolation>()
+            .register_type::<UiAntialias>()
+            .register_type::<SharedColor>()
+            .init_asset::<SharedColor>()
             .register_type::<CalculatedClip>()
             .register_type::<ContentSize>()
             .register_type::<FocusPolicy>()
@@ -137,15 +156,40 @@ impl Plugin for UiPlugin {
             .register_type::<Style>()
             .register_type::<TargetCamera>()
             .register_type::<UiImage>()
+            .register_type::<UiImageLayer>()
+            .register_type::<UiMesh>()
             .register_type::<UiImageSize>()
             .register_type::<UiRect>()
             .register_type::<UiScale>()
+            .register_type::<UiTheme>()
+            .register_type::<UiMaterialOpacity>()
+            .register_type::<UiMaterialText>()
+            .register_type::<UiVertexBudget>()
+            .register_type::<UiImageArrayBatching>()
             .register_type::<BorderColor>()
+            .register_type::<BorderSideColors>()
             .register_type::<BorderRadius>()
             .register_type::<widget::Button>()
             .register_type::<widget::Label>()
             .register_type::<ZIndex>()
+            .register_type::<UiTooltip>()
+            .register_type::<UiUnclippedOverflow>()
             .register_type::<Outline>()
+            .register_type::<BoxShadow>()
+            .register_type::<UiClipInflate>()
+            .register_type::<UiVertexPriority>()
+            .register_type::<UiClipMode>()
+            .register_type::<UiColorBlend>()
+            .register_type::<UiOpacity>()
+            .register_type::<UiStencilMask>()
+            .register_type::<UiModalBackdrop>()
+            .register_type::<UiViewportMask>()
+            .register_type::<UiForceLdr>()
+            .register_type::<UiPassLayer>()
+            .register_type::<UiViewportCulling>()
+            .register_type::<UiSceneDepth>()
+            .register_type::<UiNativeViewport>()
+            .register_type::<UiScreenCache>()
             .configure_sets(
                 PostUpdate,
                 (
@@ -165,7 +209,14 @@ impl Plugin for UiPlugin {
             PostUpdate,
             (
                 check_visibility::<WithNode>.in_set(VisibilitySystems::CheckVisibility),
+                hide_nodes_outside_camera_layers
+                    .in_set(VisibilitySystems::CheckVisibility)
+                    .after(check_visibility::<WithNode>),
+                hide_nodes_outside_camera_viewport
+                    .in_set(VisibilitySystems::CheckVisibility)
+                    .after(hide_nodes_outside_camera_layers),
                 update_target_camera_system.in_set(UiSystem::Prepare),
+                update_ui_screen_caches.before(CameraUpdateSystem),
                 ui_layout_system
                     .in_set(UiSystem::Layout)
                     .before(TransformSystem::TransformPropagate)
@@ -204,17 +255,26 @@ impl Plugin for UiPlugin {
         };
 
         render_app.init_resource::<UiPipeline>();
+
+        // Plugins adding temporal anti-aliasing may be added after this one, so the render graph
+        // is only complete here.
+        order_ui_pass_after_taa(&mut render_app.world_mut().resource_mut::<RenderGraph>());
     }
 }
 
 /// A function that should be called from [`UiPlugin::build`] when [`bevy_text`] is enabled.
 #[cfg(feature = "bevy_text")]
 fn build_text_interop(app: &mut App) {
-    use crate::widget::TextFlags;
+    use crate::widget::{TextFlags, TextFlip, TextOverflowFade, TextSectionOpacity};
     use bevy_text::TextLayoutInfo;
 
     app.register_type::<TextLayoutInfo>()
-        .register_type::<TextFlags>();
+        .register_type::<TextFlags>()
+        .register_type::<TextSectionOpacity>()
+        .register_type::<TextOverflowFade>()
+        .register_type::<TextFlip>()
+        .register_type::<UiStackDebugOverlay>()
+        .init_resource::<UiStackDebugOverlay>();
 
     app.add_systems(
         PostUpdate,
@@ -234,6 +294,8 @@ fn build_text_interop(app: &mut App) {
                 // Text2d and bevy_ui text are entirely on separate entities
                 .ambiguous_with(bevy_text::update_text2d_layout)
                 .ambiguous_with(bevy_text::calculate_bounds_text2d),
+            // Labels follow the layout and stack of the previous frame
+            update_ui_stack_debug_labels.before(UiSystem::Prepare),
         ),
     );
 
@@ -1,12 +1,33 @@
 mod pipeline;
 mod render_pass;
+pub mod ui_batch_diagnostics;
+pub mod ui_color_material;
+pub mod ui_color_matrix_material;
+pub mod ui_force_ldr;
+pub mod ui_geometry_capture;
+pub mod ui_geometry_export;
+pub mod ui_gradient_material;
+pub mod ui_isolate;
+pub mod ui_linear_target;
 mod ui_material_pipeline;
+pub mod ui_mesh;
+pub mod ui_modal_backdrop;
+pub mod ui_msaa;
+pub mod ui_opacity;
+pub mod ui_opaque;
+pub mod ui_pass_layer;
+pub mod ui_pixelate_material;
+pub mod ui_rounded_material;
+pub mod ui_scene_color;
+pub mod ui_stencil_mask;
 pub mod ui_texture_slice_pipeline;
+pub mod ui_viewport_mask;
 
 use bevy_color::{Alpha, ColorToComponents, LinearRgba};
 use bevy_core_pipeline::core_2d::graph::{Core2d, Node2d};
 use bevy_core_pipeline::core_3d::graph::{Core3d, Node3d};
 use bevy_core_pipeline::{core_2d::Camera2d, core_3d::Camera3d};
+use bevy_diagnostic::{Diagnostic, RegisterDiagnostic};
 use bevy_hierarchy::Parent;
 use bevy_render::render_phase::ViewSortedRenderPhases;
 use bevy_render::texture::TRANSPARENT_IMAGE_HANDLE;
@@ -19,38 +40,71 @@ use bevy_render::{
 use bevy_sprite::{ImageScaleMode, SpriteAssetEvents, TextureAtlas};
 pub use pipeline::*;
 pub use render_pass::*;
+use ui_batch_diagnostics::{
+    report_ui_batch_diagnostics, update_ui_batch_diagnostics, UiBatchDiagnostics,
+};
+use ui_color_material::ColorUiMaterialPlugin;
+use ui_color_matrix_material::ColorMatrixUiMaterialPlugin;
+use ui_force_ldr::{ui_target_is_hdr, ExtractedUiForceLdr, UiForceLdrPlugin};
+use ui_geometry_capture::{capture_ui_geometry, UiGeometryCapture};
+use ui_geometry_export::export_ui_geometry;
+use ui_gradient_material::GradientUiMaterialPlugin;
+use ui_isolate::isolate_extracted_uinodes;
+use ui_linear_target::UiLinearTargetPlugin;
 pub use ui_material_pipeline::*;
+use ui_mesh::{extract_uinode_meshes, write_ui_mesh_geometry, ExtractedUiMeshes};
+use ui_modal_backdrop::UiModalBackdropPlugin;
+use ui_msaa::{ui_msaa_samples, UiMsaaPlugin};
+use ui_opacity::{extract_ui_opacities, fade_extracted_uinodes, ExtractedUiOpacities};
+use ui_opaque::{UiDepthViews, UiOpaquePlugin};
+use ui_pass_layer::{ExtractedUiSceneDepths, RunUiGraphNode, UiPassLayerPlugin, UiSceneDepthItems};
+use ui_pixelate_material::PixelateUiMaterialPlugin;
+use ui_rounded_material::RoundedUiMaterialPlugin;
+use ui_scene_color::{UiSceneColorPlugin, UiSceneView};
+use ui_stencil_mask::{
+    ExtractedUiStencilMasks, UiStencil, UiStencilItems, UiStencilMaskPlugin, UiStencilMode,
+};
 use ui_texture_slice_pipeline::UiTextureSlicerPlugin;
+use ui_viewport_mask::UiViewportMaskPlugin;
 
 use crate::graph::{NodeUi, SubGraphUi};
 use crate::{
-    BackgroundColor, BorderColor, CalculatedClip, DefaultUiCamera, Display, Node, Outline, Style,
-    TargetCamera, UiImage, UiScale, Val,
+    BackgroundColor, BorderColor, BorderSideColors, BoxShadow, CalculatedClip, DefaultUiCamera,
+    Display, Node, Outline, SharedColor, Style, TargetCamera, UiClipInflate, UiClipMode,
+    UiColorBlend, UiCornerColors, UiGradientInterpolation, UiGradientTessellation, UiImage,
+    UiImageLayer, UiMaterialText, UiMesh, UiScale, UiStack, UiTheme, UiVertexPriority, Val,
 };
 
+#[cfg(feature = "bevy_text")]
+use crate::widget::{TextFlip, TextOverflowFade, TextSectionOpacity};
 use bevy_app::prelude::*;
 use bevy_asset::{load_internal_asset, AssetEvent, AssetId, Assets, Handle};
 use bevy_ecs::entity::{EntityHashMap, EntityHashSet};
 use bevy_ecs::prelude::*;
-use bevy_math::{FloatOrd, Mat4, Rect, URect, UVec4, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
+use bevy_math::{
+    FloatOrd, Mat4, Rect, URect, UVec2, UVec4, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles,
+};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_render::{
-    camera::Camera,
+    camera::{Camera, ExtractedCamera, Viewport},
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    mesh::Mesh,
     render_asset::RenderAssets,
-    render_graph::{RenderGraph, RunGraphOnViewNode},
-    render_phase::{sort_phase_system, AddRenderCommand, DrawFunctions},
+    render_graph::{Edge, RenderGraph, RenderLabel},
+    render_phase::{sort_phase_system, AddRenderCommand, DrawFunctions, SortedRenderPhase},
     render_resource::*,
     renderer::{RenderDevice, RenderQueue},
-    texture::Image,
-    view::{ExtractedView, ViewUniforms},
+    texture::{CachedTexture, Image, ImageSamplerDescriptor, TextureCache},
+    view::{ExtractedView, Msaa, ViewUniforms},
     Extract, RenderApp, RenderSet,
 };
 use bevy_sprite::TextureAtlasLayout;
 #[cfg(feature = "bevy_text")]
 use bevy_text::{PositionedGlyph, Text, TextLayoutInfo};
 use bevy_transform::components::GlobalTransform;
-use bevy_utils::HashMap;
+use bevy_utils::{tracing::warn, HashMap, HashSet};
 use bytemuck::{Pod, Zeroable};
-use std::ops::Range;
+use std::ops::{Add, Mul, Range};
 
 pub mod graph {
     use bevy_render::render_graph::{RenderLabel, RenderSubGraph};
@@ -60,7 +114,11 @@ pub mod graph {
 
     #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
     pub enum NodeUi {
+        UiPrepass,
         UiPass,
+        /// Runs the [`SubGraphUi`] of the views whose UI is drawn in their scene, see
+        /// [`UiPassLayer::InScene`](crate::UiPassLayer::InScene).
+        UiInScenePass,
     }
 }
 
@@ -68,10 +126,18 @@ pub const UI_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(130128470471
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub enum RenderUiSystem {
+    ExtractBoxShadows,
     ExtractBackgrounds,
     ExtractImages,
     ExtractBorders,
     ExtractText,
+    /// Runs in [`RenderSet::PrepareBindGroups`] once the [`UiBatch`]es of every view are prepared,
+    /// before the UI is drawn.
+    ///
+    /// Systems in this set can reorder the batches of a [`TransparentUi`] phase with
+    /// [`sort_ui_batches_by_key`], drop some with [`retain_ui_batches`], or modify the [`UiBatch`]
+    /// components of the phase items starting each batch.
+    ModifyBatches,
 }
 
 pub fn build_ui_render(app: &mut App) {
@@ -83,16 +149,25 @@ pub fn build_ui_render(app: &mut App) {
 
     render_app
         .init_resource::<SpecializedRenderPipelines<UiPipeline>>()
+        .init_resource::<UiPipelineCustomizer>()
         .init_resource::<UiImageBindGroups>()
         .init_resource::<UiMeta>()
         .init_resource::<ExtractedUiNodes>()
         .allow_ambiguous_resource::<ExtractedUiNodes>()
+        .init_resource::<UiFrameCache>()
+        .init_resource::<ExtractedUiOpacities>()
+        .init_resource::<ExtractedUiMeshes>()
         .init_resource::<DrawFunctions<TransparentUi>>()
         .init_resource::<ViewSortedRenderPhases<TransparentUi>>()
+        .init_resource::<DrawFunctions<OpaqueUi>>()
+        .init_resource::<ViewSortedRenderPhases<OpaqueUi>>()
+        .init_resource::<DrawFunctions<UiPrepass>>()
+        .init_resource::<ViewSortedRenderPhases<UiPrepass>>()
         .add_render_command::<TransparentUi, DrawUi>()
         .configure_sets(
             ExtractSchedule,
             (
+                RenderUiSystem::ExtractBoxShadows,
                 RenderUiSystem::ExtractBackgrounds,
                 RenderUiSystem::ExtractImages,
                 RenderUiSystem::ExtractBorders,
@@ -100,15 +175,29 @@ pub fn build_ui_render(app: &mut App) {
             )
                 .chain(),
         )
+        .configure_sets(
+            Render,
+            RenderUiSystem::ModifyBatches
+                .in_set(RenderSet::PrepareBindGroups)
+                .after(prepare_uinodes),
+        )
         .add_systems(
             ExtractSchedule,
             (
                 extract_default_ui_camera_view,
+                extract_ui_opacities.before(RenderUiSystem::ExtractBoxShadows),
+                extract_uinode_box_shadows.in_set(RenderUiSystem::ExtractBoxShadows),
                 extract_uinode_background_colors.in_set(RenderUiSystem::ExtractBackgrounds),
+                extract_uinode_meshes.in_set(RenderUiSystem::ExtractBackgrounds),
                 extract_uinode_images.in_set(RenderUiSystem::ExtractImages),
                 extract_uinode_borders.in_set(RenderUiSystem::ExtractBorders),
                 #[cfg(feature = "bevy_text")]
                 extract_uinode_text.in_set(RenderUiSystem::ExtractText),
+                fade_extracted_uinodes
+                    .after(extract_ui_opacities)
+                    .after(RenderUiSystem::ExtractText),
+                isolate_extracted_uinodes.after(RenderUiSystem::ExtractText),
+                prioritize_extracted_uinodes.after(RenderUiSystem::ExtractText),
             ),
         )
         .add_systems(
@@ -116,7 +205,17 @@ pub fn build_ui_render(app: &mut App) {
             (
                 queue_uinodes.in_set(RenderSet::Queue),
                 sort_phase_system::<TransparentUi>.in_set(RenderSet::PhaseSort),
+                sort_phase_system::<OpaqueUi>.in_set(RenderSet::PhaseSort),
+                sort_phase_system::<UiPrepass>.in_set(RenderSet::PhaseSort),
+                prepare_ui_prepass_textures.in_set(RenderSet::PrepareResources),
+                capture_ui_geometry.in_set(RenderSet::PrepareResources),
                 prepare_uinodes.in_set(RenderSet::PrepareBindGroups),
+                export_ui_geometry
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(prepare_uinodes),
+                update_ui_batch_diagnostics
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .after(RenderUiSystem::ModifyBatches),
             ),
         );
 
@@ -127,27 +226,107 @@ pub fn build_ui_render(app: &mut App) {
 
     if let Some(graph_2d) = graph.get_sub_graph_mut(Core2d) {
         graph_2d.add_sub_graph(SubGraphUi, ui_graph_2d);
-        graph_2d.add_node(NodeUi::UiPass, RunGraphOnViewNode::new(SubGraphUi));
+        graph_2d.add_node(NodeUi::UiPass, RunUiGraphNode::new(UiPassLayer::Overlay));
         graph_2d.add_node_edge(Node2d::EndMainPass, NodeUi::UiPass);
         graph_2d.add_node_edge(Node2d::EndMainPassPostProcessing, NodeUi::UiPass);
         graph_2d.add_node_edge(NodeUi::UiPass, Node2d::Upscaling);
+        graph_2d.add_node(
+            NodeUi::UiInScenePass,
+            RunUiGraphNode::new(UiPassLayer::InScene),
+        );
+        graph_2d.add_node_edge(Node2d::MainOpaquePass, NodeUi::UiInScenePass);
+        graph_2d.add_node_edge(NodeUi::UiInScenePass, Node2d::MainTransparentPass);
     }
 
     if let Some(graph_3d) = graph.get_sub_graph_mut(Core3d) {
         graph_3d.add_sub_graph(SubGraphUi, ui_graph_3d);
-        graph_3d.add_node(NodeUi::UiPass, RunGraphOnViewNode::new(SubGraphUi));
+        graph_3d.add_node(NodeUi::UiPass, RunUiGraphNode::new(UiPassLayer::Overlay));
         graph_3d.add_node_edge(Node3d::EndMainPass, NodeUi::UiPass);
         graph_3d.add_node_edge(Node3d::EndMainPassPostProcessing, NodeUi::UiPass);
         graph_3d.add_node_edge(NodeUi::UiPass, Node3d::Upscaling);
+        // Drawn before the transmissive and transparent objects, which are blended over the UI
+        graph_3d.add_node(
+            NodeUi::UiInScenePass,
+            RunUiGraphNode::new(UiPassLayer::InScene),
+        );
+        graph_3d.add_node_edge(Node3d::MainOpaquePass, NodeUi::UiInScenePass);
+        graph_3d.add_node_edge(NodeUi::UiInScenePass, Node3d::MainTransmissivePass);
     }
 
-    app.add_plugins(UiTextureSlicerPlugin);
+    app.init_resource::<UiVertexBudget>()
+        .init_resource::<UiImageArrayBatching>()
+        .init_resource::<UiGeometryCapture>()
+        .init_resource::<UiBatchDiagnostics>()
+        .register_diagnostic(Diagnostic::new(UiBatchDiagnostics::NODES))
+        .register_diagnostic(Diagnostic::new(UiBatchDiagnostics::PIPELINES))
+        .register_diagnostic(Diagnostic::new(UiBatchDiagnostics::BATCHES))
+        .register_diagnostic(Diagnostic::new(UiBatchDiagnostics::VERTICES))
+        .register_diagnostic(Diagnostic::new(UiBatchDiagnostics::VERTEX_BYTES))
+        .add_systems(Update, report_ui_batch_diagnostics)
+        .add_plugins((
+            UiTextureSlicerPlugin,
+            UiModalBackdropPlugin,
+            UiViewportMaskPlugin,
+            UiSceneColorPlugin,
+            UiForceLdrPlugin,
+            UiLinearTargetPlugin,
+            UiMsaaPlugin,
+            UiOpaquePlugin,
+            UiStencilMaskPlugin,
+            UiPassLayerPlugin,
+            (
+                ColorUiMaterialPlugin,
+                ColorMatrixUiMaterialPlugin,
+                GradientUiMaterialPlugin,
+                PixelateUiMaterialPlugin,
+                RoundedUiMaterialPlugin,
+            ),
+            (
+                ExtractResourcePlugin::<UiVertexBudget>::default(),
+                ExtractResourcePlugin::<UiImageArrayBatching>::default(),
+                ExtractResourcePlugin::<UiGeometryCapture>::default(),
+                ExtractResourcePlugin::<UiBatchDiagnostics>::default(),
+                ExtractResourcePlugin::<UiTheme>::default(),
+            ),
+        ));
+}
+
+/// Makes sure the UI pass of 3D cameras runs after the temporal anti-aliasing resolve, if
+/// [`TemporalAntiAliasPlugin`](bevy_core_pipeline::experimental::taa::TemporalAntiAliasPlugin)
+/// was added.
+///
+/// The UI is drawn using its own [`ExtractedView`] (see [`DefaultCameraView`]), which never
+/// carries a [`TemporalJitter`](bevy_render::camera::TemporalJitter), so UI geometry is never
+/// jittered. Rendering it after the resolve also keeps it out of the TAA history buffer, which
+/// would otherwise make moving UI ghost.
+///
+/// The graph already orders the UI pass after [`Node3d::EndMainPassPostProcessing`], which TAA
+/// precedes, but the edge is added explicitly so the ordering doesn't depend on other plugins.
+pub(crate) fn order_ui_pass_after_taa(graph: &mut RenderGraph) {
+    let Some(graph_3d) = graph.get_sub_graph_mut(Core3d) else {
+        return;
+    };
+    if graph_3d.get_node_state(Node3d::Taa).is_err()
+        || graph_3d.get_node_state(NodeUi::UiPass).is_err()
+    {
+        return;
+    }
+    let edge = Edge::NodeEdge {
+        input_node: NodeUi::UiPass.intern(),
+        output_node: Node3d::Taa.intern(),
+    };
+    if !graph_3d.has_edge(&edge) {
+        graph_3d.add_node_edge(Node3d::Taa, NodeUi::UiPass);
+    }
 }
 
 fn get_ui_graph(render_app: &mut SubApp) -> RenderGraph {
+    let ui_prepass_node = UiPrepassNode::new(render_app.world_mut());
     let ui_pass_node = UiPassNode::new(render_app.world_mut());
     let mut ui_graph = RenderGraph::default();
+    ui_graph.add_node(NodeUi::UiPrepass, ui_prepass_node);
     ui_graph.add_node(NodeUi::UiPass, ui_pass_node);
+    ui_graph.add_node_edge(NodeUi::UiPrepass, NodeUi::UiPass);
     ui_graph
 }
 
@@ -157,16 +336,61 @@ fn get_ui_graph(render_app: &mut SubApp) -> RenderGraph {
 pub enum NodeType {
     Rect,
     Border,
+    /// A blurred rounded rectangle, see [`BoxShadow`].
+    ///
+    /// The blur radius is stored in the first component of [`ExtractedUiNode::border`].
+    BoxShadow,
 }
 
+/// A UI item extracted to the render world, drawn and batched by the UI pipeline.
+///
+/// Plugins can extract their own items with [`ExtractedUiNodes::push`], which are then sorted,
+/// clipped, faded and batched like the built-in ones:
+/// - `stack_index` is the [`Node::stack_index`] of the node the item is drawn with. Items are
+///   drawn in stack order, and the items of a node share its stack index.
+/// - `transform` is the [`GlobalTransform`] of that node, and `rect` the part of the node drawn,
+///   with its min at the origin.
+/// - `image` is [`AssetId::default`] for untextured items. Other images are only drawn once their
+///   [`GpuImage`] is prepared, and items with different images aren't batched together.
+/// - `camera_entity` is the render world entity of the camera the item is drawn to.
+#[derive(PartialEq)]
 pub struct ExtractedUiNode {
     pub stack_index: u32,
     pub transform: Mat4,
     pub color: LinearRgba,
     pub rect: Rect,
     pub image: AssetId<Image>,
+    /// The array layer of `image` to display, see [`UiImageLayer`].
+    pub image_layer: Option<u32>,
+    /// The sampler `image` is drawn with instead of its own, see [`UiImage::sampler`].
+    pub sampler: Option<UiSamplerKey>,
+    /// Per-corner colors replacing `color`, see [`UiCornerColors`].
+    /// Ordering: top left, top right, bottom right, bottom left.
+    pub corner_colors: Option<[LinearRgba; 4]>,
+    /// The color space `corner_colors` are interpolated in.
+    pub gradient_interpolation: UiGradientInterpolation,
+    /// The only side of a [`NodeType::Border`] this node draws, each side of a border with
+    /// [`BorderSideColors`] is extracted as a node of its own color.
+    /// Ordering: left, top, right, bottom.
+    pub border_side: Option<u32>,
+    /// Whether the edges of the node are antialiased, see [`UiAntialias`].
+    pub antialias: UiAntialias,
+    /// The number of grid cells the node's quad is split into along each axis.
+    pub subdivisions: UVec2,
+    /// The mesh drawn instead of the node's quad, see [`UiMesh`](crate::UiMesh).
+    pub mesh: Option<AssetId<Mesh>>,
+    /// How the node is masked by the [`UiStencilMask`](crate::UiStencilMask) nodes above it.
+    pub stencil: UiStencil,
+    /// Whether the colors of `image` are premultiplied by their alpha, see
+    /// [`UiImage::premultiplied_alpha`].
+    pub premultiplied_alpha: bool,
+    /// The depth of the node in the 3D scene of its camera, which replaces the z coordinate of its
+    /// vertices, see [`UiPassLayer::InScene`](crate::UiPassLayer::InScene).
+    pub scene_depth: Option<f32>,
     pub atlas_scaling: Option<Vec2>,
     pub clip: Option<Rect>,
+    /// How `clip` is applied, see [`UiClipMode`].
+    pub clip_mode: UiClipMode,
     pub flip_x: bool,
     pub flip_y: bool,
     // Camera to render this UI node to. By the time it is extracted,
@@ -180,32 +404,132 @@ pub struct ExtractedUiNode {
     /// Ordering: left, top, right, bottom.
     pub border: [f32; 4],
     pub node_type: NodeType,
+    /// How the texture color is combined with `color`.
+    pub color_blend: UiColorBlend,
+    /// Whether the node is drawn before the others when the [`UiVertexBudget`] is exceeded, see
+    /// [`UiVertexPriority`].
+    pub vertex_priority: bool,
+}
+
+/// An empty untextured white node drawn to a placeholder camera, for extractors to override with
+/// `..Default::default()`.
+impl Default for ExtractedUiNode {
+    fn default() -> Self {
+        Self {
+            stack_index: 0,
+            transform: Mat4::IDENTITY,
+            color: LinearRgba::WHITE,
+            rect: Rect::default(),
+            image: AssetId::default(),
+            image_layer: None,
+            sampler: None,
+            corner_colors: None,
+            gradient_interpolation: UiGradientInterpolation::Linear,
+            border_side: None,
+            antialias: UiAntialias::On,
+            subdivisions: UVec2::ONE,
+            mesh: None,
+            stencil: UiStencil::None,
+            premultiplied_alpha: false,
+            scene_depth: None,
+            atlas_scaling: None,
+            clip: None,
+            clip_mode: UiClipMode::Scissor,
+            flip_x: false,
+            flip_y: false,
+            camera_entity: Entity::PLACEHOLDER,
+            border_radius: [0.; 4],
+            border: [0.; 4],
+            node_type: NodeType::Rect,
+            color_blend: UiColorBlend::Multiply,
+            vertex_priority: false,
+        }
+    }
+}
+
+impl ExtractedUiNode {
+    /// The clip applied with a scissor rect when the node is drawn, see [`UiClipMode::Scissor`].
+    pub fn scissor_clip(&self) -> Option<Rect> {
+        self.clip.filter(|_| self.clip_mode == UiClipMode::Scissor)
+    }
+
+    /// The clip applied by moving the vertices of the node, see [`UiClipMode::Vertices`].
+    pub fn vertex_clip(&self) -> Option<Rect> {
+        self.clip.filter(|_| self.clip_mode == UiClipMode::Vertices)
+    }
+
+    /// Multiplies the alpha of the colors of the node by `opacity`, see [`UiOpacity`](crate::UiOpacity).
+    pub fn fade(&mut self, opacity: f32) {
+        self.color.alpha *= opacity;
+        for color in self.corner_colors.iter_mut().flatten() {
+            color.alpha *= opacity;
+        }
+    }
+
+    /// Returns `true` if the vertices of `self` and `other` are the same, which they are when the
+    /// nodes only differ by their scissor clips.
+    fn same_geometry(&self, other: &Self) -> bool {
+        ExtractedUiNode {
+            clip: self.vertex_clip(),
+            ..*self
+        } == ExtractedUiNode {
+            clip: other.vertex_clip(),
+            ..*other
+        }
+    }
 }
 
+/// The UI items extracted this frame, keyed by render world entity.
+///
+/// Systems extracting their own items should run in one of the extraction sets of
+/// [`RenderUiSystem`] in the [`ExtractSchedule`], like
+/// `extract_chart_nodes.in_set(RenderUiSystem::ExtractBackgrounds)`, so that their items are faded,
+/// isolated and prepared along with the built-in ones. The set doesn't change the order the items
+/// are drawn in, which only depends on their stack index.
 #[derive(Resource, Default)]
 pub struct ExtractedUiNodes {
     pub uinodes: EntityHashMap<ExtractedUiNode>,
 }
 
+impl ExtractedUiNodes {
+    /// Adds `uinode` to the items extracted this frame, under a new render world entity which is
+    /// returned.
+    pub fn push(&mut self, commands: &mut Commands, uinode: ExtractedUiNode) -> Entity {
+        let entity = commands.spawn_empty().id();
+        self.uinodes.insert(entity, uinode);
+        entity
+    }
+}
+
 pub fn extract_uinode_background_colors(
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
     camera_query: Extract<Query<(Entity, &Camera)>>,
     default_ui_camera: Extract<DefaultUiCamera>,
     ui_scale: Extract<Res<UiScale>>,
     uinode_query: Extract<
-        Query<(
-            Entity,
-            &Node,
-            &GlobalTransform,
-            &ViewVisibility,
-            Option<&CalculatedClip>,
-            Option<&TargetCamera>,
-            &BackgroundColor,
-            &Style,
-            Option<&Parent>,
-        )>,
+        Query<
+            (
+                Entity,
+                &Node,
+                &GlobalTransform,
+                &ViewVisibility,
+                Option<&CalculatedClip>,
+                (Option<&UiClipInflate>, Option<&UiClipMode>),
+                Option<&TargetCamera>,
+                &BackgroundColor,
+                Option<&Handle<SharedColor>>,
+                Option<&UiCornerColors>,
+                Option<&UiGradientTessellation>,
+                Option<&UiGradientInterpolation>,
+                Option<&UiAntialias>,
+                &Style,
+                Option<&Parent>,
+            ),
+            Without<UiMesh>,
+        >,
     >,
     node_query: Extract<Query<&Node>>,
+    shared_colors: Extract<Res<Assets<SharedColor>>>,
 ) {
     for (
         entity,
@@ -213,8 +537,14 @@ pub fn extract_uinode_background_colors(
         transform,
         view_visibility,
         clip,
+        (clip_inflate, clip_mode),
         camera,
         background_color,
+        shared_color,
+        corner_colors,
+        tessellation,
+        gradient_interpolation,
+        antialias,
         style,
         parent,
     ) in &uinode_query
@@ -225,9 +555,23 @@ pub fn extract_uinode_background_colors(
         };
 
         // Skip invisible backgrounds
-        if !view_visibility.get() || background_color.0.is_fully_transparent() {
+        let color = SharedColor::resolve(background_color, shared_color, &shared_colors);
+        let is_transparent = match corner_colors {
+            Some(corner_colors) => corner_colors
+                .to_array()
+                .iter()
+                .all(Alpha::is_fully_transparent),
+            None => color.is_fully_transparent(),
+        };
+        if !view_visibility.get() || is_transparent {
             continue;
         }
+        let subdivisions = match (corner_colors, tessellation) {
+            (Some(_), Some(tessellation)) => {
+                ui_gradient_subdivisions(uinode.size(), tessellation.max_cell_size)
+            }
+            _ => UVec2::ONE,
+        };
 
         let ui_logical_viewport_size = camera_query
             .get(camera_entity)
@@ -267,31 +611,77 @@ pub fn extract_uinode_background_colors(
             ExtractedUiNode {
                 stack_index: uinode.stack_index,
                 transform: transform.compute_matrix(),
-                color: background_color.0.into(),
+                color: color.into(),
                 rect: Rect {
                     min: Vec2::ZERO,
                     max: uinode.calculated_size,
                 },
-                clip: clip.map(|clip| clip.clip),
-                image: AssetId::default(),
-                atlas_scaling: None,
-                flip_x: false,
-                flip_y: false,
+                clip: inflated_clip(clip, clip_inflate),
+                clip_mode: clip_mode.copied().unwrap_or_default(),
                 camera_entity,
                 border,
                 border_radius,
-                node_type: NodeType::Rect,
+                corner_colors: corner_colors
+                    .map(|corner_colors| corner_colors.to_array().map(LinearRgba::from)),
+                gradient_interpolation: gradient_interpolation.copied().unwrap_or_default(),
+                antialias: antialias.copied().unwrap_or_default(),
+                subdivisions,
+                ..Default::default()
             },
         );
     }
 }
 
+/// Returns the rect of the texture drawn by an image node of `size` and the scaling of the
+/// texture, for the frame `atlas_rect` of its [`TextureAtlas`] and the [`UiImage::rect`] offset by
+/// that frame.
+///
+/// The rect is scaled so the selected region of the texture fills the node, and the scaling
+/// rescales the texture to UVs in [`prepare_uinodes`]. Images without a region are drawn whole.
+pub fn ui_image_rect(
+    size: Vec2,
+    atlas_rect: Option<Rect>,
+    image_rect: Option<Rect>,
+) -> (Rect, Option<Vec2>) {
+    let mut rect = match (atlas_rect, image_rect) {
+        (None, None) => {
+            return (
+                Rect {
+                    min: Vec2::ZERO,
+                    max: size,
+                },
+                None,
+            )
+        }
+        (None, Some(image_rect)) => image_rect,
+        (Some(atlas_rect), None) => atlas_rect,
+        (Some(atlas_rect), Some(mut image_rect)) => {
+            image_rect.min += atlas_rect.min;
+            image_rect.max += atlas_rect.min;
+            image_rect
+        }
+    };
+
+    let atlas_scaling = size / rect.size();
+    rect.min *= atlas_scaling;
+    rect.max *= atlas_scaling;
+    (rect, Some(atlas_scaling))
+}
+
+/// Returns the [`UiImage::rect`] `image_rect` clamped to the `bounds` of its image or atlas frame,
+/// or `None` if no texel of the rect is inside of them.
+pub fn clamp_ui_image_rect(image_rect: Rect, bounds: Vec2) -> Option<Rect> {
+    let clamped = image_rect.intersect(Rect::from_corners(Vec2::ZERO, bounds));
+    (!clamped.is_empty()).then_some(clamped)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn extract_uinode_images(
     mut commands: Commands,
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
     camera_query: Extract<Query<(Entity, &Camera)>>,
     texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+    images: Extract<Res<Assets<Image>>>,
     ui_scale: Extract<Res<UiScale>>,
     default_ui_camera: Extract<DefaultUiCamera>,
     uinode_query: Extract<
@@ -301,19 +691,38 @@ pub fn extract_uinode_images(
                 &GlobalTransform,
                 &ViewVisibility,
                 Option<&CalculatedClip>,
+                Option<&UiClipInflate>,
                 Option<&TargetCamera>,
                 &UiImage,
                 Option<&TextureAtlas>,
                 Option<&Parent>,
                 &Style,
+                Option<&UiColorBlend>,
+                Option<&UiImageLayer>,
+                Option<&UiAntialias>,
+                Option<&UiClipMode>,
             ),
             Without<ImageScaleMode>,
         >,
     >,
     node_query: Extract<Query<&Node>>,
 ) {
-    for (uinode, transform, view_visibility, clip, camera, image, atlas, parent, style) in
-        &uinode_query
+    for (
+        uinode,
+        transform,
+        view_visibility,
+        clip,
+        clip_inflate,
+        camera,
+        image,
+        atlas,
+        parent,
+        style,
+        color_blend,
+        image_layer,
+        antialias,
+        clip_mode,
+    ) in &uinode_query
     {
         let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
         else {
@@ -331,29 +740,19 @@ pub fn extract_uinode_images(
         let atlas_rect = atlas
             .and_then(|s| s.texture_rect(&texture_atlases))
             .map(|r| r.as_rect());
-
-        let mut rect = match (atlas_rect, image.rect) {
-            (None, None) => Rect {
-                min: Vec2::ZERO,
-                max: uinode.calculated_size,
-            },
-            (None, Some(image_rect)) => image_rect,
-            (Some(atlas_rect), None) => atlas_rect,
-            (Some(atlas_rect), Some(mut image_rect)) => {
-                image_rect.min += atlas_rect.min;
-                image_rect.max += atlas_rect.min;
-                image_rect
+        let bounds = atlas_rect
+            .map(|atlas_rect| atlas_rect.size())
+            .or_else(|| images.get(&image.texture).map(Image::size_f32));
+        let image_rect = match (image.rect, bounds) {
+            (Some(image_rect), Some(bounds)) => {
+                let Some(image_rect) = clamp_ui_image_rect(image_rect, bounds) else {
+                    continue;
+                };
+                Some(image_rect)
             }
+            (image_rect, _) => image_rect,
         };
-
-        let atlas_scaling = if atlas_rect.is_some() || image.rect.is_some() {
-            let atlas_scaling = uinode.size() / rect.size();
-            rect.min *= atlas_scaling;
-            rect.max *= atlas_scaling;
-            Some(atlas_scaling)
-        } else {
-            None
-        };
+        let (rect, atlas_scaling) = ui_image_rect(uinode.size(), atlas_rect, image_rect);
 
         let ui_logical_viewport_size = camera_query
             .get(camera_entity)
@@ -395,7 +794,8 @@ pub fn extract_uinode_images(
                 transform: transform.compute_matrix(),
                 color: image.color.into(),
                 rect,
-                clip: clip.map(|clip| clip.clip),
+                clip: inflated_clip(clip, clip_inflate),
+                clip_mode: clip_mode.copied().unwrap_or_default(),
                 image: image.texture.id(),
                 atlas_scaling,
                 flip_x: image.flip_x,
@@ -403,7 +803,145 @@ pub fn extract_uinode_images(
                 camera_entity,
                 border,
                 border_radius,
-                node_type: NodeType::Rect,
+                color_blend: color_blend.copied().unwrap_or_default(),
+                image_layer: image_layer.map(|image_layer| image_layer.0),
+                sampler: image.sampler.as_ref().map(UiSamplerKey::from),
+                antialias: antialias.copied().unwrap_or_default(),
+                premultiplied_alpha: image.premultiplied_alpha,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Returns the clip of a node, grown on every side by its [`UiClipInflate`].
+pub(crate) fn inflated_clip(
+    clip: Option<&CalculatedClip>,
+    clip_inflate: Option<&UiClipInflate>,
+) -> Option<Rect> {
+    clip.map(|clip| match clip_inflate {
+        Some(clip_inflate) => clip.clip.inflate(clip_inflate.0),
+        None => clip.clip,
+    })
+}
+
+/// Returns the bounds of the quad drawn for a box shadow, in the same space as `node_center`.
+///
+/// The shadow's shape is the node grown by `spread`, moved by `offset`. The quad is further grown
+/// by `blur` on every side so the blurred edge fits inside it.
+pub(crate) fn box_shadow_rect(
+    node_center: Vec2,
+    node_size: Vec2,
+    offset: Vec2,
+    spread: f32,
+    blur: f32,
+) -> Rect {
+    let shape_size = (node_size + 2. * spread).max(Vec2::ZERO);
+    Rect::from_center_size(node_center + offset, shape_size + 2. * blur)
+}
+
+/// Returns the clip a box shadow is drawn with.
+pub(crate) fn box_shadow_clip(
+    box_shadow: &BoxShadow,
+    node_clip: Option<&CalculatedClip>,
+    clip_inflate: Option<&UiClipInflate>,
+) -> Option<Rect> {
+    if box_shadow.escape_clip {
+        None
+    } else {
+        inflated_clip(node_clip, clip_inflate)
+    }
+}
+
+pub fn extract_uinode_box_shadows(
+    mut commands: Commands,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    camera_query: Extract<Query<(Entity, &Camera)>>,
+    default_ui_camera: Extract<DefaultUiCamera>,
+    ui_scale: Extract<Res<UiScale>>,
+    uinode_query: Extract<
+        Query<(
+            &Node,
+            &GlobalTransform,
+            &ViewVisibility,
+            Option<&CalculatedClip>,
+            Option<&UiClipInflate>,
+            Option<&TargetCamera>,
+            &BoxShadow,
+            Option<&UiAntialias>,
+            Option<&UiClipMode>,
+        )>,
+    >,
+) {
+    for (
+        uinode,
+        transform,
+        view_visibility,
+        clip,
+        clip_inflate,
+        camera,
+        box_shadow,
+        antialias,
+        clip_mode,
+    ) in &uinode_query
+    {
+        let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
+        else {
+            continue;
+        };
+
+        // Skip invisible shadows
+        if !view_visibility.get() || box_shadow.color.is_fully_transparent() || uinode.is_empty() {
+            continue;
+        }
+
+        let ui_logical_viewport_size = camera_query
+            .get(camera_entity)
+            .ok()
+            .and_then(|(_, c)| c.logical_viewport_size())
+            .unwrap_or(Vec2::ZERO)
+            // The logical window resolution returned by `Window` only takes into account the window scale factor and not `UiScale`,
+            // so we have to divide by `UiScale` to get the size of the UI viewport.
+            / ui_scale.0;
+
+        let resolve = |value: Val| {
+            value
+                .resolve(uinode.size().x, ui_logical_viewport_size)
+                .unwrap_or(0.)
+        };
+        let offset = Vec2::new(resolve(box_shadow.x_offset), resolve(box_shadow.y_offset));
+        let spread = resolve(box_shadow.spread_radius);
+        let blur = resolve(box_shadow.blur_radius).max(0.);
+
+        let shadow_rect = box_shadow_rect(Vec2::ZERO, uinode.size(), offset, spread, blur);
+
+        let border_radius = [
+            uinode.border_radius.top_left,
+            uinode.border_radius.top_right,
+            uinode.border_radius.bottom_right,
+            uinode.border_radius.bottom_left,
+        ]
+        .map(|radius| if radius > 0. { radius + spread } else { 0. });
+
+        extracted_uinodes.uinodes.insert(
+            commands.spawn_empty().id(),
+            ExtractedUiNode {
+                stack_index: uinode.stack_index,
+                transform: transform.compute_matrix()
+                    * Mat4::from_translation(shadow_rect.center().extend(0.)),
+                color: box_shadow.color.into(),
+                rect: Rect {
+                    min: Vec2::ZERO,
+                    max: shadow_rect.size(),
+                },
+                clip: box_shadow_clip(box_shadow, clip, clip_inflate),
+                clip_mode: clip_mode.copied().unwrap_or_default(),
+                camera_entity,
+                border: [blur, 0., 0., 0.],
+                border_radius,
+                node_type: NodeType::BoxShadow,
+                antialias: antialias.copied().unwrap_or_default(),
+                ..Default::default()
             },
         );
     }
@@ -455,10 +993,14 @@ pub fn extract_uinode_borders(
             &GlobalTransform,
             &ViewVisibility,
             Option<&CalculatedClip>,
+            Option<&UiClipInflate>,
             Option<&TargetCamera>,
             Option<&Parent>,
             &Style,
             AnyOf<(&BorderColor, &Outline)>,
+            Option<&BorderSideColors>,
+            Option<&UiAntialias>,
+            Option<&UiClipMode>,
         )>,
     >,
     node_query: Extract<Query<&Node>>,
@@ -470,10 +1012,14 @@ pub fn extract_uinode_borders(
         global_transform,
         view_visibility,
         maybe_clip,
+        maybe_clip_inflate,
         maybe_camera,
         maybe_parent,
         style,
         (maybe_border_color, maybe_outline),
+        maybe_border_side_colors,
+        maybe_antialias,
+        maybe_clip_mode,
     ) in &uinode_query
     {
         let Some(camera_entity) = maybe_camera
@@ -483,10 +1029,22 @@ pub fn extract_uinode_borders(
             continue;
         };
 
+        let antialias = maybe_antialias.copied().unwrap_or_default();
+        let clip_mode = maybe_clip_mode.copied().unwrap_or_default();
+        let border_is_transparent = match maybe_border_side_colors {
+            Some(side_colors) => side_colors
+                .to_array()
+                .iter()
+                .all(Alpha::is_fully_transparent),
+            None => {
+                maybe_border_color.is_some_and(|border_color| border_color.0.is_fully_transparent())
+            }
+        };
+
         // Skip invisible borders
         if !view_visibility.get()
             || style.display == Display::None
-            || maybe_border_color.is_some_and(|border_color| border_color.0.is_fully_transparent())
+            || border_is_transparent
                 && maybe_outline.is_some_and(|outline| outline.color.is_fully_transparent())
         {
             continue;
@@ -530,27 +1088,47 @@ pub fn extract_uinode_borders(
         // don't extract border if no border or the node is zero-sized (a zero sized node can still have an outline).
         if !uinode.is_empty() && border != [0.; 4] {
             if let Some(border_color) = maybe_border_color {
-                extracted_uinodes.uinodes.insert(
-                    commands.spawn_empty().id(),
-                    ExtractedUiNode {
-                        stack_index: uinode.stack_index,
-                        transform: global_transform.compute_matrix(),
-                        color: border_color.0.into(),
-                        rect: Rect {
-                            max: uinode.size(),
-                            ..Default::default()
-                        },
-                        image,
-                        atlas_scaling: None,
-                        clip: maybe_clip.map(|clip| clip.clip),
-                        flip_x: false,
-                        flip_y: false,
-                        camera_entity,
-                        border_radius,
-                        border,
-                        node_type: NodeType::Border,
+                let border_node = ExtractedUiNode {
+                    stack_index: uinode.stack_index,
+                    transform: global_transform.compute_matrix(),
+                    color: border_color.0.into(),
+                    rect: Rect {
+                        max: uinode.size(),
+                        ..Default::default()
                     },
-                );
+                    image,
+                    clip: inflated_clip(maybe_clip, maybe_clip_inflate),
+                    clip_mode,
+                    camera_entity,
+                    border_radius,
+                    border,
+                    node_type: NodeType::Border,
+                    antialias,
+                    ..Default::default()
+                };
+                match maybe_border_side_colors {
+                    // Each side is drawn by a node of its own color, which only covers its side
+                    Some(side_colors) => {
+                        for (side, color) in side_colors.to_array().into_iter().enumerate() {
+                            if color.is_fully_transparent() || border[side] <= 0. {
+                                continue;
+                            }
+                            extracted_uinodes.uinodes.insert(
+                                commands.spawn_empty().id(),
+                                ExtractedUiNode {
+                                    color: color.into(),
+                                    border_side: Some(side as u32),
+                                    ..border_node
+                                },
+                            );
+                        }
+                    }
+                    None => {
+                        extracted_uinodes
+                            .uinodes
+                            .insert(commands.spawn_empty().id(), border_node);
+                    }
+                }
             }
         }
 
@@ -575,14 +1153,14 @@ pub fn extract_uinode_borders(
                         ..Default::default()
                     },
                     image,
-                    atlas_scaling: None,
-                    clip: maybe_clip.map(|clip| clip.clip),
-                    flip_x: false,
-                    flip_y: false,
+                    clip: inflated_clip(maybe_clip, maybe_clip_inflate),
+                    clip_mode,
                     camera_entity,
                     border: [uinode.outline_width(); 4],
                     border_radius: outline_radius,
                     node_type: NodeType::Border,
+                    antialias,
+                    ..Default::default()
                 },
             );
         }
@@ -593,83 +1171,273 @@ pub fn extract_uinode_borders(
 /// distance of this many units. This ensures that with a left-handed projection,
 /// as ui elements are "stacked on top of each other", they are within the camera's view
 /// and have room to grow.
-// TODO: Consider computing this value at runtime based on the maximum z-value.
+///
+/// This is the minimum: stacks too deep to fit get a further plane, see [`ui_camera_far`].
 const UI_CAMERA_FAR: f32 = 1000.0;
 
-// This value is subtracted from the far distance for the camera's z-position to ensure nodes at z == 0.0 are rendered
-// TODO: Evaluate if we still need this.
+// This value is subtracted from the far distance for the camera's z-position to ensure nodes at z == 0.0 are rendered.
+// The bottom node of the stack is offset by a `stack_z` of 0.0, without this it would lie on the far plane and be
+// clipped by the `Less` depth tests of the UI depth textures.
 const UI_CAMERA_TRANSFORM_OFFSET: f32 = -0.1;
 
+/// The distance along the z-axis between two consecutive entries of the [`UiStack`](crate::UiStack).
+///
+/// This is used to give nodes distinct depths when they are drawn into a depth attachment,
+/// such as the one written by the UI prepass.
+pub const UI_STACK_Z_STEP: f32 = 0.001;
+
+/// Returns the z offset of a node at the given stack index.
+///
+/// Nodes with a higher stack index are closer to the UI camera.
+#[inline]
+pub(crate) fn stack_z(stack_index: u32) -> f32 {
+    stack_index as f32 * UI_STACK_Z_STEP
+}
+
+/// Returns the far plane of the UI camera for a [`UiStack`](crate::UiStack) of `stack_len` nodes.
+///
+/// This is [`UI_CAMERA_FAR`] unless the topmost node would be offset past it by [`stack_z`], in
+/// which case the plane is moved back so every node of the stack stays within the camera's view.
+pub fn ui_camera_far(stack_len: usize) -> f32 {
+    let top_z = stack_z(stack_len.min(u32::MAX as usize) as u32);
+    UI_CAMERA_FAR.max(top_z - UI_CAMERA_TRANSFORM_OFFSET + 1.0)
+}
+
+/// Returns the winding of front-facing UI triangles drawn with `view`.
+///
+/// UI triangles are wound counter-clockwise in UI coordinates, with y pointing down. A projection
+/// flipping a single axis mirrors that winding, as the default UI projection does to put the
+/// origin at the top left, while one rendering to a target with a flipped y axis doesn't. Deriving
+/// the front face from the projection keeps UI geometry front-facing when culling is enabled.
+pub fn ui_front_face(view: &ExtractedView) -> FrontFace {
+    let clip_from_world = view
+        .clip_from_world
+        .unwrap_or_else(|| view.clip_from_view * view.world_from_view.compute_matrix().inverse());
+    let xy_determinant = clip_from_world.x_axis.x * clip_from_world.y_axis.y
+        - clip_from_world.y_axis.x * clip_from_world.x_axis.y;
+    if xy_determinant < 0. {
+        FrontFace::Cw
+    } else {
+        FrontFace::Ccw
+    }
+}
+
+/// Returns the number of physical pixels per logical UI pixel of the nodes rendered by `camera`,
+/// the scale factor of its render target times the [`UiScale`].
+///
+/// Each camera uses the scale factor of the window it renders to, so nodes of windows on monitors
+/// of different DPI are rasterized at their own resolution. Texture targets have a scale factor
+/// of 1, which is also used while the target of the camera isn't known yet.
+pub fn ui_target_scale_factor(camera: Option<&Camera>, ui_scale: &UiScale) -> f32 {
+    camera
+        .and_then(Camera::target_scaling_factor)
+        .unwrap_or(1.0)
+        * ui_scale.0
+}
+
+/// Returns the scissor rect keeping the pixels of `view` inside the UI `clip` rect, in physical
+/// pixels of the render target, or `None` if no pixel is inside it.
+///
+/// Pixels are kept if their center is inside the clip, and the rect never extends beyond the
+/// viewport of the view.
+pub fn ui_scissor_rect(clip: Rect, view: &ExtractedView) -> Option<URect> {
+    let clip_from_world = view
+        .clip_from_world
+        .unwrap_or_else(|| view.clip_from_view * view.world_from_view.compute_matrix().inverse());
+    let origin = view.viewport.xy().as_vec2();
+    let size = view.viewport.zw().as_vec2();
+    let [a, b] = [clip.min, clip.max].map(|corner| {
+        let ndc = clip_from_world.project_point3(corner.extend(0.)).truncate();
+        origin + (ndc * Vec2::new(0.5, -0.5) + 0.5) * size
+    });
+    let rect = Rect::from_corners(a, b).intersect(Rect::from_corners(origin, origin + size));
+    let min = rect.min.round().as_uvec2();
+    let max = rect.max.round().as_uvec2();
+    (max.cmpgt(min).all()).then(|| URect::from_corners(min, max))
+}
+
+/// Returns the view UI nodes targeting `camera` are drawn with, see [`DefaultCameraView`].
+pub(crate) fn ui_camera_view<'a>(
+    camera: Entity,
+    views: &'a Query<(Entity, &ExtractedView)>,
+    default_camera_views: &Query<&DefaultCameraView>,
+) -> Option<&'a ExtractedView> {
+    let view_entity = default_camera_views
+        .get(camera)
+        .map_or(camera, |default_camera_view| default_camera_view.0);
+    views.get(view_entity).ok().map(|(_, view)| view)
+}
+
+/// Returns the [`ui_front_face`] of the view each camera in `views` draws its UI with, see
+/// [`ui_camera_view`].
+pub(crate) fn ui_camera_front_faces(
+    views: &Query<(Entity, &ExtractedView)>,
+    default_camera_views: &Query<&DefaultCameraView>,
+) -> EntityHashMap<FrontFace> {
+    views
+        .iter()
+        .filter_map(|(camera, _)| {
+            let ui_view = ui_camera_view(camera, views, default_camera_views)?;
+            Some((camera, ui_front_face(ui_view)))
+        })
+        .collect()
+}
+
+/// The depth format used by the UI prepass.
+pub const UI_PREPASS_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// The textures written by the UI prepass of a view.
+///
+/// Only present on views with at least one node whose material has
+/// [`UiMaterial::prepass_enabled`](crate::UiMaterial::prepass_enabled) set.
+#[derive(Component)]
+pub struct ViewUiPrepassTextures {
+    /// Depth of the opted-in nodes, cleared to `1.0` (the far plane) every frame.
+    pub depth: CachedTexture,
+}
+
+/// The view the UI of a camera is drawn with, spawned by [`extract_default_ui_camera_view`] for
+/// every active 2D and 3D camera.
+///
+/// Each camera gets its own view, projecting the UI laid out for the camera's viewport onto that
+/// viewport, so cameras side by side in split-screen each draw their own UI. Nodes are only
+/// drawn by the view of their [`TargetCamera`](crate::TargetCamera), or of the
+/// [`DefaultUiCamera`] for nodes without one, so each viewport can show a different UI.
 #[derive(Component)]
 pub struct DefaultCameraView(pub Entity);
 
+/// Returns the view drawing the UI to the `physical_rect` of the render target, laid out in a
+/// viewport of `logical_size` scaled by the [`UiScale`].
+///
+/// The UI is projected with its origin at the top left of the viewport, whatever the position of
+/// the viewport in the render target.
+pub fn ui_default_camera_view(
+    physical_rect: URect,
+    logical_size: Vec2,
+    far: f32,
+    hdr: bool,
+) -> ExtractedView {
+    // use a projection matrix with the origin in the top left instead of the bottom left that comes with OrthographicProjection
+    let projection_matrix =
+        Mat4::orthographic_rh(0.0, logical_size.x, logical_size.y, 0.0, 0.0, far);
+    ExtractedView {
+        clip_from_view: projection_matrix,
+        world_from_view: GlobalTransform::from_xyz(0.0, 0.0, far + UI_CAMERA_TRANSFORM_OFFSET),
+        clip_from_world: None,
+        hdr,
+        viewport: UVec4::new(
+            physical_rect.min.x,
+            physical_rect.min.y,
+            physical_rect.width(),
+            physical_rect.height(),
+        ),
+        color_grading: Default::default(),
+    }
+}
+
+/// The [`UiNativeViewport`] of a camera, which the UI passes use in place of the camera's viewport.
+#[derive(Component, Clone, Debug)]
+pub struct ExtractedUiViewport(pub Viewport);
+
 /// Extracts all UI elements associated with a camera into the render world.
 pub fn extract_default_ui_camera_view(
     mut commands: Commands,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
+    mut opaque_render_phases: ResMut<ViewSortedRenderPhases<OpaqueUi>>,
+    mut prepass_render_phases: ResMut<ViewSortedRenderPhases<UiPrepass>>,
     ui_scale: Extract<Res<UiScale>>,
-    query: Extract<Query<(Entity, &Camera), Or<(With<Camera2d>, With<Camera3d>)>>>,
+    ui_stack: Extract<Res<UiStack>>,
+    query: Extract<
+        Query<(Entity, &Camera, Option<&UiNativeViewport>), Or<(With<Camera2d>, With<Camera3d>)>>,
+    >,
     mut live_entities: Local<EntityHashSet>,
 ) {
     live_entities.clear();
 
     let scale = ui_scale.0.recip();
-    for (entity, camera) in &query {
+    let far = ui_camera_far(ui_stack.uinodes.len());
+    for (entity, camera, native_viewport) in &query {
         // ignore inactive cameras
         if !camera.is_active {
             continue;
         }
 
-        if let (
-            Some(logical_size),
-            Some(URect {
-                min: physical_origin,
-                ..
-            }),
-            Some(physical_size),
-        ) = (
-            camera.logical_viewport_size(),
-            camera.physical_viewport_rect(),
-            camera.physical_viewport_size(),
-        ) {
-            // use a projection matrix with the origin in the top left instead of the bottom left that comes with OrthographicProjection
-            let projection_matrix = Mat4::orthographic_rh(
-                0.0,
-                logical_size.x * scale,
-                logical_size.y * scale,
-                0.0,
-                0.0,
-                UI_CAMERA_FAR,
-            );
+        // With dynamic resolution the UI keeps the native viewport, not the scene's scaled one
+        if let Some((physical_rect, logical_size)) = ui_viewport(camera, native_viewport) {
             let default_camera_view = commands
-                .spawn(ExtractedView {
-                    clip_from_view: projection_matrix,
-                    world_from_view: GlobalTransform::from_xyz(
-                        0.0,
-                        0.0,
-                        UI_CAMERA_FAR + UI_CAMERA_TRANSFORM_OFFSET,
-                    ),
-                    clip_from_world: None,
-                    hdr: camera.hdr,
-                    viewport: UVec4::new(
-                        physical_origin.x,
-                        physical_origin.y,
-                        physical_size.x,
-                        physical_size.y,
-                    ),
-                    color_grading: Default::default(),
-                })
+                .spawn((
+                    ui_default_camera_view(physical_rect, logical_size * scale, far, camera.hdr),
+                    UiSceneView(entity),
+                ))
                 .id();
-            commands
-                .get_or_spawn(entity)
-                .insert(DefaultCameraView(default_camera_view));
+            let mut camera_commands = commands.get_or_spawn(entity);
+            camera_commands.insert(DefaultCameraView(default_camera_view));
+            if let Some(UiNativeViewport(viewport)) = native_viewport {
+                camera_commands.insert(ExtractedUiViewport(viewport.clone()));
+            }
             transparent_render_phases.insert_or_clear(entity);
+            opaque_render_phases.insert_or_clear(entity);
+            prepass_render_phases.insert_or_clear(entity);
 
             live_entities.insert(entity);
         }
     }
 
     transparent_render_phases.retain(|entity, _| live_entities.contains(entity));
+    opaque_render_phases.retain(|entity, _| live_entities.contains(entity));
+    prepass_render_phases.retain(|entity, _| live_entities.contains(entity));
+}
+
+/// Allocates the [`ViewUiPrepassTextures`] for views that have items in their [`UiPrepass`] phase.
+pub fn prepare_ui_prepass_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    prepass_render_phases: Res<ViewSortedRenderPhases<UiPrepass>>,
+    views: Query<(Entity, &ExtractedCamera)>,
+) {
+    for (entity, camera) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let Some(descriptor) =
+            ui_prepass_depth_descriptor(physical_target_size, prepass_render_phases.get(&entity))
+        else {
+            commands.entity(entity).remove::<ViewUiPrepassTextures>();
+            continue;
+        };
+
+        let depth = texture_cache.get(&render_device, descriptor);
+        commands
+            .entity(entity)
+            .insert(ViewUiPrepassTextures { depth });
+    }
+}
+
+/// Returns the descriptor of the depth texture of the [`ViewUiPrepassTextures`] of a view with a
+/// target of `physical_target_size`, or `None` if its `prepass_phase` has no items.
+pub fn ui_prepass_depth_descriptor(
+    physical_target_size: UVec2,
+    prepass_phase: Option<&SortedRenderPhase<UiPrepass>>,
+) -> Option<TextureDescriptor<'static>> {
+    if prepass_phase.map_or(true, |phase| phase.items.is_empty()) {
+        return None;
+    }
+    Some(TextureDescriptor {
+        label: Some("ui_prepass_depth_texture"),
+        size: Extent3d {
+            width: physical_target_size.x,
+            height: physical_target_size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: UI_PREPASS_DEPTH_FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
 }
 
 #[cfg(feature = "bevy_text")]
@@ -681,19 +1449,37 @@ pub fn extract_uinode_text(
     texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
     ui_scale: Extract<Res<UiScale>>,
     uinode_query: Extract<
-        Query<(
-            &Node,
-            &GlobalTransform,
-            &ViewVisibility,
-            Option<&CalculatedClip>,
-            Option<&TargetCamera>,
-            &Text,
-            &TextLayoutInfo,
-        )>,
+        Query<
+            (
+                &Node,
+                &GlobalTransform,
+                &ViewVisibility,
+                Option<&CalculatedClip>,
+                Option<&TargetCamera>,
+                &Text,
+                &TextLayoutInfo,
+                Option<&TextSectionOpacity>,
+                Option<&TextOverflowFade>,
+                Option<&TextFlip>,
+                (Option<&UiClipInflate>, Option<&UiClipMode>),
+            ),
+            Without<UiMaterialText>,
+        >,
     >,
 ) {
-    for (uinode, global_transform, view_visibility, clip, camera, text, text_layout_info) in
-        &uinode_query
+    for (
+        uinode,
+        global_transform,
+        view_visibility,
+        clip,
+        camera,
+        text,
+        text_layout_info,
+        section_opacity,
+        overflow_fade,
+        flip,
+        (clip_inflate, clip_mode),
+    ) in &uinode_query
     {
         let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
         else {
@@ -705,12 +1491,10 @@ pub fn extract_uinode_text(
             continue;
         }
 
-        let scale_factor = camera_query
-            .get(camera_entity)
-            .ok()
-            .and_then(|(_, c)| c.target_scaling_factor())
-            .unwrap_or(1.0)
-            * ui_scale.0;
+        let scale_factor = ui_target_scale_factor(
+            camera_query.get(camera_entity).ok().map(|(_, c)| c),
+            &ui_scale,
+        );
         let inverse_scale_factor = scale_factor.recip();
 
         // Align the text to the nearest physical pixel:
@@ -729,17 +1513,36 @@ pub fn extract_uinode_text(
         transform.translation = transform.translation.round();
         transform.translation *= inverse_scale_factor;
 
+        // The offset of a glyph from the top left corner of the node, mirrored by its `TextFlip`
+        let flip = flip.copied().unwrap_or_default();
+        let glyph_offset = |position: Vec2| flip.mirror(position, uinode.size());
+
+        let clip = inflated_clip(clip, clip_inflate);
+        let overflow_fade = overflow_fade.zip(clip).map(|(overflow_fade, clip)| {
+            let text_size = text_layout_info.size * inverse_scale_factor;
+            let text_bounds = Rect::from_corners(
+                transform
+                    .transform_point3(glyph_offset(Vec2::ZERO).extend(0.))
+                    .truncate(),
+                transform
+                    .transform_point3(glyph_offset(text_size).extend(0.))
+                    .truncate(),
+            );
+            (overflow_fade, text_bounds, clip)
+        });
+
         let mut color = LinearRgba::WHITE;
         let mut current_section = usize::MAX;
-        for PositionedGlyph {
-            position,
-            atlas_info,
-            section_index,
-            ..
-        } in &text_layout_info.glyphs
-        {
+        for glyph_index in text_glyph_draw_order(&text_layout_info.glyphs) {
+            let PositionedGlyph {
+                position,
+                atlas_info,
+                section_index,
+                ..
+            } = &text_layout_info.glyphs[glyph_index];
+
             if *section_index != current_section {
-                color = LinearRgba::from(text.sections[*section_index].style.color);
+                color = text_section_color(text, *section_index, section_opacity);
                 current_section = *section_index;
             }
             let atlas = texture_atlases.get(&atlas_info.texture_atlas).unwrap();
@@ -747,32 +1550,86 @@ pub fn extract_uinode_text(
             let mut rect = atlas.textures[atlas_info.location.glyph_index].as_rect();
             rect.min *= inverse_scale_factor;
             rect.max *= inverse_scale_factor;
+            let glyph_transform = transform
+                * Mat4::from_translation(glyph_offset(*position * inverse_scale_factor).extend(0.));
+
+            let mut glyph_color = color;
+            if let Some((overflow_fade, text_bounds, clip)) = overflow_fade {
+                let glyph_center = glyph_transform.transform_point3(Vec3::ZERO).truncate();
+                glyph_color.alpha *= overflow_fade.alpha(glyph_center, text_bounds, clip);
+            }
+
             extracted_uinodes.uinodes.insert(
                 commands.spawn_empty().id(),
                 ExtractedUiNode {
                     stack_index: uinode.stack_index,
-                    transform: transform
-                        * Mat4::from_translation(position.extend(0.) * inverse_scale_factor),
-                    color,
+                    transform: glyph_transform,
+                    color: glyph_color,
                     rect,
                     image: atlas_info.texture.id(),
                     atlas_scaling: Some(Vec2::splat(inverse_scale_factor)),
-                    clip: clip.map(|clip| clip.clip),
-                    flip_x: false,
-                    flip_y: false,
+                    clip,
+                    clip_mode: clip_mode.copied().unwrap_or_default(),
+                    flip_x: flip.flip_x,
+                    flip_y: flip.flip_y,
                     camera_entity,
-                    border: [0.; 4],
-                    border_radius: [0.; 4],
-                    node_type: NodeType::Rect,
+                    ..Default::default()
                 },
             );
         }
     }
 }
 
+/// Returns the indices of `glyphs` grouped by atlas texture, in the order each atlas first appears.
+///
+/// Glyphs of different fonts, sizes or variable font instances are rasterized into different
+/// atlases. The glyphs of a text node don't overlap, so grouping them lets each atlas be drawn in a
+/// single batch instead of switching textures at every run of glyphs.
+///
+/// Glyphs are no longer drawn in layout order when a text node mixes atlases: every glyph of the
+/// first atlas is drawn before the glyphs of the next one. Glyphs whose quads overlap, e.g. italic
+/// or kerned glyphs of different fonts next to each other, may blend in a different order than the
+/// one they're laid out in.
+#[cfg(feature = "bevy_text")]
+pub(crate) fn text_glyph_draw_order(glyphs: &[PositionedGlyph]) -> Vec<usize> {
+    let mut atlases: Vec<AssetId<Image>> = Vec::new();
+    let mut order: Vec<(usize, usize)> = glyphs
+        .iter()
+        .enumerate()
+        .map(|(index, glyph)| {
+            let texture = glyph.atlas_info.texture.id();
+            let atlas = atlases
+                .iter()
+                .position(|atlas| *atlas == texture)
+                .unwrap_or_else(|| {
+                    atlases.push(texture);
+                    atlases.len() - 1
+                });
+            (atlas, index)
+        })
+        .collect();
+    order.sort_unstable();
+    order.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Returns the color of a text section, with its [`TextSectionOpacity`] applied.
+#[cfg(feature = "bevy_text")]
+pub(crate) fn text_section_color(
+    text: &Text,
+    section_index: usize,
+    section_opacity: Option<&TextSectionOpacity>,
+) -> LinearRgba {
+    let color = LinearRgba::from(text.sections[section_index].style.color);
+    match section_opacity {
+        Some(section_opacity) => color.with_alpha(color.alpha * section_opacity.get(section_index)),
+        None => color,
+    }
+}
+
+/// A vertex of the UI geometry, as read by `ui.wgsl`.
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-struct UiVertex {
+pub struct UiVertex {
     pub position: [f32; 3],
     pub uv: [f32; 2],
     pub color: [f32; 4],
@@ -787,12 +1644,18 @@ struct UiVertex {
     pub border: [f32; 4],
     /// Size of the UI node.
     pub size: [f32; 2],
+    /// Position relative to the center of the node, used for its rounded corners and borders.
+    pub point: [f32; 2],
+    /// The array layer sampled by nodes drawn from a texture array, see
+    /// [`UiImageArrayBatching`].
+    pub layer: u32,
 }
 
 #[derive(Resource)]
 pub struct UiMeta {
     vertices: RawBufferVec<UiVertex>,
     indices: RawBufferVec<u32>,
+    capacity: UiBufferCapacity,
     view_bind_group: Option<BindGroup>,
 }
 
@@ -801,11 +1664,66 @@ impl Default for UiMeta {
         Self {
             vertices: RawBufferVec::new(BufferUsages::VERTEX),
             indices: RawBufferVec::new(BufferUsages::INDEX),
+            capacity: UiBufferCapacity::default(),
             view_bind_group: None,
         }
     }
 }
 
+impl UiMeta {
+    /// The vertices of the UI written by [`prepare_uinodes`] this frame.
+    pub fn vertices(&self) -> &[UiVertex] {
+        self.vertices.values()
+    }
+
+    /// The indices of the UI triangles written by [`prepare_uinodes`] this frame, three per
+    /// triangle.
+    pub fn indices(&self) -> &[u32] {
+        self.indices.values()
+    }
+
+    /// The capacity of the vertex and index buffers, see [`UiBufferCapacity`].
+    pub fn capacity(&self) -> UiBufferCapacity {
+        self.capacity
+    }
+
+    /// Uploads the vertices and indices written this frame, growing the buffers to the
+    /// [`UiBufferCapacity`] first.
+    fn write_buffers(&mut self, render_device: &RenderDevice, render_queue: &RenderQueue) {
+        self.vertices.reserve(self.capacity.vertices, render_device);
+        self.indices.reserve(self.capacity.indices, render_device);
+        self.vertices.write_buffer(render_device, render_queue);
+        self.indices.write_buffer(render_device, render_queue);
+    }
+}
+
+/// The number of vertices and indices the UI vertex and index buffers are allocated for.
+///
+/// The buffers grow to hold the most vertices and indices written in a frame so far, rounded up
+/// to a power of two, and never shrink. A UI growing for a few frames, e.g. while a large menu is
+/// open, doesn't reallocate them every time it grows back. Reported by
+/// [`UiBatchDiagnostics::buffer_capacity`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UiBufferCapacity {
+    pub vertices: usize,
+    pub indices: usize,
+}
+
+impl UiBufferCapacity {
+    /// Grows the capacity to hold `vertices` and `indices`, if it doesn't already.
+    pub fn grow(&mut self, vertices: usize, indices: usize) {
+        let grow = |capacity: usize, len: usize| {
+            if len > capacity {
+                len.next_power_of_two()
+            } else {
+                capacity
+            }
+        };
+        self.vertices = grow(self.vertices, vertices);
+        self.indices = grow(self.indices, indices);
+    }
+}
+
 pub(crate) const QUAD_VERTEX_POSITIONS: [Vec3; 4] = [
     Vec3::new(-0.5, -0.5, 0.0),
     Vec3::new(0.5, -0.5, 0.0),
@@ -815,20 +1733,208 @@ pub(crate) const QUAD_VERTEX_POSITIONS: [Vec3; 4] = [
 
 pub(crate) const QUAD_INDICES: [usize; 6] = [0, 2, 3, 0, 1, 2];
 
-#[derive(Component)]
+/// The most grid cells a node's quad is split into along each axis, see [`UiGradientTessellation`].
+pub const MAX_UI_QUAD_SUBDIVISIONS: u32 = 64;
+
+/// Returns the number of grid cells along each axis for a node of `size` split into cells no
+/// larger than `max_cell_size`.
+pub fn ui_gradient_subdivisions(size: Vec2, max_cell_size: f32) -> UVec2 {
+    (size / max_cell_size.max(1.))
+        .ceil()
+        .as_uvec2()
+        .clamp(UVec2::ONE, UVec2::splat(MAX_UI_QUAD_SUBDIVISIONS))
+}
+
+/// Returns the number of vertices of a quad split into a grid of `subdivisions` cells.
+pub(crate) fn ui_quad_grid_vertex_count(subdivisions: UVec2) -> usize {
+    let subdivisions = subdivisions.max(UVec2::ONE);
+    ((subdivisions.x + 1) * (subdivisions.y + 1)) as usize
+}
+
+/// Returns the vertices of a quad split into a grid of `subdivisions` cells, as coordinates from
+/// `(0, 0)` at the top left corner to `(1, 1)` at the bottom right, row by row.
+pub(crate) fn ui_quad_grid(subdivisions: UVec2) -> impl Iterator<Item = Vec2> {
+    let cells = subdivisions.as_vec2();
+    (0..=subdivisions.y)
+        .flat_map(move |y| (0..=subdivisions.x).map(move |x| UVec2::new(x, y).as_vec2() / cells))
+}
+
+/// Returns the corner of the quad, in the order of [`QUAD_VERTEX_POSITIONS`], that the grid vertex
+/// at `index` lies on, if any.
+pub(crate) fn ui_quad_grid_corner(subdivisions: UVec2, index: usize) -> Option<usize> {
+    let columns = subdivisions.x as usize + 1;
+    let (x, y) = (index % columns, index / columns);
+    let (left, right) = (x == 0, x == subdivisions.x as usize);
+    let (top, bottom) = (y == 0, y == subdivisions.y as usize);
+    match (left, right, top, bottom) {
+        (true, _, true, _) => Some(0),
+        (_, true, true, _) => Some(1),
+        (_, true, _, true) => Some(2),
+        (true, _, _, true) => Some(3),
+        _ => None,
+    }
+}
+
+/// Returns the indices of the triangles of a quad split into a grid of `subdivisions` cells,
+/// relative to its first vertex and with the winding of [`QUAD_INDICES`].
+pub(crate) fn ui_quad_grid_indices(subdivisions: UVec2) -> impl Iterator<Item = u32> {
+    let columns = subdivisions.x + 1;
+    (0..subdivisions.y).flat_map(move |y| {
+        (0..subdivisions.x).flat_map(move |x| {
+            let top_left = y * columns + x;
+            let corners = [
+                top_left,
+                top_left + 1,
+                top_left + columns + 1,
+                top_left + columns,
+            ];
+            QUAD_INDICES.map(|i| corners[i])
+        })
+    })
+}
+
+/// Bilinearly interpolates between the corners of a quad, ordered like [`QUAD_VERTEX_POSITIONS`].
+fn bilerp<T>(corners: [T; 4], st: Vec2) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let top = corners[0] * (1. - st.x) + corners[1] * st.x;
+    let bottom = corners[3] * (1. - st.x) + corners[2] * st.x;
+    top * (1. - st.y) + bottom * st.y
+}
+
+#[derive(Component, Clone)]
 pub struct UiBatch {
     pub range: Range<u32>,
     pub image: AssetId<Image>,
+    /// The array layer of `image` bound for this batch, see [`UiImageLayer`].
+    pub image_layer: Option<u32>,
+    /// The sampler of `image` bound for this batch, see [`UiImage::sampler`].
+    pub sampler: Option<UiSamplerKey>,
     pub camera: Entity,
+    /// The clip of the nodes of this batch, applied with a scissor rect when it's drawn, see
+    /// [`ExtractedUiNode::scissor_clip`].
+    pub clip: Option<Rect>,
+    /// The stencil test of the nodes of this batch, its reference is set when it's drawn.
+    pub stencil: UiStencil,
+    /// Whether the nodes of this batch are blended as premultiplied alpha, see
+    /// [`UiImage::premultiplied_alpha`].
+    pub premultiplied_alpha: bool,
+    /// Whether `image` is bound as a texture array, each node sampling the layer of its vertices
+    /// instead of `image_layer`, see [`UiImageArrayBatching`].
+    pub image_array: bool,
+}
+
+impl UiBatch {
+    /// Returns whether `extracted_uinode` can be drawn in this batch: it's drawn to the same
+    /// camera with the same scissor rect, stencil test and blending, and either of them is
+    /// untextured or they share the same image, array layer and sampler.
+    ///
+    /// `image_array` is whether the node is drawn from a texture array, in which case its array
+    /// layer doesn't have to match, see [`UiImageArrayBatching::draws_from_array`].
+    pub fn accepts(&self, extracted_uinode: &ExtractedUiNode, image_array: bool) -> bool {
+        self.image_array == image_array
+            && (self.image == AssetId::default()
+                || extracted_uinode.image == AssetId::default()
+                || (self.image == extracted_uinode.image
+                    && (image_array || self.image_layer == extracted_uinode.image_layer)
+                    && self.sampler == extracted_uinode.sampler))
+            && self.camera == extracted_uinode.camera_entity
+            && self.clip == extracted_uinode.scissor_clip()
+            && self.stencil == extracted_uinode.stencil
+            && self.premultiplied_alpha == extracted_uinode.premultiplied_alpha
+    }
+}
+
+/// Returns the ranges of the phase `items` drawn together: the items of each batch, or a single
+/// item that isn't batched.
+fn ui_batch_blocks(items: &[TransparentUi]) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < items.len() {
+        let len = items[index]
+            .batch_range()
+            .len()
+            .clamp(1, items.len() - index);
+        blocks.push(index..index + len);
+        index += len;
+    }
+    blocks
+}
+
+/// Keeps only the batches of `phase` for which `f` returns `true`, given the phase item starting
+/// each batch, see [`RenderUiSystem::ModifyBatches`].
+pub fn retain_ui_batches(
+    phase: &mut SortedRenderPhase<TransparentUi>,
+    mut f: impl FnMut(&TransparentUi) -> bool,
+) {
+    let mut keep = vec![true; phase.items.len()];
+    for block in ui_batch_blocks(&phase.items) {
+        if !f(&phase.items[block.start]) {
+            keep[block].fill(false);
+        }
+    }
+    let mut keep = keep.into_iter();
+    phase.items.retain(|_| keep.next().unwrap());
+}
+
+/// Stably sorts the batches of `phase` by the key of the phase item starting each batch, see
+/// [`RenderUiSystem::ModifyBatches`].
+pub fn sort_ui_batches_by_key<K: Ord>(
+    phase: &mut SortedRenderPhase<TransparentUi>,
+    mut key: impl FnMut(&TransparentUi) -> K,
+) {
+    let mut blocks = ui_batch_blocks(&phase.items);
+    blocks.sort_by_cached_key(|block| key(&phase.items[block.start]));
+    let mut items: Vec<Option<TransparentUi>> = phase.items.drain(..).map(Some).collect();
+    phase.items.extend(
+        blocks
+            .into_iter()
+            .flatten()
+            .map(|index| items[index].take().unwrap()),
+    );
 }
 
 /// The values here should match the values for the constants in `ui.wgsl`
 pub mod shader_flags {
     pub const UNTEXTURED: u32 = 0;
     pub const TEXTURED: u32 = 1;
+    /// Marks the vertices at the corners of a node's quad, interior grid vertices have neither bit.
     /// Ordering: top left, top right, bottom right, bottom left.
     pub const CORNERS: [u32; 4] = [0, 2, 2 | 4, 4];
     pub const BORDER: u32 = 8;
+    pub const BOX_SHADOW: u32 = 16;
+    /// Two bits selecting the [`UiColorBlend`](crate::UiColorBlend) mode.
+    pub const COLOR_BLEND_MASK: u32 = 64 | 128;
+    pub const COLOR_BLEND_REPLACE: u32 = 64;
+    pub const COLOR_BLEND_OVERLAY: u32 = 128;
+    pub const COLOR_BLEND_TINT: u32 = 64 | 128;
+    /// The vertex colors are interpolated in Oklab, see [`UiGradientInterpolation`](crate::UiGradientInterpolation).
+    pub const OKLAB: u32 = 256;
+    /// The node only draws one side of its border, selected by [`BORDER_SIDE_MASK`], see
+    /// [`BorderSideColors`](crate::BorderSideColors).
+    pub const BORDER_SIDE: u32 = 512;
+    /// Edges are drawn without antialiasing, see [`UiAntialias`](crate::UiAntialias).
+    pub const NO_ANTIALIAS: u32 = 1024;
+    /// Two bits holding the side drawn by a [`BORDER_SIDE`] node.
+    /// Ordering: left, top, right, bottom.
+    pub const BORDER_SIDE_MASK: u32 = 2048 | 4096;
+    const BORDER_SIDE_SHIFT: u32 = 11;
+
+    /// Returns the flags of a node only drawing the `side` of its border.
+    pub const fn border_side(side: u32) -> u32 {
+        BORDER_SIDE | ((side << BORDER_SIDE_SHIFT) & BORDER_SIDE_MASK)
+    }
+
+    /// Returns the flags for `color_blend`.
+    pub const fn color_blend(color_blend: crate::UiColorBlend) -> u32 {
+        match color_blend {
+            crate::UiColorBlend::Multiply => 0,
+            crate::UiColorBlend::Replace => COLOR_BLEND_REPLACE,
+            crate::UiColorBlend::Overlay => COLOR_BLEND_OVERLAY,
+            crate::UiColorBlend::Tint => COLOR_BLEND_TINT,
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -837,13 +1943,27 @@ pub fn queue_uinodes(
     ui_pipeline: Res<UiPipeline>,
     mut pipelines: ResMut<SpecializedRenderPipelines<UiPipeline>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
-    mut views: Query<(Entity, &ExtractedView)>,
+    (views, default_camera_views): (Query<(Entity, &ExtractedView)>, Query<&DefaultCameraView>),
+    force_ldr_views: Query<(), With<ExtractedUiForceLdr>>,
+    msaa_views: Query<&Msaa>,
+    depth_views: Res<UiDepthViews>,
+    stencil_masks: Res<ExtractedUiStencilMasks>,
+    mut stencil_items: ResMut<UiStencilItems>,
+    scene_depths: Res<ExtractedUiSceneDepths>,
+    mut scene_depth_items: ResMut<UiSceneDepthItems>,
+    array_batching: Res<UiImageArrayBatching>,
+    batch_diagnostics: Res<UiBatchDiagnostics>,
     pipeline_cache: Res<PipelineCache>,
     draw_functions: Res<DrawFunctions<TransparentUi>>,
 ) {
     let draw_function = draw_functions.read().id::<DrawUi>();
+    let front_faces = ui_camera_front_faces(&views, &default_camera_views);
+    let mut specialized_pipelines = HashSet::new();
     for (entity, extracted_uinode) in extracted_uinodes.uinodes.iter() {
-        let Ok((view_entity, view)) = views.get_mut(extracted_uinode.camera_entity) else {
+        let Ok((view_entity, view)) = views.get(extracted_uinode.camera_entity) else {
+            continue;
+        };
+        let Some(&front_face) = front_faces.get(&view_entity) else {
             continue;
         };
 
@@ -851,36 +1971,737 @@ pub fn queue_uinodes(
             continue;
         };
 
+        // Masked nodes are drawn with the stencil texture of their view
+        let stencil = if stencil_masks.cameras.contains(&view_entity) {
+            extracted_uinode.stencil.mode()
+        } else {
+            UiStencilMode::Disabled
+        };
+        if stencil != UiStencilMode::Disabled {
+            stencil_items.insert(*entity);
+        }
+        // In-scene nodes are tested against the depth of their scene
+        let scene_depth = stencil == UiStencilMode::Disabled
+            && extracted_uinode.scene_depth.is_some()
+            && scene_depths.cameras.contains(&view_entity);
+        if scene_depth {
+            scene_depth_items.insert(*entity);
+        }
+
         let pipeline = pipelines.specialize(
             &pipeline_cache,
             &ui_pipeline,
-            UiPipelineKey { hdr: view.hdr },
+            UiPipelineKey {
+                hdr: ui_target_is_hdr(view, force_ldr_views.contains(view_entity)),
+                front_face,
+                samples: ui_msaa_samples(msaa_views.get(view_entity).ok()),
+                depth: depth_views.contains(view_entity),
+                scene_depth,
+                stencil,
+                premultiplied_alpha: extracted_uinode.premultiplied_alpha,
+                image_array: array_batching.draws_from_array(extracted_uinode),
+            },
         );
+        specialized_pipelines.insert(pipeline);
+        let mut stack_key = extracted_uinode.stack_index as f32;
+        if extracted_uinode.node_type == NodeType::BoxShadow {
+            // Draw shadows below their node but above the nodes lower in the stack
+            stack_key -= 0.5;
+        } else if extracted_uinode.stencil.writes() {
+            // Push masks after their node and pop them after their last descendant, above the
+            // node's items but below the shadows of the next nodes
+            stack_key += 0.25;
+        }
         transparent_phase.add(TransparentUi {
             draw_function,
             pipeline,
             entity: *entity,
-            sort_key: (
-                FloatOrd(extracted_uinode.stack_index as f32),
-                entity.index(),
-            ),
+            sort_key: (FloatOrd(stack_key), entity.index()),
             // batch_range will be calculated in prepare_uinodes
             batch_range: 0..0,
             extra_index: PhaseItemExtraIndex::NONE,
         });
     }
+    batch_diagnostics.set_queued(extracted_uinodes.uinodes.len(), specialized_pipelines.len());
+}
+
+/// The maximum number of vertices [`prepare_uinodes`] writes each frame.
+///
+/// Nodes that would exceed the budget are not drawn, and a warning is logged once each time the
+/// budget starts being exceeded. The vertices of the nodes with a [`UiVertexPriority`] are
+/// reserved first, then the other nodes are prepared in stack order, so the nodes at the bottom of
+/// the stack are kept. Unlimited by default.
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct UiVertexBudget {
+    pub max: usize,
+}
+
+impl Default for UiVertexBudget {
+    fn default() -> Self {
+        Self { max: usize::MAX }
+    }
+}
+
+impl UiVertexBudget {
+    /// Returns `true` if `additional` vertices can be added to the `len` already written.
+    pub fn allows(&self, len: usize, additional: usize) -> bool {
+        len.saturating_add(additional) <= self.max
+    }
+}
+
+/// Draws the nodes displaying an array layer of their image, see [`UiImageLayer`], from a texture
+/// array instead of a view of their layer.
+///
+/// Each layer of an image otherwise gets its own bind group, so a grid of icons packed in the
+/// layers of an array texture needs a draw call per icon. With array batching, the whole texture
+/// is bound as a 2D array and each node samples its layer from its vertices, so consecutive nodes
+/// showing layers of the same image are drawn in a single batch. Disabled by default.
+///
+/// The layers of an array are the same size, so icons of different sizes still need their own
+/// images, or a [`TextureAtlas`] per layer.
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource, Debug, Default)]
+pub struct UiImageArrayBatching {
+    pub enabled: bool,
+}
+
+impl UiImageArrayBatching {
+    pub const ENABLED: Self = Self { enabled: true };
+
+    /// Returns `true` if `extracted_uinode` is drawn from the texture array of its image.
+    pub fn draws_from_array(&self, extracted_uinode: &ExtractedUiNode) -> bool {
+        self.enabled
+            && extracted_uinode.image != AssetId::default()
+            && extracted_uinode.image_layer.is_some()
+    }
+}
+
+/// Marks the extracted items of the nodes with a [`UiVertexPriority`], see [`UiVertexBudget`].
+///
+/// The extracted items of a node share its stack index, which is used to find them regardless of
+/// the entity they were extracted to.
+pub fn prioritize_extracted_uinodes(
+    priority_query: Extract<Query<&Node, With<UiVertexPriority>>>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+) {
+    if priority_query.is_empty() {
+        return;
+    }
+
+    let stack_indices: HashSet<u32> = priority_query.iter().map(|node| node.stack_index).collect();
+    for uinode in extracted_uinodes.uinodes.values_mut() {
+        uinode.vertex_priority = stack_indices.contains(&uinode.stack_index);
+    }
+}
+
+/// Returns the number of vertices written for `extracted_uinode`, see [`UiVertexBudget`].
+fn ui_node_vertex_count(
+    extracted_uinode: &ExtractedUiNode,
+    extracted_meshes: &ExtractedUiMeshes,
+) -> usize {
+    match extracted_uinode
+        .mesh
+        .and_then(|mesh| extracted_meshes.meshes.get(&mesh))
+    {
+        Some(mesh) => mesh.positions.len(),
+        None => ui_quad_grid_vertex_count(extracted_uinode.subdivisions),
+    }
+}
+
+/// Returns `true` if the budget warning should be logged this frame, given whether nodes were
+/// skipped this frame and whether they were skipped in the previous one.
+pub(crate) fn vertex_budget_warning(was_exceeded: &mut bool, skipped_nodes: usize) -> bool {
+    let warn = skipped_nodes > 0 && !*was_exceeded;
+    *was_exceeded = skipped_nodes > 0;
+    warn
+}
+
+/// The UI prepared last frame, which [`prepare_uinodes`] reuses instead of rebuilding and uploading
+/// its vertex buffer when nothing drawn by the UI changed, e.g. for static UI in a desktop app
+/// that only redraws on input.
+///
+/// Changes to UI components are detected by comparing the nodes of each view's phase items with
+/// last frame's, in draw order. Most nodes are extracted to render world entities spawned again
+/// every frame, whose ids depend on the entities of the main world and on the order the extraction
+/// systems run in, so the nodes are compared by their position in the phase instead of by entity.
+/// The view isn't part of the vertex buffer, its uniforms are updated every frame, and neither are
+/// the scissor clips of the batches, see [`UiClipMode::Scissor`].
+#[derive(Resource, Default)]
+pub struct UiFrameCache {
+    /// The nodes prepared last frame.
+    uinodes: EntityHashMap<ExtractedUiNode>,
+    /// The entities of the items of each view's [`TransparentUi`] phase, in draw order.
+    phase_items: Vec<(Entity, Vec<Entity>)>,
+    batches: Vec<(Entity, UiBatch)>,
+    /// The batch ranges of the phase items starting a batch.
+    batch_ranges: EntityHashMap<Range<u32>>,
+    vertex_budget: UiVertexBudget,
+    /// `false` if a node couldn't be prepared last frame because its image wasn't ready yet.
+    complete: bool,
+}
+
+impl UiFrameCache {
+    /// Returns the batches of the last prepared frame, with the phase item starting each one.
+    pub fn batches(&self) -> &[(Entity, UiBatch)] {
+        &self.batches
+    }
+
+    /// Returns `true` if last frame's vertex buffer and batches can be reused this frame, in which
+    /// case they're moved to the entities of this frame's `phase_items`.
+    ///
+    /// The phase items of each view must draw the same nodes as last frame's, in the same order,
+    /// apart from their scissor clips.
+    fn reuse(
+        &mut self,
+        phase_items: &[(Entity, Vec<Entity>)],
+        uinodes: &EntityHashMap<ExtractedUiNode>,
+        vertex_budget: UiVertexBudget,
+        images_changed: bool,
+    ) -> bool {
+        if !self.complete
+            || images_changed
+            || self.vertex_budget != vertex_budget
+            || self.phase_items.len() != phase_items.len()
+        {
+            return false;
+        }
+
+        let mut current_entities = EntityHashMap::default();
+        for ((previous_view, previous_items), (view, items)) in
+            self.phase_items.iter().zip(phase_items)
+        {
+            if previous_view != view || previous_items.len() != items.len() {
+                return false;
+            }
+            for (previous, entity) in previous_items.iter().zip(items) {
+                let same = match (self.uinodes.get(previous), uinodes.get(entity)) {
+                    (Some(previous_uinode), Some(uinode)) => previous_uinode.same_geometry(uinode),
+                    // Items drawn by other pipelines
+                    (None, None) => true,
+                    _ => false,
+                };
+                if !same {
+                    return false;
+                }
+                current_entities.insert(*previous, *entity);
+            }
+        }
+
+        self.phase_items = phase_items.to_vec();
+        for (entity, _) in &mut self.batches {
+            *entity = current_entities[entity];
+        }
+        self.batch_ranges = self
+            .batch_ranges
+            .drain()
+            .map(|(entity, batch_range)| (current_entities[&entity], batch_range))
+            .collect();
+        true
+    }
+
+    /// Sets the clips of last frame's batches to the scissor clips of this frame's `uinodes`,
+    /// returning `false` if the nodes of a batch no longer share a clip.
+    ///
+    /// Scissor clips don't change the vertices, so clipped nodes can be scrolled or their clips
+    /// animated without rewriting the vertex buffer.
+    fn update_scissor_clips(&mut self, uinodes: &EntityHashMap<ExtractedUiNode>) -> bool {
+        let mut clips = EntityHashMap::default();
+        for (_, entities) in &self.phase_items {
+            let mut batch_clip = None;
+            for entity in entities {
+                let Some(uinode) = uinodes.get(entity) else {
+                    batch_clip = None;
+                    continue;
+                };
+                let clip = uinode.scissor_clip();
+                if self.batch_ranges.contains_key(entity) {
+                    batch_clip = Some(clip);
+                    clips.insert(*entity, clip);
+                } else if batch_clip.is_some_and(|batch_clip| batch_clip != clip) {
+                    return false;
+                }
+            }
+        }
+        for (entity, batch) in &mut self.batches {
+            if let Some(&clip) = clips.get(entity) {
+                batch.clip = clip;
+            }
+        }
+        true
+    }
+
+    /// Keeps the nodes, phase items and batches prepared this frame, leaving `uinodes` with the
+    /// nodes of the previous frame.
+    fn store(
+        &mut self,
+        uinodes: &mut EntityHashMap<ExtractedUiNode>,
+        phase_items: Vec<(Entity, Vec<Entity>)>,
+        batches: Vec<(Entity, UiBatch)>,
+        batch_ranges: EntityHashMap<Range<u32>>,
+        vertex_budget: UiVertexBudget,
+        complete: bool,
+    ) {
+        std::mem::swap(&mut self.uinodes, uinodes);
+        self.phase_items = phase_items;
+        self.batches = batches;
+        self.batch_ranges = batch_ranges;
+        self.vertex_budget = vertex_budget;
+        self.complete = complete;
+    }
+}
+
+/// Returns the entities of the items of each view's [`TransparentUi`] phase, in draw order.
+fn ui_phase_items(phases: &ViewSortedRenderPhases<TransparentUi>) -> Vec<(Entity, Vec<Entity>)> {
+    phases
+        .iter()
+        .map(|(view, phase)| (*view, phase.items.iter().map(|item| item.entity).collect()))
+        .collect()
 }
 
 #[derive(Resource, Default)]
 pub struct UiImageBindGroups {
     pub values: HashMap<AssetId<Image>, BindGroup>,
+    /// Bind groups of single array layers of images, see [`UiImageLayer`].
+    pub layers: HashMap<(AssetId<Image>, u32), BindGroup>,
+    /// Bind groups of images, or of their array layers, sampled with another sampler than their
+    /// own, see [`UiImage::sampler`].
+    pub sampled: HashMap<UiSampledImageKey, BindGroup>,
+    /// Bind groups of images bound as texture arrays, with their own sampler or another one, see
+    /// [`UiImageArrayBatching`].
+    pub arrays: HashMap<(AssetId<Image>, Option<UiSamplerKey>), BindGroup>,
+    /// The samplers of the `sampled` bind groups.
+    pub samplers: HashMap<UiSamplerKey, Sampler>,
+}
+
+/// The key of a bind group of an image sampled with a [`UiImage::sampler`].
+pub type UiSampledImageKey = (AssetId<Image>, Option<u32>, UiSamplerKey);
+
+impl UiImageBindGroups {
+    /// Returns the bind group of `image`, or of one of its array layers, sampled with `sampler`
+    /// or with the sampler of the image.
+    pub fn get(
+        &self,
+        image: AssetId<Image>,
+        image_layer: Option<u32>,
+        sampler: Option<UiSamplerKey>,
+    ) -> Option<&BindGroup> {
+        match (image_layer, sampler) {
+            (_, Some(sampler)) => self.sampled.get(&(image, image_layer, sampler)),
+            (Some(layer), None) => self.layers.get(&(image, layer)),
+            (None, None) => self.values.get(&image),
+        }
+    }
+
+    /// Returns the bind group of `image` bound as a texture array, sampled with `sampler` or with
+    /// the sampler of the image.
+    pub fn get_array(
+        &self,
+        image: AssetId<Image>,
+        sampler: Option<UiSamplerKey>,
+    ) -> Option<&BindGroup> {
+        self.arrays.get(&(image, sampler))
+    }
+
+    /// Removes the bind groups of `image`, e.g. once it's modified.
+    fn remove(&mut self, image: &AssetId<Image>) {
+        self.values.remove(image);
+        self.layers
+            .retain(|(layer_image, _), _| layer_image != image);
+        self.sampled
+            .retain(|(sampled_image, _, _), _| sampled_image != image);
+        self.arrays
+            .retain(|(array_image, _), _| array_image != image);
+    }
+
+    fn insert_array(
+        &mut self,
+        render_device: &RenderDevice,
+        layout: &BindGroupLayout,
+        image: AssetId<Image>,
+        sampler: Option<UiSamplerKey>,
+        gpu_image: &GpuImage,
+    ) {
+        if self.arrays.contains_key(&(image, sampler)) {
+            return;
+        }
+        let sampler_resource = match sampler {
+            Some(sampler) => &*self
+                .samplers
+                .entry(sampler)
+                .or_insert_with(|| render_device.create_sampler(&sampler.descriptor())),
+            None => &gpu_image.sampler,
+        };
+        let texture_view = gpu_image
+            .texture
+            .create_view(&ui_image_array_view_descriptor());
+        let bind_group = render_device.create_bind_group(
+            "ui_image_array_bind_group",
+            layout,
+            &BindGroupEntries::sequential((&texture_view, sampler_resource)),
+        );
+        self.arrays.insert((image, sampler), bind_group);
+    }
+
+    fn insert(
+        &mut self,
+        render_device: &RenderDevice,
+        layout: &BindGroupLayout,
+        image: AssetId<Image>,
+        image_layer: Option<u32>,
+        sampler: Option<UiSamplerKey>,
+        gpu_image: &GpuImage,
+    ) {
+        let create_bind_group = |texture_view: &TextureView, sampler: &Sampler| {
+            render_device.create_bind_group(
+                "ui_material_bind_group",
+                layout,
+                &BindGroupEntries::sequential((texture_view, sampler)),
+            )
+        };
+        let create_view = |layer| {
+            let descriptor =
+                ui_image_layer_view_descriptor(layer, gpu_image.texture.depth_or_array_layers());
+            gpu_image.texture.create_view(&descriptor)
+        };
+        if let Some(sampler) = sampler {
+            if !self.sampled.contains_key(&(image, image_layer, sampler)) {
+                let sampler_resource = self
+                    .samplers
+                    .entry(sampler)
+                    .or_insert_with(|| render_device.create_sampler(&sampler.descriptor()));
+                let bind_group = match image_layer {
+                    Some(layer) => create_bind_group(&create_view(layer), sampler_resource),
+                    None => create_bind_group(&gpu_image.texture_view, sampler_resource),
+                };
+                self.sampled
+                    .insert((image, image_layer, sampler), bind_group);
+            }
+            return;
+        }
+        match image_layer {
+            Some(layer) => {
+                self.layers
+                    .entry((image, layer))
+                    .or_insert_with(|| create_bind_group(&create_view(layer), &gpu_image.sampler));
+            }
+            None => {
+                self.values.entry(image).or_insert_with(|| {
+                    create_bind_group(&gpu_image.texture_view, &gpu_image.sampler)
+                });
+            }
+        }
+    }
+}
+
+/// The sampler a [`UiImage`] is drawn with instead of the sampler of its texture, see
+/// [`UiImage::sampler`].
+///
+/// Unlike an [`ImageSamplerDescriptor`], it can be hashed, so images sampled with different
+/// samplers get different bind groups and batches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UiSamplerKey {
+    address_modes: [AddressMode; 3],
+    filters: [FilterMode; 3],
+    /// The bits of the minimum and maximum level of detail.
+    lod_clamps: [u32; 2],
+    compare: Option<CompareFunction>,
+    anisotropy_clamp: u16,
+    border_color: Option<SamplerBorderColor>,
+}
+
+impl From<&ImageSamplerDescriptor> for UiSamplerKey {
+    fn from(descriptor: &ImageSamplerDescriptor) -> Self {
+        let descriptor = descriptor.as_wgpu();
+        Self {
+            address_modes: [
+                descriptor.address_mode_u,
+                descriptor.address_mode_v,
+                descriptor.address_mode_w,
+            ],
+            filters: [
+                descriptor.mag_filter,
+                descriptor.min_filter,
+                descriptor.mipmap_filter,
+            ],
+            lod_clamps: [
+                descriptor.lod_min_clamp.to_bits(),
+                descriptor.lod_max_clamp.to_bits(),
+            ],
+            compare: descriptor.compare,
+            anisotropy_clamp: descriptor.anisotropy_clamp,
+            border_color: descriptor.border_color,
+        }
+    }
+}
+
+impl UiSamplerKey {
+    /// Returns the descriptor the sampler is created with.
+    pub fn descriptor(&self) -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            label: Some("ui_image_sampler"),
+            address_mode_u: self.address_modes[0],
+            address_mode_v: self.address_modes[1],
+            address_mode_w: self.address_modes[2],
+            mag_filter: self.filters[0],
+            min_filter: self.filters[1],
+            mipmap_filter: self.filters[2],
+            lod_min_clamp: f32::from_bits(self.lod_clamps[0]),
+            lod_max_clamp: f32::from_bits(self.lod_clamps[1]),
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: self.border_color,
+        }
+    }
+}
+
+/// Returns the descriptor of a 2D view of a single array layer of a texture with `layer_count`
+/// layers, clamping `layer` to the last one.
+pub fn ui_image_layer_view_descriptor(
+    layer: u32,
+    layer_count: u32,
+) -> TextureViewDescriptor<'static> {
+    TextureViewDescriptor {
+        label: Some("ui_image_layer_view"),
+        dimension: Some(TextureViewDimension::D2),
+        base_array_layer: layer.min(layer_count.saturating_sub(1)),
+        array_layer_count: Some(1),
+        ..Default::default()
+    }
+}
+
+/// Returns the descriptor of a 2D array view of all of the layers of a texture, sampled by the
+/// nodes drawn from it, see [`UiImageArrayBatching`].
+pub fn ui_image_array_view_descriptor() -> TextureViewDescriptor<'static> {
+    TextureViewDescriptor {
+        label: Some("ui_image_array_view"),
+        dimension: Some(TextureViewDimension::D2Array),
+        ..Default::default()
+    }
+}
+
+/// Returns `true` if the quad with `corners` doesn't overlap `clip`, which is the case if there
+/// is a gap between their projections on the axis normal to one of their edges.
+fn ui_quad_outside_clip(corners: [Vec2; 4], clip: Rect) -> bool {
+    let clip_corners = [
+        clip.min,
+        Vec2::new(clip.max.x, clip.min.y),
+        clip.max,
+        Vec2::new(clip.min.x, clip.max.y),
+    ];
+    let project = |points: [Vec2; 4], axis: Vec2| {
+        points
+            .map(|point| point.dot(axis))
+            .into_iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            })
+    };
+    [
+        Vec2::X,
+        Vec2::Y,
+        corners[1] - corners[0],
+        corners[3] - corners[0],
+    ]
+    .into_iter()
+    .any(|edge| {
+        let axis = edge.perp();
+        let (quad_min, quad_max) = project(corners, axis);
+        let (clip_min, clip_max) = project(clip_corners, axis);
+        quad_max <= clip_min || clip_max <= quad_min
+    })
+}
+
+/// Appends the vertices and indices of `extracted_uinode` to the UI geometry, returning `false`
+/// if the node is completely clipped and was skipped.
+///
+/// `image_size` is the size of the node's image, used to rescale texture atlases, and is ignored
+/// for untextured nodes. This is how [`prepare_uinodes`] writes the vertices of [`UiMeta`], but it
+/// doesn't need a [`RenderDevice`] or a window, so the output of the UI for some extracted nodes
+/// can be checked in headless tests, independently of the GPU backend.
+pub fn write_ui_node_geometry(
+    extracted_uinode: &ExtractedUiNode,
+    image_size: Vec2,
+    vertices: &mut Vec<UiVertex>,
+    indices: &mut Vec<u32>,
+) -> bool {
+    let first_vertex = vertices.len() as u32;
+
+    let mut flags = if extracted_uinode.image != AssetId::default() {
+        shader_flags::TEXTURED
+    } else {
+        shader_flags::UNTEXTURED
+    };
+
+    let mut uinode_rect = extracted_uinode.rect;
+
+    let rect_size = uinode_rect.size().extend(1.0);
+
+    // Specify the corners of the node, offset along the z-axis by its stack index so it is tested
+    // against the depth of the opaque nodes above it, see `ViewUiDepthTexture`
+    let z = stack_z(extracted_uinode.stack_index);
+    let positions = QUAD_VERTEX_POSITIONS
+        .map(|pos| (extracted_uinode.transform * (pos * rect_size).extend(1.)).xyz() + Vec3::Z * z);
+    // Nodes drawn in the 3D scene are tested against its depth instead, see `UiSceneDepth`
+    let positions = match extracted_uinode.scene_depth {
+        Some(depth) => positions.map(|pos| pos.xy().extend(depth)),
+        None => positions,
+    };
+
+    // Calculate the effect of clipping
+    // The corners are moved along the axes of the clip, which is exact for scaled nodes but not
+    // for rotated ones. Nodes with a scissor clip are drawn whole, see `UiClipMode`
+    let mut positions_diff = if let Some(clip) = extracted_uinode.vertex_clip() {
+        [
+            Vec2::new(
+                f32::max(clip.min.x - positions[0].x, 0.),
+                f32::max(clip.min.y - positions[0].y, 0.),
+            ),
+            Vec2::new(
+                f32::min(clip.max.x - positions[1].x, 0.),
+                f32::max(clip.min.y - positions[1].y, 0.),
+            ),
+            Vec2::new(
+                f32::min(clip.max.x - positions[2].x, 0.),
+                f32::min(clip.max.y - positions[2].y, 0.),
+            ),
+            Vec2::new(
+                f32::max(clip.min.x - positions[3].x, 0.),
+                f32::min(clip.max.y - positions[3].y, 0.),
+            ),
+        ]
+    } else {
+        [Vec2::ZERO; 4]
+    };
+
+    let positions_clipped = [
+        positions[0] + positions_diff[0].extend(0.),
+        positions[1] + positions_diff[1].extend(0.),
+        positions[2] + positions_diff[2].extend(0.),
+        positions[3] + positions_diff[3].extend(0.),
+    ];
+
+    // Cull nodes that are completely clipped
+    // In a rotation around the Z-axis, this value is 0.0 for an angle of 0.0 or π
+    // In those two cases, the corners are on horizontal / vertical lines and the bounds of the
+    // node can be intersected with the clip rect
+    // For all other angles, the rotated quad is tested against the clip rect
+    let culled = if extracted_uinode.transform.x_axis[1] == 0.0 {
+        let bounds = Rect::from_corners(positions[0].xy(), positions[2].xy());
+        bounds
+            .intersect(extracted_uinode.vertex_clip().unwrap_or(bounds))
+            .is_empty()
+    } else {
+        extracted_uinode
+            .vertex_clip()
+            .is_some_and(|clip| ui_quad_outside_clip(positions.map(|pos| pos.xy()), clip))
+    };
+    if culled {
+        return false;
+    }
+    let uvs = if flags == shader_flags::UNTEXTURED {
+        [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]
+    } else {
+        // Rescale atlases. This is done here because we need texture data that might not be available in Extract.
+        let atlas_extent = extracted_uinode
+            .atlas_scaling
+            .map(|scaling| image_size * scaling)
+            .unwrap_or(uinode_rect.max);
+        // The clip moves the corners of the transformed node, convert the distances to the space
+        // of its rect, so a clipped glyph shows the matching part of its atlas rect instead of its
+        // neighbors in the atlas
+        let scale = Vec2::new(
+            extracted_uinode.transform.x_axis.xy().length(),
+            extracted_uinode.transform.y_axis.xy().length(),
+        );
+        for diff in &mut positions_diff {
+            *diff /= scale;
+        }
+        if extracted_uinode.flip_x {
+            std::mem::swap(&mut uinode_rect.max.x, &mut uinode_rect.min.x);
+            positions_diff[0].x *= -1.;
+            positions_diff[1].x *= -1.;
+            positions_diff[2].x *= -1.;
+            positions_diff[3].x *= -1.;
+        }
+        if extracted_uinode.flip_y {
+            std::mem::swap(&mut uinode_rect.max.y, &mut uinode_rect.min.y);
+            positions_diff[0].y *= -1.;
+            positions_diff[1].y *= -1.;
+            positions_diff[2].y *= -1.;
+            positions_diff[3].y *= -1.;
+        }
+        [
+            Vec2::new(
+                uinode_rect.min.x + positions_diff[0].x,
+                uinode_rect.min.y + positions_diff[0].y,
+            ),
+            Vec2::new(
+                uinode_rect.max.x + positions_diff[1].x,
+                uinode_rect.min.y + positions_diff[1].y,
+            ),
+            Vec2::new(
+                uinode_rect.max.x + positions_diff[2].x,
+                uinode_rect.max.y + positions_diff[2].y,
+            ),
+            Vec2::new(
+                uinode_rect.min.x + positions_diff[3].x,
+                uinode_rect.max.y + positions_diff[3].y,
+            ),
+        ]
+        .map(|pos| pos / atlas_extent)
+    };
+
+    let corner_colors = extracted_uinode
+        .corner_colors
+        .unwrap_or([extracted_uinode.color; 4]);
+    match extracted_uinode.node_type {
+        NodeType::Rect => {}
+        NodeType::Border => flags |= shader_flags::BORDER,
+        NodeType::BoxShadow => flags |= shader_flags::BOX_SHADOW,
+    }
+    if let Some(side) = extracted_uinode.border_side {
+        flags |= shader_flags::border_side(side);
+    }
+    flags |= shader_flags::color_blend(extracted_uinode.color_blend);
+    if extracted_uinode.gradient_interpolation == UiGradientInterpolation::Oklab {
+        flags |= shader_flags::OKLAB;
+    }
+    if extracted_uinode.antialias == UiAntialias::Off {
+        flags |= shader_flags::NO_ANTIALIAS;
+    }
+
+    let points = QUAD_VERTEX_POSITIONS.map(|pos| 0.99998 * pos.xy() * rect_size.xy());
+
+    // Without subdivisions this is a single quad made of the four corners
+    let subdivisions = extracted_uinode.subdivisions.max(UVec2::ONE);
+    for (i, st) in ui_quad_grid(subdivisions).enumerate() {
+        let corner = ui_quad_grid_corner(subdivisions, i);
+        vertices.push(UiVertex {
+            position: bilerp(positions_clipped, st).into(),
+            uv: bilerp(uvs, st).into(),
+            color: extracted_uinode
+                .gradient_interpolation
+                .mix(corner_colors, st)
+                .to_f32_array(),
+            flags: flags | corner.map_or(0, |corner| shader_flags::CORNERS[corner]),
+            radius: extracted_uinode.border_radius,
+            border: extracted_uinode.border,
+            size: rect_size.xy().into(),
+            point: bilerp(points, st).into(),
+            layer: extracted_uinode.image_layer.unwrap_or(0),
+        });
+    }
+
+    indices.extend(ui_quad_grid_indices(subdivisions).map(|i| first_vertex + i));
+
+    true
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn prepare_uinodes(
     mut commands: Commands,
-    render_device: Res<RenderDevice>,
-    render_queue: Res<RenderQueue>,
+    (render_device, render_queue): (Res<RenderDevice>, Res<RenderQueue>),
     mut ui_meta: ResMut<UiMeta>,
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
     view_uniforms: Res<ViewUniforms>,
@@ -889,8 +2710,14 @@ pub fn prepare_uinodes(
     gpu_images: Res<RenderAssets<GpuImage>>,
     mut phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
     events: Res<SpriteAssetEvents>,
-    mut previous_len: Local<usize>,
+    extracted_meshes: Res<ExtractedUiMeshes>,
+    vertex_budget: Res<UiVertexBudget>,
+    array_batching: Res<UiImageArrayBatching>,
+    batch_diagnostics: Res<UiBatchDiagnostics>,
+    mut frame_cache: ResMut<UiFrameCache>,
+    mut vertex_budget_exceeded: Local<bool>,
 ) {
+    let mut images_changed = false;
     // If an image has changed, the GpuImage has (probably) changed
     for event in &events.images {
         match event {
@@ -899,243 +2726,2421 @@ pub fn prepare_uinodes(
             // Images don't have dependencies
             AssetEvent::LoadedWithDependencies { .. } => {}
             AssetEvent::Modified { id } | AssetEvent::Removed { id } => {
-                image_bind_groups.values.remove(id);
+                image_bind_groups.remove(id);
+                images_changed = true;
             }
         };
     }
 
     if let Some(view_binding) = view_uniforms.uniforms.binding() {
-        let mut batches: Vec<(Entity, UiBatch)> = Vec::with_capacity(*previous_len);
-
-        ui_meta.vertices.clear();
-        ui_meta.indices.clear();
         ui_meta.view_bind_group = Some(render_device.create_bind_group(
             "ui_view_bind_group",
             &ui_pipeline.view_layout,
             &BindGroupEntries::single(view_binding),
         ));
 
-        // Buffer indexes
-        let mut vertices_index = 0;
-        let mut indices_index = 0;
+        let prepared = prepare_ui_batches(
+            &mut ui_meta,
+            &mut extracted_uinodes.uinodes,
+            &mut phases,
+            &mut frame_cache,
+            &extracted_meshes,
+            |image| {
+                gpu_images
+                    .get(image)
+                    .map(|gpu_image| gpu_image.size.as_vec2())
+            },
+            *vertex_budget,
+            &mut vertex_budget_exceeded,
+            &array_batching,
+            &batch_diagnostics,
+            images_changed || extracted_meshes.changed,
+        );
 
-        for ui_phase in phases.values_mut() {
-            let mut batch_item_index = 0;
-            let mut batch_image_handle = AssetId::invalid();
-
-            for item_index in 0..ui_phase.items.len() {
-                let item = &mut ui_phase.items[item_index];
-                if let Some(extracted_uinode) = extracted_uinodes.uinodes.get(&item.entity) {
-                    let mut existing_batch = batches.last_mut();
-
-                    if batch_image_handle == AssetId::invalid()
-                        || existing_batch.is_none()
-                        || (batch_image_handle != AssetId::default()
-                            && extracted_uinode.image != AssetId::default()
-                            && batch_image_handle != extracted_uinode.image)
-                        || existing_batch.as_ref().map(|(_, b)| b.camera)
-                            != Some(extracted_uinode.camera_entity)
-                    {
-                        if let Some(gpu_image) = gpu_images.get(extracted_uinode.image) {
-                            batch_item_index = item_index;
-                            batch_image_handle = extracted_uinode.image;
-
-                            let new_batch = UiBatch {
-                                range: vertices_index..vertices_index,
-                                image: extracted_uinode.image,
-                                camera: extracted_uinode.camera_entity,
-                            };
-
-                            batches.push((item.entity, new_batch));
-
-                            image_bind_groups
-                                .values
-                                .entry(batch_image_handle)
-                                .or_insert_with(|| {
-                                    render_device.create_bind_group(
-                                        "ui_material_bind_group",
-                                        &ui_pipeline.image_layout,
-                                        &BindGroupEntries::sequential((
-                                            &gpu_image.texture_view,
-                                            &gpu_image.sampler,
-                                        )),
-                                    )
-                                });
-
-                            existing_batch = batches.last_mut();
-                        } else {
-                            continue;
-                        }
-                    } else if batch_image_handle == AssetId::default()
-                        && extracted_uinode.image != AssetId::default()
-                    {
-                        if let Some(gpu_image) = gpu_images.get(extracted_uinode.image) {
-                            batch_image_handle = extracted_uinode.image;
-                            existing_batch.as_mut().unwrap().1.image = extracted_uinode.image;
-
-                            image_bind_groups
-                                .values
-                                .entry(batch_image_handle)
-                                .or_insert_with(|| {
-                                    render_device.create_bind_group(
-                                        "ui_material_bind_group",
-                                        &ui_pipeline.image_layout,
-                                        &BindGroupEntries::sequential((
-                                            &gpu_image.texture_view,
-                                            &gpu_image.sampler,
-                                        )),
-                                    )
-                                });
-                        } else {
-                            continue;
-                        }
-                    }
+        for (_, batch) in &prepared.batches {
+            let Some(gpu_image) = gpu_images.get(batch.image) else {
+                continue;
+            };
+            if batch.image_array {
+                image_bind_groups.insert_array(
+                    &render_device,
+                    &ui_pipeline.image_array_layout,
+                    batch.image,
+                    batch.sampler,
+                    gpu_image,
+                );
+            } else {
+                image_bind_groups.insert(
+                    &render_device,
+                    &ui_pipeline.image_layout,
+                    batch.image,
+                    batch.image_layer,
+                    batch.sampler,
+                    gpu_image,
+                );
+            }
+        }
+        if prepared.rewritten {
+            ui_meta.write_buffers(&render_device, &render_queue);
+        }
+        commands.insert_or_spawn_batch(prepared.batches);
+    }
+    extracted_uinodes.uinodes.clear();
+}
 
-                    let mut flags = if extracted_uinode.image != AssetId::default() {
-                        shader_flags::TEXTURED
-                    } else {
-                        shader_flags::UNTEXTURED
-                    };
+/// The UI of a frame prepared by [`prepare_ui_batches`].
+struct UiPreparedBatches {
+    /// The batches of the frame, with the phase item starting each one.
+    batches: Vec<(Entity, UiBatch)>,
+    /// `false` if the vertex and index buffers still hold last frame's UI, see [`UiFrameCache`].
+    rewritten: bool,
+}
 
-                    let mut uinode_rect = extracted_uinode.rect;
+/// Writes the vertices and indices of the nodes in the `phases` to `ui_meta` and batches them, or
+/// reuses last frame's if the [`UiFrameCache`] still holds the same UI.
+///
+/// This is the part of [`prepare_uinodes`] that doesn't need a render device: `image_size`
+/// returns the size of an image once it's ready to be drawn, and the caller uploads the buffers
+/// and creates the bind groups of the batches. `vertex_budget_exceeded` tracks whether nodes were
+/// skipped last frame, to warn once when the [`UiVertexBudget`] starts being exceeded. The
+/// [`UiBufferCapacity`] of `ui_meta` is grown to the written UI and reported to
+/// `batch_diagnostics`.
+#[allow(clippy::too_many_arguments)]
+fn prepare_ui_batches(
+    ui_meta: &mut UiMeta,
+    uinodes: &mut EntityHashMap<ExtractedUiNode>,
+    phases: &mut ViewSortedRenderPhases<TransparentUi>,
+    frame_cache: &mut UiFrameCache,
+    extracted_meshes: &ExtractedUiMeshes,
+    image_size: impl Fn(AssetId<Image>) -> Option<Vec2>,
+    vertex_budget: UiVertexBudget,
+    vertex_budget_exceeded: &mut bool,
+    array_batching: &UiImageArrayBatching,
+    batch_diagnostics: &UiBatchDiagnostics,
+    assets_changed: bool,
+) -> UiPreparedBatches {
+    let phase_items = ui_phase_items(phases);
+    if frame_cache.reuse(&phase_items, uinodes, vertex_budget, assets_changed)
+        && frame_cache.update_scissor_clips(uinodes)
+    {
+        // Nothing but the scissor clips changed, the vertex and index buffers still hold this
+        // frame's UI
+        for ui_phase in phases.values_mut() {
+            for item in &mut ui_phase.items {
+                if let Some(batch_range) = frame_cache.batch_ranges.get(&item.entity) {
+                    *item.batch_range_mut() = batch_range.clone();
+                }
+            }
+        }
+        // This frame's nodes are compared with the next frame's
+        std::mem::swap(&mut frame_cache.uinodes, uinodes);
+        return UiPreparedBatches {
+            batches: frame_cache.batches.clone(),
+            rewritten: false,
+        };
+    }
 
-                    let rect_size = uinode_rect.size().extend(1.0);
+    let mut batches: Vec<(Entity, UiBatch)> = Vec::with_capacity(frame_cache.batches.len());
+    let mut complete = true;
 
-                    // Specify the corners of the node
-                    let positions = QUAD_VERTEX_POSITIONS.map(|pos| {
-                        (extracted_uinode.transform * (pos * rect_size).extend(1.)).xyz()
-                    });
+    ui_meta.vertices.clear();
+    ui_meta.indices.clear();
 
-                    // Calculate the effect of clipping
-                    // Note: this won't work with rotation/scaling, but that's much more complex (may need more that 2 quads)
-                    let mut positions_diff = if let Some(clip) = extracted_uinode.clip {
-                        [
-                            Vec2::new(
-                                f32::max(clip.min.x - positions[0].x, 0.),
-                                f32::max(clip.min.y - positions[0].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::min(clip.max.x - positions[1].x, 0.),
-                                f32::max(clip.min.y - positions[1].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::min(clip.max.x - positions[2].x, 0.),
-                                f32::min(clip.max.y - positions[2].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::max(clip.min.x - positions[3].x, 0.),
-                                f32::min(clip.max.y - positions[3].y, 0.),
-                            ),
-                        ]
-                    } else {
-                        [Vec2::ZERO; 4]
-                    };
-
-                    let positions_clipped = [
-                        positions[0] + positions_diff[0].extend(0.),
-                        positions[1] + positions_diff[1].extend(0.),
-                        positions[2] + positions_diff[2].extend(0.),
-                        positions[3] + positions_diff[3].extend(0.),
-                    ];
-
-                    let transformed_rect_size =
-                        extracted_uinode.transform.transform_vector3(rect_size);
-
-                    // Don't try to cull nodes that have a rotation
-                    // In a rotation around the Z-axis, this value is 0.0 for an angle of 0.0 or π
-                    // In those two cases, the culling check can proceed normally as corners will be on
-                    // horizontal / vertical lines
-                    // For all other angles, bypass the culling check
-                    // This does not properly handles all rotations on all axis
-                    if extracted_uinode.transform.x_axis[1] == 0.0 {
-                        // Cull nodes that are completely clipped
-                        if positions_diff[0].x - positions_diff[1].x >= transformed_rect_size.x
-                            || positions_diff[1].y - positions_diff[2].y >= transformed_rect_size.y
-                        {
-                            continue;
-                        }
+    // Buffer indexes
+    let mut vertices_index = 0;
+    let mut skipped_nodes = 0;
+
+    // The vertices of the prioritized nodes are reserved in stack order, as long as they fit in
+    // the budget
+    let mut reserved_vertices = 0;
+    let mut reserved_nodes = EntityHashSet::default();
+    for item in phases.values().flat_map(|ui_phase| &ui_phase.items) {
+        let Some(extracted_uinode) = uinodes.get(&item.entity) else {
+            continue;
+        };
+        let vertex_count = ui_node_vertex_count(extracted_uinode, extracted_meshes);
+        if extracted_uinode.vertex_priority && vertex_budget.allows(reserved_vertices, vertex_count)
+        {
+            reserved_vertices += vertex_count;
+            reserved_nodes.insert(item.entity);
+        }
+    }
+
+    for ui_phase in phases.values_mut() {
+        let mut batch_item_index = 0;
+        let mut batch_image_handle = AssetId::invalid();
+
+        for item_index in 0..ui_phase.items.len() {
+            let item = &mut ui_phase.items[item_index];
+            if let Some(extracted_uinode) = uinodes.get(&item.entity) {
+                let mesh = extracted_uinode
+                    .mesh
+                    .and_then(|mesh| extracted_meshes.meshes.get(&mesh));
+                let vertex_count = ui_node_vertex_count(extracted_uinode, extracted_meshes);
+                if reserved_nodes.contains(&item.entity) {
+                    reserved_vertices -= vertex_count;
+                } else if !vertex_budget
+                    .allows(ui_meta.vertices.len() + reserved_vertices, vertex_count)
+                {
+                    // Skipped nodes end the batch, the batch range of its first item covers
+                    // consecutive items
+                    skipped_nodes += 1;
+                    batch_image_handle = AssetId::invalid();
+                    continue;
+                }
+
+                let mut existing_batch = batches.last_mut();
+                let image_array = array_batching.draws_from_array(extracted_uinode);
+
+                if batch_image_handle == AssetId::invalid()
+                    || !existing_batch
+                        .as_ref()
+                        .is_some_and(|(_, batch)| batch.accepts(extracted_uinode, image_array))
+                {
+                    if image_size(extracted_uinode.image).is_some() {
+                        batch_item_index = item_index;
+                        batch_image_handle = extracted_uinode.image;
+
+                        let new_batch = UiBatch {
+                            range: vertices_index..vertices_index,
+                            image: extracted_uinode.image,
+                            image_layer: extracted_uinode.image_layer,
+                            sampler: extracted_uinode.sampler,
+                            camera: extracted_uinode.camera_entity,
+                            clip: extracted_uinode.scissor_clip(),
+                            stencil: extracted_uinode.stencil,
+                            premultiplied_alpha: extracted_uinode.premultiplied_alpha,
+                            image_array,
+                        };
+
+                        batches.push((item.entity, new_batch));
+                        existing_batch = batches.last_mut();
+                    } else {
+                        complete = false;
+                        batch_image_handle = AssetId::invalid();
+                        continue;
                     }
-                    let uvs = if flags == shader_flags::UNTEXTURED {
-                        [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]
+                } else if batch_image_handle == AssetId::default()
+                    && extracted_uinode.image != AssetId::default()
+                {
+                    if image_size(extracted_uinode.image).is_some() {
+                        batch_image_handle = extracted_uinode.image;
+                        let batch = &mut existing_batch.as_mut().unwrap().1;
+                        batch.image = extracted_uinode.image;
+                        batch.image_layer = extracted_uinode.image_layer;
+                        batch.sampler = extracted_uinode.sampler;
                     } else {
-                        let image = gpu_images
-                            .get(extracted_uinode.image)
-                            .expect("Image was checked during batching and should still exist");
-                        // Rescale atlases. This is done here because we need texture data that might not be available in Extract.
-                        let atlas_extent = extracted_uinode
-                            .atlas_scaling
-                            .map(|scaling| image.size.as_vec2() * scaling)
-                            .unwrap_or(uinode_rect.max);
-                        if extracted_uinode.flip_x {
-                            std::mem::swap(&mut uinode_rect.max.x, &mut uinode_rect.min.x);
-                            positions_diff[0].x *= -1.;
-                            positions_diff[1].x *= -1.;
-                            positions_diff[2].x *= -1.;
-                            positions_diff[3].x *= -1.;
-                        }
-                        if extracted_uinode.flip_y {
-                            std::mem::swap(&mut uinode_rect.max.y, &mut uinode_rect.min.y);
-                            positions_diff[0].y *= -1.;
-                            positions_diff[1].y *= -1.;
-                            positions_diff[2].y *= -1.;
-                            positions_diff[3].y *= -1.;
-                        }
-                        [
-                            Vec2::new(
-                                uinode_rect.min.x + positions_diff[0].x,
-                                uinode_rect.min.y + positions_diff[0].y,
-                            ),
-                            Vec2::new(
-                                uinode_rect.max.x + positions_diff[1].x,
-                                uinode_rect.min.y + positions_diff[1].y,
-                            ),
-                            Vec2::new(
-                                uinode_rect.max.x + positions_diff[2].x,
-                                uinode_rect.max.y + positions_diff[2].y,
-                            ),
-                            Vec2::new(
-                                uinode_rect.min.x + positions_diff[3].x,
-                                uinode_rect.max.y + positions_diff[3].y,
-                            ),
-                        ]
-                        .map(|pos| pos / atlas_extent)
-                    };
-
-                    let color = extracted_uinode.color.to_f32_array();
-                    if extracted_uinode.node_type == NodeType::Border {
-                        flags |= shader_flags::BORDER;
+                        complete = false;
+                        batch_image_handle = AssetId::invalid();
+                        continue;
                     }
+                }
 
-                    for i in 0..4 {
-                        ui_meta.vertices.push(UiVertex {
-                            position: positions_clipped[i].into(),
-                            uv: uvs[i].into(),
-                            color,
-                            flags: flags | shader_flags::CORNERS[i],
-                            radius: extracted_uinode.border_radius,
-                            border: extracted_uinode.border,
-                            size: rect_size.xy().into(),
-                        });
+                let image_size = if extracted_uinode.image != AssetId::default() {
+                    image_size(extracted_uinode.image)
+                        .expect("Image was checked during batching and should still exist")
+                } else {
+                    Vec2::ZERO
+                };
+                let index_count = ui_meta.indices.len();
+                let written = match mesh {
+                    Some(mesh) => write_ui_mesh_geometry(
+                        extracted_uinode,
+                        mesh,
+                        ui_meta.vertices.values_mut(),
+                        ui_meta.indices.values_mut(),
+                    ),
+                    None => write_ui_node_geometry(
+                        extracted_uinode,
+                        image_size,
+                        ui_meta.vertices.values_mut(),
+                        ui_meta.indices.values_mut(),
+                    ),
+                };
+                if !written {
+                    // Nothing was written to the batch this node started
+                    if batches
+                        .last()
+                        .is_some_and(|(entity, _)| *entity == item.entity)
+                    {
+                        batches.pop();
                     }
+                    batch_image_handle = AssetId::invalid();
+                    continue;
+                }
+                vertices_index += (ui_meta.indices.len() - index_count) as u32;
 
-                    for &i in &QUAD_INDICES {
-                        ui_meta.indices.push(indices_index + i as u32);
-                    }
+                existing_batch.unwrap().1.range.end = vertices_index;
+                ui_phase.items[batch_item_index].batch_range_mut().end += 1;
+            } else {
+                batch_image_handle = AssetId::invalid();
+            }
+        }
+    }
 
-                    vertices_index += 6;
-                    indices_index += 4;
+    ui_meta
+        .capacity
+        .grow(ui_meta.vertices.len(), ui_meta.indices.len());
+    batch_diagnostics.set_buffer_capacity(ui_meta.capacity);
 
-                    existing_batch.unwrap().1.range.end = vertices_index;
-                    ui_phase.items[batch_item_index].batch_range_mut().end += 1;
-                } else {
-                    batch_image_handle = AssetId::invalid();
+    if vertex_budget_warning(vertex_budget_exceeded, skipped_nodes) {
+        warn!(
+            "The UI vertex budget of {} vertices was exceeded, {} nodes were not drawn",
+            vertex_budget.max, skipped_nodes
+        );
+    }
+
+    let batch_ranges = phases
+        .values()
+        .flat_map(|ui_phase| &ui_phase.items)
+        .filter(|item| !item.batch_range().is_empty() && uinodes.contains_key(&item.entity))
+        .map(|item| (item.entity, item.batch_range().clone()))
+        .collect();
+    frame_cache.store(
+        uinodes,
+        phase_items,
+        batches.clone(),
+        batch_ranges,
+        vertex_budget,
+        complete,
+    );
+    UiPreparedBatches {
+        batches,
+        rewritten: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy_core_pipeline::core_3d::graph::{Core3d, Node3d};
+    use bevy_math::{Mat4, Rect, Vec2, Vec3};
+    use bevy_render::{
+        render_graph::{Edge, EmptyNode, RenderGraph, RenderLabel},
+        renderer::{RenderAdapter, RenderDevice, WgpuWrapper},
+    };
+    use bevy_tasks::block_on;
+    use bevy_transform::components::GlobalTransform;
+
+    use super::{
+        box_shadow_clip, graph::NodeUi, inflated_clip, order_ui_pass_after_taa, stack_z,
+        ui_camera_far, vertex_budget_warning, UiVertexBudget, QUAD_VERTEX_POSITIONS, UI_CAMERA_FAR,
+        UI_CAMERA_TRANSFORM_OFFSET,
+    };
+    use crate::{BoxShadow, CalculatedClip, UiClipInflate};
+
+    /// An untextured white 10x10 node drawn to a placeholder camera, for the tests to override
+    /// with `..base()`.
+    pub(crate) fn base() -> super::ExtractedUiNode {
+        super::ExtractedUiNode {
+            rect: Rect::new(0., 0., 10., 10.),
+            ..Default::default()
+        }
+    }
+
+    /// A device and adapter of the first GPU found, for the tests creating pipelines. Returns
+    /// `None` on machines without one, where these tests pass without checking anything.
+    pub(crate) fn test_render_device() -> Option<(RenderDevice, RenderAdapter)> {
+        let instance = wgpu::Instance::default();
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))?;
+        let (device, _queue) =
+            block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).ok()?;
+        Some((
+            RenderDevice::from(device),
+            RenderAdapter(Arc::new(WgpuWrapper::new(adapter))),
+        ))
+    }
+
+    /// A 10x10 glyph at `x` of the text section `section_index`, rasterized into the atlas
+    /// `texture`. Every glyph shows the first rect of the atlas layout of [`extract_text`].
+    #[cfg(feature = "bevy_text")]
+    fn glyph(
+        x: f32,
+        texture: &bevy_asset::Handle<bevy_render::texture::Image>,
+        section_index: usize,
+    ) -> bevy_text::PositionedGlyph {
+        use bevy_asset::Handle;
+        use bevy_math::IVec2;
+        use bevy_text::{GlyphAtlasInfo, GlyphAtlasLocation, PositionedGlyph};
+
+        PositionedGlyph::new(
+            Vec2::new(x, 0.),
+            Vec2::splat(10.),
+            GlyphAtlasInfo {
+                texture: texture.clone(),
+                texture_atlas: Handle::default(),
+                location: GlyphAtlasLocation {
+                    glyph_index: 0,
+                    offset: IVec2::ZERO,
+                },
+            },
+            section_index,
+        )
+    }
+
+    /// Extracts the `glyphs` of a 100x20 `text` node with `components`, with
+    /// [`extract_uinode_text`](super::extract_uinode_text), in the order they're queued.
+    #[cfg(feature = "bevy_text")]
+    fn extract_text(
+        text: bevy_text::Text,
+        glyphs: Vec<bevy_text::PositionedGlyph>,
+        components: impl bevy_ecs::bundle::Bundle,
+    ) -> Vec<(bevy_ecs::entity::Entity, super::ExtractedUiNode)> {
+        use bevy_asset::{Assets, Handle};
+        use bevy_math::{URect, UVec2};
+        use bevy_render::{view::ViewVisibility, MainWorld};
+        use bevy_sprite::TextureAtlasLayout;
+        use bevy_text::TextLayoutInfo;
+
+        use super::extract_uinode_text;
+        use crate::{Node, TargetCamera, UiScale};
+
+        let mut main_world = MainWorld::default();
+        let mut layout = TextureAtlasLayout::new_empty(UVec2::splat(64));
+        layout.add_texture(URect::new(0, 0, 10, 10));
+        let mut layouts = Assets::<TextureAtlasLayout>::default();
+        layouts.insert(Handle::<TextureAtlasLayout>::default().id(), layout);
+        main_world.insert_resource(layouts);
+        main_world.init_resource::<UiScale>();
+
+        let camera = main_world.spawn_empty().id();
+        let mut view_visibility = ViewVisibility::HIDDEN;
+        view_visibility.set();
+        main_world.spawn((
+            Node {
+                calculated_size: Vec2::new(100., 20.),
+                ..Node::DEFAULT
+            },
+            GlobalTransform::from_xyz(50., 10., 0.),
+            view_visibility,
+            TargetCamera(camera),
+            text,
+            TextLayoutInfo {
+                glyphs,
+                size: Vec2::new(100., 20.),
+            },
+            components,
+        ));
+
+        // The glyphs of a node share its stack index, so they're queued in the order they're
+        // spawned
+        extract(main_world, extract_uinode_text)
+    }
+
+    /// Runs the extraction `system` on `main_world`, returning the extracted nodes in the order
+    /// they were spawned.
+    fn extract<M>(
+        main_world: bevy_render::MainWorld,
+        system: impl bevy_ecs::system::IntoSystem<(), (), M>,
+    ) -> Vec<(bevy_ecs::entity::Entity, super::ExtractedUiNode)> {
+        use bevy_ecs::{system::RunSystemOnce, world::World};
+
+        use super::ExtractedUiNodes;
+
+        let mut world = World::new();
+        world.insert_resource(main_world);
+        world.init_resource::<ExtractedUiNodes>();
+        world.run_system_once(system);
+        let mut uinodes: Vec<_> = world
+            .resource_mut::<ExtractedUiNodes>()
+            .uinodes
+            .drain()
+            .collect();
+        uinodes.sort_by_key(|(entity, _)| entity.index());
+        uinodes
+    }
+
+    /// Prepares frames of extracted nodes with [`prepare_ui_batches`](super::prepare_ui_batches),
+    /// the part of [`prepare_uinodes`](super::prepare_uinodes) that doesn't need a render device.
+    pub(crate) struct PrepareHarness {
+        pub(crate) world: bevy_ecs::world::World,
+        draw_function: bevy_render::render_phase::DrawFunctionId,
+        vertex_budget: UiVertexBudget,
+        /// Whether the last frame exceeded the vertex budget, which logs a warning the first frame
+        /// it does.
+        pub(crate) vertex_budget_exceeded: bool,
+        /// The images whose [`GpuImage`](bevy_render::texture::GpuImage) isn't prepared yet, every
+        /// other image is ready to be drawn.
+        pub(crate) unloaded_images: Vec<bevy_asset::AssetId<bevy_render::texture::Image>>,
+    }
+
+    impl PrepareHarness {
+        pub(crate) fn new() -> Self {
+            use bevy_ecs::{entity::Entity, world::World};
+            use bevy_render::render_phase::{
+                Draw, DrawError, DrawFunctions, TrackedRenderPass, ViewSortedRenderPhases,
+            };
+
+            use super::{
+                ui_mesh::ExtractedUiMeshes, ExtractedUiNodes, TransparentUi, UiBatchDiagnostics,
+                UiFrameCache, UiMeta,
+            };
+
+            struct NoopDraw;
+            impl Draw<TransparentUi> for NoopDraw {
+                fn draw<'w>(
+                    &mut self,
+                    _world: &'w World,
+                    _pass: &mut TrackedRenderPass<'w>,
+                    _view: Entity,
+                    _item: &TransparentUi,
+                ) -> Result<(), DrawError> {
+                    Ok(())
                 }
             }
+
+            let mut world = World::new();
+            world.init_resource::<ExtractedUiNodes>();
+            world.init_resource::<ExtractedUiMeshes>();
+            world.init_resource::<ViewSortedRenderPhases<TransparentUi>>();
+            world.init_resource::<UiFrameCache>();
+            world.init_resource::<UiMeta>();
+            world.init_resource::<UiBatchDiagnostics>();
+            let draw_function = DrawFunctions::<TransparentUi>::default()
+                .write()
+                .add(NoopDraw);
+            Self {
+                world,
+                draw_function,
+                vertex_budget: UiVertexBudget::default(),
+                vertex_budget_exceeded: false,
+                unloaded_images: Vec::new(),
+            }
+        }
+
+        /// Prepares the frames with a vertex budget of `max` vertices.
+        pub(crate) fn with_vertex_budget(mut self, max: usize) -> Self {
+            self.vertex_budget = UiVertexBudget { max };
+            self
+        }
+
+        /// Extracts the `uinodes`, queues them in this order to the
+        /// [`TransparentUi`](super::TransparentUi) phase of their camera, and prepares them.
+        /// Returns `true` if the vertex buffer was rewritten.
+        pub(crate) fn render_frame(
+            &mut self,
+            uinodes: impl IntoIterator<Item = (bevy_ecs::entity::Entity, super::ExtractedUiNode)>,
+        ) -> bool {
+            use bevy_ecs::system::{Res, ResMut, RunSystemOnce};
+            use bevy_math::FloatOrd;
+            use bevy_render::{
+                render_phase::{PhaseItemExtraIndex, ViewSortedRenderPhases},
+                render_resource::CachedRenderPipelineId,
+            };
+
+            use super::{
+                prepare_ui_batches, ui_mesh::ExtractedUiMeshes, ExtractedUiNodes, TransparentUi,
+                UiBatchDiagnostics, UiFrameCache, UiImageArrayBatching, UiMeta,
+            };
+
+            let uinodes: Vec<_> = uinodes.into_iter().collect();
+            let mut phases = self
+                .world
+                .resource_mut::<ViewSortedRenderPhases<TransparentUi>>();
+            for (_, uinode) in &uinodes {
+                phases.insert_or_clear(uinode.camera_entity);
+            }
+            for (index, (entity, uinode)) in uinodes.iter().enumerate() {
+                phases
+                    .get_mut(&uinode.camera_entity)
+                    .unwrap()
+                    .add(TransparentUi {
+                        sort_key: (FloatOrd(index as f32), entity.index()),
+                        entity: *entity,
+                        pipeline: CachedRenderPipelineId::INVALID,
+                        draw_function: self.draw_function,
+                        batch_range: 0..0,
+                        extra_index: PhaseItemExtraIndex::NONE,
+                    });
+            }
+            self.world
+                .resource_mut::<ExtractedUiNodes>()
+                .uinodes
+                .extend(uinodes);
+
+            let vertex_budget = self.vertex_budget;
+            let mut vertex_budget_exceeded = self.vertex_budget_exceeded;
+            let unloaded_images = self.unloaded_images.clone();
+            let (rewritten, vertex_budget_exceeded) = self.world.run_system_once(
+                move |mut ui_meta: ResMut<UiMeta>,
+                      mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+                      mut phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
+                      mut frame_cache: ResMut<UiFrameCache>,
+                      extracted_meshes: Res<ExtractedUiMeshes>,
+                      batch_diagnostics: Res<UiBatchDiagnostics>| {
+                    let prepared = prepare_ui_batches(
+                        &mut ui_meta,
+                        &mut extracted_uinodes.uinodes,
+                        &mut phases,
+                        &mut frame_cache,
+                        &extracted_meshes,
+                        |image| (!unloaded_images.contains(&image)).then_some(Vec2::ZERO),
+                        vertex_budget,
+                        &mut vertex_budget_exceeded,
+                        &UiImageArrayBatching::default(),
+                        &batch_diagnostics,
+                        false,
+                    );
+                    extracted_uinodes.uinodes.clear();
+                    (prepared.rewritten, vertex_budget_exceeded)
+                },
+            );
+            self.vertex_budget_exceeded = vertex_budget_exceeded;
+            rewritten
         }
-        ui_meta.vertices.write_buffer(&render_device, &render_queue);
-        ui_meta.indices.write_buffer(&render_device, &render_queue);
-        *previous_len = batches.len();
-        commands.insert_or_spawn_batch(batches);
     }
-    extracted_uinodes.uinodes.clear();
+
+    #[test]
+    fn nodes_wait_for_their_image_to_be_prepared() {
+        use bevy_asset::Handle;
+        use bevy_ecs::entity::Entity;
+        use bevy_render::{
+            render_phase::{PhaseItem, ViewSortedRenderPhases},
+            texture::Image,
+        };
+
+        use super::{ExtractedUiNode, TransparentUi, UiFrameCache, UiMeta};
+
+        let view = Entity::from_raw(0);
+        let image = Handle::<Image>::weak_from_u128(1).id();
+        let node = || {
+            (
+                Entity::from_raw(1),
+                ExtractedUiNode {
+                    image,
+                    camera_entity: view,
+                    ..base()
+                },
+            )
+        };
+        let batch_ranges = |harness: &PrepareHarness| -> Vec<_> {
+            harness
+                .world
+                .resource::<ViewSortedRenderPhases<TransparentUi>>()[&view]
+                .items
+                .iter()
+                .map(|item| item.batch_range().clone())
+                .collect()
+        };
+
+        // The first frame after the image is loaded, its `GpuImage` isn't prepared yet
+        let mut harness = PrepareHarness::new();
+        harness.unloaded_images.push(image);
+        harness.render_frame([node()]);
+        assert!(harness
+            .world
+            .resource::<UiFrameCache>()
+            .batches()
+            .is_empty());
+        assert!(harness.world.resource::<UiMeta>().vertices().is_empty());
+        assert_eq!(batch_ranges(&harness), vec![0..0]);
+
+        // The node is drawn once it is
+        harness.unloaded_images.clear();
+        assert!(harness.render_frame([node()]));
+        let batches = harness.world.resource::<UiFrameCache>().batches();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].1.image, image);
+        assert_eq!(batch_ranges(&harness), vec![0..1]);
+    }
+
+    #[test]
+    fn ui_pass_runs_after_taa_resolve() {
+        let mut graph_3d = RenderGraph::default();
+        graph_3d.add_node(Node3d::EndMainPass, EmptyNode);
+        graph_3d.add_node(Node3d::Taa, EmptyNode);
+        graph_3d.add_node(Node3d::EndMainPassPostProcessing, EmptyNode);
+        graph_3d.add_node(NodeUi::UiPass, EmptyNode);
+        graph_3d.add_node_edges((
+            Node3d::EndMainPass,
+            Node3d::Taa,
+            Node3d::EndMainPassPostProcessing,
+            NodeUi::UiPass,
+        ));
+        let mut graph = RenderGraph::default();
+        graph.add_sub_graph(Core3d, graph_3d);
+
+        order_ui_pass_after_taa(&mut graph);
+        // Running it again must not add a duplicate edge
+        order_ui_pass_after_taa(&mut graph);
+
+        let ui_pass = graph
+            .sub_graph(Core3d)
+            .get_node_state(NodeUi::UiPass)
+            .unwrap();
+        assert!(ui_pass.edges.has_input_edge(&Edge::NodeEdge {
+            input_node: NodeUi::UiPass.intern(),
+            output_node: Node3d::Taa.intern(),
+        }));
+    }
+
+    fn ui_depth(stack_index: u32) -> f32 {
+        ui_depth_with_far(stack_index, UI_CAMERA_FAR)
+    }
+
+    fn ui_depth_with_far(stack_index: u32, far: f32) -> f32 {
+        let clip_from_view = Mat4::orthographic_rh(0.0, 100.0, 100.0, 0.0, 0.0, far);
+        let world_from_view =
+            GlobalTransform::from_xyz(0.0, 0.0, far + UI_CAMERA_TRANSFORM_OFFSET).compute_matrix();
+        let clip = clip_from_view
+            * world_from_view.inverse()
+            * Vec3::new(50.0, 50.0, stack_z(stack_index)).extend(1.0);
+        clip.z / clip.w
+    }
+
+    #[cfg(feature = "bevy_text")]
+    #[test]
+    fn text_section_opacity_only_fades_its_section() {
+        use bevy_asset::Handle;
+        use bevy_color::{Alpha, Color, LinearRgba};
+        use bevy_text::{Text, TextSection, TextStyle};
+
+        use crate::widget::TextSectionOpacity;
+
+        let style = TextStyle {
+            color: Color::WHITE,
+            ..Default::default()
+        };
+        let text = Text::from_sections([
+            TextSection::new("f", style.clone()),
+            TextSection::new("o", style),
+        ]);
+        let mut section_opacity = TextSectionOpacity::default();
+        section_opacity.set(0, 0.5);
+
+        let atlas = Handle::weak_from_u128(1);
+        let glyphs = vec![glyph(0., &atlas, 0), glyph(10., &atlas, 1)];
+        let colors: Vec<_> = extract_text(text, glyphs, section_opacity)
+            .into_iter()
+            .map(|(_, uinode)| uinode.color)
+            .collect();
+        assert_eq!(
+            colors,
+            vec![LinearRgba::WHITE.with_alpha(0.5), LinearRgba::WHITE]
+        );
+    }
+
+    #[cfg(feature = "bevy_text")]
+    #[test]
+    fn text_clip_is_inflated() {
+        use bevy_asset::Handle;
+        use bevy_text::{Text, TextStyle};
+
+        use crate::{CalculatedClip, UiClipInflate};
+
+        let atlas = Handle::weak_from_u128(1);
+        let clip = CalculatedClip {
+            clip: Rect::new(0., 0., 100., 20.),
+        };
+        let uinodes = extract_text(
+            Text::from_section("f", TextStyle::default()),
+            vec![glyph(0., &atlas, 0)],
+            (clip, UiClipInflate(4.)),
+        );
+        assert_eq!(uinodes[0].1.clip, Some(Rect::new(-4., -4., 104., 24.)));
+    }
+
+    #[cfg(feature = "bevy_text")]
+    #[test]
+    fn overflow_fade_reduces_alpha_near_the_clip_edge() {
+        use crate::widget::TextOverflowFade;
+
+        let overflow_fade = TextOverflowFade { width: 20. };
+        let clip = Rect::new(0., 0., 100., 20.);
+        // The text overflows the right edge of its clip
+        let text_bounds = Rect::new(0., 0., 160., 20.);
+
+        assert_eq!(
+            overflow_fade.alpha(Vec2::new(50., 10.), text_bounds, clip),
+            1.
+        );
+        assert_eq!(
+            overflow_fade.alpha(Vec2::new(90., 10.), text_bounds, clip),
+            0.5
+        );
+        assert_eq!(
+            overflow_fade.alpha(Vec2::new(105., 10.), text_bounds, clip),
+            0.
+        );
+
+        // Text that fits its clip is not faded, however close to the edge
+        let fitting_bounds = Rect::new(0., 0., 100., 20.);
+        assert_eq!(
+            overflow_fade.alpha(Vec2::new(98., 10.), fitting_bounds, clip),
+            1.
+        );
+    }
+
+    #[cfg(feature = "bevy_text")]
+    #[test]
+    fn glyphs_batch_by_their_font_instance_atlas() {
+        use bevy_asset::Handle;
+        use bevy_text::{Text, TextSection, TextStyle};
+
+        use super::UiFrameCache;
+
+        // Two variable font instances, e.g. regular and bold, each with their own atlas
+        let regular = Handle::weak_from_u128(1);
+        let bold = Handle::weak_from_u128(2);
+        let text = Text::from_sections(
+            ["ab", "cd", "e"].map(|value| TextSection::new(value, TextStyle::default())),
+        );
+        // "ab" regular, "cd" bold, "e" regular
+        let glyphs = vec![
+            glyph(0., &regular, 0),
+            glyph(10., &regular, 0),
+            glyph(20., &bold, 1),
+            glyph(30., &bold, 1),
+            glyph(40., &regular, 2),
+        ];
+        let uinodes = extract_text(text, glyphs, ());
+        let positions: Vec<_> = uinodes
+            .iter()
+            .map(|(_, uinode)| uinode.transform.transform_point3(Vec3::ZERO).x)
+            .collect();
+        // The regular glyphs are drawn first, then the bold ones
+        assert_eq!(positions, vec![0., 10., 40., 20., 30.]);
+
+        let mut harness = PrepareHarness::new();
+        harness.render_frame(uinodes);
+        let batches: Vec<_> = harness
+            .world
+            .resource::<UiFrameCache>()
+            .batches()
+            .iter()
+            .map(|(_, batch)| (batch.image, batch.range.clone()))
+            .collect();
+        assert_eq!(batches, vec![(regular.id(), 0..18), (bold.id(), 18..30)]);
+    }
+
+    #[test]
+    fn box_shadow_can_escape_container_clip() {
+        use bevy_render::{view::ViewVisibility, MainWorld};
+
+        use super::extract_uinode_box_shadows;
+        use crate::{Node, TargetCamera, UiScale, Val};
+
+        let container_clip = Rect::new(0., 0., 100., 100.);
+        // Extracts the shadow of a 10x10 node touching the right edge of its clipped container
+        let extract_shadow = |escape_clip| {
+            let mut main_world = MainWorld::default();
+            main_world.init_resource::<UiScale>();
+            let camera = main_world.spawn_empty().id();
+            let mut view_visibility = ViewVisibility::HIDDEN;
+            view_visibility.set();
+            main_world.spawn((
+                Node {
+                    calculated_size: Vec2::splat(10.),
+                    ..Node::DEFAULT
+                },
+                GlobalTransform::from_xyz(95., 50., 0.),
+                view_visibility,
+                CalculatedClip {
+                    clip: container_clip,
+                },
+                TargetCamera(camera),
+                BoxShadow {
+                    x_offset: Val::Px(5.),
+                    y_offset: Val::ZERO,
+                    blur_radius: Val::Px(4.),
+                    escape_clip,
+                    ..Default::default()
+                },
+            ));
+            let mut uinodes = extract(main_world, extract_uinode_box_shadows);
+            assert_eq!(uinodes.len(), 1);
+            uinodes.pop().unwrap().1
+        };
+
+        // The shadow quad reaches past the right edge of the container
+        let shadow = extract_shadow(false);
+        let right = shadow.transform.transform_point3(Vec3::ZERO).x + 0.5 * shadow.rect.width();
+        assert_eq!(right, 109.);
+        assert_eq!(shadow.clip, Some(container_clip));
+
+        assert_eq!(extract_shadow(true).clip, None);
+    }
+
+    #[test]
+    fn clip_inflate_extends_geometry_to_inflated_bounds() {
+        let container_clip = CalculatedClip {
+            clip: Rect::new(0., 0., 100., 100.),
+        };
+        // A node overflowing the right edge of its clipped container
+        let node_rect = Rect::new(90., 40., 120., 60.);
+
+        let clip = inflated_clip(Some(&container_clip), None).unwrap();
+        assert_eq!(node_rect.intersect(clip).max.x, 100.);
+
+        let clip = inflated_clip(Some(&container_clip), Some(&UiClipInflate(8.))).unwrap();
+        assert_eq!(clip, Rect::new(-8., -8., 108., 108.));
+        assert_eq!(node_rect.intersect(clip), Rect::new(90., 40., 108., 60.));
+
+        // Nodes that aren't clipped stay unclipped
+        assert_eq!(inflated_clip(None, Some(&UiClipInflate(8.))), None);
+
+        let box_shadow = BoxShadow::default();
+        assert_eq!(
+            box_shadow_clip(&box_shadow, Some(&container_clip), Some(&UiClipInflate(8.))),
+            Some(Rect::new(-8., -8., 108., 108.))
+        );
+    }
+
+    #[test]
+    fn vertex_budget_limits_prepared_vertices() {
+        use bevy_ecs::entity::Entity;
+
+        use super::{ExtractedUiNode, UiFrameCache, UiMeta};
+
+        let view = Entity::from_raw(0);
+        let quads = |count: u32| {
+            (1..=count).map(move |i| {
+                (
+                    Entity::from_raw(i),
+                    ExtractedUiNode {
+                        stack_index: i,
+                        camera_entity: view,
+                        ..base()
+                    },
+                )
+            })
+        };
+
+        // Room for two quads of four vertices
+        let mut harness = PrepareHarness::new().with_vertex_budget(10);
+        assert!(harness.render_frame(quads(5)));
+        let ui_meta = harness.world.resource::<UiMeta>();
+        assert_eq!(ui_meta.vertices().len(), 8);
+        assert_eq!(ui_meta.indices().len(), 12);
+        // The nodes at the bottom of the stack are drawn, in a single batch
+        let frame_cache = harness.world.resource::<UiFrameCache>();
+        let batches: Vec<_> = frame_cache
+            .batches()
+            .iter()
+            .map(|(entity, batch)| (*entity, batch.range.clone()))
+            .collect();
+        assert_eq!(batches, vec![(Entity::from_raw(1), 0..12)]);
+        assert_eq!(frame_cache.batch_ranges[&Entity::from_raw(1)], 0..2);
+        // The warning is logged the first frame the budget is exceeded
+        assert!(harness.vertex_budget_exceeded);
+
+        // Within the budget, every node is drawn and the warning is reset
+        assert!(harness.render_frame(quads(2)));
+        assert_eq!(harness.world.resource::<UiMeta>().vertices().len(), 8);
+        assert!(!harness.vertex_budget_exceeded);
+
+        // The warning is logged once while the budget stays exceeded
+        let mut was_exceeded = false;
+        assert!(vertex_budget_warning(&mut was_exceeded, 3));
+        assert!(!vertex_budget_warning(&mut was_exceeded, 3));
+        assert!(!vertex_budget_warning(&mut was_exceeded, 0));
+        assert!(vertex_budget_warning(&mut was_exceeded, 3));
+
+        assert!(UiVertexBudget::default().allows(usize::MAX - 4, 4));
+    }
+
+    #[test]
+    fn skipped_nodes_end_their_batch() {
+        use bevy_asset::Handle;
+        use bevy_ecs::entity::Entity;
+        use bevy_render::{
+            render_phase::{PhaseItem, ViewSortedRenderPhases},
+            texture::Image,
+        };
+
+        use super::{ui_batch_blocks, ExtractedUiNode, TransparentUi, UiFrameCache, UiMeta};
+
+        let view = Entity::from_raw(0);
+        let quad = |index: u32, uinode: ExtractedUiNode| {
+            (
+                Entity::from_raw(index),
+                ExtractedUiNode {
+                    stack_index: index,
+                    camera_entity: view,
+                    ..uinode
+                },
+            )
+        };
+        let batches = |harness: &PrepareHarness| -> Vec<_> {
+            let phase = &harness
+                .world
+                .resource::<ViewSortedRenderPhases<TransparentUi>>()[&view];
+            // The items drawn by each batch
+            ui_batch_blocks(&phase.items)
+                .into_iter()
+                .filter(|block| !phase.items[block.start].batch_range().is_empty())
+                .map(|block| {
+                    phase.items[block]
+                        .iter()
+                        .map(|item| item.entity.index())
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+
+        // Room for two quads of four vertices, the third node is prioritized so the second one
+        // doesn't fit. The first and third nodes could share a batch, but aren't consecutive.
+        let mut harness = PrepareHarness::new().with_vertex_budget(8);
+        harness.render_frame([
+            quad(1, base()),
+            quad(2, base()),
+            quad(
+                3,
+                ExtractedUiNode {
+                    vertex_priority: true,
+                    ..base()
+                },
+            ),
+        ]);
+        assert_eq!(harness.world.resource::<UiMeta>().vertices().len(), 8);
+        assert_eq!(batches(&harness), vec![vec![1], vec![3]]);
+        assert_eq!(harness.world.resource::<UiFrameCache>().batches().len(), 2);
+
+        // Nodes whose image isn't prepared yet end their batch too
+        let image = Handle::<Image>::weak_from_u128(1).id();
+        let mut harness = PrepareHarness::new();
+        harness.unloaded_images.push(image);
+        harness.render_frame([
+            quad(1, base()),
+            quad(2, ExtractedUiNode { image, ..base() }),
+            quad(3, base()),
+            quad(4, base()),
+        ]);
+        assert_eq!(batches(&harness), vec![vec![1], vec![3, 4]]);
+    }
+
+    #[test]
+    fn buffer_capacity_never_decreases() {
+        use bevy_ecs::entity::Entity;
+
+        use super::{ExtractedUiNode, UiBatchDiagnostics, UiBufferCapacity, UiMeta};
+
+        let view = Entity::from_raw(0);
+        let quads = |count: u32| {
+            (1..=count).map(move |i| {
+                (
+                    Entity::from_raw(i),
+                    ExtractedUiNode {
+                        stack_index: i,
+                        camera_entity: view,
+                        ..base()
+                    },
+                )
+            })
+        };
+        let capacities = |harness: &PrepareHarness| {
+            (
+                harness.world.resource::<UiMeta>().capacity(),
+                harness
+                    .world
+                    .resource::<UiBatchDiagnostics>()
+                    .buffer_capacity(),
+            )
+        };
+
+        // A menu with 100 quads is open, then closes
+        let mut harness = PrepareHarness::new();
+        assert!(harness.render_frame(quads(100)));
+        // Capacities are rounded up to a power of two, so they rarely grow again
+        let open = UiBufferCapacity {
+            vertices: 512,
+            indices: 1024,
+        };
+        assert_eq!(capacities(&harness), (open, open));
+
+        assert!(harness.render_frame(quads(2)));
+        assert_eq!(harness.world.resource::<UiMeta>().vertices().len(), 8);
+        assert_eq!(capacities(&harness), (open, open));
+    }
+
+    #[test]
+    fn image_layer_view_selects_a_single_array_layer() {
+        use super::ui_image_layer_view_descriptor;
+        use bevy_render::render_resource::TextureViewDimension;
+
+        // Layer 2 of a 2D array texture with 4 layers
+        let descriptor = ui_image_layer_view_descriptor(2, 4);
+        assert_eq!(descriptor.dimension, Some(TextureViewDimension::D2));
+        assert_eq!(descriptor.base_array_layer, 2);
+        assert_eq!(descriptor.array_layer_count, Some(1));
+        assert_eq!(descriptor.base_mip_level, 0);
+
+        // Faces past the last one of a cube map show the last face
+        assert_eq!(ui_image_layer_view_descriptor(9, 6).base_array_layer, 5);
+    }
+
+    #[test]
+    fn layers_of_an_image_array_are_batched_together() {
+        use bevy_asset::Handle;
+        use bevy_ecs::entity::Entity;
+        use bevy_render::{render_resource::TextureViewDimension, texture::Image};
+
+        use super::{
+            ui_image_array_view_descriptor, write_ui_node_geometry, ExtractedUiNode, UiBatch,
+            UiImageArrayBatching,
+        };
+        use crate::ui_stencil_mask::UiStencil;
+
+        let icons = Handle::<Image>::weak_from_u128(7).id();
+        let icon = |image_layer| ExtractedUiNode {
+            rect: Rect::new(0., 0., 16., 16.),
+            image: icons,
+            image_layer,
+            ..base()
+        };
+        let batch = |image_array| UiBatch {
+            range: 0..6,
+            image: icons,
+            image_layer: Some(0),
+            sampler: None,
+            camera: Entity::PLACEHOLDER,
+            clip: None,
+            stencil: UiStencil::None,
+            premultiplied_alpha: false,
+            image_array,
+        };
+
+        // Without array batching, each layer needs its own batch
+        let batching = UiImageArrayBatching::default();
+        assert!(!batching.draws_from_array(&icon(Some(3))));
+        assert!(!batch(false).accepts(&icon(Some(3)), false));
+
+        // With it, the layers of an image share a batch, but not with the plain views of the image
+        let batching = UiImageArrayBatching::ENABLED;
+        assert!(batching.draws_from_array(&icon(Some(3))));
+        assert!(!batching.draws_from_array(&icon(None)));
+        assert!(batch(true).accepts(&icon(Some(3)), true));
+        assert!(!batch(true).accepts(&icon(None), false));
+        assert!(!batch(false).accepts(&icon(Some(0)), true));
+
+        // The layer is sampled from the vertices of each node, out of a view of every layer
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        assert!(write_ui_node_geometry(
+            &icon(Some(3)),
+            Vec2::splat(16.),
+            &mut vertices,
+            &mut indices
+        ));
+        assert!(vertices.iter().all(|vertex| vertex.layer == 3));
+        let descriptor = ui_image_array_view_descriptor();
+        assert_eq!(descriptor.dimension, Some(TextureViewDimension::D2Array));
+        assert_eq!(descriptor.array_layer_count, None);
+    }
+
+    #[test]
+    fn images_sampled_differently_get_their_own_bind_groups() {
+        use super::{UiSampledImageKey, UiSamplerKey};
+        use bevy_asset::Handle;
+        use bevy_render::{
+            render_resource::FilterMode,
+            texture::{Image, ImageSamplerDescriptor},
+        };
+        use bevy_utils::HashSet;
+
+        let linear = UiSamplerKey::from(&ImageSamplerDescriptor::linear());
+        let nearest = UiSamplerKey::from(&ImageSamplerDescriptor::nearest());
+        assert_ne!(linear, nearest);
+        assert_eq!(
+            linear,
+            UiSamplerKey::from(&ImageSamplerDescriptor::linear())
+        );
+
+        let descriptor = nearest.descriptor();
+        assert_eq!(descriptor.mag_filter, FilterMode::Nearest);
+        assert_eq!(descriptor.min_filter, FilterMode::Nearest);
+        assert_eq!(descriptor.lod_max_clamp, 32.);
+        assert_eq!(linear.descriptor().mag_filter, FilterMode::Linear);
+
+        // The same image sampled both ways has a bind group for each sampler
+        let image = Handle::<Image>::weak_from_u128(7).id();
+        let keys: HashSet<UiSampledImageKey> = [
+            (image, None, linear),
+            (image, None, nearest),
+            (image, None, linear),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn quads_are_drawn_from_four_vertices_and_six_indices() {
+        use super::{ui_quad_grid, ui_quad_grid_indices, ui_quad_grid_vertex_count, QUAD_INDICES};
+        use bevy_math::UVec2;
+
+        // Untessellated nodes are a single quad, indexed like `prepare_uinodes` does
+        let (mut vertices, mut indices) = (0, Vec::new());
+        for _ in 0..3 {
+            let first_vertex = vertices as u32;
+            vertices += ui_quad_grid(UVec2::ONE).count();
+            indices.extend(ui_quad_grid_indices(UVec2::ONE).map(|i| first_vertex + i));
+        }
+        assert_eq!(ui_quad_grid_vertex_count(UVec2::ONE), 4);
+        assert_eq!(vertices, 3 * 4);
+        assert_eq!(indices.len(), 3 * QUAD_INDICES.len());
+        assert_eq!(indices[6..12], [4, 7, 6, 4, 5, 7]);
+    }
+
+    #[test]
+    fn tessellated_gradient_produces_a_vertex_grid() {
+        use super::{
+            bilerp, ui_gradient_subdivisions, ui_quad_grid, ui_quad_grid_corner,
+            ui_quad_grid_indices, ui_quad_grid_vertex_count, QUAD_INDICES,
+        };
+        use bevy_math::{UVec2, Vec4};
+
+        let subdivisions = ui_gradient_subdivisions(Vec2::new(100., 40.), 32.);
+        assert_eq!(subdivisions, UVec2::new(4, 2));
+
+        let grid: Vec<Vec2> = ui_quad_grid(subdivisions).collect();
+        assert_eq!(grid.len(), 15);
+        assert_eq!(grid.len(), ui_quad_grid_vertex_count(subdivisions));
+        assert_eq!(grid[0], Vec2::ZERO);
+        assert_eq!(grid[1], Vec2::new(0.25, 0.));
+        assert_eq!(grid[7], Vec2::new(0.5, 0.5));
+        assert_eq!(grid[14], Vec2::ONE);
+
+        let corners: Vec<_> = (0..grid.len())
+            .filter_map(|i| ui_quad_grid_corner(subdivisions, i).map(|corner| (i, corner)))
+            .collect();
+        assert_eq!(corners, vec![(0, 0), (4, 1), (10, 3), (14, 2)]);
+
+        let indices: Vec<u32> = ui_quad_grid_indices(subdivisions).collect();
+        assert_eq!(indices.len(), 4 * 2 * QUAD_INDICES.len());
+        assert!(indices.iter().all(|&i| (i as usize) < grid.len()));
+        // The first cell has the winding of a single quad
+        assert_eq!(indices[..6], [0, 6, 5, 0, 1, 6]);
+
+        // Interior vertices get the interpolated gradient color
+        let colors = [Vec4::ZERO, Vec4::X, Vec4::ONE, Vec4::Y];
+        assert_eq!(bilerp(colors, grid[7]), Vec4::new(0.5, 0.5, 0.25, 0.25));
+
+        // Nodes without tessellation are a single quad, with its vertices row by row
+        let quad_indices: Vec<u32> = ui_quad_grid_indices(UVec2::ONE).collect();
+        assert_eq!(quad_indices, [0, 3, 2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn ui_geometry_stays_front_facing_with_a_flipped_projection() {
+        use bevy_math::{UVec2, UVec4};
+        use bevy_render::{render_resource::FrontFace, view::ExtractedView};
+
+        use super::{bilerp, ui_front_face, ui_quad_grid, ui_quad_grid_indices, QUAD_INDICES};
+
+        let view = |clip_from_view| ExtractedView {
+            clip_from_view,
+            world_from_view: GlobalTransform::from_xyz(
+                0.0,
+                0.0,
+                UI_CAMERA_FAR + UI_CAMERA_TRANSFORM_OFFSET,
+            ),
+            clip_from_world: None,
+            hdr: false,
+            viewport: UVec4::new(0, 0, 100, 100),
+            color_grading: Default::default(),
+        };
+        // The default top left origin, and a y axis flipped for a render target expecting it
+        let default_view = view(Mat4::orthographic_rh(
+            0.0,
+            100.0,
+            100.0,
+            0.0,
+            0.0,
+            UI_CAMERA_FAR,
+        ));
+        let flipped_view = view(Mat4::orthographic_rh(
+            0.0,
+            100.0,
+            0.0,
+            100.0,
+            0.0,
+            UI_CAMERA_FAR,
+        ));
+        assert_ne!(ui_front_face(&default_view), ui_front_face(&flipped_view));
+
+        let corners =
+            QUAD_VERTEX_POSITIONS.map(|position| position * 50. + Vec3::new(50., 50., 0.));
+        let grid: Vec<Vec3> = ui_quad_grid(UVec2::splat(2))
+            .map(|st| bilerp(corners, st))
+            .collect();
+        let triangles: Vec<[Vec3; 3]> = QUAD_INDICES
+            .chunks(3)
+            .map(|triangle| [0, 1, 2].map(|i| corners[triangle[i]]))
+            .chain(
+                ui_quad_grid_indices(UVec2::splat(2))
+                    .collect::<Vec<_>>()
+                    .chunks(3)
+                    .map(|triangle| [0, 1, 2].map(|i| grid[triangle[i] as usize])),
+            )
+            .collect();
+
+        for view in [default_view, flipped_view] {
+            // Triangles are front-facing if their winding in normalized device coordinates
+            // matches the front face, so back face culling keeps them
+            let clip_from_world =
+                view.clip_from_view * view.world_from_view.compute_matrix().inverse();
+            let front_face = ui_front_face(&view);
+            for triangle in &triangles {
+                let [a, b, c] = triangle.map(|position| {
+                    let clip = clip_from_world * position.extend(1.);
+                    clip.truncate().truncate() / clip.w
+                });
+                let counter_clockwise = (b - a).perp_dot(c - a) > 0.;
+                assert_eq!(counter_clockwise, front_face == FrontFace::Ccw);
+            }
+        }
+    }
+
+    #[test]
+    fn batch_dropped_by_a_user_system_is_not_drawn() {
+        use bevy_ecs::{
+            entity::Entity,
+            schedule::{IntoSystemConfigs, Schedule},
+            system::ResMut,
+            world::World,
+        };
+        use bevy_math::FloatOrd;
+        use bevy_render::{
+            render_phase::{
+                Draw, DrawError, DrawFunctions, PhaseItem, PhaseItemExtraIndex, SortedRenderPhase,
+                TrackedRenderPass, ViewSortedRenderPhases,
+            },
+            render_resource::CachedRenderPipelineId,
+        };
+
+        use super::{retain_ui_batches, sort_ui_batches_by_key, RenderUiSystem, TransparentUi};
+
+        struct NoopDraw;
+        impl Draw<TransparentUi> for NoopDraw {
+            fn draw<'w>(
+                &mut self,
+                _world: &'w World,
+                _pass: &mut TrackedRenderPass<'w>,
+                _view: Entity,
+                _item: &TransparentUi,
+            ) -> Result<(), DrawError> {
+                Ok(())
+            }
+        }
+        let draw_function = DrawFunctions::<TransparentUi>::default()
+            .write()
+            .add(NoopDraw);
+        let item = |index: u32, batch_len: u32| TransparentUi {
+            sort_key: (FloatOrd(index as f32), index),
+            entity: Entity::from_raw(index),
+            pipeline: CachedRenderPipelineId::INVALID,
+            draw_function,
+            batch_range: 0..batch_len,
+            extra_index: PhaseItemExtraIndex::NONE,
+        };
+        let view = Entity::from_raw(100);
+        // Returns the entities of the phase items starting each drawn batch, in draw order
+        let drawn = |world: &World| -> Vec<u32> {
+            world.resource::<ViewSortedRenderPhases<TransparentUi>>()[&view]
+                .items
+                .iter()
+                .filter(|item| !item.batch_range().is_empty())
+                .map(|item| item.entity.index())
+                .collect()
+        };
+
+        let mut phases = ViewSortedRenderPhases::<TransparentUi>(Default::default());
+        phases.insert(
+            view,
+            SortedRenderPhase {
+                items: vec![
+                    item(0, 3),
+                    item(1, 0),
+                    item(2, 0),
+                    item(3, 1),
+                    item(4, 2),
+                    item(5, 0),
+                ],
+            },
+        );
+        let mut world = World::new();
+        world.insert_resource(phases);
+
+        let dropped = Entity::from_raw(3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (move |mut phases: ResMut<ViewSortedRenderPhases<TransparentUi>>| {
+                for phase in phases.values_mut() {
+                    retain_ui_batches(phase, |item| item.entity != dropped);
+                    // Draw the last batches first
+                    sort_ui_batches_by_key(phase, |item| std::cmp::Reverse(item.entity.index()));
+                }
+            })
+            .in_set(RenderUiSystem::ModifyBatches),
+        );
+        schedule.run(&mut world);
+
+        assert_eq!(drawn(&world), [4, 0]);
+        // The items batched with a drawn item follow it
+        let items: Vec<u32> = world.resource::<ViewSortedRenderPhases<TransparentUi>>()[&view]
+            .items
+            .iter()
+            .map(|item| item.entity.index())
+            .collect();
+        assert_eq!(items, [4, 5, 0, 1, 2]);
+    }
+
+    #[test]
+    fn unchanged_frame_does_not_rewrite_the_vertex_buffer() {
+        use bevy_color::LinearRgba;
+        use bevy_ecs::entity::Entity;
+
+        use super::ExtractedUiNode;
+
+        let view = Entity::from_raw(0);
+        let entity = Entity::from_raw(1);
+        let node = |color| ExtractedUiNode {
+            color,
+            camera_entity: view,
+            ..base()
+        };
+
+        let mut harness = PrepareHarness::new();
+        let mut render_frame = |color| harness.render_frame([(entity, node(color))]);
+
+        assert!(render_frame(LinearRgba::RED));
+        assert!(!render_frame(LinearRgba::RED));
+        assert!(!render_frame(LinearRgba::RED));
+        assert!(render_frame(LinearRgba::BLUE));
+        assert!(!render_frame(LinearRgba::BLUE));
+    }
+
+    #[test]
+    fn identical_extracted_frames_reuse_the_frame_cache() {
+        use bevy_ecs::system::RunSystemOnce;
+        use bevy_render::{view::ViewVisibility, MainWorld};
+
+        use super::{extract_uinode_box_shadows, ExtractedUiNodes, UiFrameCache};
+        use crate::{Node, TargetCamera, UiScale};
+
+        // Two nodes with a shadow, each extracted as a node spawned in the render world
+        let mut main_world = MainWorld::default();
+        main_world.init_resource::<UiScale>();
+        let camera = main_world.spawn_empty().id();
+        let mut view_visibility = ViewVisibility::HIDDEN;
+        view_visibility.set();
+        for x in [20., 60.] {
+            main_world.spawn((
+                Node {
+                    calculated_size: Vec2::splat(10.),
+                    ..Node::DEFAULT
+                },
+                GlobalTransform::from_xyz(x, 50., 0.),
+                view_visibility,
+                TargetCamera(camera),
+                BoxShadow::default(),
+            ));
+        }
+
+        let mut harness = PrepareHarness::new();
+        harness.world.insert_resource(main_world);
+        // Extracts and prepares a frame like the render app, whose render world entities were
+        // cleared at the end of the previous frame. `other_entities` are spawned before the
+        // shadows, e.g. by the extraction systems of other plugins.
+        let mut render_frame = |other_entities: usize| {
+            harness.world.clear_entities();
+            for _ in 0..other_entities {
+                harness.world.spawn_empty();
+            }
+            harness.world.run_system_once(extract_uinode_box_shadows);
+            let mut uinodes: Vec<_> = harness
+                .world
+                .resource_mut::<ExtractedUiNodes>()
+                .uinodes
+                .drain()
+                .collect();
+            uinodes.sort_by_key(|(entity, _)| entity.index());
+            let entities: Vec<_> = uinodes.iter().map(|(entity, _)| *entity).collect();
+            let rewritten = harness.render_frame(uinodes);
+            let batch_entity = harness.world.resource::<UiFrameCache>().batches()[0].0;
+            (rewritten, entities, batch_entity)
+        };
+
+        let (rewritten, first_entities, batch_entity) = render_frame(0);
+        assert!(rewritten);
+        assert_eq!(batch_entity, first_entities[0]);
+        // The shadows get other render world entities, the cache is still reused and its batch
+        // moved to the new entity of the first shadow
+        let (rewritten, second_entities, batch_entity) = render_frame(3);
+        assert_ne!(first_entities, second_entities);
+        assert!(!rewritten);
+        assert_eq!(batch_entity, second_entities[0]);
+    }
+
+    #[test]
+    fn scissor_clip_animation_keeps_the_vertex_buffer() {
+        use bevy_ecs::entity::Entity;
+        use bevy_math::{URect, UVec4};
+        use bevy_render::view::ExtractedView;
+
+        use super::{ui_scissor_rect, ExtractedUiNode, UiFrameCache};
+        use crate::UiClipMode;
+
+        let view = Entity::from_raw(0);
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        let node = |clip, clip_mode| ExtractedUiNode {
+            clip,
+            clip_mode,
+            camera_entity: view,
+            ..base()
+        };
+        // The UI of a 10x10 window with a scale factor of 1
+        let extracted_view = ExtractedView {
+            clip_from_view: Mat4::orthographic_rh(0.0, 10.0, 10.0, 0.0, 0.0, UI_CAMERA_FAR),
+            world_from_view: GlobalTransform::from_xyz(
+                0.0,
+                0.0,
+                UI_CAMERA_FAR + UI_CAMERA_TRANSFORM_OFFSET,
+            ),
+            clip_from_world: None,
+            hdr: false,
+            viewport: UVec4::new(0, 0, 10, 10),
+            color_grading: Default::default(),
+        };
+
+        let mut harness = PrepareHarness::new();
+        // Prepares two nodes with `clips`, returning whether the vertex buffer was rewritten and
+        // the scissor rects the batches are drawn with
+        let mut render_frame = |clips: [Option<Rect>; 2], clip_mode| {
+            let rewritten = harness.render_frame([
+                (first, node(clips[0], clip_mode)),
+                (second, node(clips[1], clip_mode)),
+            ]);
+            let scissor_rects: Vec<_> = harness
+                .world
+                .resource::<UiFrameCache>()
+                .batches()
+                .iter()
+                .map(|(_, batch)| {
+                    batch
+                        .clip
+                        .and_then(|clip| ui_scissor_rect(clip, &extracted_view))
+                })
+                .collect();
+            (rewritten, scissor_rects)
+        };
+
+        let top = Some(Rect::new(0., 0., 10., 5.));
+        let bottom = Some(Rect::new(0., 5., 10., 10.));
+        let top_rect = Some(URect::new(0, 0, 10, 5));
+        let bottom_rect = Some(URect::new(0, 5, 10, 10));
+
+        assert_eq!(
+            render_frame([top, top], UiClipMode::Scissor),
+            (true, vec![top_rect])
+        );
+        // Scrolling the clip only updates the scissor rect of the batch
+        assert_eq!(
+            render_frame([bottom, bottom], UiClipMode::Scissor),
+            (false, vec![bottom_rect])
+        );
+        // The nodes of the batch no longer share a clip, so it's split
+        assert_eq!(
+            render_frame([top, bottom], UiClipMode::Scissor),
+            (true, vec![top_rect, bottom_rect])
+        );
+        assert_eq!(
+            render_frame([bottom, bottom], UiClipMode::Scissor),
+            (false, vec![bottom_rect, bottom_rect])
+        );
+        // Clips moving the vertices rewrite them
+        assert_eq!(
+            render_frame([bottom, bottom], UiClipMode::Vertices),
+            (true, vec![None])
+        );
+        assert_eq!(
+            render_frame([top, top], UiClipMode::Vertices),
+            (true, vec![None])
+        );
+    }
+
+    #[test]
+    fn rotated_node_is_clipped_with_a_scissor_rect() {
+        use bevy_math::{Quat, URect, UVec4};
+        use bevy_render::view::ExtractedView;
+        use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+        use super::{ui_scissor_rect, write_ui_node_geometry, ExtractedUiNode};
+
+        // A 100x100 node at the center of a 200x200 UI, rotated by 45° and clipped to the top half
+        let center = Vec3::new(100., 100., 0.);
+        let clip = Rect::new(0., 0., 200., 100.);
+        let node = ExtractedUiNode {
+            transform: Mat4::from_rotation_translation(Quat::from_rotation_z(FRAC_PI_4), center),
+            rect: Rect::new(0., 0., 100., 100.),
+            clip: Some(clip),
+            ..base()
+        };
+
+        // The node is drawn whole, a diamond with its corners on the axes through its center
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        assert!(write_ui_node_geometry(
+            &node,
+            Vec2::ZERO,
+            &mut vertices,
+            &mut indices
+        ));
+        let corners: Vec<Vec3> = vertices
+            .iter()
+            .map(|vertex| Vec3::from(vertex.position))
+            .collect();
+        for corner in &corners {
+            assert!((corner.distance(center) - 50. * SQRT_2).abs() < 1e-3);
+        }
+
+        // The UI of a window with a scale factor of 2
+        let view = ExtractedView {
+            clip_from_view: Mat4::orthographic_rh(0.0, 200.0, 200.0, 0.0, 0.0, UI_CAMERA_FAR),
+            world_from_view: GlobalTransform::from_xyz(
+                0.0,
+                0.0,
+                UI_CAMERA_FAR + UI_CAMERA_TRANSFORM_OFFSET,
+            ),
+            clip_from_world: None,
+            hdr: false,
+            viewport: UVec4::new(0, 0, 400, 400),
+            color_grading: Default::default(),
+        };
+        let scissor = ui_scissor_rect(clip, &view).unwrap();
+        assert_eq!(scissor, URect::new(0, 0, 400, 200));
+
+        // Only the top half of the diamond is visible
+        let pixel = |position: Vec3| (position.truncate() * 2.).as_uvec2();
+        let top = corners.iter().min_by(|a, b| a.y.total_cmp(&b.y)).unwrap();
+        let bottom = corners.iter().max_by(|a, b| a.y.total_cmp(&b.y)).unwrap();
+        assert!(scissor.contains(pixel(*top)));
+        assert!(scissor.contains(pixel(center - Vec3::Y)));
+        assert!(!scissor.contains(pixel(center + Vec3::Y)));
+        assert!(!scissor.contains(pixel(*bottom)));
+
+        // A clip outside the viewport keeps no pixels
+        assert_eq!(
+            ui_scissor_rect(Rect::new(300., 0., 400., 100.), &view),
+            None
+        );
+    }
+
+    #[test]
+    fn atlas_frames_fill_their_image_node() {
+        use bevy_asset::Assets;
+        use bevy_math::UVec2;
+        use bevy_sprite::{TextureAtlas, TextureAtlasLayout};
+
+        use super::ui_image_rect;
+
+        // A sheet of 4x2 frames of 16x16 pixels, drawn in a 64x32 node
+        let mut layouts = Assets::<TextureAtlasLayout>::default();
+        let layout = layouts.add(TextureAtlasLayout::from_grid(
+            UVec2::splat(16),
+            4,
+            2,
+            None,
+            None,
+        ));
+        let size = Vec2::new(64., 32.);
+        let frame = |index| {
+            TextureAtlas {
+                layout: layout.clone(),
+                index,
+            }
+            .texture_rect(&layouts)
+            .map(|rect| rect.as_rect())
+        };
+        assert_eq!(frame(5), Some(Rect::new(16., 16., 32., 32.)));
+
+        // The frame is scaled to the node, the scaling maps it back to the texture
+        let (rect, scaling) = ui_image_rect(size, frame(5), None);
+        let scaling = scaling.unwrap();
+        assert_eq!(scaling, Vec2::new(4., 2.));
+        assert_eq!(rect, Rect::new(64., 32., 128., 64.));
+        assert_eq!(
+            Rect::from_corners(rect.min / scaling, rect.max / scaling),
+            frame(5).unwrap()
+        );
+
+        // The image rect is offset by the frame
+        let (rect, scaling) = ui_image_rect(size, frame(1), Some(Rect::new(0., 0., 8., 8.)));
+        assert_eq!(rect.min / scaling.unwrap(), Vec2::new(16., 0.));
+        assert_eq!(rect.max / scaling.unwrap(), Vec2::new(24., 8.));
+
+        // Without either, the whole texture is drawn
+        assert_eq!(
+            ui_image_rect(size, None, None),
+            (Rect::new(0., 0., 64., 32.), None)
+        );
+    }
+
+    #[test]
+    fn image_rect_shows_a_quarter_of_the_texture() {
+        use bevy_asset::Handle;
+
+        use super::{
+            clamp_ui_image_rect, ui_image_rect, write_ui_node_geometry, ExtractedUiNode, UiMeta,
+        };
+
+        // The bottom right quarter of a 64x64 texture, drawn in a 100x100 node
+        let image_size = Vec2::splat(64.);
+        let quarter = Rect::new(32., 32., 64., 64.);
+        let size = Vec2::splat(100.);
+        let (rect, atlas_scaling) = ui_image_rect(size, None, Some(quarter));
+        let node = ExtractedUiNode {
+            transform: Mat4::from_translation((size / 2.).extend(0.)),
+            rect,
+            image: Handle::weak_from_u128(1).id(),
+            atlas_scaling,
+            ..base()
+        };
+        let mut ui_meta = UiMeta::default();
+        assert!(write_ui_node_geometry(
+            &node,
+            image_size,
+            ui_meta.vertices.values_mut(),
+            ui_meta.indices.values_mut(),
+        ));
+
+        // The quarter fills the node
+        let vertices = ui_meta.vertices();
+        assert_eq!(vertices[0].position, [0., 0., 0.]);
+        assert_eq!(vertices[3].position, [100., 100., 0.]);
+        let uvs: Vec<_> = vertices.iter().map(|vertex| vertex.uv).collect();
+        assert_eq!(uvs, [[0.5, 0.5], [1., 0.5], [0.5, 1.], [1., 1.]]);
+
+        // Rects exceeding the texture are clamped to it
+        assert_eq!(
+            clamp_ui_image_rect(Rect::new(32., 32., 96., 96.), image_size),
+            Some(quarter)
+        );
+        assert_eq!(clamp_ui_image_rect(quarter, image_size), Some(quarter));
+        assert_eq!(
+            clamp_ui_image_rect(Rect::new(64., 0., 96., 32.), image_size),
+            None
+        );
+    }
+
+    #[test]
+    fn rotated_nodes_outside_their_clip_are_culled() {
+        use bevy_math::Quat;
+        use std::f32::consts::FRAC_PI_4;
+
+        use super::{write_ui_node_geometry, ExtractedUiNode};
+        use crate::UiClipMode;
+
+        // A 100x100 node centered on (100, 100)
+        let center = Vec3::new(100., 100., 0.);
+        let node = |transform, clip| ExtractedUiNode {
+            transform,
+            rect: Rect::new(0., 0., 100., 100.),
+            clip: Some(clip),
+            clip_mode: UiClipMode::Vertices,
+            ..base()
+        };
+        let is_drawn = |transform, clip| {
+            let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+            write_ui_node_geometry(
+                &node(transform, clip),
+                Vec2::ONE,
+                &mut vertices,
+                &mut indices,
+            )
+        };
+
+        // Rotated by 45°, the node is a diamond. The clip overlaps its bounding box but not the
+        // diamond itself
+        let diamond = Mat4::from_rotation_translation(Quat::from_rotation_z(FRAC_PI_4), center);
+        assert!(!is_drawn(diamond, Rect::new(0., 0., 60., 60.)));
+        assert!(is_drawn(diamond, Rect::new(0., 0., 70., 70.)));
+
+        // Rotated by exactly π, the corners are on horizontal and vertical lines
+        let upside_down =
+            Mat4::from_translation(center) * Mat4::from_scale(Vec3::new(-1., -1., 1.));
+        assert_eq!(upside_down.x_axis[1], 0.);
+        assert!(!is_drawn(upside_down, Rect::new(0., 0., 40., 40.)));
+        assert!(is_drawn(upside_down, Rect::new(0., 0., 60., 60.)));
+    }
+
+    #[test]
+    fn color_blend_flags_only_use_the_blend_bits() {
+        use super::shader_flags;
+        use crate::UiColorBlend;
+
+        let modes = [
+            UiColorBlend::Multiply,
+            UiColorBlend::Replace,
+            UiColorBlend::Overlay,
+            UiColorBlend::Tint,
+        ];
+        for (i, a) in modes.iter().enumerate() {
+            let flags = shader_flags::color_blend(*a);
+            assert_eq!(flags & !shader_flags::COLOR_BLEND_MASK, 0);
+            for b in &modes[i + 1..] {
+                assert_ne!(flags, shader_flags::color_blend(*b));
+            }
+        }
+        assert_eq!(shader_flags::color_blend(UiColorBlend::Multiply), 0);
+    }
+
+    #[test]
+    fn color_blend_reaches_the_vertex_flags() {
+        use bevy_asset::Handle;
+        use bevy_color::{ColorToComponents, LinearRgba};
+
+        use super::{shader_flags, write_ui_node_geometry, ExtractedUiNode};
+        use crate::UiColorBlend;
+
+        let tint = LinearRgba::new(1., 0., 0., 0.5);
+        let vertices_of = |color_blend| {
+            let image = ExtractedUiNode {
+                color: tint,
+                image: Handle::weak_from_u128(1).id(),
+                color_blend,
+                ..base()
+            };
+            let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+            assert!(write_ui_node_geometry(
+                &image,
+                Vec2::splat(10.),
+                &mut vertices,
+                &mut indices
+            ));
+            vertices
+        };
+
+        for color_blend in [
+            UiColorBlend::Multiply,
+            UiColorBlend::Replace,
+            UiColorBlend::Overlay,
+            UiColorBlend::Tint,
+        ] {
+            for vertex in vertices_of(color_blend) {
+                assert_eq!(
+                    vertex.flags & shader_flags::COLOR_BLEND_MASK,
+                    shader_flags::color_blend(color_blend)
+                );
+                assert_ne!(vertex.flags & shader_flags::TEXTURED, 0);
+                // The tint is passed unchanged, the shader combines it with the texture
+                assert_eq!(vertex.color, tint.to_f32_array());
+            }
+        }
+    }
+
+    #[test]
+    fn each_border_side_is_drawn_by_its_own_node() {
+        use super::{shader_flags, write_ui_node_geometry, ExtractedUiNode, NodeType};
+
+        let border = |border_side| ExtractedUiNode {
+            border: [5.; 4],
+            border_side,
+            node_type: NodeType::Border,
+            ..base()
+        };
+        let side_flags = |border_side| {
+            let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+            assert!(write_ui_node_geometry(
+                &border(border_side),
+                Vec2::ONE,
+                &mut vertices,
+                &mut indices
+            ));
+            vertices[0].flags & (shader_flags::BORDER_SIDE | shader_flags::BORDER_SIDE_MASK)
+        };
+
+        // A border of a single color covers every side
+        assert_eq!(side_flags(None), 0);
+        // Otherwise each side selects its own part of the border
+        let sides: Vec<_> = (0..4).map(|side| side_flags(Some(side))).collect();
+        for (i, flags) in sides.iter().enumerate() {
+            assert_eq!(*flags, shader_flags::border_side(i as u32));
+            assert!(!sides[i + 1..].contains(flags));
+        }
+        assert_eq!(
+            shader_flags::BORDER_SIDE_MASK & (shader_flags::NO_ANTIALIAS | shader_flags::OKLAB),
+            0
+        );
+    }
+
+    #[test]
+    fn no_antialias_node_reaches_the_vertex_flags() {
+        use bevy_ecs::entity::Entity;
+
+        use super::{shader_flags, ExtractedUiNode, UiMeta};
+        use crate::UiAntialias;
+
+        let view = Entity::from_raw(0);
+        let rounded = |antialias| ExtractedUiNode {
+            border_radius: [5.; 4],
+            antialias,
+            camera_entity: view,
+            ..base()
+        };
+
+        let mut harness = PrepareHarness::new();
+        harness.render_frame([
+            (Entity::from_raw(1), rounded(UiAntialias::On)),
+            (Entity::from_raw(2), rounded(UiAntialias::Off)),
+        ]);
+
+        let vertices = harness.world.resource::<UiMeta>().vertices();
+        assert_eq!(vertices.len(), 8);
+        let (smooth, hard) = vertices.split_at(4);
+        assert!(smooth
+            .iter()
+            .all(|vertex| vertex.flags & shader_flags::NO_ANTIALIAS == 0));
+        assert!(hard
+            .iter()
+            .all(|vertex| vertex.flags & shader_flags::NO_ANTIALIAS != 0));
+    }
+
+    #[test]
+    fn oklab_gradient_uploads_a_different_midpoint_than_linear() {
+        use bevy_color::{ColorToComponents, LinearRgba, Oklaba};
+        use bevy_ecs::entity::Entity;
+        use bevy_math::{UVec2, Vec4};
+
+        use super::{shader_flags, ExtractedUiNode, UiMeta};
+        use crate::UiGradientInterpolation;
+
+        let view = Entity::from_raw(0);
+        let blue = LinearRgba::BLUE;
+        let yellow = LinearRgba::rgb(1., 1., 0.);
+        // A horizontal gradient split in two columns, so the top row has a vertex halfway
+        let gradient = |gradient_interpolation| ExtractedUiNode {
+            corner_colors: Some([blue, yellow, yellow, blue]),
+            gradient_interpolation,
+            subdivisions: UVec2::new(2, 1),
+            camera_entity: view,
+            ..base()
+        };
+
+        let mut harness = PrepareHarness::new();
+        harness.render_frame([
+            (
+                Entity::from_raw(1),
+                gradient(UiGradientInterpolation::Linear),
+            ),
+            (
+                Entity::from_raw(2),
+                gradient(UiGradientInterpolation::Oklab),
+            ),
+        ]);
+
+        let vertices = harness.world.resource::<UiMeta>().vertices();
+        assert_eq!(vertices.len(), 12);
+        let (linear, oklab) = vertices.split_at(6);
+        assert!(linear
+            .iter()
+            .all(|vertex| vertex.flags & shader_flags::OKLAB == 0));
+        assert!(oklab
+            .iter()
+            .all(|vertex| vertex.flags & shader_flags::OKLAB != 0));
+
+        // Both start and end at the corner colors
+        for vertices in [linear, oklab] {
+            let color = |i: usize| Vec4::from_array(vertices[i].color);
+            assert!(color(0).abs_diff_eq(blue.to_vec4(), 1e-4));
+            assert!(color(2).abs_diff_eq(yellow.to_vec4(), 1e-4));
+        }
+
+        // The Oklab midpoint is halfway between the perceived lightness of blue and yellow
+        let lightness = |color: LinearRgba| Oklaba::from(color).lightness;
+        let midpoint_lightness = 0.5 * (lightness(blue) + lightness(yellow));
+        let linear_midpoint = LinearRgba::from_f32_array(linear[1].color);
+        let oklab_midpoint = LinearRgba::from_f32_array(oklab[1].color);
+        assert_eq!(linear_midpoint, LinearRgba::rgb(0.5, 0.5, 0.5));
+        assert!((lightness(oklab_midpoint) - midpoint_lightness).abs() < 1e-4);
+        assert!((lightness(linear_midpoint) - midpoint_lightness).abs() > 0.05);
+    }
+
+    #[test]
+    fn prepass_depth_orders_nodes_by_stack_index() {
+        let back = ui_depth(0);
+        let front = ui_depth(10);
+
+        // Both nodes are inside the depth range of the UI camera
+        assert!((0.0..=1.0).contains(&back));
+        assert!((0.0..=1.0).contains(&front));
+
+        // The node higher in the stack passes the prepass `LessEqual` depth test
+        assert!(front < back);
+    }
+
+    #[test]
+    fn deep_stacks_stay_within_the_ui_camera_far_plane() {
+        // Stacks that fit keep the default far plane
+        assert_eq!(ui_camera_far(0), UI_CAMERA_FAR);
+        assert_eq!(ui_camera_far(1001), UI_CAMERA_FAR);
+
+        // A stack whose topmost node would be offset past the default far plane
+        let stack_len = 2_000_000;
+        assert!(UI_CAMERA_FAR < stack_z(stack_len - 1));
+        let far = ui_camera_far(stack_len as usize);
+        let back = ui_depth_with_far(0, far);
+        let front = ui_depth_with_far(stack_len - 1, far);
+
+        // The furthest and the topmost nodes both render, in stack order
+        assert!((0.0..=1.0).contains(&back));
+        assert!((0.0..=1.0).contains(&front));
+        assert!(front < back);
+    }
+
+    #[test]
+    fn headless_ui_geometry_is_exact() {
+        use bevy_asset::Handle;
+        use bevy_color::{ColorToComponents, LinearRgba};
+
+        use super::{shader_flags, write_ui_node_geometry, ExtractedUiNode, UiMeta};
+
+        let node = |center: Vec2, size: Vec2, color| ExtractedUiNode {
+            transform: Mat4::from_translation(center.extend(0.)),
+            color,
+            rect: Rect::from_corners(Vec2::ZERO, size),
+            image: Default::default(),
+            ..base()
+        };
+        // A red panel, and a mirrored image to its right
+        let panel = node(Vec2::new(50., 25.), Vec2::new(100., 50.), LinearRgba::RED);
+        let image = ExtractedUiNode {
+            image: Handle::weak_from_u128(1).id(),
+            flip_x: true,
+            ..node(Vec2::new(110., 10.), Vec2::splat(20.), LinearRgba::WHITE)
+        };
+
+        // Prepared like `prepare_uinodes` does, without a window or a render device
+        let mut ui_meta = UiMeta::default();
+        for uinode in [&panel, &image] {
+            assert!(write_ui_node_geometry(
+                uinode,
+                Vec2::splat(64.),
+                ui_meta.vertices.values_mut(),
+                ui_meta.indices.values_mut(),
+            ));
+        }
+
+        let vertices = ui_meta.vertices();
+        assert_eq!(vertices.len(), 8);
+        let positions: Vec<_> = vertices.iter().map(|vertex| vertex.position).collect();
+        assert_eq!(
+            positions,
+            [
+                [0., 0., 0.],
+                [100., 0., 0.],
+                [0., 50., 0.],
+                [100., 50., 0.],
+                [100., 0., 0.],
+                [120., 0., 0.],
+                [100., 20., 0.],
+                [120., 20., 0.],
+            ]
+        );
+        let uvs: Vec<_> = vertices.iter().map(|vertex| vertex.uv).collect();
+        assert_eq!(
+            uvs,
+            [
+                [0., 0.],
+                [1., 0.],
+                [0., 1.],
+                [1., 1.],
+                // Mirrored horizontally
+                [1., 0.],
+                [0., 0.],
+                [1., 1.],
+                [0., 1.],
+            ]
+        );
+        let flags: Vec<_> = vertices.iter().map(|vertex| vertex.flags).collect();
+        let corners = shader_flags::CORNERS;
+        assert_eq!(
+            flags,
+            [
+                corners[0],
+                corners[1],
+                corners[3],
+                corners[2],
+                shader_flags::TEXTURED | corners[0],
+                shader_flags::TEXTURED | corners[1],
+                shader_flags::TEXTURED | corners[3],
+                shader_flags::TEXTURED | corners[2],
+            ]
+        );
+        assert!(vertices[..4]
+            .iter()
+            .all(|vertex| vertex.color == LinearRgba::RED.to_f32_array()
+                && vertex.size == [100., 50.]));
+        assert!(vertices[4..]
+            .iter()
+            .all(|vertex| vertex.color == LinearRgba::WHITE.to_f32_array()
+                && vertex.size == [20., 20.]));
+        assert_eq!(ui_meta.indices(), [0, 3, 2, 0, 1, 3, 4, 7, 6, 4, 5, 7]);
+    }
+
+    #[test]
+    fn text_clipped_mid_glyph_shows_part_of_the_glyph() {
+        use bevy_asset::Handle;
+
+        use super::{write_ui_node_geometry, ExtractedUiNode, UiMeta};
+        use crate::UiClipMode;
+
+        // The second glyph of a line, whose 8x16 rect sits right of the first glyph in a 64x16
+        // atlas, clipped in the middle by the right edge of its text node
+        let atlas_size = Vec2::new(64., 16.);
+        let glyph = |transform, flip_x| ExtractedUiNode {
+            transform,
+            rect: Rect::new(8., 0., 16., 16.),
+            image: Handle::weak_from_u128(1).id(),
+            atlas_scaling: Some(Vec2::ONE),
+            clip: Some(Rect::new(0., 0., 10., 16.)),
+            clip_mode: UiClipMode::Vertices,
+            flip_x,
+            ..base()
+        };
+        // Returns the horizontal extents of the glyph's quad and of its uvs, in atlas pixels
+        let clipped = |glyph: ExtractedUiNode| {
+            let mut ui_meta = UiMeta::default();
+            assert!(write_ui_node_geometry(
+                &glyph,
+                atlas_size,
+                ui_meta.vertices.values_mut(),
+                ui_meta.indices.values_mut(),
+            ));
+            let vertices = ui_meta.vertices();
+            let left = (vertices[0].position[0], vertices[0].uv[0] * atlas_size.x);
+            let right = (vertices[1].position[0], vertices[1].uv[0] * atlas_size.x);
+            (left, right)
+        };
+
+        // The left part of the glyph is drawn up to the clip, from its own columns of the atlas
+        let centered = Mat4::from_translation(Vec3::new(12., 8., 0.));
+        assert_eq!(clipped(glyph(centered, false)), ((8., 8.), (10., 10.)));
+
+        // Text scaled by its transform shows the same part of the glyph, not its neighbor
+        let scaled = Mat4::from_translation(Vec3::new(6., 4., 0.))
+            * Mat4::from_scale(Vec3::new(0.5, 0.5, 1.));
+        assert_eq!(
+            clipped(ExtractedUiNode {
+                clip: Some(Rect::new(0., 0., 5., 8.)),
+                ..glyph(scaled, false)
+            }),
+            ((4., 8.), (5., 10.))
+        );
+
+        // A mirrored glyph shows its right columns on the left
+        assert_eq!(clipped(glyph(centered, true)), ((8., 16.), (10., 14.)));
+    }
+
+    #[test]
+    fn side_by_side_viewports_draw_their_own_ui() {
+        use bevy_math::{URect, UVec2, UVec4};
+        use bevy_render::view::ExtractedView;
+
+        use super::{ui_default_camera_view, ui_scissor_rect};
+
+        // Split-screen in a 1600x600 window with a scale factor of 2, each camera taking a half
+        let half = UVec2::new(800, 600);
+        let logical_size = Vec2::new(400., 300.);
+        let left = ui_default_camera_view(
+            URect::from_corners(UVec2::ZERO, half),
+            logical_size,
+            1000.,
+            false,
+        );
+        let right = ui_default_camera_view(
+            URect::from_corners(UVec2::new(800, 0), UVec2::new(1600, 600)),
+            logical_size,
+            1000.,
+            false,
+        );
+        assert_eq!(left.viewport, UVec4::new(0, 0, 800, 600));
+        assert_eq!(right.viewport, UVec4::new(800, 0, 800, 600));
+
+        // Both project the UI laid out for a 400x300 viewport onto their own viewport
+        assert_eq!(left.clip_from_view, right.clip_from_view);
+        let ndc = |view: &ExtractedView, point: Vec2| {
+            (view.clip_from_view * view.world_from_view.compute_matrix().inverse())
+                .project_point3(point.extend(0.))
+                .truncate()
+        };
+        assert!(ndc(&left, Vec2::ZERO).abs_diff_eq(Vec2::new(-1., 1.), 1e-6));
+        assert!(ndc(&left, logical_size).abs_diff_eq(Vec2::new(1., -1.), 1e-6));
+
+        // Clips are converted to the pixels of their own viewport
+        let clip = Rect::new(0., 0., 200., 150.);
+        assert_eq!(
+            ui_scissor_rect(clip, &left),
+            Some(URect::new(0, 0, 400, 300))
+        );
+        assert_eq!(
+            ui_scissor_rect(clip, &right),
+            Some(URect::new(800, 0, 1200, 300))
+        );
+    }
+
+    #[test]
+    fn each_window_uses_its_own_ui_scale_factor() {
+        use bevy_asset::{AssetEvent, Assets};
+        use bevy_core_pipeline::core_2d::Camera2dBundle;
+        use bevy_ecs::{event::Events, system::RunSystemOnce, world::World};
+        use bevy_render::{
+            camera::{
+                camera_system, Camera, ManualTextureViews, OrthographicProjection, RenderTarget,
+            },
+            texture::Image,
+        };
+        use bevy_window::{
+            Window, WindowCreated, WindowRef, WindowResized, WindowResolution,
+            WindowScaleFactorChanged,
+        };
+
+        use super::ui_target_scale_factor;
+        use crate::UiScale;
+
+        let mut world = World::new();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<WindowResized>>();
+        world.init_resource::<Events<WindowScaleFactorChanged>>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<ManualTextureViews>();
+
+        // Two windows on monitors of different DPI, and a texture
+        let camera = |world: &mut World, target| {
+            world
+                .spawn(Camera2dBundle {
+                    camera: Camera {
+                        target,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .id()
+        };
+        let window = |world: &mut World, scale_factor| {
+            let window = world
+                .spawn(Window {
+                    resolution: WindowResolution::new(800., 600.)
+                        .with_scale_factor_override(scale_factor),
+                    ..Default::default()
+                })
+                .id();
+            camera(world, RenderTarget::Window(WindowRef::Entity(window)))
+        };
+        let low_dpi = window(&mut world, 1.);
+        let high_dpi = window(&mut world, 2.);
+        let image = world.resource_mut::<Assets<Image>>().add(Image::default());
+        let texture = camera(&mut world, RenderTarget::Image(image));
+        world.run_system_once(camera_system::<OrthographicProjection>);
+
+        let scale_factor = |world: &World, camera, ui_scale| {
+            ui_target_scale_factor(world.get::<Camera>(camera), &UiScale(ui_scale))
+        };
+        assert_eq!(scale_factor(&world, low_dpi, 1.), 1.);
+        assert_eq!(scale_factor(&world, high_dpi, 1.), 2.);
+        assert_eq!(scale_factor(&world, high_dpi, 1.5), 3.);
+        assert_eq!(scale_factor(&world, texture, 1.), 1.);
+
+        // Nodes without a camera, or whose camera's target isn't known yet, aren't scaled
+        let unknown = camera(&mut world, RenderTarget::Window(WindowRef::Primary));
+        assert_eq!(scale_factor(&world, unknown, 2.), 2.);
+        assert_eq!(ui_target_scale_factor(None, &UiScale(1.)), 1.);
+    }
+
+    #[test]
+    fn pushed_nodes_are_batched_with_the_built_in_ones() {
+        use bevy_asset::{AssetId, Handle};
+        use bevy_ecs::{
+            entity::Entity,
+            schedule::{IntoSystemConfigs, IntoSystemSetConfigs, Schedule},
+            system::{Commands, ResMut},
+            world::World,
+        };
+        use bevy_render::texture::Image;
+        use bevy_utils::HashMap;
+
+        use super::{
+            ui_opacity::{fade_extracted_uinodes, ExtractedUiOpacities},
+            ExtractedUiNode, ExtractedUiNodes, RenderUiSystem, UiBatch,
+        };
+
+        let camera = Entity::from_raw(0);
+        let node = |stack_index| ExtractedUiNode {
+            stack_index,
+            camera_entity: camera,
+            ..base()
+        };
+
+        let mut world = World::new();
+        let built_in = world.spawn_empty().id();
+        let mut extracted_uinodes = ExtractedUiNodes::default();
+        extracted_uinodes.uinodes.insert(built_in, node(0));
+        world.insert_resource(extracted_uinodes);
+        // The custom node is faded by the opacity of its stack index like any other node
+        world.insert_resource(ExtractedUiOpacities {
+            opacities: HashMap::from_iter([(1, 0.5)]),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule
+            .configure_sets(
+                (
+                    RenderUiSystem::ExtractBoxShadows,
+                    RenderUiSystem::ExtractBackgrounds,
+                    RenderUiSystem::ExtractImages,
+                    RenderUiSystem::ExtractBorders,
+                    RenderUiSystem::ExtractText,
+                )
+                    .chain(),
+            )
+            .add_systems((
+                (move |mut commands: Commands, mut extracted_uinodes: ResMut<ExtractedUiNodes>| {
+                    extracted_uinodes.push(&mut commands, node(1));
+                })
+                .in_set(RenderUiSystem::ExtractBackgrounds),
+                fade_extracted_uinodes.after(RenderUiSystem::ExtractText),
+            ));
+        schedule.run(&mut world);
+
+        let extracted_uinodes = world.resource::<ExtractedUiNodes>();
+        assert_eq!(extracted_uinodes.uinodes.len(), 2);
+        let (&pushed, custom) = extracted_uinodes
+            .uinodes
+            .iter()
+            .find(|(entity, _)| **entity != built_in)
+            .unwrap();
+        assert!(world.get_entity(pushed).is_some());
+        assert_eq!(custom.color.alpha, 0.5);
+
+        // The batch started by the built-in node draws the custom node too
+        let built_in = &extracted_uinodes.uinodes[&built_in];
+        let batch = UiBatch {
+            range: 0..6,
+            image: built_in.image,
+            image_layer: built_in.image_layer,
+            sampler: built_in.sampler,
+            camera: built_in.camera_entity,
+            clip: built_in.scissor_clip(),
+            stencil: built_in.stencil,
+            premultiplied_alpha: built_in.premultiplied_alpha,
+            image_array: false,
+        };
+        assert!(batch.accepts(custom, false));
+
+        // Unless it's drawn to another camera, clipped, blended differently or with another image
+        let other_camera = ExtractedUiNode {
+            camera_entity: Entity::from_raw(1),
+            ..*custom
+        };
+        assert!(!batch.accepts(&other_camera, false));
+        let clipped = ExtractedUiNode {
+            clip: Some(Rect::new(0., 0., 5., 5.)),
+            ..*custom
+        };
+        assert!(!batch.accepts(&clipped, false));
+        let premultiplied = ExtractedUiNode {
+            premultiplied_alpha: true,
+            ..*custom
+        };
+        assert!(!batch.accepts(&premultiplied, false));
+        let textured_batch = UiBatch {
+            image: AssetId::invalid(),
+            ..batch
+        };
+        assert!(textured_batch.accepts(custom, false));
+        let other_image = ExtractedUiNode {
+            image: Handle::<Image>::weak_from_u128(1).id(),
+            ..*custom
+        };
+        assert!(!textured_batch.accepts(&other_image, false));
+    }
+
+    #[test]
+    fn ui_drawn_without_a_view_bind_group_fails_gracefully() {
+        use bevy_render::render_phase::RenderCommandResult;
+
+        use super::{ui_view_bind_group, UiMeta};
+
+        // Nothing was prepared, as when no view uniforms were available
+        let ui_meta = UiMeta::default();
+        assert!(matches!(
+            ui_view_bind_group(ui_meta.view_bind_group.as_ref()),
+            Err(RenderCommandResult::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn overlapping_nodes_are_ordered_by_stack_index() {
+        use bevy_ecs::{entity::Entity, world::World};
+        use bevy_math::FloatOrd;
+        use bevy_render::{
+            render_phase::{
+                Draw, DrawError, DrawFunctions, PhaseItemExtraIndex, SortedPhaseItem,
+                TrackedRenderPass,
+            },
+            render_resource::CachedRenderPipelineId,
+        };
+
+        use super::{write_ui_node_geometry, ExtractedUiNode, TransparentUi};
+
+        // Three nodes covering the same area, all laid out at z == 0.0
+        let node = |stack_index| ExtractedUiNode {
+            stack_index,
+            transform: Mat4::from_translation(Vec3::new(50., 50., 0.)),
+            rect: Rect::new(0., 0., 40., 40.),
+            ..base()
+        };
+        let nodes = [node(2), node(0), node(1)];
+
+        // Each quad gets the z of its stack index
+        let mut depths = Vec::new();
+        for node in &nodes {
+            let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+            assert!(write_ui_node_geometry(
+                node,
+                Vec2::ZERO,
+                &mut vertices,
+                &mut indices
+            ));
+            let z = stack_z(node.stack_index);
+            assert!(vertices.iter().all(|vertex| vertex.position[2] == z));
+            depths.push((node.stack_index, ui_depth(node.stack_index)));
+        }
+        // The bottom node is in front of the far plane, and each node in front of the one below
+        depths.sort_by_key(|(stack_index, _)| *stack_index);
+        assert!(depths.iter().all(|(_, depth)| (0.0..1.0).contains(depth)));
+        assert!(depths.windows(2).all(|pair| pair[1].1 < pair[0].1));
+
+        // Queued in any order, the nodes are blended back to front
+        struct NoopDraw;
+        impl Draw<TransparentUi> for NoopDraw {
+            fn draw<'w>(
+                &mut self,
+                _world: &'w World,
+                _pass: &mut TrackedRenderPass<'w>,
+                _view: Entity,
+                _item: &TransparentUi,
+            ) -> Result<(), DrawError> {
+                Ok(())
+            }
+        }
+        let draw_function = DrawFunctions::<TransparentUi>::default()
+            .write()
+            .add(NoopDraw);
+        let mut items: Vec<_> = nodes
+            .iter()
+            .map(|node| TransparentUi {
+                sort_key: (FloatOrd(node.stack_index as f32), node.stack_index),
+                entity: Entity::from_raw(node.stack_index),
+                pipeline: CachedRenderPipelineId::INVALID,
+                draw_function,
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            })
+            .collect();
+        TransparentUi::sort(&mut items);
+        let order: Vec<_> = items.iter().map(|item| item.entity.index()).collect();
+        assert_eq!(order, [0, 1, 2]);
+    }
 }
@@ -1,7 +1,9 @@
+use std::sync::{Arc, RwLock};
+
 use bevy_ecs::prelude::*;
 use bevy_render::{
     render_resource::{
-        binding_types::{sampler, texture_2d, uniform_buffer},
+        binding_types::{sampler, texture_2d, texture_2d_array, uniform_buffer},
         *,
     },
     renderer::RenderDevice,
@@ -9,10 +11,66 @@ use bevy_render::{
     view::{ViewTarget, ViewUniform},
 };
 
+use super::{
+    ui_opaque::ui_depth_stencil_state,
+    ui_pass_layer::ui_scene_depth_state,
+    ui_stencil_mask::{ui_stencil_state, UiStencilMode},
+};
+
 #[derive(Resource)]
 pub struct UiPipeline {
     pub view_layout: BindGroupLayout,
     pub image_layout: BindGroupLayout,
+    /// The layout of the images bound as texture arrays, see
+    /// [`UiImageArrayBatching`](crate::UiImageArrayBatching).
+    pub image_array_layout: BindGroupLayout,
+    pub customizer: UiPipelineCustomizer,
+}
+
+/// Modifies the descriptor of every UI pipeline once it is specialized.
+///
+/// Applied to the [`UiPipeline`], the texture slice pipeline and every
+/// [`UiMaterialPipeline`](crate::UiMaterialPipeline), after
+/// [`UiMaterial::specialize`](crate::UiMaterial::specialize). Useful to inject a shader def, add a
+/// push constant range or change the labels of all of them.
+///
+/// This is a resource of the render app. The pipelines share its customizations, so those added
+/// after a pipeline is created apply to the pipelines it specializes from then on, but pipelines
+/// already specialized are kept as they are.
+#[derive(Resource, Clone, Default)]
+pub struct UiPipelineCustomizer {
+    customizations: Arc<RwLock<Vec<UiPipelineCustomization>>>,
+}
+
+type UiPipelineCustomization = Box<dyn Fn(&mut RenderPipelineDescriptor) + Send + Sync>;
+
+impl UiPipelineCustomizer {
+    /// Adds a customization, applied after those added before it.
+    pub fn add(
+        &self,
+        customization: impl Fn(&mut RenderPipelineDescriptor) + Send + Sync + 'static,
+    ) -> &Self {
+        self.customizations
+            .write()
+            .unwrap()
+            .push(Box::new(customization));
+        self
+    }
+
+    /// Applies all of the customizations to `descriptor`.
+    pub fn customize(&self, descriptor: &mut RenderPipelineDescriptor) {
+        for customization in self.customizations.read().unwrap().iter() {
+            customization(descriptor);
+        }
+    }
+
+    /// Returns the customizer of the render `world`, sharing its customizations, or an empty one.
+    pub fn from_render_world(world: &World) -> Self {
+        world
+            .get_resource::<UiPipelineCustomizer>()
+            .cloned()
+            .unwrap_or_default()
+    }
 }
 
 impl FromWorld for UiPipeline {
@@ -38,9 +96,22 @@ impl FromWorld for UiPipeline {
             ),
         );
 
+        let image_array_layout = render_device.create_bind_group_layout(
+            "ui_image_array_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d_array(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
         UiPipeline {
             view_layout,
             image_layout,
+            image_array_layout,
+            customizer: UiPipelineCustomizer::from_render_world(world),
         }
     }
 }
@@ -48,6 +119,36 @@ impl FromWorld for UiPipeline {
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct UiPipelineKey {
     pub hdr: bool,
+    /// The winding of front-facing triangles, see [`ui_front_face`](crate::ui_front_face).
+    pub front_face: FrontFace,
+    /// The number of MSAA samples of the UI pass, see
+    /// [`ui_msaa_samples`](crate::ui_msaa::ui_msaa_samples).
+    pub samples: u32,
+    /// Whether the UI of the view is drawn with a
+    /// [`ViewUiDepthTexture`](crate::ui_opaque::ViewUiDepthTexture).
+    pub depth: bool,
+    /// Whether the nodes are tested against the depth of the scene of the view, see
+    /// [`UiPassLayer::InScene`](crate::UiPassLayer::InScene).
+    pub scene_depth: bool,
+    /// How the nodes use the
+    /// [`ViewUiStencilTexture`](crate::ui_stencil_mask::ViewUiStencilTexture) of the view.
+    pub stencil: UiStencilMode,
+    /// Whether the nodes are blended as premultiplied alpha, see
+    /// [`UiImage::premultiplied_alpha`](crate::UiImage::premultiplied_alpha).
+    pub premultiplied_alpha: bool,
+    /// Whether the image is bound as a texture array, see
+    /// [`UiImageArrayBatching`](crate::UiImageArrayBatching).
+    pub image_array: bool,
+}
+
+/// Returns the blend state of the UI pipeline, the colors written by the shader are premultiplied
+/// by their alpha when `premultiplied_alpha` is set.
+pub fn ui_blend_state(premultiplied_alpha: bool) -> BlendState {
+    if premultiplied_alpha {
+        BlendState::PREMULTIPLIED_ALPHA_BLENDING
+    } else {
+        BlendState::ALPHA_BLENDING
+    }
 }
 
 impl SpecializedRenderPipeline for UiPipeline {
@@ -71,11 +172,32 @@ impl SpecializedRenderPipeline for UiPipeline {
                 VertexFormat::Float32x4,
                 // border size
                 VertexFormat::Float32x2,
+                // point
+                VertexFormat::Float32x2,
+                // array layer
+                VertexFormat::Uint32,
             ],
         );
-        let shader_defs = Vec::new();
+        let mut shader_defs: Vec<ShaderDefVal> = Vec::new();
+        if key.stencil.writes() {
+            shader_defs.push("STENCIL_WRITE".into());
+        }
+        if key.premultiplied_alpha {
+            shader_defs.push("PREMULTIPLIED_ALPHA".into());
+        }
+        if key.scene_depth {
+            shader_defs.push("SCENE_DEPTH".into());
+        }
+        if key.image_array {
+            shader_defs.push("IMAGE_ARRAY".into());
+        }
+        let image_layout = if key.image_array {
+            self.image_array_layout.clone()
+        } else {
+            self.image_layout.clone()
+        };
 
-        RenderPipelineDescriptor {
+        let mut descriptor = RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: super::UI_SHADER_HANDLE,
                 entry_point: "vertex".into(),
@@ -92,14 +214,19 @@ impl SpecializedRenderPipeline for UiPipeline {
                     } else {
                         TextureFormat::bevy_default()
                     },
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: ColorWrites::ALL,
+                    blend: Some(ui_blend_state(key.premultiplied_alpha)),
+                    // Masks are only drawn to the stencil texture
+                    write_mask: if key.stencil.writes() {
+                        ColorWrites::empty()
+                    } else {
+                        ColorWrites::ALL
+                    },
                 })],
             }),
-            layout: vec![self.view_layout.clone(), self.image_layout.clone()],
+            layout: vec![self.view_layout.clone(), image_layout],
             push_constant_ranges: Vec::new(),
             primitive: PrimitiveState {
-                front_face: FrontFace::Ccw,
+                front_face: key.front_face,
                 cull_mode: None,
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
@@ -107,13 +234,84 @@ impl SpecializedRenderPipeline for UiPipeline {
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
             },
-            depth_stencil: None,
+            // Hidden by the opaque nodes above, `Less` also keeps the box shadow of an opaque node,
+            // at the same depth, below the node
+            depth_stencil: ui_stencil_state(key.stencil)
+                .or_else(|| key.scene_depth.then(ui_scene_depth_state))
+                .or_else(|| {
+                    key.depth
+                        .then(|| ui_depth_stencil_state(false, CompareFunction::Less))
+                }),
             multisample: MultisampleState {
-                count: 1,
+                count: key.samples,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             label: Some("ui_pipeline".into()),
-        }
+        };
+        self.customizer.customize(&mut descriptor);
+        descriptor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::{FromWorld, World};
+    use bevy_render::render_resource::*;
+
+    use super::{ui_blend_state, UiPipeline, UiPipelineCustomizer, UiPipelineKey};
+    use crate::render::{tests::test_render_device, ui_stencil_mask::UiStencilMode};
+
+    #[test]
+    fn premultiplied_images_select_premultiplied_blending() {
+        let straight = ui_blend_state(false);
+        assert_eq!(straight.color.src_factor, BlendFactor::SrcAlpha);
+        assert_eq!(straight.color.dst_factor, BlendFactor::OneMinusSrcAlpha);
+
+        // The source color is already multiplied by its alpha
+        let premultiplied = ui_blend_state(true);
+        assert_eq!(premultiplied.color.src_factor, BlendFactor::One);
+        assert_eq!(
+            premultiplied.color.dst_factor,
+            BlendFactor::OneMinusSrcAlpha
+        );
+        assert_eq!(premultiplied.alpha, straight.alpha);
+    }
+
+    #[test]
+    fn customizer_modifies_specialized_pipelines() {
+        let Some((render_device, _)) = test_render_device() else {
+            return;
+        };
+        let mut world = World::new();
+        world.insert_resource(render_device);
+        world.init_resource::<UiPipelineCustomizer>();
+        let pipeline = UiPipeline::from_world(&mut world);
+
+        // Added to the resource once the pipeline is created
+        world
+            .resource::<UiPipelineCustomizer>()
+            .add(|descriptor| {
+                let label = descriptor.label.take().unwrap_or_default();
+                descriptor.label = Some(format!("debug_{label}").into());
+            })
+            .add(|descriptor| descriptor.vertex.shader_defs.push("DEBUG".into()));
+
+        let descriptor = pipeline.specialize(UiPipelineKey {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            samples: 1,
+            depth: false,
+            scene_depth: false,
+            stencil: UiStencilMode::Disabled,
+            premultiplied_alpha: false,
+            image_array: false,
+        });
+
+        assert_eq!(descriptor.label.as_deref(), Some("debug_ui_pipeline"));
+        assert_eq!(
+            descriptor.vertex.shader_defs,
+            vec![ShaderDefVal::from("DEBUG")]
+        );
     }
 }
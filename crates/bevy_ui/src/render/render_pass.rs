@@ -1,24 +1,70 @@
-use std::ops::Range;
+use std::{cmp::Reverse, ops::Range};
 
-use super::{UiBatch, UiImageBindGroups, UiMeta};
+use super::{
+    ui_force_ldr::{
+        composite_ui_intermediate_texture, ui_intermediate_color_attachment, ViewUiLdrTexture,
+    },
+    ui_linear_target::{ui_linear_runs, UiLinearItems, ViewUiLinearTexture},
+    ui_modal_backdrop::{
+        render_ui_modal_backdrop, ui_pass_segments, UiModalBackdropMeta, UiPassSegment,
+    },
+    ui_msaa::ViewUiMsaaTexture,
+    ui_opaque::ViewUiDepthTexture,
+    ui_pass_layer::UiSceneDepthItems,
+    ui_scene_color::{copy_ui_scene_color, ViewUiSceneColorTexture},
+    ui_scissor_rect,
+    ui_stencil_mask::{UiStencilItems, ViewUiStencilTexture},
+    ui_viewport_mask::{copy_ui_viewport_mask_scene, render_ui_viewport_mask, ViewUiViewportMask},
+    ExtractedUiViewport, UiBatch, UiImageBindGroups, UiMeta, ViewUiPrepassTextures,
+};
 use crate::DefaultCameraView;
 use bevy_ecs::{
     prelude::*,
     system::{lifetimeless::*, SystemParamItem},
 };
-use bevy_math::FloatOrd;
+use bevy_math::{FloatOrd, URect, Vec4Swizzles};
 use bevy_render::{
-    camera::ExtractedCamera,
+    camera::{ExtractedCamera, Viewport},
+    diagnostic::RecordDiagnostics,
     render_graph::*,
     render_phase::*,
-    render_resource::{CachedRenderPipelineId, RenderPassDescriptor},
+    render_resource::{
+        BindGroup, CachedRenderPipelineId, LoadOp, Operations, RenderPassDepthStencilAttachment,
+        RenderPassDescriptor, StoreOp,
+    },
     renderer::*,
     view::*,
 };
-use bevy_utils::tracing::error;
+use bevy_utils::{tracing::error, warn_once};
 
+/// Renders the [`OpaqueUi`] and [`TransparentUi`] phases of a view.
+///
+/// Views with a [`ViewUiDepthTexture`] draw their opaque phase first, then their transparent phase
+/// tested against the depth the opaque phase wrote. Views with a [`ViewUiStencilTexture`] draw
+/// each run of consecutive [`UiStencilItems`] with it, the stencil values written by the masks
+/// are kept from one run to the next. Runs of consecutive [`UiSceneDepthItems`] are tested against
+/// the [`ViewDepthTexture`] of the scene, see [`UiPassLayer::InScene`](crate::UiPassLayer::InScene).
+///
+/// With the [`RenderDiagnosticsPlugin`](bevy_render::diagnostic::RenderDiagnosticsPlugin), the
+/// time spent in the pass is recorded as the `render/ui_pass/elapsed_cpu` diagnostic, and as
+/// `render/ui_pass/elapsed_gpu` on backends supporting timestamp queries.
 pub struct UiPassNode {
-    ui_view_query: QueryState<(&'static ViewTarget, &'static ExtractedCamera), With<ExtractedView>>,
+    ui_view_query: QueryState<
+        (
+            &'static ViewTarget,
+            &'static ExtractedCamera,
+            Option<&'static ViewUiViewportMask>,
+            Option<&'static ViewUiSceneColorTexture>,
+            Option<&'static ViewUiLdrTexture>,
+            Option<&'static ViewUiLinearTexture>,
+            Option<&'static ViewUiMsaaTexture>,
+            Option<&'static ViewUiDepthTexture>,
+            Option<&'static ViewUiStencilTexture>,
+            Option<&'static ViewDepthTexture>,
+            Option<&'static ExtractedUiViewport>,
+        ),
+        With<ExtractedView>,
+    >,
     default_camera_view_query: QueryState<&'static DefaultCameraView>,
 }
 
@@ -54,11 +100,303 @@ impl Node for UiPassNode {
         let Some(transparent_phase) = transparent_render_phases.get(&input_view_entity) else {
             return Ok(());
         };
+        let opaque_phase = world
+            .get_resource::<ViewSortedRenderPhases<OpaqueUi>>()
+            .and_then(|opaque_render_phases| opaque_render_phases.get(&input_view_entity));
 
-        let Ok((target, camera)) = self.ui_view_query.get_manual(world, input_view_entity) else {
+        let Ok((
+            target,
+            camera,
+            viewport_mask,
+            scene_color,
+            ldr_texture,
+            linear_texture,
+            msaa_texture,
+            depth_texture,
+            stencil_texture,
+            scene_depth_texture,
+            ui_viewport,
+        )) = self.ui_view_query.get_manual(world, input_view_entity)
+        else {
             return Ok(());
         };
-        if transparent_phase.items.is_empty() {
+        if transparent_phase.items.is_empty()
+            && opaque_phase.map_or(true, |phase| phase.items.is_empty())
+        {
+            return Ok(());
+        }
+
+        // use the "default" view entity if it is defined
+        let view_entity = if let Ok(default_view) = self
+            .default_camera_view_query
+            .get_manual(world, input_view_entity)
+        {
+            default_view.0
+        } else {
+            input_view_entity
+        };
+
+        // Modal backdrops split the phase, each one blurs what was rendered before it
+        let segments = match world.get_resource::<UiModalBackdropMeta>() {
+            Some(modal_backdrops) => ui_pass_segments(
+                transparent_phase.items.iter().map(|item| item.entity),
+                |entity| modal_backdrops.contains(entity),
+            ),
+            None => vec![UiPassSegment::Nodes(0..transparent_phase.items.len())],
+        };
+
+        let diagnostics = render_context.diagnostic_recorder();
+        let time_span = diagnostics.time_span(render_context.command_encoder(), "ui_pass");
+
+        // The viewport mask restores the scene around its shape once all of the UI is drawn
+        if let Some(viewport_mask) = viewport_mask {
+            copy_ui_viewport_mask_scene(render_context, world, target, viewport_mask);
+        }
+        // Materials reading the scene sample it as it was before any UI was drawn
+        if let Some(scene_color) = scene_color {
+            copy_ui_scene_color(render_context, world, target, scene_color);
+        }
+
+        // Opaque nodes are drawn front to back, writing the depth the transparent nodes are tested
+        // against
+        if let Some(depth_texture) = depth_texture {
+            let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("ui_opaque_pass"),
+                color_attachments: &[Some(target.get_unsampled_color_attachment())],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &depth_texture.depth.default_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Some(viewport) = ui_camera_viewport(camera, ui_viewport) {
+                render_pass.set_camera_viewport(viewport);
+            }
+            if let Some(opaque_phase) = opaque_phase {
+                if let Err(err) = opaque_phase.render(&mut render_pass, world, view_entity) {
+                    error!("Error encountered while rendering the ui opaque phase {err:?}");
+                }
+            }
+        }
+
+        // The stencil texture is cleared before the first masked nodes are drawn
+        let mut stencil_cleared = false;
+
+        for segment in segments {
+            match segment {
+                UiPassSegment::Nodes(range) => {
+                    // Nodes of linear materials are drawn in the linear texture of the view
+                    let runs = match (linear_texture, world.get_resource::<UiLinearItems>()) {
+                        (Some(_), Some(linear_items)) => ui_linear_runs(range, |index| {
+                            linear_items.contains(transparent_phase.items[index].entity)
+                        }),
+                        _ => vec![(range, false)],
+                    };
+                    // Masked nodes are drawn with the stencil texture of the view, they're never
+                    // linear
+                    let stencil_items = world.get_resource::<UiStencilItems>();
+                    let runs = runs.into_iter().flat_map(|(range, linear)| {
+                        let runs = match (stencil_texture, stencil_items) {
+                            (Some(_), Some(stencil_items)) if !linear => {
+                                ui_linear_runs(range, |index| {
+                                    stencil_items.contains(transparent_phase.items[index].entity)
+                                })
+                            }
+                            _ => vec![(range, false)],
+                        };
+                        runs.into_iter()
+                            .map(move |(range, stenciled)| (range, linear, stenciled))
+                    });
+                    // In-scene nodes are drawn with the depth texture of the scene, they're never
+                    // linear or masked
+                    let scene_depth_items = world.get_resource::<UiSceneDepthItems>();
+                    let runs = runs.flat_map(|(range, linear, stenciled)| {
+                        let runs = match (scene_depth_texture, scene_depth_items) {
+                            (Some(_), Some(scene_depth_items)) if !linear && !stenciled => {
+                                ui_linear_runs(range, |index| {
+                                    scene_depth_items
+                                        .contains(transparent_phase.items[index].entity)
+                                })
+                            }
+                            _ => vec![(range, false)],
+                        };
+                        runs.into_iter().map(move |(range, scene_depth)| {
+                            (range, linear, stenciled, scene_depth)
+                        })
+                    });
+                    for (range, linear, stenciled, scene_depth) in runs {
+                        // Cameras forcing their UI to LDR draw it to their LDR texture instead, and
+                        // cameras with MSAA to their multisampled texture
+                        let intermediate =
+                            if let Some(linear_texture) = linear_texture.filter(|_| linear) {
+                                Some((
+                                    &linear_texture.texture,
+                                    linear_texture.sampled.as_ref(),
+                                    linear_texture.composite_pipeline,
+                                ))
+                            } else if let Some(ldr_texture) = ldr_texture {
+                                Some((
+                                    &ldr_texture.texture,
+                                    ldr_texture.sampled.as_ref(),
+                                    ldr_texture.composite_pipeline,
+                                ))
+                            } else {
+                                msaa_texture.map(|msaa_texture| {
+                                    (
+                                        &msaa_texture.texture,
+                                        Some(&msaa_texture.sampled),
+                                        msaa_texture.composite_pipeline,
+                                    )
+                                })
+                            };
+                        let color_attachment = match intermediate {
+                            Some((texture, sampled, _)) => {
+                                ui_intermediate_color_attachment(texture, sampled)
+                            }
+                            None => target.get_unsampled_color_attachment(),
+                        };
+                        let mut render_pass =
+                            render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                                label: Some("ui_pass"),
+                                color_attachments: &[Some(color_attachment)],
+                                depth_stencil_attachment: match stencil_texture
+                                    .filter(|_| stenciled)
+                                {
+                                    Some(stencil_texture) => {
+                                        Some(RenderPassDepthStencilAttachment {
+                                            view: &stencil_texture.texture.default_view,
+                                            depth_ops: None,
+                                            stencil_ops: Some(Operations {
+                                                load: if stencil_cleared {
+                                                    LoadOp::Load
+                                                } else {
+                                                    LoadOp::Clear(0)
+                                                },
+                                                store: StoreOp::Store,
+                                            }),
+                                        })
+                                    }
+                                    None if scene_depth => {
+                                        scene_depth_texture.map(|scene_depth_texture| {
+                                            scene_depth_texture.get_attachment(StoreOp::Store)
+                                        })
+                                    }
+                                    None => depth_texture.map(|depth_texture| {
+                                        RenderPassDepthStencilAttachment {
+                                            view: &depth_texture.depth.default_view,
+                                            depth_ops: Some(Operations {
+                                                load: LoadOp::Load,
+                                                store: StoreOp::Store,
+                                            }),
+                                            stencil_ops: None,
+                                        }
+                                    }),
+                                },
+                                timestamp_writes: None,
+                                occlusion_query_set: None,
+                            });
+                        if let Some(viewport) = ui_camera_viewport(camera, ui_viewport) {
+                            render_pass.set_camera_viewport(viewport);
+                        }
+                        if let Err(err) = transparent_phase.render_range(
+                            &mut render_pass,
+                            world,
+                            view_entity,
+                            range,
+                        ) {
+                            error!("Error encountered while rendering the ui phase {err:?}");
+                        }
+                        drop(render_pass);
+                        stencil_cleared |= stenciled;
+
+                        if let Some((texture, _, composite_pipeline)) = intermediate {
+                            composite_ui_intermediate_texture(
+                                render_context,
+                                world,
+                                target,
+                                texture,
+                                composite_pipeline,
+                            );
+                        }
+                    }
+                }
+                UiPassSegment::ModalBackdrop(index) => {
+                    render_ui_modal_backdrop(
+                        render_context,
+                        world,
+                        target,
+                        &transparent_phase.items[index],
+                    );
+                }
+            }
+        }
+
+        if let Some(viewport_mask) = viewport_mask {
+            render_ui_viewport_mask(render_context, world, target, camera, viewport_mask);
+        }
+
+        time_span.end(render_context.command_encoder());
+
+        Ok(())
+    }
+}
+
+/// Renders the [`UiPrepass`] phase of a view into its [`ViewUiPrepassTextures`].
+pub struct UiPrepassNode {
+    ui_view_query: QueryState<
+        (
+            &'static ViewUiPrepassTextures,
+            &'static ExtractedCamera,
+            Option<&'static ExtractedUiViewport>,
+        ),
+        With<ExtractedView>,
+    >,
+    default_camera_view_query: QueryState<&'static DefaultCameraView>,
+}
+
+impl UiPrepassNode {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            ui_view_query: world.query_filtered(),
+            default_camera_view_query: world.query(),
+        }
+    }
+}
+
+impl Node for UiPrepassNode {
+    fn update(&mut self, world: &mut World) {
+        self.ui_view_query.update_archetypes(world);
+        self.default_camera_view_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let input_view_entity = graph.view_entity();
+
+        let Some(prepass_render_phases) = world.get_resource::<ViewSortedRenderPhases<UiPrepass>>()
+        else {
+            return Ok(());
+        };
+
+        let Some(prepass_phase) = prepass_render_phases.get(&input_view_entity) else {
+            return Ok(());
+        };
+
+        let Ok((prepass_textures, camera, ui_viewport)) =
+            self.ui_view_query.get_manual(world, input_view_entity)
+        else {
+            return Ok(());
+        };
+        if prepass_phase.items.is_empty() {
             return Ok(());
         }
 
@@ -72,23 +410,40 @@ impl Node for UiPassNode {
             input_view_entity
         };
         let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("ui_pass"),
-            color_attachments: &[Some(target.get_unsampled_color_attachment())],
-            depth_stencil_attachment: None,
+            label: Some("ui_prepass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &prepass_textures.depth.default_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(1.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
             timestamp_writes: None,
             occlusion_query_set: None,
         });
-        if let Some(viewport) = camera.viewport.as_ref() {
+        if let Some(viewport) = ui_camera_viewport(camera, ui_viewport) {
             render_pass.set_camera_viewport(viewport);
         }
-        if let Err(err) = transparent_phase.render(&mut render_pass, world, view_entity) {
-            error!("Error encountered while rendering the ui phase {err:?}");
+        if let Err(err) = prepass_phase.render(&mut render_pass, world, view_entity) {
+            error!("Error encountered while rendering the ui prepass phase {err:?}");
         }
 
         Ok(())
     }
 }
 
+/// Returns the viewport the UI of `camera` is drawn to, see [`ExtractedUiViewport`].
+fn ui_camera_viewport<'a>(
+    camera: &'a ExtractedCamera,
+    ui_viewport: Option<&'a ExtractedUiViewport>,
+) -> Option<&'a Viewport> {
+    ui_viewport
+        .map(|ui_viewport| &ui_viewport.0)
+        .or(camera.viewport.as_ref())
+}
+
 pub struct TransparentUi {
     pub sort_key: (FloatOrd, u32),
     pub entity: Entity,
@@ -151,10 +506,144 @@ impl CachedRenderPipelinePhaseItem for TransparentUi {
     }
 }
 
+/// A phase item of a node drawn with an opaque [`UiMaterial`](crate::UiMaterial), see
+/// [`UiMaterial::alpha_mode`](crate::UiMaterial::alpha_mode).
+///
+/// Items are sorted by stack index like [`TransparentUi`], but drawn front to back, so the depth
+/// test discards the fragments of opaque nodes hidden by the nodes above them.
+pub struct OpaqueUi {
+    pub sort_key: (FloatOrd, u32),
+    pub entity: Entity,
+    pub pipeline: CachedRenderPipelineId,
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for OpaqueUi {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl SortedPhaseItem for OpaqueUi {
+    type SortKey = (FloatOrd, u32);
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        self.sort_key
+    }
+
+    #[inline]
+    fn sort(items: &mut [Self]) {
+        items.sort_by_key(|item| Reverse(item.sort_key()));
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for OpaqueUi {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+/// A phase item drawn into the depth-only UI prepass.
+///
+/// Only nodes whose [`UiMaterial::prepass_enabled`](crate::UiMaterial::prepass_enabled) returns
+/// `true` are queued into this phase. Items are sorted by stack index, like [`TransparentUi`].
+pub struct UiPrepass {
+    pub sort_key: (FloatOrd, u32),
+    pub entity: Entity,
+    pub pipeline: CachedRenderPipelineId,
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for UiPrepass {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl SortedPhaseItem for UiPrepass {
+    type SortKey = (FloatOrd, u32);
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        self.sort_key
+    }
+
+    #[inline]
+    fn sort(items: &mut [Self]) {
+        items.sort_by_key(SortedPhaseItem::sort_key);
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for UiPrepass {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
 pub type DrawUi = (
     SetItemPipeline,
     SetUiViewBindGroup<0>,
     SetUiTextureBindGroup<1>,
+    SetUiScissorRect,
     DrawUiNode,
 );
 
@@ -171,13 +660,30 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetUiViewBindGroup<I> {
         ui_meta: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let Some(view_bind_group) = ui_meta.into_inner().view_bind_group.as_ref() else {
-            return RenderCommandResult::Failure("view_bind_group not available");
-        };
+        let view_bind_group =
+            match ui_view_bind_group(ui_meta.into_inner().view_bind_group.as_ref()) {
+                Ok(view_bind_group) => view_bind_group,
+                Err(result) => return result,
+            };
         pass.set_bind_group(I, view_bind_group, &[view_uniform.offset]);
         RenderCommandResult::Success
     }
 }
+
+/// Returns the view bind group bound by a UI render command, or the result the command returns
+/// without it.
+///
+/// The bind group is only created when the view uniforms are available as the nodes are prepared.
+/// A phase item drawn without it fails instead of panicking, which is warned about once.
+pub fn ui_view_bind_group(
+    view_bind_group: Option<&BindGroup>,
+) -> Result<&BindGroup, RenderCommandResult> {
+    view_bind_group.ok_or_else(|| {
+        warn_once!("The view bind group of the UI is not available, the UI was not drawn");
+        RenderCommandResult::Failure("view_bind_group not available")
+    })
+}
+
 pub struct SetUiTextureBindGroup<const I: usize>;
 impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetUiTextureBindGroup<I> {
     type Param = SRes<UiImageBindGroups>;
@@ -197,10 +703,64 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetUiTextureBindGroup<I>
             return RenderCommandResult::Skip;
         };
 
-        pass.set_bind_group(I, image_bind_groups.values.get(&batch.image).unwrap(), &[]);
+        // The bind group may have been removed since the batch was prepared, e.g. if its image
+        // was modified. Skip the batch rather than failing the whole pass.
+        let image_bind_group = if batch.image_array {
+            image_bind_groups.get_array(batch.image, batch.sampler)
+        } else {
+            image_bind_groups.get(batch.image, batch.image_layer, batch.sampler)
+        };
+        let Some(image_bind_group) = image_bind_group else {
+            warn_once!(
+                "The bind group of UI image {:?} is missing, nodes using it were not drawn",
+                batch.image
+            );
+            return RenderCommandResult::Failure("missing ui image bind group");
+        };
+        pass.set_bind_group(I, image_bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+/// Sets the scissor rect of a [`UiBatch`] to its clip, see
+/// [`UiClipMode::Scissor`](crate::UiClipMode::Scissor).
+///
+/// Items that aren't batches of UI nodes, and batches without a clip, reset it to the whole
+/// viewport so they aren't clipped by the batch drawn before them. Batches clipped away entirely
+/// are skipped.
+pub struct SetUiScissorRect;
+impl<P: PhaseItem> RenderCommand<P> for SetUiScissorRect {
+    type Param = ();
+    type ViewQuery = Read<ExtractedView>;
+    type ItemQuery = Read<UiBatch>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        view: &'w ExtractedView,
+        batch: Option<&'w UiBatch>,
+        _param: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let scissor = match batch.and_then(|batch| batch.clip) {
+            Some(clip) => ui_scissor_rect(clip, view),
+            None => Some(URect::from_corners(
+                view.viewport.xy(),
+                view.viewport.xy() + view.viewport.zw(),
+            )),
+        };
+        let Some(scissor) = scissor else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_scissor_rect(
+            scissor.min.x,
+            scissor.min.y,
+            scissor.width(),
+            scissor.height(),
+        );
         RenderCommandResult::Success
     }
 }
+
 pub struct DrawUiNode;
 impl<P: PhaseItem> RenderCommand<P> for DrawUiNode {
     type Param = SRes<UiMeta>;
@@ -234,6 +794,8 @@ impl<P: PhaseItem> RenderCommand<P> for DrawUiNode {
             0,
             bevy_render::render_resource::IndexFormat::Uint32,
         );
+        // Test the stencil values of the masks above the nodes, or write their own
+        pass.set_stencil_reference(batch.stencil.reference());
         // Draw the vertices
         pass.draw_indexed(batch.range.clone(), 0, 0..1);
         RenderCommandResult::Success
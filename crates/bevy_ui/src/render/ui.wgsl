@@ -1,9 +1,17 @@
 #import bevy_render::view::View
 
 const TEXTURED = 1u;
-const RIGHT_VERTEX = 2u;
-const BOTTOM_VERTEX = 4u;
 const BORDER: u32 = 8u;
+const BOX_SHADOW: u32 = 16u;
+const COLOR_BLEND_MASK: u32 = 192u;
+const COLOR_BLEND_REPLACE: u32 = 64u;
+const COLOR_BLEND_OVERLAY: u32 = 128u;
+const COLOR_BLEND_TINT: u32 = 192u;
+const OKLAB: u32 = 256u;
+const BORDER_SIDE: u32 = 512u;
+const NO_ANTIALIAS: u32 = 1024u;
+const BORDER_SIDE_MASK: u32 = 6144u;
+const BORDER_SIDE_SHIFT: u32 = 11u;
 
 fn enabled(flags: u32, mask: u32) -> bool {
     return (flags & mask) != 0u;
@@ -11,6 +19,36 @@ fn enabled(flags: u32, mask: u32) -> bool {
 
 @group(0) @binding(0) var<uniform> view: View;
 
+fn signed_cbrt(x: f32) -> f32 {
+    return sign(x) * pow(abs(x), 1.0 / 3.0);
+}
+
+// Must match the conversions of `bevy_color::Oklaba`
+fn linear_rgb_to_oklab(color: vec3<f32>) -> vec3<f32> {
+    let l = signed_cbrt(0.4122214708 * color.r + 0.5363325363 * color.g + 0.0514459929 * color.b);
+    let m = signed_cbrt(0.2119034982 * color.r + 0.6806995451 * color.g + 0.1073969566 * color.b);
+    let s = signed_cbrt(0.0883024619 * color.r + 0.2817188376 * color.g + 0.6299787005 * color.b);
+    return vec3(
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    );
+}
+
+fn oklab_to_linear_rgb(color: vec3<f32>) -> vec3<f32> {
+    let l_ = color.x + 0.3963377774 * color.y + 0.2158037573 * color.z;
+    let m_ = color.x - 0.1055613458 * color.y - 0.0638541728 * color.z;
+    let s_ = color.x - 0.0894841775 * color.y - 1.2914855480 * color.z;
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+    return vec3(
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    );
+}
+
 struct VertexOutput {
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
@@ -22,6 +60,9 @@ struct VertexOutput {
 
     // Position relative to the center of the rectangle.
     @location(6) point: vec2<f32>,
+
+    // The array layer of the texture, see `UiImageArrayBatching`.
+    @location(7) @interpolate(flat) layer: u32,
     @builtin(position) position: vec4<f32>,
 };
 
@@ -38,30 +79,49 @@ fn vertex(
     // x: left, y: top, z: right, w: bottom.
     @location(5) border: vec4<f32>,
     @location(6) size: vec2<f32>,
+    // Position relative to the center of the rectangle.
+    @location(7) point: vec2<f32>,
+    @location(8) layer: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
     out.uv = vertex_uv;
     out.position = view.clip_from_world * vec4(vertex_position, 1.0);
+#ifdef SCENE_DEPTH
+    // The z coordinate holds the depth of the node in the scene, tested against its depth texture
+    out.position.z = vertex_position.z * out.position.w;
+#endif
     out.color = vertex_color;
+    if enabled(flags, OKLAB) {
+        // Interpolated in Oklab, converted back in the fragment shader
+        out.color = vec4(linear_rgb_to_oklab(vertex_color.rgb), vertex_color.a);
+    }
     out.flags = flags;
     out.radius = radius;
     out.size = size;
     out.border = border;
-    var point = 0.49999 * size;
-    if (flags & RIGHT_VERTEX) == 0u {
-        point.x *= -1.;
-    }
-    if (flags & BOTTOM_VERTEX) == 0u {
-        point.y *= -1.;
-    }
     out.point = point;
+    out.layer = layer;
 
     return out;
 }
 
+#ifdef IMAGE_ARRAY
+@group(1) @binding(0) var sprite_texture: texture_2d_array<f32>;
+#else
 @group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+#endif
 @group(1) @binding(1) var sprite_sampler: sampler;
 
+fn sample_texture(in: VertexOutput) -> vec4<f32> {
+#ifdef IMAGE_ARRAY
+    // Layers past the last one show the last layer, like the views of a single layer
+    let layer = min(in.layer, textureNumLayers(sprite_texture) - 1u);
+    return textureSample(sprite_texture, sprite_sampler, in.uv, layer);
+#else
+    return textureSample(sprite_texture, sprite_sampler, in.uv);
+#endif
+}
+
 // The returned value is the shortest distance from the given point to the boundary of the rounded 
 // box.
 // 
@@ -120,17 +180,45 @@ fn sd_inset_rounded_box(point: vec2<f32>, size: vec2<f32>, radius: vec4<f32>, in
     return sd_rounded_box(inner_point, inner_size, r);
 }
 
-// get alpha for antialiasing for sdf
-fn antialias(distance: f32) -> f32 {
+// get alpha for antialiasing for sdf, or a hard edge if the node is drawn without antialiasing.
+fn antialias(distance: f32, flags: u32) -> f32 {
+    if enabled(flags, NO_ANTIALIAS) {
+        return 1.0 - step(0.0, distance);
+    }
     // Using the fwidth(distance) was causing artifacts, so just use the distance.
     // This antialiases between the distance values of 0.25 and -0.25
     return clamp(0.0, 1.0, 0.5 - 2.0 * distance);
 }
 
+// The index of the border side `point` belongs to: 0 left, 1 top, 2 right, 3 bottom.
+// The side closest to the point relative to its width is chosen, which splits the corners along
+// the diagonals from their outer to their inner corner.
+fn border_side(point: vec2<f32>, size: vec2<f32>, border: vec4<f32>) -> u32 {
+    let half_size = 0.5 * size;
+    let distance = vec4(point + half_size, half_size - point);
+    let relative = select(vec4(3.4e38), distance / max(border, vec4(1e-6)), vec4(0.0) < border);
+    var side = 0u;
+    var nearest = relative.x;
+    for (var i = 1u; i < 4u; i++) {
+        if relative[i] < nearest {
+            side = i;
+            nearest = relative[i];
+        }
+    }
+    return side;
+}
+
 fn draw(in: VertexOutput, texture_color: vec4<f32>) -> vec4<f32> {
     // Only use the color sampled from the texture if the `TEXTURED` flag is enabled. 
     // This allows us to draw both textured and untextured shapes together in the same batch.
-    let color = select(in.color, in.color * texture_color, enabled(in.flags, TEXTURED));
+    let color = select(in.color, blend_colors(in, texture_color), enabled(in.flags, TEXTURED));
+    if enabled(in.flags, BORDER_SIDE) {
+        // Each side of the border is drawn by its own node, which only covers its side
+        let side = (in.flags & BORDER_SIDE_MASK) >> BORDER_SIDE_SHIFT;
+        if border_side(in.point, in.size, in.border) != side {
+            return vec4(color.rgb, 0.0);
+        }
+    }
 
     // Signed distances. The magnitude is the distance of the point from the edge of the shape.
     // * Negative values indicate that the point is inside the shape.
@@ -154,28 +242,93 @@ fn draw(in: VertexOutput, texture_color: vec4<f32>) -> vec4<f32> {
     // This select statement ensures we only perform anti-aliasing where a non-zero width border 
     // is present, otherwise an outline about the external boundary would be drawn even without 
     // a border.
-    let t = select(1.0 - step(0.0, border_distance), antialias(border_distance), external_distance < internal_distance);
+    let t = select(1.0 - step(0.0, border_distance), antialias(border_distance, in.flags), external_distance < internal_distance);
 
-    // Blend mode ALPHA_BLENDING is used for UI elements, so we don't premultiply alpha here.
-    return vec4(color.rgb, saturate(color.a * t));
+    return apply_coverage(color, t);
 }
 
 fn draw_background(in: VertexOutput, texture_color: vec4<f32>) -> vec4<f32> {
-    let color = select(in.color, in.color * texture_color, enabled(in.flags, TEXTURED));
+    let color = select(in.color, blend_colors(in, texture_color), enabled(in.flags, TEXTURED));
 
     // When drawing the background only draw the internal area and not the border.
     let internal_distance = sd_inset_rounded_box(in.point, in.size, in.radius, in.border);
-    let t = antialias(internal_distance);
+    let t = antialias(internal_distance, in.flags);
+    return apply_coverage(color, t);
+}
+
+fn draw_box_shadow(in: VertexOutput) -> vec4<f32> {
+    // The blur radius is stored in the first component of the border.
+    let blur = in.border.x;
+
+    // The quad is grown by the blur radius on every side, the shape of the shadow is not.
+    let shadow_size = in.size - 2.0 * blur;
+    let distance = sd_rounded_box(in.point, shadow_size, in.radius);
+
+    let t = select(antialias(distance, in.flags), 1.0 - smoothstep(-blur, blur, distance), 0.0 < blur);
+    return apply_coverage(in.color, t);
+}
+
+// Fades `color` by the coverage `t` of the shape.
+fn apply_coverage(color: vec4<f32>, t: f32) -> vec4<f32> {
+#ifdef PREMULTIPLIED_ALPHA
+    return saturate(color * t);
+#else
+    // Blend mode ALPHA_BLENDING is used for UI elements, so we don't premultiply alpha here.
     return vec4(color.rgb, saturate(color.a * t));
+#endif
+}
+
+// Combines the texture color with the vertex color, see `UiColorBlend`.
+fn blend_colors(in: VertexOutput, texture_color: vec4<f32>) -> vec4<f32> {
+    let alpha = texture_color.a * in.color.a;
+    switch in.flags & COLOR_BLEND_MASK {
+        case COLOR_BLEND_REPLACE: {
+            return texture_color;
+        }
+        case COLOR_BLEND_OVERLAY: {
+            let dark = 2.0 * texture_color.rgb * in.color.rgb;
+            let bright = 1.0 - 2.0 * (1.0 - texture_color.rgb) * (1.0 - in.color.rgb);
+            return vec4(select(bright, dark, texture_color.rgb < vec3(0.5)), alpha);
+        }
+        case COLOR_BLEND_TINT: {
+            let luminance = dot(texture_color.rgb, vec3(0.2126, 0.7152, 0.0722));
+            return vec4(in.color.rgb * luminance, alpha);
+        }
+        default: {
+            return in.color * texture_color;
+        }
+    }
 }
 
 @fragment
-fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    let texture_color = textureSample(sprite_texture, sprite_sampler, in.uv);
+fn fragment(vertex_output: VertexOutput) -> @location(0) vec4<f32> {
+    var in = vertex_output;
+    if enabled(in.flags, OKLAB) {
+        in.color = vec4(oklab_to_linear_rgb(in.color.rgb), in.color.a);
+    }
+#ifdef PREMULTIPLIED_ALPHA
+    // The texture is premultiplied by its alpha, so the tint has to be too
+    in.color = vec4(in.color.rgb * in.color.a, in.color.a);
+#endif
+
+    let texture_color = sample_texture(in);
+
+#ifdef STENCIL_WRITE
+    // Only the inside of the shape of a mask is written to the stencil texture
+    let mask = draw_background(in, texture_color);
+    if mask.a < 0.5 {
+        discard;
+    }
+    return mask;
+#else
+    if enabled(in.flags, BOX_SHADOW) {
+        return draw_box_shadow(in);
+    }
 
     if enabled(in.flags, BORDER) {
         return draw(in, texture_color);    
     } else {
         return draw_background(in, texture_color);
     }
+#endif
 }
@@ -0,0 +1,235 @@
+use std::{
+    mem::size_of,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use bevy_diagnostic::{DiagnosticPath, Diagnostics};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    extract_resource::ExtractResource,
+    render_phase::{PhaseItem, ViewSortedRenderPhases},
+};
+
+use super::{TransparentUi, UiBufferCapacity, UiMeta, UiVertex};
+
+/// Reports how many batches the UI was drawn with in the last rendered frame, to measure how well
+/// nodes are batched.
+///
+/// Consecutive nodes in the [`TransparentUi`] phase are batched together when they're drawn with
+/// the same pipeline and image, so nodes interleaving materials or images in the
+/// [`UiStack`](crate::UiStack) each need their own draw call. Batches are never merged across
+/// other nodes, which keeps the transparent nodes drawn in stack order.
+///
+/// The node and pipeline counts are updated in the render world by
+/// [`queue_uinodes`](crate::queue_uinodes), the other counts once the batches are final, after
+/// [`RenderUiSystem::ModifyBatches`](crate::RenderUiSystem::ModifyBatches). They're also added to
+/// the [`DiagnosticsStore`](bevy_diagnostic::DiagnosticsStore) at the next
+/// [`Update`](bevy_app::Update), a frame behind, so regressions in the batching of the UI are
+/// measurable without a GPU profiler. Add the
+/// [`LogDiagnosticsPlugin`](bevy_diagnostic::LogDiagnosticsPlugin) to print them.
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct UiBatchDiagnostics {
+    counts: Arc<Mutex<UiBatchCounts>>,
+    buffer_capacity: Arc<Mutex<UiBufferCapacity>>,
+}
+
+/// The batches of the UI of every view in a frame, see [`UiBatchDiagnostics`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UiBatchCounts {
+    /// The number of extracted nodes, the length of
+    /// [`ExtractedUiNodes::uinodes`](crate::ExtractedUiNodes::uinodes).
+    pub nodes: usize,
+    /// The number of distinct pipelines specialized for the extracted nodes.
+    pub pipelines: usize,
+    /// The number of batches, each drawn with one draw call.
+    pub batches: usize,
+    /// The number of phase items drawn in these batches.
+    pub quads: usize,
+    /// The number of vertices in the UI vertex buffer.
+    pub vertices: usize,
+}
+
+impl UiBatchCounts {
+    /// The size in bytes of the vertices in the UI vertex buffer.
+    pub fn vertex_bytes(&self) -> usize {
+        self.vertices * size_of::<UiVertex>()
+    }
+}
+
+impl UiBatchDiagnostics {
+    /// The number of extracted nodes of the UI.
+    pub const NODES: DiagnosticPath = DiagnosticPath::const_new("ui/nodes");
+    /// The number of distinct pipelines specialized for the UI.
+    pub const PIPELINES: DiagnosticPath = DiagnosticPath::const_new("ui/pipelines");
+    /// The number of batches the UI is drawn with.
+    pub const BATCHES: DiagnosticPath = DiagnosticPath::const_new("ui/batches");
+    /// The number of vertices of the UI.
+    pub const VERTICES: DiagnosticPath = DiagnosticPath::const_new("ui/vertices");
+    /// The size in bytes of the vertices of the UI.
+    pub const VERTEX_BYTES: DiagnosticPath = DiagnosticPath::const_new("ui/vertex_bytes");
+
+    /// Returns the counts of the last rendered frame.
+    pub fn counts(&self) -> UiBatchCounts {
+        *self.counts.lock().unwrap()
+    }
+
+    /// Returns the capacity of the UI vertex and index buffers, which never decreases.
+    pub fn buffer_capacity(&self) -> UiBufferCapacity {
+        *self.buffer_capacity.lock().unwrap()
+    }
+
+    pub(crate) fn set_buffer_capacity(&self, capacity: UiBufferCapacity) {
+        *self.buffer_capacity.lock().unwrap() = capacity;
+    }
+
+    /// Sets the number of extracted nodes and of the distinct pipelines specialized for them.
+    pub(crate) fn set_queued(&self, nodes: usize, pipelines: usize) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.nodes = nodes;
+        counts.pipelines = pipelines;
+    }
+}
+
+/// Counts the batches of phase items with these `batch_ranges`.
+///
+/// Items starting a batch have a non-empty range covering the items in it, the other items of the
+/// batch have an empty range.
+pub fn ui_batch_counts(batch_ranges: impl IntoIterator<Item = Range<u32>>) -> UiBatchCounts {
+    batch_ranges
+        .into_iter()
+        .filter(|range| !range.is_empty())
+        .fold(UiBatchCounts::default(), |counts, range| UiBatchCounts {
+            batches: counts.batches + 1,
+            quads: counts.quads + range.len(),
+            ..counts
+        })
+}
+
+pub fn update_ui_batch_diagnostics(
+    diagnostics: Res<UiBatchDiagnostics>,
+    transparent_render_phases: Res<ViewSortedRenderPhases<TransparentUi>>,
+    ui_meta: Res<UiMeta>,
+) {
+    let items = transparent_render_phases
+        .values()
+        .flat_map(|phase| &phase.items);
+    let mut counts = diagnostics.counts.lock().unwrap();
+    // The nodes and pipelines were counted when the nodes were queued
+    *counts = UiBatchCounts {
+        nodes: counts.nodes,
+        pipelines: counts.pipelines,
+        vertices: ui_meta.vertices().len(),
+        ..ui_batch_counts(items.map(|item| item.batch_range().clone()))
+    };
+}
+
+/// Adds the [`UiBatchCounts`] of the last rendered frame to the
+/// [`DiagnosticsStore`](bevy_diagnostic::DiagnosticsStore), see [`UiBatchDiagnostics`].
+pub fn report_ui_batch_diagnostics(
+    mut diagnostics: Diagnostics,
+    batch_diagnostics: Res<UiBatchDiagnostics>,
+) {
+    let counts = batch_diagnostics.counts();
+    diagnostics.add_measurement(&UiBatchDiagnostics::NODES, || counts.nodes as f64);
+    diagnostics.add_measurement(&UiBatchDiagnostics::PIPELINES, || counts.pipelines as f64);
+    diagnostics.add_measurement(&UiBatchDiagnostics::BATCHES, || counts.batches as f64);
+    diagnostics.add_measurement(&UiBatchDiagnostics::VERTICES, || counts.vertices as f64);
+    diagnostics.add_measurement(&UiBatchDiagnostics::VERTEX_BYTES, || {
+        counts.vertex_bytes() as f64
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ui_batch_counts, UiBatchCounts};
+
+    #[test]
+    fn interleaved_materials_fragment_batches() {
+        // Image buttons and material panels alternating in the stack, each drawn on its own
+        let interleaved = [0..1, 0..1, 0..1, 0..1];
+        assert_eq!(
+            ui_batch_counts(interleaved),
+            UiBatchCounts {
+                batches: 4,
+                quads: 4,
+                ..Default::default()
+            }
+        );
+
+        // The same nodes, with the buttons and the panels consecutive
+        let grouped = [0..2, 0..0, 0..2, 0..0];
+        assert_eq!(
+            ui_batch_counts(grouped),
+            UiBatchCounts {
+                batches: 2,
+                quads: 4,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn frame_counts_are_reported_to_the_diagnostics_store() {
+        use std::mem::size_of;
+
+        use bevy_diagnostic::{Diagnostic, DiagnosticsStore};
+        use bevy_ecs::{entity::Entity, system::RunSystemOnce, world::World};
+
+        use super::{report_ui_batch_diagnostics, update_ui_batch_diagnostics, UiBatchDiagnostics};
+        use crate::render::{
+            tests::{base, PrepareHarness},
+            ExtractedUiNode, UiVertex,
+        };
+
+        let view = Entity::from_raw(0);
+        let node = |premultiplied_alpha| ExtractedUiNode {
+            premultiplied_alpha,
+            camera_entity: view,
+            ..base()
+        };
+
+        let batch_diagnostics = UiBatchDiagnostics::default();
+        // As counted by `queue_uinodes`: a fourth node was extracted for a camera without a
+        // phase, and the nodes drawn with and without premultiplied alpha were specialized
+        // different pipelines
+        batch_diagnostics.set_queued(4, 2);
+        let mut harness = PrepareHarness::new();
+        harness.world.insert_resource(batch_diagnostics.clone());
+        // The third node is drawn with premultiplied alpha, so it can't join the first batch
+        harness.render_frame([
+            (Entity::from_raw(1), node(false)),
+            (Entity::from_raw(2), node(false)),
+            (Entity::from_raw(3), node(true)),
+        ]);
+        harness.world.run_system_once(update_ui_batch_diagnostics);
+
+        let mut world = World::new();
+        let mut store = DiagnosticsStore::default();
+        for path in [
+            UiBatchDiagnostics::NODES,
+            UiBatchDiagnostics::PIPELINES,
+            UiBatchDiagnostics::BATCHES,
+            UiBatchDiagnostics::VERTICES,
+            UiBatchDiagnostics::VERTEX_BYTES,
+        ] {
+            store.add(Diagnostic::new(path));
+        }
+        world.insert_resource(store);
+        // Extracted to the render world, so both worlds share the counts
+        world.insert_resource(batch_diagnostics);
+        world.run_system_once(report_ui_batch_diagnostics);
+
+        let store = world.resource::<DiagnosticsStore>();
+        let value = |path| store.get(&path).and_then(Diagnostic::value);
+        assert_eq!(value(UiBatchDiagnostics::NODES), Some(4.));
+        assert_eq!(value(UiBatchDiagnostics::PIPELINES), Some(2.));
+        assert_eq!(value(UiBatchDiagnostics::BATCHES), Some(2.));
+        // Four vertices per quad
+        assert_eq!(value(UiBatchDiagnostics::VERTICES), Some(12.));
+        assert_eq!(
+            value(UiBatchDiagnostics::VERTEX_BYTES),
+            Some((12 * size_of::<UiVertex>()) as f64)
+        );
+    }
+}
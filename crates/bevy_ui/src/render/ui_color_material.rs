@@ -0,0 +1,127 @@
+use bevy_asset::*;
+use bevy_color::{Color, ColorToComponents, LinearRgba};
+use bevy_math::Vec4;
+use bevy_reflect::prelude::*;
+use bevy_render::{
+    render_asset::RenderAssets,
+    render_resource::*,
+    texture::{GpuImage, Image},
+};
+
+use crate::*;
+
+pub const COLOR_UI_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(9352014735612097745);
+
+/// Adds the built-in [`ColorUiMaterial`].
+pub struct ColorUiMaterialPlugin;
+
+impl Plugin for ColorUiMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            COLOR_UI_MATERIAL_SHADER_HANDLE,
+            "ui_color_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(UiMaterialPlugin::<ColorUiMaterial>::default())
+            .register_asset_reflect::<ColorUiMaterial>();
+    }
+}
+
+/// A [`UiMaterial`] filling a node with a texture tinted by a color, the UI counterpart of
+/// [`ColorMaterial`](bevy_sprite::ColorMaterial), for nodes drawn with a material without writing
+/// a shader.
+///
+/// Without a texture the node is filled with `color`, the white
+/// [`FallbackImage`](bevy_render::texture::FallbackImage) is sampled instead.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[uniform(0, ColorUiMaterialUniform)]
+pub struct ColorUiMaterial {
+    pub color: Color,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl ColorUiMaterial {
+    /// A material filling nodes with `color`.
+    pub fn from_color(color: impl Into<Color>) -> Self {
+        Self::from(color.into())
+    }
+}
+
+impl Default for ColorUiMaterial {
+    fn default() -> Self {
+        Self {
+            color: Color::WHITE,
+            texture: None,
+        }
+    }
+}
+
+impl From<Color> for ColorUiMaterial {
+    fn from(color: Color) -> Self {
+        Self {
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Handle<Image>> for ColorUiMaterial {
+    fn from(texture: Handle<Image>) -> Self {
+        Self {
+            texture: Some(texture),
+            ..Default::default()
+        }
+    }
+}
+
+/// The GPU representation of the uniform data of a [`ColorUiMaterial`].
+#[derive(Clone, Default, ShaderType)]
+pub struct ColorUiMaterialUniform {
+    pub color: Vec4,
+}
+
+impl AsBindGroupShaderType<ColorUiMaterialUniform> for ColorUiMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<GpuImage>,
+    ) -> ColorUiMaterialUniform {
+        ColorUiMaterialUniform {
+            color: LinearRgba::from(self.color).to_vec4(),
+        }
+    }
+}
+
+impl UiMaterial for ColorUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        COLOR_UI_MATERIAL_SHADER_HANDLE.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::Handle;
+    use bevy_color::{palettes::css::ORANGE, Color, ColorToComponents, LinearRgba};
+    use bevy_render::{render_asset::RenderAssets, render_resource::AsBindGroupShaderType};
+
+    use super::ColorUiMaterial;
+
+    #[test]
+    fn color_reaches_the_shader_in_linear_space() {
+        let material = ColorUiMaterial::from_color(ORANGE);
+        assert_eq!(material.texture, None);
+        let uniform = material.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.color, LinearRgba::from(ORANGE).to_vec4());
+        assert_eq!(uniform.color.w, 1.);
+
+        // A textured material is untinted by default
+        let textured = ColorUiMaterial::from(Handle::default());
+        assert_eq!(textured.color, Color::WHITE);
+        assert!(textured.texture.is_some());
+    }
+}
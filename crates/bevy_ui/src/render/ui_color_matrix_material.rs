@@ -0,0 +1,141 @@
+use bevy_asset::*;
+use bevy_color::{Color, ColorToComponents, LinearRgba};
+use bevy_math::{Mat4, Vec4};
+use bevy_reflect::prelude::*;
+use bevy_render::{
+    render_asset::RenderAssets,
+    render_resource::*,
+    texture::{GpuImage, Image},
+};
+
+use crate::*;
+
+pub const COLOR_MATRIX_UI_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(4175920368421950231);
+
+/// Adds the built-in [`ColorMatrixUiMaterial`].
+pub struct ColorMatrixUiMaterialPlugin;
+
+impl Plugin for ColorMatrixUiMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            COLOR_MATRIX_UI_MATERIAL_SHADER_HANDLE,
+            "ui_color_matrix_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(UiMaterialPlugin::<ColorMatrixUiMaterial>::default())
+            .register_asset_reflect::<ColorMatrixUiMaterial>();
+    }
+}
+
+/// A [`UiMaterial`] filling a node with a texture whose color is transformed by a color matrix.
+///
+/// The sampled texture color is treated as a column vector `(r, g, b, a)` and multiplied by
+/// `color_matrix` before it is tinted by `color`. This can be used for hue rotations, sepia or
+/// grayscale effects and channel swaps without writing a shader.
+///
+/// Without a texture the white [`FallbackImage`](bevy_render::texture::FallbackImage) is
+/// transformed instead.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[uniform(0, ColorMatrixUiMaterialUniform)]
+pub struct ColorMatrixUiMaterial {
+    pub color_matrix: Mat4,
+    pub color: Color,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl ColorMatrixUiMaterial {
+    /// Leaves colors unchanged.
+    pub const IDENTITY: Mat4 = Mat4::IDENTITY;
+
+    /// Replaces the color channels with the luminance of the color, using the Rec. 709 weights.
+    pub const GRAYSCALE: Mat4 = Mat4::from_cols(
+        Vec4::new(0.2126, 0.2126, 0.2126, 0.),
+        Vec4::new(0.7152, 0.7152, 0.7152, 0.),
+        Vec4::new(0.0722, 0.0722, 0.0722, 0.),
+        Vec4::W,
+    );
+
+    /// A material drawing `texture` transformed by `color_matrix`.
+    pub fn new(color_matrix: Mat4, texture: Handle<Image>) -> Self {
+        Self {
+            color_matrix,
+            texture: Some(texture),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ColorMatrixUiMaterial {
+    fn default() -> Self {
+        Self {
+            color_matrix: Self::IDENTITY,
+            color: Color::WHITE,
+            texture: None,
+        }
+    }
+}
+
+/// The GPU representation of the uniform data of a [`ColorMatrixUiMaterial`].
+#[derive(Clone, Default, ShaderType)]
+pub struct ColorMatrixUiMaterialUniform {
+    pub color_matrix: Mat4,
+    pub color: Vec4,
+}
+
+impl AsBindGroupShaderType<ColorMatrixUiMaterialUniform> for ColorMatrixUiMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<GpuImage>,
+    ) -> ColorMatrixUiMaterialUniform {
+        ColorMatrixUiMaterialUniform {
+            color_matrix: self.color_matrix,
+            color: LinearRgba::from(self.color).to_vec4(),
+        }
+    }
+}
+
+impl UiMaterial for ColorMatrixUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        COLOR_MATRIX_UI_MATERIAL_SHADER_HANDLE.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::Handle;
+    use bevy_color::{palettes::css::ORANGE, Color, ColorToComponents, LinearRgba};
+    use bevy_math::Vec4;
+    use bevy_render::{render_asset::RenderAssets, render_resource::AsBindGroupShaderType};
+
+    use super::ColorMatrixUiMaterial;
+
+    #[test]
+    fn color_matrix_reaches_the_shader_with_the_tint() {
+        let material = ColorMatrixUiMaterial {
+            color: ORANGE.into(),
+            ..ColorMatrixUiMaterial::new(ColorMatrixUiMaterial::GRAYSCALE, Handle::default())
+        };
+        let uniform = material.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.color_matrix, ColorMatrixUiMaterial::GRAYSCALE);
+        assert_eq!(uniform.color, LinearRgba::from(ORANGE).to_vec4());
+
+        // The grayscale matrix maps every color channel to the luminance and keeps the alpha
+        let gray = uniform.color_matrix * Vec4::new(0.8, 0.3, 0.1, 0.5);
+        let luminance = 0.2126 * 0.8 + 0.7152 * 0.3 + 0.0722 * 0.1;
+        for channel in [gray.x, gray.y, gray.z] {
+            assert!((channel - luminance).abs() < 1e-6);
+        }
+        assert_eq!(gray.w, 0.5);
+
+        // The default material leaves the untinted texture unchanged
+        let default = ColorMatrixUiMaterial::default();
+        assert_eq!(default.color_matrix, ColorMatrixUiMaterial::IDENTITY);
+        assert_eq!(default.color, Color::WHITE);
+    }
+}
@@ -0,0 +1,21 @@
+#import bevy_ui::{
+    ui_material_mask::ui_material_mask,
+    ui_vertex_output::UiVertexOutput,
+}
+
+struct ColorMatrixUiMaterial {
+    color_matrix: mat4x4<f32>,
+    color: vec4<f32>,
+}
+
+@group(1) @binding(0) var<uniform> material: ColorMatrixUiMaterial;
+// The white fallback image when the material has no texture
+@group(1) @binding(1) var material_texture: texture_2d<f32>;
+@group(1) @binding(2) var material_sampler: sampler;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let texture_color = textureSample(material_texture, material_sampler, in.uv);
+    let color = material.color * (material.color_matrix * texture_color);
+    return vec4(color.rgb, color.a * in.opacity * ui_material_mask(in));
+}
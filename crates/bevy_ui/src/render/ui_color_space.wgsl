@@ -0,0 +1,24 @@
+#define_import_path bevy_ui::ui_color_space
+
+// Converts a sRGB-encoded channel to linear.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+    return pow((value + 0.055) / 1.055, 2.4);
+}
+
+// Returns the linear color the target expects for the `color` output by a fragment shader,
+// decoding it when the material outputs sRGB-encoded colors (`UI_OUTPUT_SRGB`).
+fn ui_output_color(color: vec4<f32>) -> vec4<f32> {
+#ifdef UI_OUTPUT_SRGB
+    return vec4<f32>(
+        srgb_to_linear(color.r),
+        srgb_to_linear(color.g),
+        srgb_to_linear(color.b),
+        color.a,
+    );
+#else
+    return color;
+#endif
+}
@@ -0,0 +1,233 @@
+use bevy_color::LinearRgba;
+use bevy_core_pipeline::blit::{BlitPipeline, BlitPipelineKey};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    camera::{Camera, ExtractedCamera},
+    render_resource::*,
+    renderer::{RenderContext, RenderDevice},
+    texture::{BevyDefault, CachedTexture, TextureCache},
+    view::*,
+    Extract, ExtractSchedule, Render, RenderSet,
+};
+
+use super::ui_msaa::{ui_msaa_samples, ui_sampled_texture};
+use crate::*;
+
+/// Renders the UI of cameras with [`UiForceLdr`] to an LDR texture.
+///
+/// The UI pipelines of these cameras are specialized for an LDR target even if the camera is
+/// HDR. Their UI is drawn into an intermediate LDR texture of the view, which is composited over
+/// the camera's HDR main texture after each run of nodes, so modal backdrops and the
+/// [`UiViewportMask`] still see all of the UI drawn before them.
+pub struct UiForceLdrPlugin;
+
+impl Plugin for UiForceLdrPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .add_systems(ExtractSchedule, extract_ui_force_ldr)
+                .add_systems(
+                    Render,
+                    prepare_ui_ldr_textures.in_set(RenderSet::PrepareResources),
+                );
+        }
+    }
+}
+
+/// Marks a camera whose UI is rendered to an LDR texture, see [`UiForceLdr`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ExtractedUiForceLdr;
+
+pub fn extract_ui_force_ldr(
+    mut commands: Commands,
+    camera_query: Extract<Query<(Entity, &Camera), With<UiForceLdr>>>,
+) {
+    for (entity, camera) in &camera_query {
+        // The UI of LDR cameras is already rendered to an LDR texture
+        if !camera.is_active || !camera.hdr {
+            continue;
+        }
+        commands.get_or_spawn(entity).insert(ExtractedUiForceLdr);
+    }
+}
+
+/// Returns whether the UI pipelines of `view` target an HDR texture.
+///
+/// `force_ldr` is whether the camera of `view` has an [`ExtractedUiForceLdr`].
+pub fn ui_target_is_hdr(view: &ExtractedView, force_ldr: bool) -> bool {
+    view.hdr && !force_ldr
+}
+
+/// The texture the UI of a view with an [`ExtractedUiForceLdr`] is rendered to.
+#[derive(Component)]
+pub struct ViewUiLdrTexture {
+    pub texture: CachedTexture,
+    /// The multisampled texture resolved into `texture`, if the view uses [`Msaa`].
+    pub sampled: Option<CachedTexture>,
+    /// Blends `texture` over the main texture of the view.
+    pub composite_pipeline: CachedRenderPipelineId,
+}
+
+pub fn prepare_ui_ldr_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    views: Query<(Entity, &ViewTarget, &ExtractedCamera, Option<&Msaa>), With<ExtractedUiForceLdr>>,
+) {
+    for (entity, target, camera, msaa) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_ldr_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::bevy_default(),
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+
+        let sampled = ui_sampled_texture(
+            &render_device,
+            &mut texture_cache,
+            "ui_ldr_sampled_texture",
+            TextureFormat::bevy_default(),
+            physical_target_size,
+            ui_msaa_samples(msaa),
+        );
+
+        let composite_pipeline = blit_pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            ui_composite_pipeline_key(target.main_texture_format()),
+        );
+
+        commands.entity(entity).insert(ViewUiLdrTexture {
+            texture,
+            sampled,
+            composite_pipeline,
+        });
+    }
+}
+
+/// Returns the key of the pipeline blending an intermediate UI texture over a main texture of
+/// `main_texture_format`.
+pub fn ui_composite_pipeline_key(main_texture_format: TextureFormat) -> BlitPipelineKey {
+    // The UI pipelines blend with straight alpha, so the texture holds premultiplied colors
+    // over a transparent background
+    BlitPipelineKey {
+        texture_format: main_texture_format,
+        blend_state: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        samples: 1,
+    }
+}
+
+/// Returns the color attachment a run of UI nodes is drawn to in an intermediate `texture`,
+/// clearing it first.
+///
+/// With MSAA, the nodes are drawn in the multisampled `sampled` texture, resolved into `texture`.
+pub(crate) fn ui_intermediate_color_attachment<'a>(
+    texture: &'a CachedTexture,
+    sampled: Option<&'a CachedTexture>,
+) -> RenderPassColorAttachment<'a> {
+    let (view, resolve_target) = match sampled {
+        Some(sampled) => (&sampled.default_view, Some(&*texture.default_view)),
+        None => (&texture.default_view, None),
+    };
+    RenderPassColorAttachment {
+        view,
+        resolve_target,
+        ops: Operations {
+            load: LoadOp::Clear(LinearRgba::NONE.into()),
+            store: StoreOp::Store,
+        },
+    }
+}
+
+/// Blends the UI drawn to an intermediate `texture` over the main texture of `target` with
+/// `composite_pipeline`, see [`ui_composite_pipeline_key`].
+pub(crate) fn composite_ui_intermediate_texture(
+    render_context: &mut RenderContext,
+    world: &World,
+    target: &ViewTarget,
+    texture: &CachedTexture,
+    composite_pipeline: CachedRenderPipelineId,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let blit_pipeline = world.resource::<BlitPipeline>();
+
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(composite_pipeline) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "ui_composite_bind_group",
+        &blit_pipeline.texture_bind_group,
+        &BindGroupEntries::sequential((&texture.default_view, &blit_pipeline.sampler)),
+    );
+
+    let mut render_pass =
+        render_context
+            .command_encoder()
+            .begin_render_pass(&RenderPassDescriptor {
+                label: Some("ui_composite_pass"),
+                color_attachments: &[Some(target.get_unsampled_color_attachment())],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{Mat4, UVec4};
+    use bevy_render::view::ExtractedView;
+    use bevy_transform::components::GlobalTransform;
+
+    use super::ui_target_is_hdr;
+    use crate::{ui_front_face, ui_stencil_mask::UiStencilMode, UiPipelineKey};
+
+    #[test]
+    fn force_ldr_node_specializes_an_ldr_pipeline_on_an_hdr_view() {
+        let view = ExtractedView {
+            clip_from_view: Mat4::orthographic_rh(0.0, 100.0, 100.0, 0.0, 0.0, 1000.0),
+            world_from_view: GlobalTransform::IDENTITY,
+            clip_from_world: None,
+            hdr: true,
+            viewport: UVec4::new(0, 0, 100, 100),
+            color_grading: Default::default(),
+        };
+        let key = |force_ldr| UiPipelineKey {
+            hdr: ui_target_is_hdr(&view, force_ldr),
+            front_face: ui_front_face(&view),
+            samples: 1,
+            depth: false,
+            scene_depth: false,
+            stencil: UiStencilMode::Disabled,
+            premultiplied_alpha: false,
+            image_array: false,
+        };
+
+        assert!(key(false).hdr);
+        assert!(!key(true).hdr);
+
+        let ldr_view = ExtractedView { hdr: false, ..view };
+        assert!(!ui_target_is_hdr(&ldr_view, false));
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use bevy_asset::AssetId;
+use bevy_ecs::prelude::*;
+use bevy_math::{Rect, Vec3};
+use bevy_render::{
+    extract_resource::ExtractResource, render_phase::ViewSortedRenderPhases, texture::Image,
+};
+
+use super::{ExtractedUiNode, ExtractedUiNodes, TransparentUi};
+
+/// Captures the UI geometry of a single frame as text, for golden-file tests of UI layout and
+/// styling that don't depend on pixel comparisons.
+///
+/// Call [`UiGeometryCapture::request`], then [`UiGeometryCapture::take`] returns the snapshot once
+/// a frame has been rendered. The snapshot lists the [`ExtractedUiNode`]s of each view in the
+/// order they are drawn, see [`ui_geometry_snapshot`] for the format.
+#[derive(Resource, ExtractResource, Clone, Default)]
+pub struct UiGeometryCapture(Arc<Mutex<UiGeometryCaptureState>>);
+
+#[derive(Default)]
+struct UiGeometryCaptureState {
+    requested: bool,
+    snapshot: Option<String>,
+}
+
+impl UiGeometryCapture {
+    /// Requests a snapshot of the next rendered frame.
+    pub fn request(&self) {
+        self.0.lock().unwrap().requested = true;
+    }
+
+    /// Returns the requested snapshot, if it has been captured yet.
+    pub fn take(&self) -> Option<String> {
+        self.0.lock().unwrap().snapshot.take()
+    }
+
+    fn is_requested(&self) -> bool {
+        self.0.lock().unwrap().requested
+    }
+
+    fn finish(&self, snapshot: String) {
+        let mut state = self.0.lock().unwrap();
+        state.requested = false;
+        state.snapshot = Some(snapshot);
+    }
+}
+
+/// Writes the sorted [`ExtractedUiNodes`] of every view into the [`UiGeometryCapture`], if a
+/// snapshot was requested.
+pub fn capture_ui_geometry(
+    capture: Res<UiGeometryCapture>,
+    extracted_uinodes: Res<ExtractedUiNodes>,
+    transparent_render_phases: Res<ViewSortedRenderPhases<TransparentUi>>,
+) {
+    if !capture.is_requested() {
+        return;
+    }
+
+    let mut views: Vec<_> = transparent_render_phases.iter().collect();
+    views.sort_by_key(|(view_entity, _)| **view_entity);
+
+    capture.finish(ui_geometry_snapshot(views.into_iter().map(
+        |(_, transparent_phase)| {
+            transparent_phase
+                .items
+                .iter()
+                .filter_map(|item| extracted_uinodes.uinodes.get(&item.entity))
+        },
+    )));
+}
+
+/// Formats the nodes of each view, in draw order, as RON-like text.
+///
+/// Values are rounded to 3 decimals and entities are left out so that the output is stable across
+/// runs. Images are written as `default` when the node is untextured.
+pub fn ui_geometry_snapshot<'a, V, N>(views: V) -> String
+where
+    V: IntoIterator<Item = N>,
+    N: IntoIterator<Item = &'a ExtractedUiNode>,
+{
+    let mut snapshot = String::from("[\n");
+    for (view_index, nodes) in views.into_iter().enumerate() {
+        let _ = writeln!(snapshot, "    (view: {view_index}, nodes: [");
+        for node in nodes {
+            write_node(&mut snapshot, node);
+        }
+        snapshot.push_str("    ]),\n");
+    }
+    snapshot.push(']');
+    snapshot
+}
+
+fn write_node(out: &mut String, node: &ExtractedUiNode) {
+    let position = node.transform.transform_point3(Vec3::ZERO);
+    let color = node.color;
+    let image = if node.image == AssetId::<Image>::default() {
+        "default".to_string()
+    } else {
+        format!("{:?}", node.image)
+    };
+    let clip = node
+        .clip
+        .map(|clip| format!("Some({})", format_rect(clip)))
+        .unwrap_or_else(|| "None".to_string());
+
+    let _ = writeln!(
+        out,
+        "        (stack_index: {}, type: {:?}, position: ({}, {}), rect: {}, color: ({}, {}, {}, {}), \
+        clip: {clip}, image: {image}, flip: ({}, {}), border: {}, border_radius: {}),",
+        node.stack_index,
+        node.node_type,
+        format_f32(position.x),
+        format_f32(position.y),
+        format_rect(node.rect),
+        format_f32(color.red),
+        format_f32(color.green),
+        format_f32(color.blue),
+        format_f32(color.alpha),
+        node.flip_x,
+        node.flip_y,
+        format_array(node.border),
+        format_array(node.border_radius),
+    );
+}
+
+fn format_f32(value: f32) -> String {
+    let rounded = (value * 1000.).round() / 1000.;
+    // Avoid writing `-0.000`
+    format!("{:.3}", if rounded == 0. { 0. } else { rounded })
+}
+
+fn format_rect(rect: Rect) -> String {
+    format!(
+        "(({}, {}), ({}, {}))",
+        format_f32(rect.min.x),
+        format_f32(rect.min.y),
+        format_f32(rect.max.x),
+        format_f32(rect.max.y)
+    )
+}
+
+fn format_array(values: [f32; 4]) -> String {
+    format!(
+        "({}, {}, {}, {})",
+        format_f32(values[0]),
+        format_f32(values[1]),
+        format_f32(values[2]),
+        format_f32(values[3])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::LinearRgba;
+    use bevy_math::{Mat4, Rect, Vec2, Vec3};
+
+    use super::ui_geometry_snapshot;
+    use crate::{render::tests::base, ExtractedUiNode, NodeType};
+
+    fn node(stack_index: u32, center: Vec2, size: Vec2, node_type: NodeType) -> ExtractedUiNode {
+        ExtractedUiNode {
+            stack_index,
+            transform: Mat4::from_translation(center.extend(0.)),
+            color: LinearRgba::rgb(1., 0.5, 0.),
+            rect: Rect {
+                min: Vec2::ZERO,
+                max: size,
+            },
+            node_type,
+            ..base()
+        }
+    }
+
+    #[test]
+    fn snapshot_matches_golden_file() {
+        let background = node(0, Vec2::new(50., 25.), Vec2::new(100., 50.), NodeType::Rect);
+        let mut border = node(
+            1,
+            Vec2::new(50., 25.),
+            Vec2::new(100., 50.),
+            NodeType::Border,
+        );
+        border.border = [2., 2., 2., 2.];
+        border.border_radius = [5., 5., 5., 5.];
+        border.clip = Some(Rect::new(0., 0., 80., 40.));
+        border.transform *= Mat4::from_translation(Vec3::new(-0.0001, 0., 0.));
+
+        let snapshot = ui_geometry_snapshot([[&background, &border]]);
+
+        assert_eq!(
+            snapshot,
+            "[
+    (view: 0, nodes: [
+        (stack_index: 0, type: Rect, position: (50.000, 25.000), rect: ((0.000, 0.000), (100.000, 50.000)), color: (1.000, 0.500, 0.000, 1.000), clip: None, image: default, flip: (false, false), border: (0.000, 0.000, 0.000, 0.000), border_radius: (0.000, 0.000, 0.000, 0.000)),
+        (stack_index: 1, type: Border, position: (50.000, 25.000), rect: ((0.000, 0.000), (100.000, 50.000)), color: (1.000, 0.500, 0.000, 1.000), clip: Some(((0.000, 0.000), (80.000, 40.000))), image: default, flip: (false, false), border: (2.000, 2.000, 2.000, 2.000), border_radius: (5.000, 5.000, 5.000, 5.000)),
+    ]),
+]"
+        );
+    }
+}
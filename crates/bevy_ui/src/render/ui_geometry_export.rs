@@ -0,0 +1,194 @@
+use std::ops::Range;
+
+use bevy_asset::AssetId;
+use bevy_ecs::prelude::*;
+use bevy_math::Rect;
+use bevy_render::{
+    render_resource::{BufferUsages, RawBufferVec},
+    renderer::{RenderDevice, RenderQueue},
+    texture::Image,
+};
+
+use super::{UiBatch, UiFrameCache, UiMeta, UiVertex};
+
+/// Buffers the UI geometry prepared by [`prepare_uinodes`](super::prepare_uinodes) is copied to
+/// every frame, to render the UI in a custom pass, e.g. onto a 3D object.
+///
+/// Insert it into the render app to enable the copy. The buffers hold the same vertices and
+/// indices as the ones used by the built-in UI pass, and are written to the GPU during
+/// [`RenderSet::PrepareBindGroups`](bevy_render::RenderSet::PrepareBindGroups), after
+/// `prepare_uinodes`. Each batch is drawn with a single texture, see [`UiExportedBatch`].
+#[derive(Resource)]
+pub struct UiGeometryExport {
+    pub vertices: RawBufferVec<UiVertex>,
+    pub indices: RawBufferVec<u32>,
+    /// The batches of the UI of every view, in the order the built-in pass draws them.
+    pub batches: Vec<UiExportedBatch>,
+}
+
+impl UiGeometryExport {
+    /// Creates empty buffers with the given usages, in addition to `VERTEX` and `INDEX`.
+    pub fn new(usages: BufferUsages) -> Self {
+        Self {
+            vertices: RawBufferVec::new(BufferUsages::VERTEX | usages),
+            indices: RawBufferVec::new(BufferUsages::INDEX | usages),
+            batches: Vec::new(),
+        }
+    }
+
+    /// Replaces the exported geometry with `vertices`, `indices` and `batches`.
+    pub fn copy<'a>(
+        &mut self,
+        vertices: &[UiVertex],
+        indices: &[u32],
+        batches: impl IntoIterator<Item = &'a (Entity, UiBatch)>,
+    ) {
+        self.vertices.clear();
+        self.vertices.extend(vertices.iter().copied());
+        self.indices.clear();
+        self.indices.extend(indices.iter().copied());
+        self.batches.clear();
+        self.batches
+            .extend(batches.into_iter().map(|(entity, batch)| UiExportedBatch {
+                entity: *entity,
+                range: batch.range.clone(),
+                image: batch.image,
+                image_layer: batch.image_layer,
+                image_array: batch.image_array,
+                camera: batch.camera,
+                clip: batch.clip,
+            }));
+    }
+}
+
+impl Default for UiGeometryExport {
+    fn default() -> Self {
+        Self::new(BufferUsages::empty())
+    }
+}
+
+/// A range of the [`UiGeometryExport`] indices drawn with the same texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UiExportedBatch {
+    /// The phase item starting the batch.
+    pub entity: Entity,
+    /// The range of indices drawn by the batch.
+    pub range: Range<u32>,
+    pub image: AssetId<Image>,
+    /// The array layer of `image`, see [`UiImageLayer`](crate::UiImageLayer).
+    pub image_layer: Option<u32>,
+    /// Whether `image` is sampled as a texture array, at the
+    /// [`UiVertex::layer`] of each vertex instead of `image_layer`, see
+    /// [`UiImageArrayBatching`](crate::UiImageArrayBatching).
+    pub image_array: bool,
+    /// The camera the batch is drawn to.
+    pub camera: Entity,
+    /// The clip of the batch, to apply with a scissor rect, see
+    /// [`ui_scissor_rect`](super::ui_scissor_rect).
+    pub clip: Option<Rect>,
+}
+
+/// Copies this frame's UI geometry to the [`UiGeometryExport`], if there is one.
+pub fn export_ui_geometry(
+    export: Option<ResMut<UiGeometryExport>>,
+    ui_meta: Res<UiMeta>,
+    frame_cache: Res<UiFrameCache>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let Some(mut export) = export else {
+        return;
+    };
+
+    export.copy(
+        ui_meta.vertices.values(),
+        ui_meta.indices.values(),
+        frame_cache.batches(),
+    );
+    export.vertices.write_buffer(&render_device, &render_queue);
+    export.indices.write_buffer(&render_device, &render_queue);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::{AssetId, Handle};
+    use bevy_ecs::entity::Entity;
+    use bevy_math::Rect;
+    use bevy_render::texture::Image;
+
+    use super::{UiExportedBatch, UiGeometryExport};
+    use crate::{ui_stencil_mask::UiStencil, UiBatch, UiVertex};
+
+    #[test]
+    fn geometry_is_copied_into_the_external_buffers_with_its_batches() {
+        let camera = Entity::from_raw(0);
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        let image = Handle::<Image>::weak_from_u128(42).id();
+        let batches = [
+            (
+                first,
+                UiBatch {
+                    range: 0..12,
+                    image: AssetId::default(),
+                    image_layer: None,
+                    sampler: None,
+                    camera,
+                    clip: None,
+                    stencil: UiStencil::None,
+                    premultiplied_alpha: false,
+                    image_array: false,
+                },
+            ),
+            (
+                second,
+                UiBatch {
+                    range: 12..18,
+                    image,
+                    image_layer: Some(1),
+                    sampler: None,
+                    camera,
+                    clip: Some(Rect::new(0., 0., 50., 20.)),
+                    stencil: UiStencil::None,
+                    premultiplied_alpha: false,
+                    image_array: false,
+                },
+            ),
+        ];
+        let vertices: Vec<UiVertex> = (0..12).map(|_| bytemuck::Zeroable::zeroed()).collect();
+        let indices: Vec<u32> = (0..18).collect();
+
+        let mut export = UiGeometryExport::default();
+        export.copy(&vertices, &indices, &batches);
+        // Copying again replaces the previous frame's geometry
+        export.copy(&vertices, &indices, &batches);
+
+        assert_eq!(export.vertices.len(), 12);
+        assert_eq!(export.indices.values(), &indices);
+        assert_eq!(
+            export.batches,
+            [
+                UiExportedBatch {
+                    entity: first,
+                    range: 0..12,
+                    image: AssetId::default(),
+                    image_layer: None,
+                    image_array: false,
+                    camera,
+                    clip: None,
+                },
+                UiExportedBatch {
+                    entity: second,
+                    range: 12..18,
+                    image,
+                    image_layer: Some(1),
+                    image_array: false,
+                    camera,
+                    clip: Some(Rect::new(0., 0., 50., 20.)),
+                },
+            ]
+        );
+        for batch in &export.batches {
+            assert!(batch.range.end as usize <= export.indices.len());
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy_asset::*;
+use bevy_color::{Color, ColorToComponents, LinearRgba};
+use bevy_math::{Vec2, Vec4};
+use bevy_reflect::prelude::*;
+use bevy_render::{render_asset::RenderAssets, render_resource::*, texture::GpuImage};
+
+use crate::*;
+
+pub const GRADIENT_UI_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(2870513473865210784);
+
+/// Adds the built-in [`GradientUiMaterial`].
+pub struct GradientUiMaterialPlugin;
+
+impl Plugin for GradientUiMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            GRADIENT_UI_MATERIAL_SHADER_HANDLE,
+            "ui_gradient_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(UiMaterialPlugin::<GradientUiMaterial>::default())
+            .register_asset_reflect::<GradientUiMaterial>();
+    }
+}
+
+/// How a [`GradientUiMaterial`] fills the parts of its node beyond its `start` and `end` points,
+/// like the `spreadMethod` of SVG gradients.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiGradientSpread {
+    /// Extends the colors of the stops.
+    #[default]
+    Pad,
+    /// Starts the gradient over after each stop.
+    Repeat,
+    /// Runs the gradient backwards and forwards, mirroring it at each stop.
+    Reflect,
+}
+
+impl UiGradientSpread {
+    fn index(self) -> u32 {
+        match self {
+            UiGradientSpread::Pad => 0,
+            UiGradientSpread::Repeat => 1,
+            UiGradientSpread::Reflect => 2,
+        }
+    }
+}
+
+/// A [`UiMaterial`] filling a node with a linear gradient between two stops.
+///
+/// `start` and `end` are points relative to the node, `(0, 0)` at its top left corner and `(1, 1)`
+/// at its bottom right. The gradient runs along the vector between them, which can point in any
+/// direction, and the lines of equal color are perpendicular to it on screen whatever the aspect
+/// ratio of the node. `spread` fills the node beyond the stops.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[uniform(0, GradientUiMaterialUniform)]
+pub struct GradientUiMaterial {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub spread: UiGradientSpread,
+}
+
+impl GradientUiMaterial {
+    /// A gradient from the top left to the bottom right corner of the node.
+    pub fn diagonal(start_color: impl Into<Color>, end_color: impl Into<Color>) -> Self {
+        Self {
+            start: Vec2::ZERO,
+            end: Vec2::ONE,
+            start_color: start_color.into(),
+            end_color: end_color.into(),
+            spread: UiGradientSpread::Pad,
+        }
+    }
+
+    /// Returns the gradient with `spread`.
+    pub fn with_spread(mut self, spread: UiGradientSpread) -> Self {
+        self.spread = spread;
+        self
+    }
+}
+
+impl Default for GradientUiMaterial {
+    fn default() -> Self {
+        Self {
+            start: Vec2::new(0., 0.5),
+            end: Vec2::new(1., 0.5),
+            start_color: Color::BLACK,
+            end_color: Color::WHITE,
+            spread: UiGradientSpread::Pad,
+        }
+    }
+}
+
+/// The GPU representation of the uniform data of a [`GradientUiMaterial`].
+#[derive(Clone, Default, ShaderType)]
+pub struct GradientUiMaterialUniform {
+    pub start_color: Vec4,
+    pub end_color: Vec4,
+    /// The start stop in `xy` and the end stop in `zw`, relative to the node.
+    pub stops: Vec4,
+    pub spread: u32,
+}
+
+impl AsBindGroupShaderType<GradientUiMaterialUniform> for GradientUiMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<GpuImage>,
+    ) -> GradientUiMaterialUniform {
+        GradientUiMaterialUniform {
+            start_color: LinearRgba::from(self.start_color).to_vec4(),
+            end_color: LinearRgba::from(self.end_color).to_vec4(),
+            stops: self.start.extend(self.end.x).extend(self.end.y),
+            spread: self.spread.index(),
+        }
+    }
+}
+
+impl UiMaterial for GradientUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        GRADIENT_UI_MATERIAL_SHADER_HANDLE.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::{Color, ColorToComponents, LinearRgba};
+    use bevy_math::{Vec2, Vec4};
+    use bevy_render::{render_asset::RenderAssets, render_resource::AsBindGroupShaderType};
+
+    use super::{GradientUiMaterial, UiGradientSpread};
+
+    #[test]
+    fn stops_are_packed_start_then_end() {
+        let gradient = GradientUiMaterial {
+            start: Vec2::new(0.1, 0.2),
+            end: Vec2::new(0.7, 0.9),
+            start_color: Color::BLACK,
+            end_color: Color::WHITE,
+            spread: UiGradientSpread::Pad,
+        };
+        let uniform = gradient.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.stops, Vec4::new(0.1, 0.2, 0.7, 0.9));
+        assert_eq!(uniform.start_color, LinearRgba::BLACK.to_vec4());
+        assert_eq!(uniform.end_color, LinearRgba::WHITE.to_vec4());
+    }
+
+    #[test]
+    fn spread_index_matches_the_shader_constants() {
+        let shader = include_str!("ui_gradient_material.wgsl");
+        for (spread, name) in [
+            (UiGradientSpread::Pad, "SPREAD_PAD"),
+            (UiGradientSpread::Repeat, "SPREAD_REPEAT"),
+            (UiGradientSpread::Reflect, "SPREAD_REFLECT"),
+        ] {
+            let uniform = GradientUiMaterial::diagonal(Color::BLACK, Color::WHITE)
+                .with_spread(spread)
+                .as_bind_group_shader_type(&RenderAssets::default());
+            let constant = format!("const {name}: u32 = {}u;", uniform.spread);
+            assert!(shader.contains(&constant), "missing `{constant}`");
+        }
+    }
+}
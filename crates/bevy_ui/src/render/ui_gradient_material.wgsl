@@ -0,0 +1,40 @@
+#import bevy_ui::{
+    ui_material_mask::ui_material_mask,
+    ui_vertex_output::UiVertexOutput,
+}
+
+const SPREAD_PAD: u32 = 0u;
+const SPREAD_REPEAT: u32 = 1u;
+const SPREAD_REFLECT: u32 = 2u;
+
+struct GradientUiMaterial {
+    start_color: vec4<f32>,
+    end_color: vec4<f32>,
+    // The start stop in `xy` and the end stop in `zw`, relative to the node.
+    stops: vec4<f32>,
+    spread: u32,
+}
+
+@group(1) @binding(0) var<uniform> material: GradientUiMaterial;
+
+fn spread(t: f32) -> f32 {
+    if material.spread == SPREAD_REPEAT {
+        return t - floor(t);
+    }
+    if material.spread == SPREAD_REFLECT {
+        let r = t - 2.0 * floor(t * 0.5);
+        return select(r, 2.0 - r, 1.0 < r);
+    }
+    return clamp(t, 0.0, 1.0);
+}
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    // Project in pixels, so the lines of equal color stay perpendicular to the gradient vector
+    // on nodes that aren't square.
+    let start = material.stops.xy * in.size;
+    let vector = material.stops.zw * in.size - start;
+    let t = dot(in.uv * in.size - start, vector) / max(dot(vector, vector), 1.1920929e-7);
+    let color = mix(material.start_color, material.end_color, spread(t));
+    return vec4(color.rgb, color.a * in.opacity * ui_material_mask(in));
+}
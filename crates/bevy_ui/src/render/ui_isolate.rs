@@ -0,0 +1,123 @@
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_hierarchy::Children;
+use bevy_render::Extract;
+use bevy_utils::HashSet;
+
+use super::ExtractedUiNodes;
+use crate::Node;
+
+/// Renders only some UI nodes and their descendants, e.g. to inspect them in isolation.
+///
+/// While the resource exists, [`isolate_extracted_uinodes`] drops every other node from the
+/// [`ExtractedUiNodes`], including their borders, outlines, shadows and text. Remove the resource
+/// to render the whole UI again. [`UiMaterial`](crate::UiMaterial) nodes and texture slices are
+/// not isolated.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct UiIsolate {
+    /// The root nodes of the rendered subtrees.
+    pub roots: EntityHashSet,
+}
+
+/// Returns the stack indices of the `roots` and of all of their descendants.
+pub fn isolated_stack_indices(
+    roots: &EntityHashSet,
+    node_query: &Query<&Node>,
+    children_query: &Query<&Children>,
+) -> HashSet<u32> {
+    let mut stack_indices = HashSet::new();
+    let mut entities: Vec<Entity> = roots.iter().copied().collect();
+    while let Some(entity) = entities.pop() {
+        let Ok(node) = node_query.get(entity) else {
+            continue;
+        };
+        stack_indices.insert(node.stack_index);
+        if let Ok(children) = children_query.get(entity) {
+            entities.extend(children);
+        }
+    }
+    stack_indices
+}
+
+/// Keeps only the extracted nodes of the [`UiIsolate`] subtrees, if there is one.
+///
+/// The extracted items of a node share its stack index, which is used to find them regardless of
+/// the entity they were extracted to.
+pub fn isolate_extracted_uinodes(
+    isolate: Extract<Option<Res<UiIsolate>>>,
+    node_query: Extract<Query<&Node>>,
+    children_query: Extract<Query<&Children>>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+) {
+    let Some(isolate) = isolate.as_ref() else {
+        return;
+    };
+
+    let stack_indices = isolated_stack_indices(&isolate.roots, &node_query, &children_query);
+    extracted_uinodes
+        .uinodes
+        .retain(|_, uinode| stack_indices.contains(&uinode.stack_index));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        entity::EntityHashSet,
+        system::{Query, RunSystemOnce},
+        world::World,
+    };
+    use bevy_hierarchy::{BuildChildren, Children};
+
+    use super::isolated_stack_indices;
+    use crate::{render::tests::base, ExtractedUiNode, ExtractedUiNodes, Node};
+
+    fn uinode(stack_index: u32) -> ExtractedUiNode {
+        ExtractedUiNode {
+            stack_index,
+            ..base()
+        }
+    }
+
+    #[test]
+    fn only_the_isolated_subtree_is_extracted() {
+        let mut world = World::new();
+        let node = |stack_index| Node {
+            stack_index,
+            ..Default::default()
+        };
+        // root -> (isolated -> (child -> grandchild), sibling)
+        let root = world.spawn(node(0)).id();
+        let isolated = world.spawn(node(1)).set_parent(root).id();
+        let child = world.spawn(node(2)).set_parent(isolated).id();
+        world.spawn(node(3)).set_parent(child);
+        world.spawn(node(4)).set_parent(root);
+
+        let roots = EntityHashSet::from_iter([isolated]);
+        let stack_indices = world.run_system_once(
+            move |node_query: Query<&Node>, children_query: Query<&Children>| {
+                isolated_stack_indices(&roots, &node_query, &children_query)
+            },
+        );
+
+        // A node and an extra item extracted to another entity for each stack index, as borders
+        // and text are
+        let mut extracted_uinodes = ExtractedUiNodes::default();
+        for stack_index in 0..5 {
+            for _ in 0..2 {
+                extracted_uinodes
+                    .uinodes
+                    .insert(world.spawn_empty().id(), uinode(stack_index));
+            }
+        }
+        extracted_uinodes
+            .uinodes
+            .retain(|_, uinode| stack_indices.contains(&uinode.stack_index));
+
+        let mut kept: Vec<u32> = extracted_uinodes
+            .uinodes
+            .values()
+            .map(|uinode| uinode.stack_index)
+            .collect();
+        kept.sort();
+        assert_eq!(kept, [1, 1, 2, 2, 3, 3]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::Range;
+
+use bevy_core_pipeline::blit::BlitPipeline;
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_render::{
+    camera::ExtractedCamera,
+    render_phase::ViewSortedRenderPhases,
+    render_resource::*,
+    renderer::RenderDevice,
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    ExtractSchedule, Render, RenderSet,
+};
+
+use super::{
+    ui_force_ldr::ui_composite_pipeline_key,
+    ui_msaa::{ui_msaa_samples, ui_sampled_texture},
+};
+use crate::*;
+
+/// Blends the nodes of [`UiMaterial`]s with a [`UiTargetColorSpace::Linear`] target in a linear
+/// texture on LDR views.
+///
+/// Their phase items are recorded in [`UiLinearItems`] when they're queued. The [`UiPassNode`]
+/// draws each run of consecutive linear items into the view's [`ViewUiLinearTexture`], and
+/// composites it over the main texture before drawing the rest of the UI.
+pub struct UiLinearTargetPlugin;
+
+impl Plugin for UiLinearTargetPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<UiLinearItems>()
+                .allow_ambiguous_resource::<UiLinearItems>()
+                .add_systems(ExtractSchedule, clear_ui_linear_items)
+                .add_systems(
+                    Render,
+                    prepare_ui_linear_textures.in_set(RenderSet::PrepareResources),
+                );
+        }
+    }
+}
+
+/// The [`TransparentUi`] phase items drawn in a linear texture this frame.
+#[derive(Resource, Default)]
+pub struct UiLinearItems {
+    entities: EntityHashSet,
+}
+
+impl UiLinearItems {
+    pub fn insert(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    /// Returns `true` if the phase item for `entity` is drawn in a linear texture.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+pub fn clear_ui_linear_items(mut linear_items: ResMut<UiLinearItems>) {
+    linear_items.entities.clear();
+}
+
+/// Returns whether the pipeline of a node blended in `color_space` targets an HDR texture.
+///
+/// `target_is_hdr` is whether the rest of the UI of the view targets an HDR texture, see
+/// [`ui_target_is_hdr`](super::ui_force_ldr::ui_target_is_hdr).
+pub fn ui_material_target_is_hdr(target_is_hdr: bool, color_space: UiTargetColorSpace) -> bool {
+    target_is_hdr || color_space == UiTargetColorSpace::Linear
+}
+
+/// The linear texture the nodes of a view in [`UiLinearItems`] are drawn to.
+#[derive(Component)]
+pub struct ViewUiLinearTexture {
+    pub texture: CachedTexture,
+    /// The multisampled texture resolved into `texture`, if the view uses [`Msaa`].
+    pub sampled: Option<CachedTexture>,
+    /// Blends `texture` over the main texture of the view.
+    pub composite_pipeline: CachedRenderPipelineId,
+}
+
+pub fn prepare_ui_linear_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    linear_items: Res<UiLinearItems>,
+    transparent_render_phases: Res<ViewSortedRenderPhases<TransparentUi>>,
+    views: Query<(Entity, &ViewTarget, &ExtractedCamera, Option<&Msaa>)>,
+) {
+    for (entity, target, camera, msaa) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+        let Some(transparent_phase) = transparent_render_phases.get(&entity) else {
+            continue;
+        };
+        if !transparent_phase
+            .items
+            .iter()
+            .any(|item| linear_items.contains(item.entity))
+        {
+            continue;
+        }
+
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_linear_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: ViewTarget::TEXTURE_FORMAT_HDR,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+
+        let sampled = ui_sampled_texture(
+            &render_device,
+            &mut texture_cache,
+            "ui_linear_sampled_texture",
+            ViewTarget::TEXTURE_FORMAT_HDR,
+            physical_target_size,
+            ui_msaa_samples(msaa),
+        );
+
+        let composite_pipeline = blit_pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            ui_composite_pipeline_key(target.main_texture_format()),
+        );
+
+        commands.entity(entity).insert(ViewUiLinearTexture {
+            texture,
+            sampled,
+            composite_pipeline,
+        });
+    }
+}
+
+/// Splits a run of phase items into runs that are all drawn in a linear texture or all drawn in
+/// the target, returned with whether they are linear.
+pub fn ui_linear_runs(
+    range: Range<usize>,
+    is_linear: impl Fn(usize) -> bool,
+) -> Vec<(Range<usize>, bool)> {
+    let mut runs: Vec<(Range<usize>, bool)> = Vec::new();
+    for index in range {
+        let linear = is_linear(index);
+        match runs.last_mut() {
+            Some((run, run_linear)) if *run_linear == linear => run.end = index + 1,
+            _ => runs.push((index..index + 1, linear)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_render::{
+        render_resource::{BlendState, TextureFormat},
+        texture::BevyDefault,
+        view::ViewTarget,
+    };
+
+    use super::{ui_linear_runs, ui_material_target_is_hdr};
+    use crate::{ui_force_ldr::ui_composite_pipeline_key, UiTargetColorSpace};
+
+    #[test]
+    fn linear_material_specializes_a_linear_pipeline_composited_over_srgb() {
+        // The material pipeline picks the HDR format for an HDR key
+        assert!(ui_material_target_is_hdr(false, UiTargetColorSpace::Linear));
+        assert!(!ui_material_target_is_hdr(false, UiTargetColorSpace::Srgb));
+        assert!(ui_material_target_is_hdr(true, UiTargetColorSpace::Srgb));
+        assert!(!ViewTarget::TEXTURE_FORMAT_HDR.is_srgb());
+
+        // The linear texture holds premultiplied colors, blended over the sRGB main texture
+        let composite = ui_composite_pipeline_key(TextureFormat::bevy_default());
+        assert!(composite.texture_format.is_srgb());
+        assert_eq!(
+            composite.blend_state,
+            Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+        );
+
+        let linear = [false, true, true, false, true];
+        assert_eq!(
+            ui_linear_runs(0..5, |index| linear[index]),
+            [(0..1, false), (1..3, true), (3..4, false), (4..5, true)]
+        );
+        assert_eq!(ui_linear_runs(1..3, |index| linear[index]), [(1..3, true)]);
+    }
+}
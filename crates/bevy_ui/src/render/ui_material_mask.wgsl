@@ -0,0 +1,12 @@
+#define_import_path bevy_ui::ui_material_mask
+
+#import bevy_ui::ui_vertex_output::UiVertexOutput
+
+// The glyph atlas of text nodes, or a white texture for other nodes.
+@group(2) @binding(0) var mask_texture: texture_2d<f32>;
+@group(2) @binding(1) var mask_sampler: sampler;
+
+// Returns the coverage of the glyph at `in`, 1 outside of text nodes.
+fn ui_material_mask(in: UiVertexOutput) -> f32 {
+    return textureSample(mask_texture, mask_sampler, in.mask_uv).a;
+}
@@ -2,42 +2,95 @@ use std::{hash::Hash, marker::PhantomData, ops::Range};
 
 use bevy_asset::*;
 use bevy_ecs::{
+    entity::EntityHashMap,
     prelude::Component,
-    query::ROQueryItem,
+    query::{AnyOf, Changed},
+    removal_detection::RemovedComponents,
     storage::SparseSet,
-    system::lifetimeless::{Read, SRes},
+    system::lifetimeless::{Read, SQuery, SRes},
     system::*,
+    world::EntityRef,
 };
 use bevy_hierarchy::Parent;
-use bevy_math::{FloatOrd, Mat4, Rect, Vec2, Vec4Swizzles};
+use bevy_math::{FloatOrd, Mat4, Rect, Vec2, Vec3, Vec4, Vec4Swizzles, VectorSpace};
 use bevy_render::{
+    camera::Camera,
     extract_component::ExtractComponentPlugin,
     globals::{GlobalsBuffer, GlobalsUniform},
     render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
     render_phase::*,
-    render_resource::{binding_types::uniform_buffer, *},
+    render_resource::{
+        binding_types::{sampler, texture_2d, uniform_buffer, uniform_buffer_sized},
+        *,
+    },
     renderer::{RenderDevice, RenderQueue},
-    texture::BevyDefault,
+    storage::GpuShaderStorageBuffer,
+    texture::{BevyDefault, FallbackImage, GpuImage, Image},
     view::*,
     Extract, ExtractSchedule, Render, RenderSet,
 };
+#[cfg(feature = "bevy_text")]
+use bevy_sprite::TextureAtlasLayout;
+#[cfg(feature = "bevy_text")]
+use bevy_text::{Text, TextLayoutInfo};
 use bevy_transform::prelude::GlobalTransform;
-use bevy_window::{PrimaryWindow, Window};
+use bevy_utils::{tracing::warn, HashMap, HashSet};
 use bytemuck::{Pod, Zeroable};
 
+#[cfg(feature = "bevy_text")]
+use super::text_glyph_draw_order;
+use super::{
+    ui_camera_front_faces,
+    ui_force_ldr::{ui_target_is_hdr, ExtractedUiForceLdr},
+    ui_linear_target::{ui_material_target_is_hdr, UiLinearItems},
+    ui_msaa::ui_msaa_samples,
+    ui_opacity::{extract_ui_opacities, fade_extracted_ui_material_nodes},
+    ui_opaque::{ui_depth_stencil_state, UiDepthViews},
+    ui_scene_color::{UiSceneColorViews, UiSceneView, UiSceneViewLayout, ViewUiSceneColorTexture},
+};
 use crate::*;
 
 pub const UI_MATERIAL_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(10074188772096983955);
 
 const UI_VERTEX_OUTPUT_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(10123618247720234751);
 
+const UI_MATERIAL_MASK_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(7519826143094671265);
+
+const UI_COLOR_SPACE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(3286410957328146072);
+
 /// Adds the necessary ECS resources and render logic to enable rendering entities using the given
 /// [`UiMaterial`] asset type (which includes [`UiMaterial`] types).
-pub struct UiMaterialPlugin<M: UiMaterial>(PhantomData<M>);
+///
+/// The resources and pipelines of the plugin are per material type, so a material type can only
+/// be added once. To draw the same material struct with another shader, like a debug variant,
+/// replace the shader of its plugin with [`UiMaterialPlugin::with_fragment_shader`], or wrap the
+/// struct in a new type implementing [`UiMaterial`].
+pub struct UiMaterialPlugin<M: UiMaterial> {
+    /// Replaces [`UiMaterial::fragment_shader`] for every material of the plugin, when set.
+    pub fragment_shader: Option<ShaderRef>,
+    pub _marker: PhantomData<M>,
+}
 
 impl<M: UiMaterial> Default for UiMaterialPlugin<M> {
     fn default() -> Self {
-        Self(Default::default())
+        Self {
+            fragment_shader: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<M: UiMaterial> UiMaterialPlugin<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws the materials with `shader` instead of their [`UiMaterial::fragment_shader`].
+    ///
+    /// [`ShaderRef::Default`] draws them with the default UI material fragment shader.
+    pub fn with_fragment_shader(mut self, shader: impl Into<ShaderRef>) -> Self {
+        self.fragment_shader = Some(shader.into());
+        self
     }
 }
 
@@ -52,32 +105,70 @@ where
             "ui_vertex_output.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            UI_MATERIAL_MASK_SHADER_HANDLE,
+            "ui_material_mask.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            UI_COLOR_SPACE_SHADER_HANDLE,
+            "ui_color_space.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(
             app,
             UI_MATERIAL_SHADER_HANDLE,
             "ui_material.wgsl",
             Shader::from_wgsl
         );
-        app.init_asset::<M>().add_plugins((
-            ExtractComponentPlugin::<Handle<M>>::extract_visible(),
-            RenderAssetPlugin::<PreparedUiMaterial<M>>::default(),
-        ));
+        app.init_asset::<M>()
+            .add_plugins((
+                ExtractComponentPlugin::<Handle<M>>::extract_visible(),
+                // Storage buffers are usually created along with the materials binding them, so
+                // preparing them first avoids retrying those materials on the next frame
+                RenderAssetPlugin::<PreparedUiMaterial<M>, GpuShaderStorageBuffer>::default(),
+            ))
+            .add_systems(
+                PostUpdate,
+                (
+                    retheme_ui_materials::<M>,
+                    crossfade_ui_materials::<M>,
+                    #[cfg(feature = "bevy_text")]
+                    mark_ui_material_text::<M>,
+                ),
+            );
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .add_render_command::<TransparentUi, DrawUiMaterial<M>>()
+                .add_render_command::<OpaqueUi, DrawUiMaterial<M>>()
+                .add_render_command::<UiPrepass, DrawUiMaterial<M>>()
                 .init_resource::<ExtractedUiMaterialNodes<M>>()
                 .init_resource::<UiMaterialMeta<M>>()
+                .init_resource::<UiMaterialRetries<M>>()
                 .init_resource::<SpecializedRenderPipelines<UiMaterialPipeline<M>>>()
                 .add_systems(
                     ExtractSchedule,
-                    extract_ui_material_nodes::<M>.in_set(RenderUiSystem::ExtractBackgrounds),
+                    (
+                        extract_ui_material_nodes::<M>.in_set(RenderUiSystem::ExtractBackgrounds),
+                        #[cfg(feature = "bevy_text")]
+                        extract_ui_material_text::<M>.in_set(RenderUiSystem::ExtractText),
+                        fade_extracted_ui_material_nodes::<M>
+                            .after(extract_ui_opacities)
+                            .after(RenderUiSystem::ExtractText),
+                        invalidate_modified_ui_material_pipelines::<M>,
+                    ),
                 )
                 .add_systems(
                     Render,
                     (
                         queue_ui_material_nodes::<M>.in_set(RenderSet::Queue),
-                        prepare_uimaterial_nodes::<M>.in_set(RenderSet::PrepareBindGroups),
+                        warn_unprepared_ui_materials::<M>.in_set(RenderSet::PrepareResources),
+                        prepare_uimaterial_nodes::<M>
+                            .in_set(RenderSet::PrepareBindGroups)
+                            .before(RenderUiSystem::ModifyBatches),
                     ),
                 );
         }
@@ -86,14 +177,38 @@ where
     fn finish(&self, app: &mut App) {
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app.init_resource::<UiMaterialPipeline<M>>();
+            if let Some(shader) = &self.fragment_shader {
+                let world = render_app.world_mut();
+                let fragment_shader = match shader {
+                    ShaderRef::Default => None,
+                    ShaderRef::Handle(handle) => Some(handle.clone()),
+                    ShaderRef::Path(path) => {
+                        Some(world.resource::<AssetServer>().load(path.clone()))
+                    }
+                };
+                world
+                    .resource_mut::<UiMaterialPipeline<M>>()
+                    .fragment_shader = fragment_shader;
+            }
         }
     }
 }
 
 #[derive(Resource)]
 pub struct UiMaterialMeta<M: UiMaterial> {
-    vertices: RawBufferVec<UiMaterialVertex>,
+    /// The bytes of the [`UiMaterialVertex`] of each vertex, each followed by the custom vertex
+    /// data of its node, see [`UiMaterial::vertex_buffer_layout`].
+    vertices: RawBufferVec<u8>,
     view_bind_group: Option<BindGroup>,
+    /// The view bind group of each camera whose UI reads the scene, see
+    /// [`UiMaterial::reads_view_transform`].
+    scene_view_bind_groups: EntityHashMap<BindGroup>,
+    /// The bind group of each mask bound this frame, see [`UiMaterialBatch::mask_bind_group`].
+    mask_bind_groups: HashMap<Option<AssetId<Image>>, BindGroup>,
+    /// The push constants of each batch, when they fall back to a uniform, see
+    /// [`UiPushConstantMode::Uniform`].
+    push_constant_uniforms: RawBufferVec<u8>,
+    push_constant_bind_group: Option<BindGroup>,
     marker: PhantomData<M>,
 }
 
@@ -102,6 +217,10 @@ impl<M: UiMaterial> Default for UiMaterialMeta<M> {
         Self {
             vertices: RawBufferVec::new(BufferUsages::VERTEX),
             view_bind_group: Default::default(),
+            scene_view_bind_groups: Default::default(),
+            mask_bind_groups: Default::default(),
+            push_constant_uniforms: RawBufferVec::new(BufferUsages::UNIFORM),
+            push_constant_bind_group: None,
             marker: PhantomData,
         }
     }
@@ -114,6 +233,55 @@ pub struct UiMaterialVertex {
     pub uv: [f32; 2],
     pub size: [f32; 2],
     pub border_widths: [f32; 4],
+    /// The opacity of the material, see [`UiMaterialOpacity`].
+    pub opacity: f32,
+    /// The UV of the mask, the glyph atlas of text nodes, see [`UiMaterialText`].
+    pub mask_uv: [f32; 2],
+}
+
+/// Returns the layout of the vertex buffer of [`UiMaterial`]s with the default
+/// [`UiMaterial::vertex_buffer_layout`], one [`UiMaterialVertex`] per vertex.
+pub fn ui_material_vertex_layout() -> VertexBufferLayout {
+    ui_material_vertex_layout_with([])
+}
+
+/// Returns a vertex buffer layout for [`UiMaterial::vertex_buffer_layout`], with the attributes of
+/// [`UiMaterialVertex`] at locations 0 to 5 followed by custom attributes of `formats`.
+///
+/// The custom attributes start at location 6, packed right after the 56 bytes of the
+/// [`UiMaterialVertex`].
+pub fn ui_material_vertex_layout_with(
+    formats: impl IntoIterator<Item = VertexFormat>,
+) -> VertexBufferLayout {
+    VertexBufferLayout::from_vertex_formats(
+        VertexStepMode::Vertex,
+        [
+            // position
+            VertexFormat::Float32x3,
+            // uv
+            VertexFormat::Float32x2,
+            // size
+            VertexFormat::Float32x2,
+            // border_widths
+            VertexFormat::Float32x4,
+            // opacity
+            VertexFormat::Float32,
+            // mask_uv
+            VertexFormat::Float32x2,
+        ]
+        .into_iter()
+        .chain(formats),
+    )
+}
+
+/// Appends the bytes of `vertex` followed by the custom `vertex_data` of its node to `vertices`.
+pub(crate) fn push_ui_material_vertex(
+    vertices: &mut Vec<u8>,
+    vertex: UiMaterialVertex,
+    vertex_data: &[u8],
+) {
+    vertices.extend_from_slice(bytemuck::bytes_of(&vertex));
+    vertices.extend_from_slice(vertex_data);
 }
 
 // in this [`UiMaterialPipeline`] there is (currently) no batching going on.
@@ -123,6 +291,105 @@ pub struct UiMaterialBatch<M: UiMaterial> {
     /// The range of vertices inside the [`UiMaterialMeta`]
     pub range: Range<u32>,
     pub material: AssetId<M>,
+    /// The glyph atlas bound as the mask of the batch, or `None` for a white texture.
+    pub mask: Option<AssetId<Image>>,
+    /// The bind group of the prepared `material`, looked up once per batch when it's prepared so
+    /// drawing it doesn't go through [`RenderAssets`].
+    pub bind_group: BindGroup,
+    /// The bind group of the `mask`.
+    pub mask_bind_group: BindGroup,
+    /// The push constants of the nodes of the batch, see [`UiMaterial::push_constant_size`].
+    pub push_constants: Vec<u8>,
+    /// The offset of the `push_constants` in the uniform buffer they fall back to, see
+    /// [`UiPushConstantMode::Uniform`].
+    pub push_constant_offset: u32,
+}
+
+/// A batch laid out by [`batch_ui_material_items`], turned into a [`UiMaterialBatch`] once its
+/// bind groups are looked up.
+struct UiMaterialBatchLayout<M: UiMaterial> {
+    range: Range<u32>,
+    material: AssetId<M>,
+    mask: Option<AssetId<Image>>,
+    push_constants: Vec<u8>,
+    push_constant_offset: u32,
+}
+
+/// How the push constants of a [`UiMaterial`] are passed to its shaders, see
+/// [`UiMaterial::push_constant_size`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiPushConstantMode {
+    /// The material has no push constants.
+    None,
+    /// The bytes of each batch are set as push constants, read by shaders with the
+    /// `UI_PUSH_CONSTANTS` shader def.
+    PushConstants,
+    /// The device doesn't support push constants of this size, the bytes of each batch are
+    /// written to a uniform buffer bound to group 3 with a dynamic offset, read by shaders with
+    /// the `UI_PUSH_CONSTANTS_UNIFORM` shader def.
+    Uniform,
+}
+
+impl UiPushConstantMode {
+    /// Returns how push constants of `size` bytes are passed on a device with `features`,
+    /// supporting up to `max_push_constant_size` bytes of them.
+    pub fn new(size: u32, features: WgpuFeatures, max_push_constant_size: u32) -> Self {
+        if size == 0 {
+            Self::None
+        } else if features.contains(WgpuFeatures::PUSH_CONSTANTS) && size <= max_push_constant_size
+        {
+            Self::PushConstants
+        } else {
+            Self::Uniform
+        }
+    }
+}
+
+/// Returns the number of bytes between the push constants of consecutive batches in the uniform
+/// buffer of [`UiPushConstantMode::Uniform`]: `size` rounded up to the 16 bytes of a uniform
+/// struct, then to the `alignment` of dynamic offsets.
+pub fn ui_push_constant_uniform_stride(size: u32, alignment: u32) -> u32 {
+    size.next_multiple_of(16).next_multiple_of(alignment.max(1))
+}
+
+/// Returns the push constants of the node `entity` drawn with `material`, padded with zeros or
+/// truncated to [`UiMaterial::push_constant_size`].
+pub(crate) fn extract_ui_material_push_constants<M: UiMaterial>(
+    material: &M,
+    entity: Option<EntityRef>,
+) -> Vec<u8> {
+    let size = M::push_constant_size() as usize;
+    let mut data = Vec::new();
+    if size == 0 {
+        return data;
+    }
+    if let Some(entity) = entity {
+        material.extract_push_constants(entity, &mut data);
+    }
+    data.resize(size, 0);
+    data
+}
+
+/// Returns the shader defs of the pipeline of material `M` specialized for `key`: the material's
+/// own [`UiMaterial::shader_defs`], followed by the ones set by the UI for `push_constants`,
+/// [`UiMaterial::color_space`] and [`UiMaterial::reads_view_transform`].
+pub fn ui_material_shader_defs<M: UiMaterial>(
+    key: &UiMaterialKey<M>,
+    push_constants: UiPushConstantMode,
+) -> Vec<ShaderDefVal> {
+    let mut shader_defs = M::shader_defs(key);
+    match push_constants {
+        UiPushConstantMode::None => {}
+        UiPushConstantMode::PushConstants => shader_defs.push("UI_PUSH_CONSTANTS".into()),
+        UiPushConstantMode::Uniform => shader_defs.push("UI_PUSH_CONSTANTS_UNIFORM".into()),
+    }
+    if M::color_space() == UiColorSpace::Srgb {
+        shader_defs.push("UI_OUTPUT_SRGB".into());
+    }
+    if M::reads_view_transform() {
+        shader_defs.push("UI_SCENE_VIEW".into());
+    }
+    shader_defs
 }
 
 /// Render pipeline data for a given [`UiMaterial`]
@@ -130,8 +397,23 @@ pub struct UiMaterialBatch<M: UiMaterial> {
 pub struct UiMaterialPipeline<M: UiMaterial> {
     pub ui_layout: BindGroupLayout,
     pub view_layout: BindGroupLayout,
+    /// The layout of the mask texture and sampler, bound after the material.
+    pub mask_layout: BindGroupLayout,
+    /// See [`UiMaterial::vertex_buffer_layout`].
+    pub vertex_layout: VertexBufferLayout,
     pub vertex_shader: Option<Handle<Shader>>,
     pub fragment_shader: Option<Handle<Shader>>,
+    /// See [`UiMaterial::fallback_fragment_shader`]. `Some(None)` uses the default fragment shader.
+    pub fallback_fragment_shader: Option<Option<Handle<Shader>>>,
+    /// How the push constants of the material are passed on this device.
+    pub push_constants: UiPushConstantMode,
+    /// The layout of the uniform the push constants fall back to, see
+    /// [`UiPushConstantMode::Uniform`].
+    pub push_constant_layout: Option<BindGroupLayout>,
+    /// The layout bound in place of `view_layout` if the material reads the scene, see
+    /// [`UiMaterial::reads_view_transform`].
+    pub scene_view_layout: Option<UiSceneViewLayout>,
+    pub customizer: UiPipelineCustomizer,
     marker: PhantomData<M>,
 }
 
@@ -142,27 +424,14 @@ where
     type Key = UiMaterialKey<M>;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let vertex_layout = VertexBufferLayout::from_vertex_formats(
-            VertexStepMode::Vertex,
-            vec![
-                // position
-                VertexFormat::Float32x3,
-                // uv
-                VertexFormat::Float32x2,
-                // size
-                VertexFormat::Float32x2,
-                // border_widths
-                VertexFormat::Float32x4,
-            ],
-        );
-        let shader_defs = Vec::new();
+        let shader_defs = ui_material_shader_defs(&key, self.push_constants);
 
         let mut descriptor = RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: UI_MATERIAL_SHADER_HANDLE,
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![vertex_layout],
+                buffers: vec![self.vertex_layout.clone()],
             },
             fragment: Some(FragmentState {
                 shader: UI_MATERIAL_SHADER_HANDLE,
@@ -174,14 +443,14 @@ where
                     } else {
                         TextureFormat::bevy_default()
                     },
-                    blend: Some(BlendState::ALPHA_BLENDING),
+                    blend: ui_material_blend_state(key.clone()),
                     write_mask: ColorWrites::ALL,
                 })],
             }),
             layout: vec![],
             push_constant_ranges: Vec::new(),
             primitive: PrimitiveState {
-                front_face: FrontFace::Ccw,
+                front_face: key.front_face,
                 cull_mode: None,
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
@@ -191,7 +460,7 @@ where
             },
             depth_stencil: None,
             multisample: MultisampleState {
-                count: 1,
+                count: key.samples,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -201,18 +470,94 @@ where
             descriptor.vertex.shader = vertex_shader.clone();
         }
 
-        if let Some(fragment_shader) = &self.fragment_shader {
+        if key.fallback {
+            descriptor.label = Some("ui_material_fallback_pipeline".into());
+            if let Some(Some(fallback_fragment_shader)) = &self.fallback_fragment_shader {
+                descriptor.fragment.as_mut().unwrap().shader = fallback_fragment_shader.clone();
+            }
+        } else if let Some(fragment_shader) = &self.fragment_shader {
             descriptor.fragment.as_mut().unwrap().shader = fragment_shader.clone();
         }
 
-        descriptor.layout = vec![self.view_layout.clone(), self.ui_layout.clone()];
+        let view_layout = match &self.scene_view_layout {
+            Some(scene_view_layout) => &scene_view_layout.layout,
+            None => &self.view_layout,
+        };
+        descriptor.layout = vec![
+            view_layout.clone(),
+            self.ui_layout.clone(),
+            self.mask_layout.clone(),
+        ];
+        if self.push_constants == UiPushConstantMode::PushConstants {
+            descriptor.push_constant_ranges.push(PushConstantRange {
+                stages: ShaderStages::VERTEX_FRAGMENT,
+                range: 0..M::push_constant_size(),
+            });
+        }
+        if let Some(push_constant_layout) = &self.push_constant_layout {
+            descriptor.layout.push(push_constant_layout.clone());
+        }
+
+        if key.prepass {
+            // The UI prepass only writes depth
+            descriptor.label = Some("ui_material_prepass_pipeline".into());
+            descriptor.fragment = None;
+        } else if key.opaque {
+            descriptor.label = Some("ui_material_opaque_pipeline".into());
+        }
+        descriptor.depth_stencil = ui_material_depth_stencil(key.clone());
 
         M::specialize(&mut descriptor, key);
+        self.customizer.customize(&mut descriptor);
 
         descriptor
     }
 }
 
+/// Returns the depth state of the [`UiMaterialPipeline`] specialized for `key`, or `None` if it has
+/// no depth attachment.
+///
+/// The UI prepass and the [`OpaqueUi`] phase write depth, while the [`TransparentUi`] phase of a
+/// view with a [`ViewUiDepthTexture`](super::ui_opaque::ViewUiDepthTexture) only tests against it.
+pub fn ui_material_depth_stencil<M: UiMaterial>(
+    key: UiMaterialKey<M>,
+) -> Option<DepthStencilState> {
+    if !key.prepass && !key.depth {
+        return None;
+    }
+    let depth_write_enabled = key.prepass || key.opaque;
+    Some(ui_depth_stencil_state(
+        depth_write_enabled,
+        M::depth_compare(key),
+    ))
+}
+
+/// Returns the blend state of the [`UiMaterialPipeline`] specialized for `key`.
+///
+/// Nodes in the [`OpaqueUi`] phase replace the color below them, other nodes are blended with
+/// [`UiMaterial::blend_state`].
+pub fn ui_material_blend_state<M: UiMaterial>(key: UiMaterialKey<M>) -> Option<BlendState> {
+    if key.opaque {
+        return None;
+    }
+    M::blend_state(key)
+}
+
+impl<M: UiMaterial> UiMaterialPipeline<M> {
+    /// Returns `true` if `shader` is the vertex, fragment or fallback fragment shader of this
+    /// pipeline.
+    pub fn uses_shader(&self, shader: AssetId<Shader>) -> bool {
+        [
+            &self.vertex_shader,
+            &self.fragment_shader,
+            self.fallback_fragment_shader.as_ref().unwrap_or(&None),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|handle| handle.id() == shader)
+    }
+}
+
 impl<M: UiMaterial> FromWorld for UiMaterialPipeline<M> {
     fn from_world(world: &mut World) -> Self {
         let asset_server = world.resource::<AssetServer>();
@@ -229,10 +574,44 @@ impl<M: UiMaterial> FromWorld for UiMaterialPipeline<M> {
                 ),
             ),
         );
+        let scene_view_layout =
+            M::reads_view_transform().then(|| UiSceneViewLayout::new(render_device));
+
+        let mask_layout = render_device.create_bind_group_layout(
+            "ui_material_mask_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        let push_constant_size = M::push_constant_size();
+        let push_constants = UiPushConstantMode::new(
+            push_constant_size,
+            render_device.features(),
+            render_device.limits().max_push_constant_size,
+        );
+        let push_constant_layout = (push_constants == UiPushConstantMode::Uniform).then(|| {
+            render_device.create_bind_group_layout(
+                "ui_material_push_constant_layout",
+                &BindGroupLayoutEntries::single(
+                    ShaderStages::VERTEX_FRAGMENT,
+                    uniform_buffer_sized(
+                        true,
+                        BufferSize::new(push_constant_size.next_multiple_of(16) as u64),
+                    ),
+                ),
+            )
+        });
 
         UiMaterialPipeline {
             ui_layout,
             view_layout,
+            mask_layout,
+            vertex_layout: M::vertex_buffer_layout().unwrap_or_else(ui_material_vertex_layout),
             vertex_shader: match M::vertex_shader() {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
@@ -243,6 +622,15 @@ impl<M: UiMaterial> FromWorld for UiMaterialPipeline<M> {
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
+            fallback_fragment_shader: M::fallback_fragment_shader().map(|shader| match shader {
+                ShaderRef::Default => None,
+                ShaderRef::Handle(handle) => Some(handle),
+                ShaderRef::Path(path) => Some(asset_server.load(path)),
+            }),
+            push_constants,
+            push_constant_layout,
+            scene_view_layout,
+            customizer: UiPipelineCustomizer::from_render_world(world),
             marker: PhantomData,
         }
     }
@@ -252,27 +640,50 @@ pub type DrawUiMaterial<M> = (
     SetItemPipeline,
     SetMatUiViewBindGroup<M, 0>,
     SetUiMaterialBindGroup<M, 1>,
+    SetUiMaterialMaskBindGroup<M, 2>,
+    SetUiPushConstants<M, 3>,
+    SetUiScissorRect,
     DrawUiMaterialNode<M>,
 );
 
+/// Sets the view bind group of a [`UiMaterial`], along with the view of the scene below the UI
+/// when [`UiMaterial::reads_view_transform`] is set.
 pub struct SetMatUiViewBindGroup<M: UiMaterial, const I: usize>(PhantomData<M>);
 impl<P: PhaseItem, M: UiMaterial, const I: usize> RenderCommand<P> for SetMatUiViewBindGroup<M, I> {
-    type Param = SRes<UiMaterialMeta<M>>;
-    type ViewQuery = Read<ViewUniformOffset>;
+    type Param = (SRes<UiMaterialMeta<M>>, SQuery<Read<ViewUniformOffset>>);
+    type ViewQuery = (Entity, Read<ViewUniformOffset>, Option<Read<UiSceneView>>);
     type ItemQuery = ();
 
     fn render<'w>(
         _item: &P,
-        view_uniform: &'w ViewUniformOffset,
+        (view_entity, view_uniform, scene_view): (
+            Entity,
+            &'w ViewUniformOffset,
+            Option<&'w UiSceneView>,
+        ),
         _entity: Option<()>,
-        ui_meta: SystemParamItem<'w, '_, Self::Param>,
+        (ui_meta, view_uniforms): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        pass.set_bind_group(
-            I,
-            ui_meta.into_inner().view_bind_group.as_ref().unwrap(),
-            &[view_uniform.offset],
-        );
+        let ui_meta = ui_meta.into_inner();
+        if M::reads_view_transform() {
+            // The view of the camera the UI is drawn over, or the view itself when the UI isn't
+            // drawn with a view of its own
+            let scene_view = scene_view.map_or(view_entity, |scene_view| scene_view.0);
+            let (Some(bind_group), Ok(scene_uniform)) = (
+                ui_meta.scene_view_bind_groups.get(&scene_view),
+                view_uniforms.get(scene_view),
+            ) else {
+                return RenderCommandResult::Skip;
+            };
+            pass.set_bind_group(I, bind_group, &[view_uniform.offset, scene_uniform.offset]);
+            return RenderCommandResult::Success;
+        }
+        let view_bind_group = match ui_view_bind_group(ui_meta.view_bind_group.as_ref()) {
+            Ok(view_bind_group) => view_bind_group,
+            Err(result) => return result,
+        };
+        pass.set_bind_group(I, view_bind_group, &[view_uniform.offset]);
         RenderCommandResult::Success
     }
 }
@@ -281,24 +692,78 @@ pub struct SetUiMaterialBindGroup<M: UiMaterial, const I: usize>(PhantomData<M>)
 impl<P: PhaseItem, M: UiMaterial, const I: usize> RenderCommand<P>
     for SetUiMaterialBindGroup<M, I>
 {
-    type Param = SRes<RenderAssets<PreparedUiMaterial<M>>>;
+    type Param = ();
+    type ViewQuery = ();
+    type ItemQuery = Read<UiMaterialBatch<M>>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        batch: Option<&'w UiMaterialBatch<M>>,
+        _param: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(batch) = batch else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, &batch.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+pub struct SetUiMaterialMaskBindGroup<M: UiMaterial, const I: usize>(PhantomData<M>);
+impl<P: PhaseItem, M: UiMaterial, const I: usize> RenderCommand<P>
+    for SetUiMaterialMaskBindGroup<M, I>
+{
+    type Param = ();
     type ViewQuery = ();
     type ItemQuery = Read<UiMaterialBatch<M>>;
 
     fn render<'w>(
         _item: &P,
         _view: (),
-        material_handle: Option<ROQueryItem<'_, Self::ItemQuery>>,
-        materials: SystemParamItem<'w, '_, Self::Param>,
+        batch: Option<&'w UiMaterialBatch<M>>,
+        _param: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let Some(material_handle) = material_handle else {
+        let Some(batch) = batch else {
             return RenderCommandResult::Skip;
         };
-        let Some(material) = materials.into_inner().get(material_handle.material) else {
+        pass.set_bind_group(I, &batch.mask_bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Sets the push constants of a [`UiMaterialBatch`], or binds them to group `I` when they fall
+/// back to a uniform, see [`UiMaterial::push_constant_size`].
+pub struct SetUiPushConstants<M: UiMaterial, const I: usize>(PhantomData<M>);
+impl<P: PhaseItem, M: UiMaterial, const I: usize> RenderCommand<P> for SetUiPushConstants<M, I> {
+    type Param = (SRes<UiMaterialPipeline<M>>, SRes<UiMaterialMeta<M>>);
+    type ViewQuery = ();
+    type ItemQuery = Read<UiMaterialBatch<M>>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        batch: Option<&'w UiMaterialBatch<M>>,
+        (ui_material_pipeline, ui_meta): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(batch) = batch else {
             return RenderCommandResult::Skip;
         };
-        pass.set_bind_group(I, &material.bind_group, &[]);
+        match ui_material_pipeline.push_constants {
+            UiPushConstantMode::None => {}
+            UiPushConstantMode::PushConstants => {
+                pass.set_push_constants(ShaderStages::VERTEX_FRAGMENT, 0, &batch.push_constants);
+            }
+            UiPushConstantMode::Uniform => {
+                let Some(bind_group) = &ui_meta.into_inner().push_constant_bind_group else {
+                    return RenderCommandResult::Failure("push_constant_bind_group not available");
+                };
+                pass.set_bind_group(I, bind_group, &[batch.push_constant_offset]);
+            }
+        }
         RenderCommandResult::Success
     }
 }
@@ -327,6 +792,44 @@ impl<P: PhaseItem, M: UiMaterial> RenderCommand<P> for DrawUiMaterialNode<M> {
     }
 }
 
+/// Marks all specialized pipelines of the [`UiMaterial`] `M` as stale, so they are specialized
+/// again, keeping their pipeline ids, the next time a node using `M` is queued.
+///
+/// This is done automatically when one of the material's shaders is modified, but can also be
+/// called on the render world to force a rebuild.
+pub fn invalidate_ui_material_pipelines<M: UiMaterial>(render_world: &mut World) {
+    if let Some(mut pipelines) =
+        render_world.get_resource_mut::<SpecializedRenderPipelines<UiMaterialPipeline<M>>>()
+    {
+        pipelines.clear();
+    }
+}
+
+/// Returns `true` if one of `events` modified a shader for which `uses_shader` returns `true`.
+pub(crate) fn ui_material_shader_modified<'a>(
+    events: impl IntoIterator<Item = &'a AssetEvent<Shader>>,
+    uses_shader: impl Fn(AssetId<Shader>) -> bool,
+) -> bool {
+    events.into_iter().any(|event| match event {
+        AssetEvent::Modified { id } => uses_shader(*id),
+        _ => false,
+    })
+}
+
+/// Invalidates the specialized pipelines of `M` when its vertex or fragment shader is
+/// hot-reloaded, see [`invalidate_ui_material_pipelines`].
+pub fn invalidate_modified_ui_material_pipelines<M: UiMaterial>(
+    mut shader_events: Extract<EventReader<AssetEvent<Shader>>>,
+    ui_material_pipeline: Res<UiMaterialPipeline<M>>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<UiMaterialPipeline<M>>>,
+) {
+    if ui_material_shader_modified(shader_events.read(), |id| {
+        ui_material_pipeline.uses_shader(id)
+    }) {
+        pipelines.clear();
+    }
+}
+
 pub struct ExtractedUiMaterialNode<M: UiMaterial> {
     pub stack_index: u32,
     pub transform: Mat4,
@@ -334,12 +837,36 @@ pub struct ExtractedUiMaterialNode<M: UiMaterial> {
     pub border: [f32; 4],
     pub material: AssetId<M>,
     pub clip: Option<Rect>,
+    /// The opacity the material is drawn with, see [`UiMaterialOpacity`].
+    pub opacity: f32,
+    /// Whether this is the material a node crossfades to, drawn over the node's own material, see
+    /// [`UiMaterialCrossfade`].
+    pub crossfade: bool,
+    /// The bytes of the custom vertex attributes of this node, appended to each of its vertices,
+    /// see [`UiMaterial::extract_vertex_data`]. Empty with the default vertex layout.
+    pub vertex_data: Vec<u8>,
+    /// The push constants of this node, see [`UiMaterial::extract_push_constants`]. Empty
+    /// without push constants.
+    pub push_constants: Vec<u8>,
+    /// The glyphs of a text node, drawn instead of the quad of the node, see [`UiMaterialText`].
+    pub glyphs: Vec<ExtractedUiMaterialGlyph>,
+    /// The glyph atlas the `glyphs` are masked with, or `None` for nodes without glyphs.
+    pub mask: Option<AssetId<Image>>,
     // Camera to render this UI node to. By the time it is extracted,
     // it is defaulted to a single camera if only one exists.
     // Nodes with ambiguous camera will be ignored.
     pub camera_entity: Entity,
 }
 
+/// A glyph of a text node drawn with a [`UiMaterial`], see [`UiMaterialText`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExtractedUiMaterialGlyph {
+    /// The rect of the glyph, relative to the center of its node.
+    pub rect: Rect,
+    /// The rect of the glyph in its atlas, in UV coordinates.
+    pub mask_rect: Rect,
+}
+
 #[derive(Resource)]
 pub struct ExtractedUiMaterialNodes<M: UiMaterial> {
     pub uinodes: SparseSet<Entity, ExtractedUiMaterialNode<M>>,
@@ -355,6 +882,7 @@ impl<M: UiMaterial> Default for ExtractedUiMaterialNodes<M> {
 
 #[allow(clippy::too_many_arguments)]
 pub fn extract_ui_material_nodes<M: UiMaterial>(
+    mut commands: Commands,
     mut extracted_uinodes: ResMut<ExtractedUiMaterialNodes<M>>,
     materials: Extract<Res<Assets<M>>>,
     default_ui_camera: Extract<DefaultUiCamera>,
@@ -365,32 +893,39 @@ pub fn extract_ui_material_nodes<M: UiMaterial>(
                 &Node,
                 &Style,
                 &GlobalTransform,
-                &Handle<M>,
+                AnyOf<(&Handle<M>, &UiMaterialCrossfade<M>)>,
+                Option<&UiMaterialOpacity>,
                 &ViewVisibility,
                 Option<&CalculatedClip>,
                 Option<&TargetCamera>,
                 Option<&Parent>,
             ),
-            Without<BackgroundColor>,
+            (Without<BackgroundColor>, Without<UiMaterialText>),
         >,
     >,
-    windows: Extract<Query<&Window, With<PrimaryWindow>>>,
+    camera_query: Extract<Query<&Camera>>,
     ui_scale: Extract<Res<UiScale>>,
     node_query: Extract<Query<&Node>>,
+    entities: Extract<Query<EntityRef>>,
+    mut depth_views: ResMut<UiDepthViews>,
 ) {
-    let ui_logical_viewport_size = windows
-        .get_single()
-        .map(Window::size)
-        .unwrap_or(Vec2::ZERO)
-        // The logical window resolution returned by `Window` only takes into account the window scale factor and not `UiScale`,
-        // so we have to divide by `UiScale` to get the size of the UI viewport.
-        / ui_scale.0;
+    let has_vertex_data = M::vertex_buffer_layout().is_some();
 
     // If there is only one camera, we use it as default
     let default_single_camera = default_ui_camera.get();
 
-    for (entity, uinode, style, transform, handle, view_visibility, clip, camera, maybe_parent) in
-        uinode_query.iter()
+    for (
+        entity,
+        uinode,
+        style,
+        transform,
+        (handle, crossfade),
+        opacity,
+        view_visibility,
+        clip,
+        camera,
+        maybe_parent,
+    ) in uinode_query.iter()
     {
         let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_single_camera) else {
             continue;
@@ -401,10 +936,15 @@ pub fn extract_ui_material_nodes<M: UiMaterial>(
             continue;
         }
 
-        // Skip loading materials
-        if !materials.contains(handle) {
-            continue;
-        }
+        // The viewport of the camera rendering the node, rather than the primary window
+        let ui_logical_viewport_size = camera_query
+            .get(camera_entity)
+            .ok()
+            .and_then(Camera::logical_viewport_size)
+            .unwrap_or(Vec2::ZERO)
+            // The logical window resolution returned by `Window` only takes into account the window scale factor and not `UiScale`,
+            // so we have to divide by `UiScale` to get the size of the UI viewport.
+            / ui_scale.0;
 
         // Both vertical and horizontal percentage border values are calculated based on the width of the parent node
         // <https://developer.mozilla.org/en-US/docs/Web/CSS/border-width>
@@ -426,9 +966,29 @@ pub fn extract_ui_material_nodes<M: UiMaterial>(
             resolve_border_thickness(style.border.bottom, parent_width, ui_logical_viewport_size)
                 / uinode.size().y;
 
-        extracted_uinodes.uinodes.insert(
-            entity,
-            ExtractedUiMaterialNode {
+        // The node's own material, then the material it crossfades to
+        let own_material = handle.map(|handle| (handle, opacity.map_or(1., |opacity| opacity.0)));
+        let crossfade_material =
+            crossfade.map(|crossfade| (&crossfade.material, crossfade.opacities()[1]));
+        for (crossfade, (handle, opacity)) in [(false, own_material), (true, crossfade_material)]
+            .into_iter()
+            .filter_map(|(crossfade, material)| Some((crossfade, material?)))
+        {
+            // Skip loading materials
+            let Some(material) = materials.get(handle) else {
+                continue;
+            };
+
+            let mut vertex_data = Vec::new();
+            if has_vertex_data {
+                if let Ok(entity_ref) = entities.get(entity) {
+                    material.extract_vertex_data(entity_ref, &mut vertex_data);
+                }
+            }
+            let push_constants =
+                extract_ui_material_push_constants(material, entities.get(entity).ok());
+
+            let extracted_uinode = ExtractedUiMaterialNode {
                 stack_index: uinode.stack_index,
                 transform: transform.compute_matrix(),
                 material: handle.id(),
@@ -438,178 +998,623 @@ pub fn extract_ui_material_nodes<M: UiMaterial>(
                 },
                 border: [left, right, top, bottom],
                 clip: clip.map(|clip| clip.clip),
+                opacity,
+                crossfade,
+                vertex_data,
+                push_constants,
+                glyphs: Vec::new(),
+                mask: None,
                 camera_entity,
-            },
-        );
+            };
+            // Opaque nodes are drawn with a depth texture, if their camera supports one
+            if ui_material_node_is_opaque(&extracted_uinode) {
+                depth_views.request(camera_entity);
+            }
+            extracted_uinodes.uinodes.insert(
+                if crossfade {
+                    commands.spawn_empty().id()
+                } else {
+                    entity
+                },
+                extracted_uinode,
+            );
+        }
     }
 }
 
+/// Extracts the glyphs of text nodes drawn with a material of type `M`, see [`UiMaterialText`].
+///
+/// The glyphs are grouped by atlas like [`extract_uinode_text`](super::extract_uinode_text), each
+/// group extracted as a node masked with its atlas.
+#[cfg(feature = "bevy_text")]
 #[allow(clippy::too_many_arguments)]
-pub fn prepare_uimaterial_nodes<M: UiMaterial>(
+pub fn extract_ui_material_text<M: UiMaterial>(
     mut commands: Commands,
-    render_device: Res<RenderDevice>,
-    render_queue: Res<RenderQueue>,
-    mut ui_meta: ResMut<UiMaterialMeta<M>>,
     mut extracted_uinodes: ResMut<ExtractedUiMaterialNodes<M>>,
-    view_uniforms: Res<ViewUniforms>,
-    globals_buffer: Res<GlobalsBuffer>,
-    ui_material_pipeline: Res<UiMaterialPipeline<M>>,
-    mut phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
-    mut previous_len: Local<usize>,
+    materials: Extract<Res<Assets<M>>>,
+    default_ui_camera: Extract<DefaultUiCamera>,
+    camera_query: Extract<Query<&Camera>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+    ui_scale: Extract<Res<UiScale>>,
+    uinode_query: Extract<
+        Query<
+            (
+                Entity,
+                &Node,
+                &GlobalTransform,
+                &Handle<M>,
+                Option<&UiMaterialOpacity>,
+                &ViewVisibility,
+                Option<&CalculatedClip>,
+                Option<&TargetCamera>,
+                &TextLayoutInfo,
+            ),
+            With<UiMaterialText>,
+        >,
+    >,
+    entities: Extract<Query<EntityRef>>,
 ) {
-    if let (Some(view_binding), Some(globals_binding)) = (
-        view_uniforms.uniforms.binding(),
-        globals_buffer.buffer.binding(),
-    ) {
-        let mut batches: Vec<(Entity, UiMaterialBatch<M>)> = Vec::with_capacity(*previous_len);
+    let has_vertex_data = M::vertex_buffer_layout().is_some();
+    for (
+        entity,
+        uinode,
+        transform,
+        handle,
+        opacity,
+        view_visibility,
+        clip,
+        camera,
+        text_layout_info,
+    ) in &uinode_query
+    {
+        let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
+        else {
+            continue;
+        };
 
-        ui_meta.vertices.clear();
-        ui_meta.view_bind_group = Some(render_device.create_bind_group(
-            "ui_material_view_bind_group",
-            &ui_material_pipeline.view_layout,
-            &BindGroupEntries::sequential((view_binding, globals_binding)),
-        ));
-        let mut index = 0;
+        // Skip if not visible or if size is set to zero (e.g. when a parent is set to `Display::None`)
+        if !view_visibility.get() || uinode.is_empty() {
+            continue;
+        }
 
-        for ui_phase in phases.values_mut() {
-            let mut batch_item_index = 0;
-            let mut batch_shader_handle = AssetId::invalid();
+        // Skip loading materials
+        let Some(material) = materials.get(handle) else {
+            continue;
+        };
+
+        let mut vertex_data = Vec::new();
+        if has_vertex_data {
+            if let Ok(entity_ref) = entities.get(entity) {
+                material.extract_vertex_data(entity_ref, &mut vertex_data);
+            }
+        }
+        let push_constants =
+            extract_ui_material_push_constants(material, entities.get(entity).ok());
+
+        let inverse_scale_factor =
+            ui_target_scale_factor(camera_query.get(camera_entity).ok(), &ui_scale).recip();
+
+        let mut runs: Vec<(AssetId<Image>, Vec<ExtractedUiMaterialGlyph>)> = Vec::new();
+        for glyph_index in text_glyph_draw_order(&text_layout_info.glyphs) {
+            let glyph = &text_layout_info.glyphs[glyph_index];
+            let Some(atlas) = texture_atlases.get(&glyph.atlas_info.texture_atlas) else {
+                continue;
+            };
+            let atlas_rect = atlas.textures[glyph.atlas_info.location.glyph_index].as_rect();
+            let atlas_size = atlas.size.as_vec2();
+            let extracted_glyph = ExtractedUiMaterialGlyph {
+                rect: Rect::from_center_size(
+                    glyph.position * inverse_scale_factor - 0.5 * uinode.size(),
+                    atlas_rect.size() * inverse_scale_factor,
+                ),
+                mask_rect: Rect {
+                    min: atlas_rect.min / atlas_size,
+                    max: atlas_rect.max / atlas_size,
+                },
+            };
+            let texture = glyph.atlas_info.texture.id();
+            match runs.last_mut() {
+                Some((atlas_texture, glyphs)) if *atlas_texture == texture => {
+                    glyphs.push(extracted_glyph);
+                }
+                _ => runs.push((texture, vec![extracted_glyph])),
+            }
+        }
 
-            for item_index in 0..ui_phase.items.len() {
-                let item = &mut ui_phase.items[item_index];
-                if let Some(extracted_uinode) = extracted_uinodes.uinodes.get(item.entity) {
-                    let mut existing_batch = batches
-                        .last_mut()
-                        .filter(|_| batch_shader_handle == extracted_uinode.material);
+        for (run_index, (texture, glyphs)) in runs.into_iter().enumerate() {
+            extracted_uinodes.uinodes.insert(
+                if run_index == 0 {
+                    entity
+                } else {
+                    commands.spawn_empty().id()
+                },
+                ExtractedUiMaterialNode {
+                    stack_index: uinode.stack_index,
+                    transform: transform.compute_matrix(),
+                    material: handle.id(),
+                    rect: Rect {
+                        min: Vec2::ZERO,
+                        max: uinode.calculated_size,
+                    },
+                    border: [0.; 4],
+                    clip: clip.map(|clip| clip.clip),
+                    opacity: opacity.map_or(1., |opacity| opacity.0),
+                    crossfade: false,
+                    vertex_data: vertex_data.clone(),
+                    push_constants: push_constants.clone(),
+                    glyphs,
+                    mask: Some(texture),
+                    camera_entity,
+                },
+            );
+        }
+    }
+}
 
-                    if existing_batch.is_none() {
-                        batch_item_index = item_index;
-                        batch_shader_handle = extracted_uinode.material;
+/// Marks the text nodes with a material of type `M` with [`UiMaterialText`], so their glyphs are
+/// drawn with the material instead of their text colors.
+#[cfg(feature = "bevy_text")]
+pub fn mark_ui_material_text<M: UiMaterial>(
+    mut commands: Commands,
+    text_materials: Query<Entity, (With<Text>, Added<Handle<M>>)>,
+    mut removed_materials: RemovedComponents<Handle<M>>,
+) {
+    for entity in removed_materials.read() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<UiMaterialText>();
+        }
+    }
+    for entity in &text_materials {
+        commands.entity(entity).insert(UiMaterialText);
+    }
+}
 
-                        let new_batch = UiMaterialBatch {
-                            range: index..index,
-                            material: extracted_uinode.material,
-                        };
+/// Returns the quads a material node is drawn with, relative to the center of the node: the
+/// node itself, or one quad per glyph of a text node.
+///
+/// The corners are in the order of [`QUAD_VERTEX_POSITIONS`], each with the UV of the node in
+/// `xy`, so the material spans the whole node, and the UV of the mask in `zw`.
+pub(crate) fn ui_material_quads<M: UiMaterial>(
+    extracted_uinode: &ExtractedUiMaterialNode<M>,
+) -> Vec<([Vec2; 4], [Vec4; 4])> {
+    let size = extracted_uinode.rect.size();
+    let quad = |rect: Rect, mask_rect: Rect| {
+        let corners = QUAD_VERTEX_POSITIONS.map(|position| position.truncate() + 0.5);
+        (
+            corners.map(|corner| rect.min + corner * rect.size()),
+            corners.map(|corner| {
+                let uv = (rect.min + corner * rect.size()) / size + 0.5;
+                let mask_uv = mask_rect.min + corner * mask_rect.size();
+                Vec4::new(uv.x, uv.y, mask_uv.x, mask_uv.y)
+            }),
+        )
+    };
+    if extracted_uinode.glyphs.is_empty() {
+        let node = Rect::from_center_size(Vec2::ZERO, size);
+        vec![quad(node, Rect::new(0., 0., 1., 1.))]
+    } else {
+        extracted_uinode
+            .glyphs
+            .iter()
+            .map(|glyph| quad(glyph.rect, glyph.mask_rect))
+            .collect()
+    }
+}
 
-                        batches.push((item.entity, new_batch));
+/// Returns `true` if `extracted_uinode` covers its quad with an opaque color, so it can be drawn
+/// in the [`OpaqueUi`] phase, see [`UiAlphaMode::Opaque`].
+pub fn ui_material_node_is_opaque<M: UiMaterial>(
+    extracted_uinode: &ExtractedUiMaterialNode<M>,
+) -> bool {
+    M::alpha_mode() == UiAlphaMode::Opaque
+        && !M::prepass_enabled()
+        && extracted_uinode.opacity >= 1.
+        && extracted_uinode.glyphs.is_empty()
+}
 
-                        existing_batch = batches.last_mut();
-                    }
+/// Returns the position of a material node in the [`TransparentUi`] and [`UiPrepass`] phases,
+/// which draws the material a node crossfades to over the node's own material.
+pub(crate) fn ui_material_stack_position<M: UiMaterial>(
+    extracted_uinode: &ExtractedUiMaterialNode<M>,
+) -> FloatOrd {
+    let offset = if extracted_uinode.crossfade { 0.5 } else { 0. };
+    FloatOrd(extracted_uinode.stack_index as f32 + offset)
+}
 
-                    let uinode_rect = extracted_uinode.rect;
-
-                    let rect_size = uinode_rect.size().extend(1.0);
-
-                    let positions = QUAD_VERTEX_POSITIONS.map(|pos| {
-                        (extracted_uinode.transform * (pos * rect_size).extend(1.0)).xyz()
-                    });
-
-                    let positions_diff = if let Some(clip) = extracted_uinode.clip {
-                        [
-                            Vec2::new(
-                                f32::max(clip.min.x - positions[0].x, 0.),
-                                f32::max(clip.min.y - positions[0].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::min(clip.max.x - positions[1].x, 0.),
-                                f32::max(clip.min.y - positions[1].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::min(clip.max.x - positions[2].x, 0.),
-                                f32::min(clip.max.y - positions[2].y, 0.),
-                            ),
-                            Vec2::new(
-                                f32::max(clip.min.x - positions[3].x, 0.),
-                                f32::min(clip.max.y - positions[3].y, 0.),
-                            ),
-                        ]
-                    } else {
-                        [Vec2::ZERO; 4]
-                    };
-
-                    let positions_clipped = [
-                        positions[0] + positions_diff[0].extend(0.),
-                        positions[1] + positions_diff[1].extend(0.),
-                        positions[2] + positions_diff[2].extend(0.),
-                        positions[3] + positions_diff[3].extend(0.),
-                    ];
-
-                    let transformed_rect_size =
-                        extracted_uinode.transform.transform_vector3(rect_size);
-
-                    // Don't try to cull nodes that have a rotation
-                    // In a rotation around the Z-axis, this value is 0.0 for an angle of 0.0 or π
-                    // In those two cases, the culling check can proceed normally as corners will be on
-                    // horizontal / vertical lines
-                    // For all other angles, bypass the culling check
-                    // This does not properly handles all rotations on all axis
-                    if extracted_uinode.transform.x_axis[1] == 0.0 {
-                        // Cull nodes that are completely clipped
-                        if positions_diff[0].x - positions_diff[1].x >= transformed_rect_size.x
-                            || positions_diff[1].y - positions_diff[2].y >= transformed_rect_size.y
-                        {
-                            continue;
-                        }
-                    }
-                    let uvs = [
-                        Vec2::new(
-                            uinode_rect.min.x + positions_diff[0].x,
-                            uinode_rect.min.y + positions_diff[0].y,
-                        ),
-                        Vec2::new(
-                            uinode_rect.max.x + positions_diff[1].x,
-                            uinode_rect.min.y + positions_diff[1].y,
-                        ),
-                        Vec2::new(
-                            uinode_rect.max.x + positions_diff[2].x,
-                            uinode_rect.max.y + positions_diff[2].y,
-                        ),
-                        Vec2::new(
-                            uinode_rect.min.x + positions_diff[3].x,
-                            uinode_rect.max.y + positions_diff[3].y,
-                        ),
-                    ]
-                    .map(|pos| pos / uinode_rect.max);
-
-                    for i in QUAD_INDICES {
-                        ui_meta.vertices.push(UiMaterialVertex {
-                            position: positions_clipped[i].into(),
-                            uv: uvs[i].into(),
-                            size: extracted_uinode.rect.size().into(),
-                            border_widths: extracted_uinode.border,
-                        });
-                    }
+/// Keeps the [`UiMaterialOpacity`] of nodes crossfading to a material of type `M` in sync with the
+/// blend of their [`UiMaterialCrossfade`], and removes it with the crossfade.
+pub fn crossfade_ui_materials<M: UiMaterial>(
+    mut commands: Commands,
+    crossfades: Query<(Entity, &UiMaterialCrossfade<M>), Changed<UiMaterialCrossfade<M>>>,
+    mut removed_crossfades: RemovedComponents<UiMaterialCrossfade<M>>,
+) {
+    for entity in removed_crossfades.read() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<UiMaterialOpacity>();
+        }
+    }
+    for (entity, crossfade) in &crossfades {
+        commands
+            .entity(entity)
+            .insert(UiMaterialOpacity(crossfade.opacities()[0]));
+    }
+}
 
-                    index += QUAD_INDICES.len() as u32;
-                    existing_batch.unwrap().1.range.end = index;
-                    ui_phase.items[batch_item_index].batch_range_mut().end += 1;
-                } else {
-                    batch_shader_handle = AssetId::invalid();
+/// Clips the quad of a material node with its `clip` rect.
+///
+/// `positions` and `uvs` are the corners of the quad in the order of [`QUAD_VERTEX_POSITIONS`].
+/// Returns a list of triangles covering the part of the quad inside `clip`, with the UVs of their
+/// vertices interpolated from the corners, or nothing if the quad is completely clipped. Rotated
+/// quads are clipped exactly, as the clipped outline is computed in UI space.
+pub(crate) fn clip_ui_material_quad<V: VectorSpace>(
+    positions: [Vec3; 4],
+    uvs: [V; 4],
+    clip: Option<Rect>,
+) -> Vec<(Vec3, V)> {
+    let mut polygon: Vec<(Vec3, V)> = positions.into_iter().zip(uvs).collect();
+    if let Some(clip) = clip {
+        // Sutherland-Hodgman, against each edge of the clip rect in turn
+        let edges: [(fn(Vec3) -> f32, f32, f32); 4] = [
+            (|p| p.x, clip.min.x, 1.),
+            (|p| p.x, clip.max.x, -1.),
+            (|p| p.y, clip.min.y, 1.),
+            (|p| p.y, clip.max.y, -1.),
+        ];
+        for (axis, bound, sign) in edges {
+            let distance = |(p, _): &(Vec3, V)| (axis(*p) - bound) * sign;
+            let mut clipped = Vec::with_capacity(polygon.len() + 1);
+            for (i, current) in polygon.iter().enumerate() {
+                let previous = &polygon[(i + polygon.len() - 1) % polygon.len()];
+                let (d_previous, d_current) = (distance(previous), distance(current));
+                if (d_previous >= 0.) != (d_current >= 0.) {
+                    let t = d_previous / (d_previous - d_current);
+                    clipped.push((previous.0.lerp(current.0, t), previous.1.lerp(current.1, t)));
                 }
+                if d_current >= 0. {
+                    clipped.push(*current);
+                }
+            }
+            polygon = clipped;
+            if polygon.len() < 3 {
+                return Vec::new();
             }
         }
-        ui_meta.vertices.write_buffer(&render_device, &render_queue);
-        *previous_len = batches.len();
-        commands.insert_or_spawn_batch(batches);
     }
-    extracted_uinodes.uinodes.clear();
-}
 
-pub struct PreparedUiMaterial<T: UiMaterial> {
-    pub bindings: Vec<(u32, OwnedBindingResource)>,
-    pub bind_group: BindGroup,
-    pub key: T::Data,
+    // The clipped outline is convex, triangulate it as a fan with the winding of `QUAD_INDICES`
+    (1..polygon.len() - 1)
+        .flat_map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+        .collect()
 }
 
-impl<M: UiMaterial> RenderAsset for PreparedUiMaterial<M> {
-    type SourceAsset = M;
-
-    type Param = (SRes<RenderDevice>, SRes<UiMaterialPipeline<M>>, M::Param);
+/// Batches the consecutive `items` of a phase drawn with the same material and mask, appending
+/// their vertices to `vertices` starting at vertex `index`.
+///
+/// Nodes are skipped while `prepare_mask` returns `false` for their mask, or `material_is_ready`
+/// for the material of the batch they start.
+fn batch_ui_material_items<M: UiMaterial, P: PhaseItem>(
+    items: &mut [P],
+    extracted_uinodes: &ExtractedUiMaterialNodes<M>,
+    vertices: &mut Vec<u8>,
+    mut prepare_mask: impl FnMut(Option<AssetId<Image>>) -> bool,
+    material_is_ready: impl Fn(AssetId<M>) -> bool,
+    batches: &mut Vec<(Entity, UiMaterialBatchLayout<M>)>,
+    index: &mut u32,
+) {
+    let mut batch_item_index = 0;
+    let mut batch_shader_handle = AssetId::invalid();
+    let mut batch_mask = None;
+
+    for item_index in 0..items.len() {
+        let item = &mut items[item_index];
+        if let Some(extracted_uinode) = extracted_uinodes.uinodes.get(item.entity) {
+            // Text is masked with its glyph atlas, other nodes with a white texture
+            let mask = extracted_uinode.mask;
+            if !prepare_mask(mask) {
+                batch_shader_handle = AssetId::invalid();
+                continue;
+            }
+
+            // Text and other nodes are batched separately, even with the same material, and so are
+            // nodes with different push constants
+            let mut existing_batch = batches.last_mut().filter(|(_, batch)| {
+                batch_shader_handle == extracted_uinode.material
+                    && batch_mask == mask
+                    && batch.push_constants == extracted_uinode.push_constants
+            });
+
+            if existing_batch.is_none() {
+                // The material may have been removed since the node was queued
+                if !material_is_ready(extracted_uinode.material) {
+                    batch_shader_handle = AssetId::invalid();
+                    continue;
+                }
+                batch_item_index = item_index;
+                batch_shader_handle = extracted_uinode.material;
+                batch_mask = mask;
+
+                let new_batch = UiMaterialBatchLayout {
+                    range: *index..*index,
+                    material: extracted_uinode.material,
+                    mask,
+                    push_constants: extracted_uinode.push_constants.clone(),
+                    push_constant_offset: 0,
+                };
+
+                batches.push((item.entity, new_batch));
+
+                existing_batch = batches.last_mut();
+            }
+
+            // Offset the nodes along the z-axis by their stack index so they have
+            // distinct depths in the UI prepass and the UI depth texture.
+            let z = stack_z(extracted_uinode.stack_index);
+
+            let mut vertex_count = 0;
+            for (corners, uvs) in ui_material_quads(extracted_uinode) {
+                let positions = corners.map(|corner| {
+                    (extracted_uinode.transform * corner.extend(0.).extend(1.0)).xyz() + Vec3::Z * z
+                });
+
+                // Clip the quad itself, so the output of procedural fragment shaders that
+                // don't sample anything is masked to the clip rect like textured nodes
+                // are, whatever the transform of the node.
+                let vertices = clip_ui_material_quad(positions, uvs, extracted_uinode.clip);
+
+                for (position, uv) in &vertices {
+                    push_ui_material_vertex(
+                        vertices,
+                        UiMaterialVertex {
+                            position: (*position).into(),
+                            uv: uv.xy().into(),
+                            size: extracted_uinode.rect.size().into(),
+                            border_widths: extracted_uinode.border,
+                            opacity: extracted_uinode.opacity,
+                            mask_uv: uv.zw().into(),
+                        },
+                        &extracted_uinode.vertex_data,
+                    );
+                }
+                vertex_count += vertices.len() as u32;
+            }
+
+            // Cull nodes that are completely clipped
+            if vertex_count == 0 {
+                continue;
+            }
+
+            *index += vertex_count;
+            existing_batch.unwrap().1.range.end = *index;
+            items[batch_item_index].batch_range_mut().end += 1;
+        } else {
+            batch_shader_handle = AssetId::invalid();
+        }
+    }
+}
+
+/// Writes the push constants of the `batches` to the uniform buffer they fall back to, when the
+/// device doesn't support them, see [`UiPushConstantMode::Uniform`].
+fn write_ui_push_constant_uniforms<M: UiMaterial>(
+    ui_meta: &mut UiMaterialMeta<M>,
+    batches: &mut [(Entity, UiMaterialBatchLayout<M>)],
+    render_device: &RenderDevice,
+    render_queue: &RenderQueue,
+    ui_material_pipeline: &UiMaterialPipeline<M>,
+) {
+    ui_meta.push_constant_uniforms.clear();
+    ui_meta.push_constant_bind_group = None;
+    let Some(layout) = &ui_material_pipeline.push_constant_layout else {
+        return;
+    };
+    if batches.is_empty() {
+        return;
+    }
+
+    let size = M::push_constant_size();
+    let stride = ui_push_constant_uniform_stride(
+        size,
+        render_device.limits().min_uniform_buffer_offset_alignment,
+    );
+    layout_ui_push_constant_uniforms(ui_meta.push_constant_uniforms.values_mut(), batches, stride);
+    ui_meta
+        .push_constant_uniforms
+        .write_buffer(render_device, render_queue);
+
+    let Some(buffer) = ui_meta.push_constant_uniforms.buffer() else {
+        return;
+    };
+    ui_meta.push_constant_bind_group = Some(render_device.create_bind_group(
+        "ui_material_push_constant_bind_group",
+        layout,
+        &BindGroupEntries::single(BufferBinding {
+            buffer,
+            offset: 0,
+            size: BufferSize::new(size.next_multiple_of(16) as u64),
+        }),
+    ));
+}
+
+/// Appends the push constants of each of the `batches` to `values`, `stride` bytes apart, and
+/// sets the offset at which each batch finds them.
+fn layout_ui_push_constant_uniforms<M: UiMaterial>(
+    values: &mut Vec<u8>,
+    batches: &mut [(Entity, UiMaterialBatchLayout<M>)],
+    stride: u32,
+) {
+    for (_, batch) in batches {
+        batch.push_constant_offset = values.len() as u32;
+        values.extend_from_slice(&batch.push_constants);
+        values.resize((batch.push_constant_offset + stride) as usize, 0);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_uimaterial_nodes<M: UiMaterial>(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut ui_meta: ResMut<UiMaterialMeta<M>>,
+    mut extracted_uinodes: ResMut<ExtractedUiMaterialNodes<M>>,
+    view_uniforms: Res<ViewUniforms>,
+    globals_buffer: Res<GlobalsBuffer>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    fallback_image: Res<FallbackImage>,
+    ui_material_pipeline: Res<UiMaterialPipeline<M>>,
+    render_materials: Res<RenderAssets<PreparedUiMaterial<M>>>,
+    mut phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
+    mut opaque_phases: ResMut<ViewSortedRenderPhases<OpaqueUi>>,
+    mut prepass_phases: ResMut<ViewSortedRenderPhases<UiPrepass>>,
+    scene_color_textures: Query<(Entity, &ViewUiSceneColorTexture)>,
+    mut previous_len: Local<usize>,
+) {
+    if let (Some(view_binding), Some(globals_binding)) = (
+        view_uniforms.uniforms.binding(),
+        globals_buffer.buffer.binding(),
+    ) {
+        let mut batches: Vec<(Entity, UiMaterialBatchLayout<M>)> =
+            Vec::with_capacity(*previous_len);
+
+        let ui_meta = &mut *ui_meta;
+        ui_meta.vertices.clear();
+        ui_meta.mask_bind_groups.clear();
+        ui_meta.scene_view_bind_groups.clear();
+        // Materials reading the scene bind a view layout of their own, with a bind group per view
+        if let Some(scene_view_layout) = &ui_material_pipeline.scene_view_layout {
+            for (entity, scene_color) in &scene_color_textures {
+                let bind_group = scene_view_layout.create_bind_group(
+                    &render_device,
+                    view_binding.clone(),
+                    globals_binding.clone(),
+                    scene_color,
+                );
+                ui_meta.scene_view_bind_groups.insert(entity, bind_group);
+            }
+        }
+        ui_meta.view_bind_group = Some(render_device.create_bind_group(
+            "ui_material_view_bind_group",
+            &ui_material_pipeline.view_layout,
+            &BindGroupEntries::sequential((view_binding, globals_binding)),
+        ));
+        let mut index = 0;
+
+        // The bind group of each mask is created the first time a node is drawn with it
+        let mut prepare_mask = |mask: Option<AssetId<Image>>| {
+            if ui_meta.mask_bind_groups.contains_key(&mask) {
+                return true;
+            }
+            let mask_image = match mask {
+                Some(id) => gpu_images.get(id),
+                None => Some(&fallback_image.d2),
+            };
+            let Some(mask_image) = mask_image else {
+                return false;
+            };
+            let mask_bind_group = render_device.create_bind_group(
+                "ui_material_mask_bind_group",
+                &ui_material_pipeline.mask_layout,
+                &BindGroupEntries::sequential((&mask_image.texture_view, &mask_image.sampler)),
+            );
+            ui_meta.mask_bind_groups.insert(mask, mask_bind_group);
+            true
+        };
+        let material_is_ready = |material: AssetId<M>| render_materials.get(material).is_some();
+
+        // Opaque and transparent nodes are batched in the order they are drawn
+        for ui_phase in opaque_phases.values_mut() {
+            batch_ui_material_items(
+                &mut ui_phase.items,
+                &extracted_uinodes,
+                ui_meta.vertices.values_mut(),
+                &mut prepare_mask,
+                &material_is_ready,
+                &mut batches,
+                &mut index,
+            );
+        }
+        for ui_phase in phases.values_mut() {
+            batch_ui_material_items(
+                &mut ui_phase.items,
+                &extracted_uinodes,
+                ui_meta.vertices.values_mut(),
+                &mut prepare_mask,
+                &material_is_ready,
+                &mut batches,
+                &mut index,
+            );
+        }
+
+        if M::prepass_enabled() {
+            // The prepass phase holds a subset of the transparent phase in the same order, so
+            // a batch of consecutive nodes in the transparent phase is also consecutive there.
+            for (view_entity, prepass_phase) in prepass_phases.iter_mut() {
+                let Some(ui_phase) = phases.get(view_entity) else {
+                    continue;
+                };
+                let batch_ranges: EntityHashMap<Range<u32>> = ui_phase
+                    .items
+                    .iter()
+                    .filter(|item| extracted_uinodes.uinodes.contains(item.entity))
+                    .map(|item| (item.entity, item.batch_range.clone()))
+                    .collect();
+                for item in &mut prepass_phase.items {
+                    if let Some(batch_range) = batch_ranges.get(&item.entity) {
+                        item.batch_range = batch_range.clone();
+                    }
+                }
+            }
+        }
+
+        ui_meta.vertices.write_buffer(&render_device, &render_queue);
+        write_ui_push_constant_uniforms(
+            ui_meta,
+            &mut batches,
+            &render_device,
+            &render_queue,
+            &ui_material_pipeline,
+        );
+        *previous_len = batches.len();
+        let batches: Vec<_> = batches
+            .into_iter()
+            .filter_map(|(entity, batch)| {
+                let material = render_materials.get(batch.material)?;
+                let batch = UiMaterialBatch {
+                    range: batch.range,
+                    material: batch.material,
+                    mask: batch.mask,
+                    bind_group: material.bind_group.clone(),
+                    mask_bind_group: ui_meta.mask_bind_groups[&batch.mask].clone(),
+                    push_constants: batch.push_constants,
+                    push_constant_offset: batch.push_constant_offset,
+                };
+                Some((entity, batch))
+            })
+            .collect();
+        commands.insert_or_spawn_batch(batches);
+    }
+    extracted_uinodes.uinodes.clear();
+}
+
+pub struct PreparedUiMaterial<T: UiMaterial> {
+    pub bindings: Vec<(u32, OwnedBindingResource)>,
+    pub bind_group: BindGroup,
+    pub key: T::Data,
+}
+
+impl<M: UiMaterial> RenderAsset for PreparedUiMaterial<M> {
+    type SourceAsset = M;
+
+    type Param = (
+        SRes<RenderDevice>,
+        SRes<UiMaterialPipeline<M>>,
+        Option<SRes<UiTheme>>,
+        M::Param,
+    );
 
     fn prepare_asset(
         material: Self::SourceAsset,
-        (render_device, pipeline, ref mut material_param): &mut SystemParamItem<Self::Param>,
+        (render_device, pipeline, theme, ref mut material_param): &mut SystemParamItem<Self::Param>,
     ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
+        let material = themed_ui_material(material, theme.as_deref());
         match material.as_bind_group(&pipeline.ui_layout, render_device, material_param) {
             Ok(prepared) => Ok(PreparedUiMaterial {
                 bindings: prepared.bindings,
@@ -624,6 +1629,106 @@ impl<M: UiMaterial> RenderAsset for PreparedUiMaterial<M> {
     }
 }
 
+/// The number of consecutive frames the material of a UI node can wait to be prepared before
+/// [`warn_unprepared_ui_materials`] warns about it.
+pub const UI_MATERIAL_RETRY_WARN_FRAMES: u32 = 300;
+
+/// Counts the consecutive frames the materials of type `M` drawn by UI nodes haven't been
+/// prepared for, e.g. because they bind a texture that never loads.
+#[derive(Resource)]
+pub struct UiMaterialRetries<M: UiMaterial> {
+    frames: HashMap<AssetId<M>, u32>,
+}
+
+impl<M: UiMaterial> Default for UiMaterialRetries<M> {
+    fn default() -> Self {
+        Self {
+            frames: Default::default(),
+        }
+    }
+}
+
+impl<M: UiMaterial> UiMaterialRetries<M> {
+    /// Counts another frame for the `unprepared` materials, and returns the ones that have now
+    /// been waiting for [`UI_MATERIAL_RETRY_WARN_FRAMES`] frames, so each is only returned once.
+    ///
+    /// The count of a material is cleared once it's prepared or no longer drawn.
+    pub fn update(&mut self, unprepared: impl IntoIterator<Item = AssetId<M>>) -> Vec<AssetId<M>> {
+        let unprepared: HashSet<AssetId<M>> = unprepared.into_iter().collect();
+        self.frames.retain(|id, _| unprepared.contains(id));
+
+        let mut stuck = Vec::new();
+        for id in unprepared {
+            let frames = self.frames.entry(id).or_insert(0);
+            *frames = frames.saturating_add(1);
+            if *frames == UI_MATERIAL_RETRY_WARN_FRAMES {
+                stuck.push(id);
+            }
+        }
+        stuck
+    }
+}
+
+/// Warns once about each material of type `M` that UI nodes have been waiting for
+/// [`UI_MATERIAL_RETRY_WARN_FRAMES`] frames to be prepared, see [`UiMaterialRetries`].
+///
+/// Nodes aren't drawn while their material isn't prepared, which happens when
+/// [`AsBindGroup::as_bind_group`] keeps asking to retry it on the next frame, e.g. because one of
+/// its textures or storage buffers never loads.
+pub fn warn_unprepared_ui_materials<M: UiMaterial>(
+    extracted_uinodes: Res<ExtractedUiMaterialNodes<M>>,
+    render_materials: Res<RenderAssets<PreparedUiMaterial<M>>>,
+    mut retries: ResMut<UiMaterialRetries<M>>,
+) {
+    let unprepared = extracted_uinodes
+        .uinodes
+        .values()
+        .map(|uinode| uinode.material)
+        .filter(|material| render_materials.get(*material).is_none());
+    for material in retries.update(unprepared) {
+        warn!(
+            "The {} material {material} of a UI node hasn't been prepared for \
+            {UI_MATERIAL_RETRY_WARN_FRAMES} frames, its node isn't drawn. Check that the \
+            textures and buffers it binds are loaded.",
+            std::any::type_name::<M>()
+        );
+    }
+}
+
+/// Returns `material` with the values of `theme` applied, if it uses the [`UiTheme`].
+pub(crate) fn themed_ui_material<M: UiMaterial>(mut material: M, theme: Option<&UiTheme>) -> M {
+    if let Some(theme) = theme.filter(|_| M::uses_theme()) {
+        material.apply_theme(theme);
+    }
+    material
+}
+
+/// Marks every material of a theme-aware [`UiMaterial`] type as modified when the [`UiTheme`]
+/// changes, so they're extracted and prepared again with the new theme.
+pub fn retheme_ui_materials<M: UiMaterial>(
+    theme: Option<Res<UiTheme>>,
+    mut materials: ResMut<Assets<M>>,
+) {
+    if !M::uses_theme() || !theme.is_some_and(|theme| theme.is_changed() && !theme.is_added()) {
+        return;
+    }
+    // Mutably iterating queues a modified event for each material
+    for _ in materials.iter_mut() {}
+}
+
+/// Returns the pipeline to draw a material node with: `pipeline` once it has been compiled, or
+/// `fallback` until then.
+pub(crate) fn select_ui_material_pipeline<Id>(
+    state: &CachedPipelineState,
+    pipeline: Id,
+    fallback: Option<Id>,
+) -> Id {
+    match (state, fallback) {
+        (CachedPipelineState::Ok(_), _) | (_, None) => pipeline,
+        (_, Some(fallback)) => fallback,
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn queue_ui_material_nodes<M: UiMaterial>(
     extracted_uinodes: Res<ExtractedUiMaterialNodes<M>>,
@@ -633,33 +1738,106 @@ pub fn queue_ui_material_nodes<M: UiMaterial>(
     pipeline_cache: Res<PipelineCache>,
     render_materials: Res<RenderAssets<PreparedUiMaterial<M>>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
-    mut views: Query<&ExtractedView>,
+    opaque_draw_functions: Res<DrawFunctions<OpaqueUi>>,
+    mut opaque_render_phases: ResMut<ViewSortedRenderPhases<OpaqueUi>>,
+    prepass_draw_functions: Res<DrawFunctions<UiPrepass>>,
+    mut prepass_render_phases: ResMut<ViewSortedRenderPhases<UiPrepass>>,
+    (views, default_camera_views): (Query<(Entity, &ExtractedView)>, Query<&DefaultCameraView>),
+    (force_ldr_views, msaa_views): (Query<(), With<ExtractedUiForceLdr>>, Query<&Msaa>),
+    depth_views: Res<UiDepthViews>,
+    mut linear_items: ResMut<UiLinearItems>,
+    mut scene_color_views: ResMut<UiSceneColorViews>,
 ) where
     M::Data: PartialEq + Eq + Hash + Clone,
 {
     let draw_function = draw_functions.read().id::<DrawUiMaterial<M>>();
+    let opaque_draw_function = opaque_draw_functions.read().id::<DrawUiMaterial<M>>();
+    let prepass_draw_function = prepass_draw_functions.read().id::<DrawUiMaterial<M>>();
 
+    let front_faces = ui_camera_front_faces(&views, &default_camera_views);
     for (entity, extracted_uinode) in extracted_uinodes.uinodes.iter() {
         let Some(material) = render_materials.get(extracted_uinode.material) else {
             continue;
         };
-        let Ok(view) = views.get_mut(extracted_uinode.camera_entity) else {
+        let Ok((view_entity, view)) = views.get(extracted_uinode.camera_entity) else {
             continue;
         };
-        let Some(transparent_phase) =
-            transparent_render_phases.get_mut(&extracted_uinode.camera_entity)
-        else {
+        let Some(&front_face) = front_faces.get(&view_entity) else {
             continue;
         };
+        let target_is_hdr = ui_target_is_hdr(view, force_ldr_views.contains(view_entity));
+        let hdr = ui_material_target_is_hdr(target_is_hdr, M::target_color_space());
+        let samples = ui_msaa_samples(msaa_views.get(view_entity).ok());
+        let depth = depth_views.contains(view_entity);
+        // Opaque nodes are drawn straight to the target, not in a linear texture
+        let linear = hdr && !target_is_hdr;
+        let opaque = depth && !linear && ui_material_node_is_opaque(extracted_uinode);
+        // The main texture of the view is copied for the nodes reading the scene below the UI
+        if M::reads_view_transform() {
+            scene_color_views.insert(view_entity);
+        }
 
         let pipeline = pipelines.specialize(
             &pipeline_cache,
             &ui_material_pipeline,
             UiMaterialKey {
-                hdr: view.hdr,
+                hdr,
+                front_face,
+                prepass: false,
+                opaque,
+                depth,
+                fallback: false,
+                samples,
                 bind_group_data: material.key.clone(),
             },
         );
+        // Pipelines are compiled without blocking, draw with the fallback until it is ready
+        let fallback = ui_material_pipeline
+            .fallback_fragment_shader
+            .is_some()
+            .then(|| {
+                pipelines.specialize(
+                    &pipeline_cache,
+                    &ui_material_pipeline,
+                    UiMaterialKey {
+                        hdr,
+                        front_face,
+                        prepass: false,
+                        opaque,
+                        depth,
+                        fallback: true,
+                        samples,
+                        bind_group_data: material.key.clone(),
+                    },
+                )
+            });
+        let pipeline = select_ui_material_pipeline(
+            pipeline_cache.get_render_pipeline_state(pipeline),
+            pipeline,
+            fallback,
+        );
+        let sort_key = (ui_material_stack_position(extracted_uinode), entity.index());
+        if opaque {
+            let Some(opaque_phase) = opaque_render_phases.get_mut(&extracted_uinode.camera_entity)
+            else {
+                continue;
+            };
+            opaque_phase.add(OpaqueUi {
+                draw_function: opaque_draw_function,
+                pipeline,
+                entity: *entity,
+                sort_key,
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+            continue;
+        }
+
+        let Some(transparent_phase) =
+            transparent_render_phases.get_mut(&extracted_uinode.camera_entity)
+        else {
+            continue;
+        };
         transparent_phase
             .items
             .reserve(extracted_uinodes.uinodes.len());
@@ -667,12 +1845,1430 @@ pub fn queue_ui_material_nodes<M: UiMaterial>(
             draw_function,
             pipeline,
             entity: *entity,
-            sort_key: (
-                FloatOrd(extracted_uinode.stack_index as f32),
-                entity.index(),
-            ),
+            sort_key,
             batch_range: 0..0,
             extra_index: PhaseItemExtraIndex::NONE,
         });
+        // Linear nodes drawn on an LDR target are drawn in a linear texture instead
+        if linear {
+            linear_items.insert(*entity);
+        }
+
+        queue_ui_material_prepass(
+            &mut prepass_render_phases,
+            prepass_draw_function,
+            *entity,
+            extracted_uinode,
+            || {
+                pipelines.specialize(
+                    &pipeline_cache,
+                    &ui_material_pipeline,
+                    UiMaterialKey {
+                        hdr,
+                        front_face,
+                        prepass: true,
+                        opaque: false,
+                        depth: false,
+                        fallback: false,
+                        // The prepass depth texture isn't multisampled
+                        samples: 1,
+                        bind_group_data: material.key.clone(),
+                    },
+                )
+            },
+        );
+    }
+}
+
+/// Adds the transparent node `entity` to the [`UiPrepass`] phase of its camera if its material
+/// opted in with [`UiMaterial::prepass_enabled`], drawn with the pipeline returned by
+/// `specialize`.
+fn queue_ui_material_prepass<M: UiMaterial>(
+    prepass_render_phases: &mut ViewSortedRenderPhases<UiPrepass>,
+    draw_function: DrawFunctionId,
+    entity: Entity,
+    extracted_uinode: &ExtractedUiMaterialNode<M>,
+    specialize: impl FnOnce() -> CachedRenderPipelineId,
+) {
+    if !M::prepass_enabled() {
+        return;
+    }
+    let Some(prepass_phase) = prepass_render_phases.get_mut(&extracted_uinode.camera_entity) else {
+        return;
+    };
+    prepass_phase.add(UiPrepass {
+        draw_function,
+        pipeline: specialize(),
+        entity,
+        sort_key: (ui_material_stack_position(extracted_uinode), entity.index()),
+        // batch_range will be copied from the transparent phase in prepare_uimaterial_nodes
+        batch_range: 0..0,
+        extra_index: PhaseItemExtraIndex::NONE,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::{Asset, AssetEvent, Handle};
+    use bevy_render::render_resource::{CachedPipelineState, Shader};
+
+    use super::{
+        clip_ui_material_quad, crossfade_ui_materials, push_ui_material_vertex,
+        retheme_ui_materials, select_ui_material_pipeline, themed_ui_material,
+        ui_material_blend_state, ui_material_depth_stencil, ui_material_node_is_opaque,
+        ui_material_quads, ui_material_shader_modified, ui_material_stack_position,
+        ui_material_vertex_layout, ui_material_vertex_layout_with, ExtractedUiMaterialGlyph,
+        ExtractedUiMaterialNode, UiMaterialRetries, UiMaterialVertex,
+        UI_MATERIAL_RETRY_WARN_FRAMES,
+    };
+
+    #[test]
+    fn push_constants_fall_back_to_a_uniform_without_device_support() {
+        use bevy_ecs::{component::Component, world::World};
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, WgpuFeatures};
+
+        use super::{
+            extract_ui_material_push_constants, ui_push_constant_uniform_stride, UiPushConstantMode,
+        };
+        use crate::UiMaterial;
+
+        assert_eq!(
+            UiPushConstantMode::new(0, WgpuFeatures::PUSH_CONSTANTS, 128),
+            UiPushConstantMode::None
+        );
+        assert_eq!(
+            UiPushConstantMode::new(16, WgpuFeatures::PUSH_CONSTANTS, 128),
+            UiPushConstantMode::PushConstants
+        );
+        // WebGL2, and devices supporting fewer bytes
+        assert_eq!(
+            UiPushConstantMode::new(16, WgpuFeatures::empty(), 0),
+            UiPushConstantMode::Uniform
+        );
+        assert_eq!(
+            UiPushConstantMode::new(256, WgpuFeatures::PUSH_CONSTANTS, 128),
+            UiPushConstantMode::Uniform
+        );
+
+        // Each batch gets its own dynamic offset in the uniform buffer
+        assert_eq!(ui_push_constant_uniform_stride(4, 256), 256);
+        assert_eq!(ui_push_constant_uniform_stride(260, 256), 512);
+        assert_eq!(ui_push_constant_uniform_stride(20, 1), 32);
+
+        #[derive(Component)]
+        struct Hover(f32);
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct HoverMaterial {}
+
+        impl UiMaterial for HoverMaterial {
+            fn push_constant_size() -> u32 {
+                8
+            }
+
+            fn extract_push_constants(
+                &self,
+                entity: bevy_ecs::world::EntityRef,
+                data: &mut Vec<u8>,
+            ) {
+                if let Some(hover) = entity.get::<Hover>() {
+                    data.extend_from_slice(&hover.0.to_le_bytes());
+                }
+            }
+        }
+
+        // The bytes of each node are padded to the size of the push constants
+        let mut world = World::new();
+        let hovered = world.spawn(Hover(0.5)).id();
+        let push_constants =
+            extract_ui_material_push_constants(&HoverMaterial {}, Some(world.entity(hovered)));
+        assert_eq!(push_constants.len(), 8);
+        assert_eq!(push_constants[..4], 0.5f32.to_le_bytes());
+        assert_eq!(push_constants[4..], [0; 4]);
+        assert_eq!(
+            extract_ui_material_push_constants(&HoverMaterial {}, None),
+            vec![0; 8]
+        );
+    }
+
+    #[test]
+    fn modifying_the_material_shader_invalidates_its_pipelines() {
+        let material_shader = Handle::<Shader>::weak_from_u128(1).id();
+        let other_shader = Handle::<Shader>::weak_from_u128(2).id();
+        let uses_shader = |id| id == material_shader;
+
+        assert!(ui_material_shader_modified(
+            &[AssetEvent::Modified {
+                id: material_shader
+            }],
+            uses_shader
+        ));
+        assert!(!ui_material_shader_modified(
+            &[AssetEvent::Modified { id: other_shader }],
+            uses_shader
+        ));
+        // Loading the shader the first time doesn't require respecializing
+        assert!(!ui_material_shader_modified(
+            &[AssetEvent::Added {
+                id: material_shader
+            }],
+            uses_shader
+        ));
+    }
+
+    #[test]
+    fn modified_shaders_respecialize_the_material_pipelines_in_place() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use bevy_ecs::{event::Events, system::RunSystemOnce, world::World};
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_resource::{
+                AsBindGroup, FrontFace, PipelineCache, RenderPipelineDescriptor,
+                SpecializedRenderPipelines,
+            },
+            MainWorld,
+        };
+
+        use super::{
+            invalidate_modified_ui_material_pipelines, UiMaterialPipeline, UiPipelineCustomizer,
+            UiPushConstantMode,
+        };
+        use crate::{render::tests::test_render_device, UiMaterial, UiMaterialKey};
+
+        let Some((render_device, render_adapter)) = test_render_device() else {
+            return;
+        };
+
+        static SPECIALIZED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct ReloadedMaterial {}
+
+        impl UiMaterial for ReloadedMaterial {
+            fn specialize(descriptor: &mut RenderPipelineDescriptor, _key: UiMaterialKey<Self>) {
+                let count = SPECIALIZED.fetch_add(1, Ordering::Relaxed) + 1;
+                descriptor.label = Some(format!("reloaded_{count}").into());
+            }
+        }
+
+        type ReloadedPipelines = SpecializedRenderPipelines<UiMaterialPipeline<ReloadedMaterial>>;
+
+        let fragment_shader = Handle::<Shader>::weak_from_u128(1);
+        let layout = render_device.create_bind_group_layout("test_layout", &[]);
+        let mut render_world = World::new();
+        render_world.insert_resource(UiMaterialPipeline::<ReloadedMaterial> {
+            ui_layout: layout.clone(),
+            view_layout: layout.clone(),
+            mask_layout: layout,
+            vertex_layout: ui_material_vertex_layout(),
+            vertex_shader: None,
+            fragment_shader: Some(fragment_shader.clone()),
+            fallback_fragment_shader: None,
+            push_constants: UiPushConstantMode::None,
+            push_constant_layout: None,
+            scene_view_layout: None,
+            customizer: UiPipelineCustomizer::default(),
+            marker: Default::default(),
+        });
+        render_world.insert_resource(PipelineCache::new(render_device, render_adapter, true));
+        render_world.init_resource::<ReloadedPipelines>();
+        let mut main_world = MainWorld::default();
+        main_world.init_resource::<Events<AssetEvent<Shader>>>();
+        render_world.insert_resource(main_world);
+
+        let key = UiMaterialKey::<ReloadedMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque: false,
+            depth: false,
+            fallback: false,
+            samples: 1,
+            bind_group_data: (),
+        };
+        let specialize_and_process = |render_world: &mut World| {
+            render_world
+                .run_system_once(invalidate_modified_ui_material_pipelines::<ReloadedMaterial>);
+            let mut pipelines = render_world.remove_resource::<ReloadedPipelines>().unwrap();
+            let id = pipelines.specialize(
+                render_world.resource::<PipelineCache>(),
+                render_world.resource::<UiMaterialPipeline<ReloadedMaterial>>(),
+                key.clone(),
+            );
+            render_world.insert_resource(pipelines);
+            let mut pipeline_cache = render_world.resource_mut::<PipelineCache>();
+            pipeline_cache.process_queue();
+            (id, pipeline_cache.pipelines().count())
+        };
+
+        let (id, count) = specialize_and_process(&mut render_world);
+        assert_eq!(count, 1);
+        // The cached pipeline is used as long as its shaders aren't modified
+        assert_eq!(specialize_and_process(&mut render_world), (id, 1));
+        assert_eq!(SPECIALIZED.load(Ordering::Relaxed), 1);
+
+        render_world
+            .resource_mut::<MainWorld>()
+            .send_event(AssetEvent::Modified {
+                id: fragment_shader.id(),
+            });
+        // Specialized again, replacing the previous pipeline instead of adding one
+        assert_eq!(specialize_and_process(&mut render_world), (id, 1));
+        assert_eq!(SPECIALIZED.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            render_world
+                .resource::<PipelineCache>()
+                .get_render_pipeline_descriptor(id)
+                .label
+                .as_deref(),
+            Some("reloaded_2")
+        );
+    }
+
+    #[test]
+    fn materials_removed_after_extraction_are_not_drawn() {
+        use bevy_app::SubApp;
+        use bevy_ecs::{system::RunSystemOnce, world::World};
+        use bevy_math::{Mat4, Rect, URect, Vec2};
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_asset::RenderAssets,
+            render_phase::{AddRenderCommand, DrawFunctions, ViewSortedRenderPhases},
+            render_resource::{AsBindGroup, PipelineCache, SpecializedRenderPipelines},
+        };
+
+        use super::{
+            queue_ui_material_nodes, DrawUiMaterial, ExtractedUiMaterialNodes, PreparedUiMaterial,
+            UiMaterialPipeline, UiPipelineCustomizer, UiPushConstantMode,
+        };
+        use crate::{
+            render::{
+                tests::test_render_device, ui_linear_target::UiLinearItems,
+                ui_opaque::UiDepthViews, ui_scene_color::UiSceneColorViews,
+            },
+            ui_default_camera_view, OpaqueUi, TransparentUi, UiMaterial, UiPrepass, UI_CAMERA_FAR,
+        };
+
+        let Some((render_device, render_adapter)) = test_render_device() else {
+            return;
+        };
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct RemovedMaterial {}
+
+        impl UiMaterial for RemovedMaterial {}
+
+        let layout = render_device.create_bind_group_layout("test_layout", &[]);
+        let bind_group = render_device.create_bind_group("test_bind_group", &layout, &[]);
+        let mut render_app = SubApp::new();
+        let world = render_app.world_mut();
+        world.insert_resource(UiMaterialPipeline::<RemovedMaterial> {
+            ui_layout: layout.clone(),
+            view_layout: layout.clone(),
+            mask_layout: layout,
+            vertex_layout: ui_material_vertex_layout(),
+            vertex_shader: None,
+            fragment_shader: None,
+            fallback_fragment_shader: None,
+            push_constants: UiPushConstantMode::None,
+            push_constant_layout: None,
+            scene_view_layout: None,
+            customizer: UiPipelineCustomizer::default(),
+            marker: Default::default(),
+        });
+        world.insert_resource(PipelineCache::new(render_device, render_adapter, true));
+        world.init_resource::<SpecializedRenderPipelines<UiMaterialPipeline<RemovedMaterial>>>();
+        world.init_resource::<RenderAssets<PreparedUiMaterial<RemovedMaterial>>>();
+        world.init_resource::<ExtractedUiMaterialNodes<RemovedMaterial>>();
+        world.init_resource::<DrawFunctions<TransparentUi>>();
+        world.init_resource::<DrawFunctions<OpaqueUi>>();
+        world.init_resource::<DrawFunctions<UiPrepass>>();
+        world.init_resource::<ViewSortedRenderPhases<TransparentUi>>();
+        world.init_resource::<ViewSortedRenderPhases<OpaqueUi>>();
+        world.init_resource::<ViewSortedRenderPhases<UiPrepass>>();
+        world.init_resource::<UiDepthViews>();
+        world.init_resource::<UiLinearItems>();
+        world.init_resource::<UiSceneColorViews>();
+        render_app
+            .add_render_command::<TransparentUi, DrawUiMaterial<RemovedMaterial>>()
+            .add_render_command::<OpaqueUi, DrawUiMaterial<RemovedMaterial>>()
+            .add_render_command::<UiPrepass, DrawUiMaterial<RemovedMaterial>>();
+
+        // A node extracted with its material, drawn by a camera
+        let world = render_app.world_mut();
+        let physical_rect = URect::new(0, 0, 100, 100);
+        let camera = world
+            .spawn(ui_default_camera_view(
+                physical_rect,
+                Vec2::splat(100.),
+                UI_CAMERA_FAR,
+                false,
+            ))
+            .id();
+        let node = world.spawn_empty().id();
+        let material = Handle::<RemovedMaterial>::weak_from_u128(1).id();
+        world
+            .resource_mut::<ExtractedUiMaterialNodes<RemovedMaterial>>()
+            .uinodes
+            .insert(
+                node,
+                ExtractedUiMaterialNode {
+                    stack_index: 0,
+                    transform: Mat4::IDENTITY,
+                    rect: Rect::new(0., 0., 100., 100.),
+                    border: [0.; 4],
+                    material,
+                    clip: None,
+                    opacity: 1.,
+                    crossfade: false,
+                    vertex_data: Vec::new(),
+                    push_constants: Vec::new(),
+                    glyphs: Vec::new(),
+                    mask: None,
+                    camera_entity: camera,
+                },
+            );
+        world
+            .resource_mut::<RenderAssets<PreparedUiMaterial<RemovedMaterial>>>()
+            .insert(
+                material,
+                PreparedUiMaterial {
+                    bindings: Vec::new(),
+                    bind_group,
+                    key: (),
+                },
+            );
+        let queued_items = |world: &mut World| {
+            world
+                .resource_mut::<ViewSortedRenderPhases<TransparentUi>>()
+                .insert_or_clear(camera);
+            world.run_system_once(queue_ui_material_nodes::<RemovedMaterial>);
+            world.resource::<ViewSortedRenderPhases<TransparentUi>>()[&camera]
+                .items
+                .iter()
+                .map(|item| item.entity)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(queued_items(world), [node]);
+
+        // The material is removed once the node was extracted, before it is queued
+        world
+            .resource_mut::<RenderAssets<PreparedUiMaterial<RemovedMaterial>>>()
+            .remove(material);
+        assert!(queued_items(world).is_empty());
+    }
+
+    #[test]
+    fn procedural_material_output_is_masked_to_the_clip_rect() {
+        use bevy_math::{Quat, Rect, Vec2, Vec3};
+
+        let uvs = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let quad = |rotation: Quat| {
+            [
+                Vec3::new(-50., -50., 0.),
+                Vec3::new(50., -50., 0.),
+                Vec3::new(50., 50., 0.),
+                Vec3::new(-50., 50., 0.),
+            ]
+            .map(|corner| rotation * corner)
+        };
+        let clip = Rect::new(-25., -60., 60., 30.);
+        let inside = |position: Vec3| clip.inflate(1e-3).contains(position.truncate());
+
+        // The quad is cut at the clip rect, with the UVs of the procedural content following
+        let vertices = clip_ui_material_quad(quad(Quat::IDENTITY), uvs, Some(clip));
+        assert_eq!(vertices.len(), 6);
+        for (position, uv) in &vertices {
+            assert!(inside(*position));
+            assert!(uv.abs_diff_eq((position.truncate() + 50.) / 100., 1e-5));
+        }
+
+        // Rotated nodes are masked too
+        let rotation = Quat::from_rotation_z(0.5);
+        let vertices = clip_ui_material_quad(quad(rotation), uvs, Some(clip));
+        assert!(vertices.len() >= 6 && vertices.len() % 3 == 0);
+        for (position, uv) in &vertices {
+            assert!(inside(*position));
+            let local = rotation.inverse() * *position;
+            assert!(uv.abs_diff_eq((local.truncate() + 50.) / 100., 1e-4));
+        }
+
+        // Unclipped nodes keep their whole quad, completely clipped nodes are culled
+        assert_eq!(clip_ui_material_quad(quad(rotation), uvs, None).len(), 6);
+        let outside = Rect::new(100., 100., 200., 200.);
+        assert!(clip_ui_material_quad(quad(rotation), uvs, Some(outside)).is_empty());
+    }
+
+    #[test]
+    fn changing_the_theme_accent_reprepares_theme_aware_materials() {
+        use bevy_asset::Assets;
+        use bevy_color::{Color, LinearRgba};
+        use bevy_ecs::{
+            event::Events,
+            system::{IntoSystem, RunSystemOnce, System},
+            world::World,
+        };
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::AsBindGroup;
+
+        use crate::{UiMaterial, UiTheme};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct AccentMaterial {
+            #[uniform(0)]
+            accent: LinearRgba,
+        }
+
+        impl UiMaterial for AccentMaterial {
+            fn uses_theme() -> bool {
+                true
+            }
+
+            fn apply_theme(&mut self, theme: &UiTheme) {
+                self.accent = theme.accent.into();
+            }
+        }
+
+        let mut world = World::new();
+        world.init_resource::<UiTheme>();
+        world.init_resource::<Assets<AccentMaterial>>();
+        world.init_resource::<Events<AssetEvent<AccentMaterial>>>();
+        let ids = [(); 2].map(|_| {
+            world
+                .resource_mut::<Assets<AccentMaterial>>()
+                .add(AccentMaterial {
+                    accent: LinearRgba::BLACK,
+                })
+                .id()
+        });
+        let mut retheme = IntoSystem::into_system(retheme_ui_materials::<AccentMaterial>);
+        retheme.initialize(&mut world);
+        let modified = |world: &mut World| -> Vec<_> {
+            world.run_system_once(Assets::<AccentMaterial>::asset_events);
+            let mut events = world.resource_mut::<Events<AssetEvent<AccentMaterial>>>();
+            let modified = events
+                .iter_current_update_events()
+                .filter_map(|event| match event {
+                    AssetEvent::Modified { id } => Some(*id),
+                    _ => None,
+                })
+                .collect();
+            events.clear();
+            modified
+        };
+
+        // Adding the theme doesn't prepare the materials again
+        retheme.run((), &mut world);
+        assert!(modified(&mut world).is_empty());
+
+        world.resource_mut::<UiTheme>().accent = Color::srgb(1., 0.5, 0.);
+        retheme.run((), &mut world);
+        assert_eq!(modified(&mut world), ids);
+
+        // They're prepared with the new accent, while the assets keep their own
+        let theme = *world.resource::<UiTheme>();
+        for id in ids {
+            let material = world.resource::<Assets<AccentMaterial>>().get(id).unwrap();
+            assert_eq!(material.accent, LinearRgba::BLACK);
+            let prepared = themed_ui_material(material.clone(), Some(&theme));
+            assert_eq!(prepared.accent, LinearRgba::from(theme.accent));
+        }
+
+        // Unchanged themes don't prepare them again
+        retheme.run((), &mut world);
+        assert!(modified(&mut world).is_empty());
+    }
+
+    #[test]
+    fn custom_vertex_attributes_follow_the_default_ones() {
+        use bevy_render::render_resource::VertexFormat;
+
+        let default_layout = ui_material_vertex_layout();
+        assert_eq!(
+            default_layout.array_stride as usize,
+            std::mem::size_of::<UiMaterialVertex>()
+        );
+
+        // A corner radius per node
+        let layout = ui_material_vertex_layout_with([VertexFormat::Float32]);
+        assert_eq!(layout.array_stride, default_layout.array_stride + 4);
+        assert_eq!(layout.attributes[..6], default_layout.attributes[..]);
+        let radius = layout.attributes[6];
+        assert_eq!(radius.shader_location, 6);
+        assert_eq!(radius.offset, default_layout.array_stride);
+
+        let vertex = UiMaterialVertex {
+            position: [1., 2., 3.],
+            uv: [0., 1.],
+            size: [100., 50.],
+            border_widths: [0.; 4],
+            opacity: 1.,
+            mask_uv: [0., 1.],
+        };
+        let mut vertices = Vec::new();
+        for _ in 0..3 {
+            push_ui_material_vertex(&mut vertices, vertex, bytemuck::bytes_of(&8f32));
+        }
+        assert_eq!(vertices.len(), 3 * layout.array_stride as usize);
+        let offset = (layout.array_stride + radius.offset) as usize;
+        assert_eq!(
+            bytemuck::pod_read_unaligned::<f32>(&vertices[offset..offset + 4]),
+            8.
+        );
+
+        // The default layout only holds the vertex
+        let mut vertices = Vec::new();
+        push_ui_material_vertex(&mut vertices, vertex, &[]);
+        assert_eq!(vertices.len(), default_layout.array_stride as usize);
+    }
+
+    #[test]
+    fn crossfading_draws_both_materials_with_complementary_opacity() {
+        use bevy_ecs::{
+            entity::Entity,
+            system::{IntoSystem, System},
+            world::World,
+        };
+        use bevy_math::{Mat4, Rect, Vec2};
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::AsBindGroup;
+
+        use crate::{UiMaterial, UiMaterialCrossfade, UiMaterialOpacity};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct FadeMaterial {}
+
+        impl UiMaterial for FadeMaterial {}
+
+        let mut world = World::new();
+        let from = Handle::<FadeMaterial>::weak_from_u128(1);
+        let to = Handle::<FadeMaterial>::weak_from_u128(2);
+        let crossfade = UiMaterialCrossfade::new(to.clone(), 0.5);
+        let node = world.spawn((from.clone(), crossfade.clone())).id();
+        let mut system = IntoSystem::into_system(crossfade_ui_materials::<FadeMaterial>);
+        system.initialize(&mut world);
+        system.run((), &mut world);
+        system.apply_deferred(&mut world);
+
+        // The node's own material is faded out as much as the other one is faded in
+        let own_opacity = world.get::<UiMaterialOpacity>(node).unwrap().0;
+        let [_, target_opacity] = crossfade.opacities();
+        assert_eq!(own_opacity, 0.5);
+        assert_eq!(own_opacity + target_opacity, 1.);
+        assert_eq!(UiMaterialCrossfade::new(to, 2.).opacities(), [0., 1.]);
+
+        // Both are drawn, the target over the node's own material and under the next node
+        let extracted =
+            |material: &Handle<FadeMaterial>, opacity, crossfade| ExtractedUiMaterialNode {
+                stack_index: 3,
+                transform: Mat4::IDENTITY,
+                rect: Rect::from_corners(Vec2::ZERO, Vec2::splat(100.)),
+                border: [0.; 4],
+                material: material.id(),
+                clip: None,
+                opacity,
+                crossfade,
+                vertex_data: Vec::new(),
+                push_constants: Vec::new(),
+                glyphs: Vec::new(),
+                mask: None,
+                camera_entity: Entity::PLACEHOLDER,
+            };
+        let own = ui_material_stack_position(&extracted(&from, own_opacity, false));
+        let target =
+            ui_material_stack_position(&extracted(&crossfade.material, target_opacity, true));
+        assert!(own < target);
+        assert!(target.0 < 4.);
+
+        // Changing the blend updates the opacity, removing the crossfade resets it
+        world
+            .get_mut::<UiMaterialCrossfade<FadeMaterial>>(node)
+            .unwrap()
+            .blend = 0.25;
+        system.run((), &mut world);
+        system.apply_deferred(&mut world);
+        assert_eq!(world.get::<UiMaterialOpacity>(node).unwrap().0, 0.75);
+        world
+            .entity_mut(node)
+            .remove::<UiMaterialCrossfade<FadeMaterial>>();
+        system.run((), &mut world);
+        system.apply_deferred(&mut world);
+        assert!(world.get::<UiMaterialOpacity>(node).is_none());
+    }
+
+    #[test]
+    fn text_glyphs_span_the_material_and_sample_their_atlas() {
+        use bevy_asset::AssetId;
+        use bevy_ecs::entity::Entity;
+        use bevy_math::{Mat4, Rect, Vec2, Vec4};
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::AsBindGroup;
+
+        use crate::UiMaterial;
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct FillMaterial {}
+
+        impl UiMaterial for FillMaterial {}
+
+        let node = |glyphs: Vec<ExtractedUiMaterialGlyph>| ExtractedUiMaterialNode {
+            stack_index: 0,
+            transform: Mat4::IDENTITY,
+            rect: Rect::from_corners(Vec2::ZERO, Vec2::new(200., 50.)),
+            border: [0.; 4],
+            material: AssetId::<FillMaterial>::invalid(),
+            clip: None,
+            opacity: 1.,
+            crossfade: false,
+            vertex_data: Vec::new(),
+            push_constants: Vec::new(),
+            mask: (!glyphs.is_empty()).then(AssetId::invalid),
+            glyphs,
+            camera_entity: Entity::PLACEHOLDER,
+        };
+
+        // Nodes without glyphs are a single quad, with the whole white mask
+        let quads = ui_material_quads(&node(Vec::new()));
+        assert_eq!(quads.len(), 1);
+        let (corners, uvs) = quads[0];
+        assert_eq!(corners[0], Vec2::new(-100., -25.));
+        assert_eq!(corners[2], Vec2::new(100., 25.));
+        assert_eq!(
+            uvs,
+            [
+                Vec4::ZERO,
+                Vec4::new(1., 0., 1., 0.),
+                Vec4::ONE,
+                Vec4::new(0., 1., 0., 1.)
+            ]
+        );
+
+        // Each glyph samples the material where it lies in the node, and its own atlas rect
+        let glyphs = vec![
+            ExtractedUiMaterialGlyph {
+                rect: Rect::new(-100., -25., -50., 25.),
+                mask_rect: Rect::new(0., 0., 0.25, 0.5),
+            },
+            ExtractedUiMaterialGlyph {
+                rect: Rect::new(50., -25., 100., 25.),
+                mask_rect: Rect::new(0.5, 0.5, 0.75, 1.),
+            },
+        ];
+        let quads = ui_material_quads(&node(glyphs));
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[0].1[0], Vec4::new(0., 0., 0., 0.));
+        assert_eq!(quads[0].1[2], Vec4::new(0.25, 1., 0.25, 0.5));
+        assert_eq!(quads[1].0[0], Vec2::new(50., -25.));
+        assert_eq!(quads[1].1[0], Vec4::new(0.75, 0., 0.5, 0.5));
+        assert_eq!(quads[1].1[2], Vec4::new(1., 1., 0.75, 1.));
+    }
+
+    #[cfg(feature = "bevy_text")]
+    #[test]
+    fn only_text_nodes_with_a_material_are_drawn_as_material_text() {
+        use bevy_ecs::{
+            system::{IntoSystem, System},
+            world::World,
+        };
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::AsBindGroup;
+        use bevy_text::Text;
+
+        use super::mark_ui_material_text;
+        use crate::{UiMaterial, UiMaterialText};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct FillMaterial {}
+
+        impl UiMaterial for FillMaterial {}
+
+        let mut world = World::new();
+        let material = Handle::<FillMaterial>::weak_from_u128(1);
+        let text = world.spawn((Text::default(), material.clone())).id();
+        let plain_text = world.spawn(Text::default()).id();
+        let panel = world.spawn(material).id();
+        let mut system = IntoSystem::into_system(mark_ui_material_text::<FillMaterial>);
+        system.initialize(&mut world);
+        system.run((), &mut world);
+        system.apply_deferred(&mut world);
+
+        assert!(world.get::<UiMaterialText>(text).is_some());
+        assert!(world.get::<UiMaterialText>(plain_text).is_none());
+        assert!(world.get::<UiMaterialText>(panel).is_none());
+
+        // Removing the material draws the text with its colors again
+        world.entity_mut(text).remove::<Handle<FillMaterial>>();
+        system.run((), &mut world);
+        system.apply_deferred(&mut world);
+        assert!(world.get::<UiMaterialText>(text).is_none());
+    }
+
+    #[test]
+    fn depth_compare_is_applied_to_the_depth_state() {
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, CompareFunction, FrontFace};
+
+        use crate::{UiMaterial, UiMaterialKey};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct OverlayMaterial {}
+
+        impl UiMaterial for OverlayMaterial {
+            fn prepass_enabled() -> bool {
+                true
+            }
+
+            fn depth_compare(_key: UiMaterialKey<Self>) -> CompareFunction {
+                CompareFunction::Always
+            }
+        }
+
+        let key = |prepass| UiMaterialKey::<OverlayMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass,
+            opaque: false,
+            depth: false,
+            fallback: false,
+            samples: 1,
+            bind_group_data: (),
+        };
+
+        let depth_stencil = ui_material_depth_stencil(key(true)).unwrap();
+        assert_eq!(depth_stencil.depth_compare, CompareFunction::Always);
+        assert!(depth_stencil.depth_write_enabled);
+
+        // The main UI pass has no depth attachment
+        assert!(ui_material_depth_stencil(key(false)).is_none());
+    }
+
+    #[test]
+    fn opaque_nodes_write_depth_and_transparent_nodes_only_test_it() {
+        use bevy_asset::AssetId;
+        use bevy_ecs::entity::Entity;
+        use bevy_math::{Mat4, Rect, Vec2};
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, CompareFunction, FrontFace};
+
+        use crate::{UiAlphaMode, UiMaterial, UiMaterialKey};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct SolidMaterial {}
+
+        impl UiMaterial for SolidMaterial {
+            fn alpha_mode() -> UiAlphaMode {
+                UiAlphaMode::Opaque
+            }
+        }
+
+        let node = |opacity| ExtractedUiMaterialNode {
+            stack_index: 0,
+            transform: Mat4::IDENTITY,
+            rect: Rect::from_corners(Vec2::ZERO, Vec2::splat(100.)),
+            border: [0.; 4],
+            material: AssetId::<SolidMaterial>::invalid(),
+            clip: None,
+            opacity,
+            crossfade: false,
+            vertex_data: Vec::new(),
+            push_constants: Vec::new(),
+            glyphs: Vec::new(),
+            mask: None,
+            camera_entity: Entity::PLACEHOLDER,
+        };
+        assert!(ui_material_node_is_opaque(&node(1.)));
+        // Faded nodes are blended over the nodes below them
+        assert!(!ui_material_node_is_opaque(&node(0.5)));
+
+        let key = |opaque, depth| UiMaterialKey::<SolidMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque,
+            depth,
+            fallback: false,
+            samples: 1,
+            bind_group_data: (),
+        };
+        let opaque = ui_material_depth_stencil(key(true, true)).unwrap();
+        assert!(opaque.depth_write_enabled);
+        assert_eq!(opaque.depth_compare, CompareFunction::LessEqual);
+        let transparent = ui_material_depth_stencil(key(false, true)).unwrap();
+        assert!(!transparent.depth_write_enabled);
+        assert!(ui_material_depth_stencil(key(false, false)).is_none());
+    }
+
+    #[test]
+    fn opted_in_materials_are_queued_to_the_prepass_and_get_its_texture() {
+        use bevy_asset::AssetId;
+        use bevy_ecs::{entity::Entity, world::World};
+        use bevy_math::{Mat4, Rect, UVec2, Vec2};
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_phase::{
+                Draw, DrawError, DrawFunctions, TrackedRenderPass, ViewSortedRenderPhases,
+            },
+            render_resource::{AsBindGroup, CachedRenderPipelineId, TextureUsages},
+        };
+
+        use super::queue_ui_material_prepass;
+        use crate::{ui_prepass_depth_descriptor, UiMaterial, UiPrepass, UI_PREPASS_DEPTH_FORMAT};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct OutlineMaterial {}
+
+        impl UiMaterial for OutlineMaterial {
+            fn prepass_enabled() -> bool {
+                true
+            }
+        }
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct PlainMaterial {}
+
+        impl UiMaterial for PlainMaterial {}
+
+        struct NoopDraw;
+        impl Draw<UiPrepass> for NoopDraw {
+            fn draw<'w>(
+                &mut self,
+                _world: &'w World,
+                _pass: &mut TrackedRenderPass<'w>,
+                _view: Entity,
+                _item: &UiPrepass,
+            ) -> Result<(), DrawError> {
+                Ok(())
+            }
+        }
+        let draw_function = DrawFunctions::<UiPrepass>::default().write().add(NoopDraw);
+
+        fn node<M: UiMaterial>(
+            stack_index: u32,
+            camera_entity: Entity,
+        ) -> ExtractedUiMaterialNode<M> {
+            ExtractedUiMaterialNode {
+                stack_index,
+                transform: Mat4::IDENTITY,
+                rect: Rect::from_corners(Vec2::ZERO, Vec2::splat(100.)),
+                border: [0.; 4],
+                material: AssetId::<M>::invalid(),
+                clip: None,
+                opacity: 1.,
+                crossfade: false,
+                vertex_data: Vec::new(),
+                push_constants: Vec::new(),
+                glyphs: Vec::new(),
+                mask: None,
+                camera_entity,
+            }
+        }
+
+        // One view with an outlined node above a plain one, and a view with only a plain node
+        let (outlined_view, plain_view) = (Entity::from_raw(0), Entity::from_raw(1));
+        let mut phases = ViewSortedRenderPhases::<UiPrepass>::default();
+        phases.insert_or_clear(outlined_view);
+        phases.insert_or_clear(plain_view);
+        let mut specialized = 0;
+        let mut specialize = || {
+            specialized += 1;
+            CachedRenderPipelineId::INVALID
+        };
+        queue_ui_material_prepass(
+            &mut phases,
+            draw_function,
+            Entity::from_raw(2),
+            &node::<PlainMaterial>(0, outlined_view),
+            &mut specialize,
+        );
+        queue_ui_material_prepass(
+            &mut phases,
+            draw_function,
+            Entity::from_raw(3),
+            &node::<OutlineMaterial>(1, outlined_view),
+            &mut specialize,
+        );
+        queue_ui_material_prepass(
+            &mut phases,
+            draw_function,
+            Entity::from_raw(4),
+            &node::<PlainMaterial>(0, plain_view),
+            &mut specialize,
+        );
+
+        // Only the opted-in material is queued, and only its prepass pipeline is specialized
+        let items: Vec<Entity> = phases[&outlined_view]
+            .items
+            .iter()
+            .map(|item| item.entity)
+            .collect();
+        assert_eq!(items, [Entity::from_raw(3)]);
+        assert!(phases[&plain_view].items.is_empty());
+        assert_eq!(specialized, 1);
+
+        // The view drawing it gets a prepass depth texture the size of its target
+        let size = UVec2::new(1280, 720);
+        let depth = ui_prepass_depth_descriptor(size, phases.get(&outlined_view)).unwrap();
+        assert_eq!(depth.format, UI_PREPASS_DEPTH_FORMAT);
+        assert_eq!((depth.size.width, depth.size.height), (size.x, size.y));
+        assert!(depth
+            .usage
+            .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING));
+        assert!(ui_prepass_depth_descriptor(size, phases.get(&plain_view)).is_none());
+        assert!(ui_prepass_depth_descriptor(size, None).is_none());
+    }
+
+    #[test]
+    fn material_is_specialized_separately_for_each_sample_count() {
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_resource::{AsBindGroup, FrontFace},
+            view::Msaa,
+        };
+        use bevy_utils::HashSet;
+
+        use crate::{ui_msaa::ui_msaa_samples, UiMaterial, UiMaterialKey};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct TestMaterial {}
+
+        impl UiMaterial for TestMaterial {}
+
+        let key = |msaa| UiMaterialKey::<TestMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque: false,
+            depth: false,
+            fallback: false,
+            samples: ui_msaa_samples(msaa),
+            bind_group_data: (),
+        };
+
+        // Views without MSAA keep specializing single sampled pipelines
+        assert_eq!(key(None).samples, 1);
+        assert_eq!(key(Some(&Msaa::Off)).samples, 1);
+        assert_eq!(key(Some(&Msaa::Sample4)).samples, 4);
+
+        // `SpecializedRenderPipelines` caches a pipeline id per distinct key
+        let keys: HashSet<_> = [key(Some(&Msaa::Off)), key(Some(&Msaa::Sample4)), key(None)]
+            .into_iter()
+            .collect();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn bind_group_data_selects_shader_defs_and_pipelines() {
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, FrontFace, ShaderDefVal};
+        use bevy_utils::HashSet;
+
+        use crate::{UiMaterial, UiMaterialKey};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        #[bind_group_data(FeatureKey)]
+        struct FeatureMaterial {
+            rounded: bool,
+        }
+
+        #[derive(PartialEq, Eq, Hash, Clone)]
+        struct FeatureKey {
+            rounded: bool,
+        }
+
+        impl From<&FeatureMaterial> for FeatureKey {
+            fn from(material: &FeatureMaterial) -> Self {
+                Self {
+                    rounded: material.rounded,
+                }
+            }
+        }
+
+        impl UiMaterial for FeatureMaterial {
+            fn shader_defs(key: &UiMaterialKey<Self>) -> Vec<ShaderDefVal> {
+                if key.bind_group_data.rounded {
+                    vec!["ROUNDED".into()]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+
+        let key = |material: &FeatureMaterial| UiMaterialKey::<FeatureMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque: false,
+            depth: false,
+            fallback: false,
+            samples: 1,
+            bind_group_data: material.into(),
+        };
+        let rounded = key(&FeatureMaterial { rounded: true });
+        let square = key(&FeatureMaterial { rounded: false });
+
+        assert_eq!(
+            FeatureMaterial::shader_defs(&rounded),
+            [ShaderDefVal::from("ROUNDED")]
+        );
+        assert!(FeatureMaterial::shader_defs(&square).is_empty());
+
+        // `SpecializedRenderPipelines` caches a pipeline id per distinct key
+        let keys: HashSet<_> = [rounded.clone(), square, rounded].into_iter().collect();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn color_space_sets_the_output_shader_def() {
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, FrontFace, ShaderDefVal};
+
+        use super::{ui_material_shader_defs, UiPushConstantMode};
+        use crate::{UiColorSpace, UiMaterial, UiMaterialKey};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct LinearMaterial {}
+
+        impl UiMaterial for LinearMaterial {}
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct SrgbMaterial {}
+
+        impl UiMaterial for SrgbMaterial {
+            fn color_space() -> UiColorSpace {
+                UiColorSpace::Srgb
+            }
+        }
+
+        fn key<M: UiMaterial<Data = ()>>() -> UiMaterialKey<M> {
+            UiMaterialKey {
+                hdr: false,
+                front_face: FrontFace::Ccw,
+                prepass: false,
+                opaque: false,
+                depth: false,
+                fallback: false,
+                samples: 1,
+                bind_group_data: (),
+            }
+        }
+        let output_srgb = ShaderDefVal::from("UI_OUTPUT_SRGB");
+
+        assert!(
+            !ui_material_shader_defs(&key::<LinearMaterial>(), UiPushConstantMode::None)
+                .contains(&output_srgb)
+        );
+        // The shader decodes its output with `ui_output_color` only when the def is set
+        assert_eq!(
+            ui_material_shader_defs(&key::<SrgbMaterial>(), UiPushConstantMode::None),
+            [output_srgb.clone()]
+        );
+        assert_eq!(
+            ui_material_shader_defs(&key::<SrgbMaterial>(), UiPushConstantMode::Uniform),
+            [ShaderDefVal::from("UI_PUSH_CONSTANTS_UNIFORM"), output_srgb]
+        );
+    }
+
+    #[test]
+    fn materials_can_bind_storage_buffers_larger_than_uniforms() {
+        use bevy_math::Vec4;
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_resource::AsBindGroup, settings::WgpuLimits, storage::ShaderStorageBuffer,
+        };
+
+        use crate::UiMaterial;
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct PaletteMaterial {
+            #[storage(0, read_only)]
+            _palette: Handle<ShaderStorageBuffer>,
+        }
+
+        impl UiMaterial for PaletteMaterial {}
+
+        fn is_ui_material<M: UiMaterial>() {}
+        is_ui_material::<PaletteMaterial>();
+
+        // 4096 colors don't fit in the smallest uniform binding size, but do in a storage buffer
+        let palette = ShaderStorageBuffer::from(vec![Vec4::ONE; 4096]);
+        let size = palette.data.unwrap().len();
+        assert_eq!(size, 4096 * 16);
+        assert!(
+            size as u32 > WgpuLimits::downlevel_webgl2_defaults().max_uniform_buffer_binding_size
+        );
+    }
+
+    #[test]
+    fn materials_choose_their_blend_state() {
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{
+            AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, FrontFace,
+        };
+        use bevy_utils::HashSet;
+
+        use crate::{UiMaterial, UiMaterialKey};
+
+        const ADDITIVE: BlendState = BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent::OVER,
+        };
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        #[bind_group_data(GlowKey)]
+        struct GlowMaterial {
+            additive: bool,
+        }
+
+        #[derive(PartialEq, Eq, Hash, Clone)]
+        struct GlowKey {
+            additive: bool,
+        }
+
+        impl From<&GlowMaterial> for GlowKey {
+            fn from(material: &GlowMaterial) -> Self {
+                Self {
+                    additive: material.additive,
+                }
+            }
+        }
+
+        impl UiMaterial for GlowMaterial {
+            fn blend_state(key: UiMaterialKey<Self>) -> Option<BlendState> {
+                Some(if key.bind_group_data.additive {
+                    ADDITIVE
+                } else {
+                    BlendState::ALPHA_BLENDING
+                })
+            }
+        }
+
+        let key = |additive, opaque| UiMaterialKey::<GlowMaterial> {
+            hdr: false,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque,
+            depth: opaque,
+            fallback: false,
+            samples: 1,
+            bind_group_data: (&GlowMaterial { additive }).into(),
+        };
+        assert_eq!(ui_material_blend_state(key(true, false)), Some(ADDITIVE));
+        assert_eq!(
+            ui_material_blend_state(key(false, false)),
+            Some(BlendState::ALPHA_BLENDING)
+        );
+        // Opaque nodes aren't blended
+        assert_eq!(ui_material_blend_state(key(true, true)), None);
+
+        // `SpecializedRenderPipelines` caches a pipeline id per distinct key
+        let keys: HashSet<_> = [key(true, false), key(false, false), key(true, false)]
+            .into_iter()
+            .collect();
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn fallback_is_used_until_the_pipeline_is_compiled() {
+        let (pipeline, fallback) = (1, 2);
+
+        // Creating a pipeline doesn't block, it stays queued until the cache processes it
+        assert_eq!(
+            select_ui_material_pipeline(&CachedPipelineState::Queued, pipeline, Some(fallback)),
+            fallback
+        );
+
+        // Without a fallback, the node waits for its own pipeline
+        assert_eq!(
+            select_ui_material_pipeline(&CachedPipelineState::Queued, pipeline, None),
+            pipeline
+        );
+    }
+
+    #[test]
+    fn materials_stuck_retrying_are_warned_about_once() {
+        use bevy_reflect::TypePath;
+        use bevy_render::{render_resource::AsBindGroup, texture::Image};
+
+        use crate::UiMaterial;
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct IconMaterial {
+            #[texture(0)]
+            #[sampler(1)]
+            icon: Handle<Image>,
+        }
+
+        impl UiMaterial for IconMaterial {}
+
+        // The icon of `stuck` never loads, so preparing it is retried every frame
+        let stuck = Handle::<IconMaterial>::weak_from_u128(1).id();
+        let loaded = Handle::<IconMaterial>::weak_from_u128(2).id();
+        let mut retries = UiMaterialRetries::<IconMaterial>::default();
+        let mut warnings = Vec::new();
+        for frame in 0..3 * UI_MATERIAL_RETRY_WARN_FRAMES {
+            let unprepared = if frame < 10 {
+                vec![stuck, loaded]
+            } else {
+                vec![stuck]
+            };
+            warnings.extend(retries.update(unprepared));
+        }
+        assert_eq!(warnings, vec![stuck]);
+
+        // The count is cleared once the material is prepared
+        retries.update([]);
+        for _ in 1..UI_MATERIAL_RETRY_WARN_FRAMES {
+            assert!(retries.update([stuck]).is_empty());
+        }
+        assert_eq!(retries.update([stuck]), vec![stuck]);
+    }
+
+    #[test]
+    fn plugins_replace_the_fragment_shader_of_their_material() {
+        use bevy_reflect::TypePath;
+        use bevy_render::render_resource::{AsBindGroup, ShaderRef};
+
+        use crate::{UiMaterial, UiMaterialPlugin};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct PanelMaterial {}
+
+        impl UiMaterial for PanelMaterial {
+            fn fragment_shader() -> ShaderRef {
+                "shaders/panel.wgsl".into()
+            }
+        }
+
+        // The default plugin keeps the shader of the material
+        assert!(UiMaterialPlugin::<PanelMaterial>::default()
+            .fragment_shader
+            .is_none());
+
+        let debug = UiMaterialPlugin::<PanelMaterial>::new()
+            .with_fragment_shader("shaders/panel_debug.wgsl");
+        assert!(matches!(
+            debug.fragment_shader,
+            Some(ShaderRef::Path(path)) if path.path().to_str() == Some("shaders/panel_debug.wgsl")
+        ));
+
+        let handle = Handle::<Shader>::weak_from_u128(1);
+        let debug = UiMaterialPlugin::<PanelMaterial>::new().with_fragment_shader(handle.clone());
+        assert!(matches!(debug.fragment_shader, Some(ShaderRef::Handle(h)) if h == handle));
+    }
+
+    #[test]
+    fn batches_are_split_by_push_constants_and_get_their_own_uniform_offset() {
+        use bevy_ecs::{entity::Entity, world::World};
+        use bevy_math::{FloatOrd, Mat4, Rect, Vec2};
+        use bevy_reflect::TypePath;
+        use bevy_render::{
+            render_phase::{
+                Draw, DrawError, DrawFunctions, PhaseItemExtraIndex, TrackedRenderPass,
+            },
+            render_resource::{AsBindGroup, CachedRenderPipelineId},
+        };
+
+        use super::{batch_ui_material_items, layout_ui_push_constant_uniforms};
+        use crate::{ExtractedUiMaterialNodes, TransparentUi, UiMaterial};
+
+        #[derive(AsBindGroup, Asset, TypePath, Clone)]
+        struct HighlightMaterial {}
+
+        impl UiMaterial for HighlightMaterial {
+            fn push_constant_size() -> u32 {
+                4
+            }
+        }
+
+        struct NoopDraw;
+        impl Draw<TransparentUi> for NoopDraw {
+            fn draw<'w>(
+                &mut self,
+                _world: &'w World,
+                _pass: &mut TrackedRenderPass<'w>,
+                _view: Entity,
+                _item: &TransparentUi,
+            ) -> Result<(), DrawError> {
+                Ok(())
+            }
+        }
+        let draw_function = DrawFunctions::<TransparentUi>::default()
+            .write()
+            .add(NoopDraw);
+
+        // Two buttons with the same highlight, a highlighted one, then another plain one
+        let material = Handle::<HighlightMaterial>::weak_from_u128(1).id();
+        let mut extracted_uinodes = ExtractedUiMaterialNodes::<HighlightMaterial>::default();
+        let mut items = Vec::new();
+        for (index, highlight) in [0f32, 0., 1., 0.].into_iter().enumerate() {
+            let entity = Entity::from_raw(index as u32);
+            extracted_uinodes.uinodes.insert(
+                entity,
+                ExtractedUiMaterialNode {
+                    stack_index: index as u32,
+                    transform: Mat4::IDENTITY,
+                    rect: Rect::from_corners(Vec2::ZERO, Vec2::splat(100.)),
+                    border: [0.; 4],
+                    material,
+                    clip: None,
+                    opacity: 1.,
+                    crossfade: false,
+                    vertex_data: Vec::new(),
+                    push_constants: highlight.to_le_bytes().to_vec(),
+                    glyphs: Vec::new(),
+                    mask: None,
+                    camera_entity: Entity::PLACEHOLDER,
+                },
+            );
+            items.push(TransparentUi {
+                sort_key: (FloatOrd(index as f32), entity.index()),
+                entity,
+                pipeline: CachedRenderPipelineId::INVALID,
+                draw_function,
+                batch_range: 0..0,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+
+        let (mut vertices, mut batches, mut index) = (Vec::new(), Vec::new(), 0);
+        batch_ui_material_items(
+            &mut items,
+            &extracted_uinodes,
+            &mut vertices,
+            |_| true,
+            |_| true,
+            &mut batches,
+            &mut index,
+        );
+
+        // Equal push constants share a batch, unequal ones start a new one
+        assert_eq!(batches.len(), 3);
+        let entities: Vec<_> = batches.iter().map(|(entity, _)| entity.index()).collect();
+        assert_eq!(entities, [0, 2, 3]);
+        let lengths: Vec<_> = items.iter().map(|item| item.batch_range.len()).collect();
+        assert_eq!(lengths, [2, 0, 1, 1]);
+        assert_eq!(batches[0].1.range.len(), 2 * batches[1].1.range.len());
+        assert_eq!(batches[2].1.range.end, index);
+        assert_eq!(batches[1].1.push_constants, 1f32.to_le_bytes());
+
+        // Each batch reads its own push constants from the uniform buffer they fall back to
+        let mut values = Vec::new();
+        layout_ui_push_constant_uniforms(&mut values, &mut batches, 256);
+        let offsets: Vec<_> = batches
+            .iter()
+            .map(|(_, batch)| batch.push_constant_offset)
+            .collect();
+        assert_eq!(offsets, [0, 256, 512]);
+        assert_eq!(values.len(), 768);
+        for (_, batch) in &batches {
+            let offset = batch.push_constant_offset as usize;
+            assert_eq!(values[offset..offset + 4], batch.push_constants);
+        }
     }
 }
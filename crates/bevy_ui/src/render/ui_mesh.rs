@@ -0,0 +1,268 @@
+use bevy_asset::{AssetId, Assets, Handle};
+use bevy_color::{Alpha, ColorToComponents};
+use bevy_ecs::prelude::*;
+use bevy_math::{Rect, Vec2, Vec3, Vec4Swizzles};
+use bevy_render::{
+    mesh::{Mesh, VertexAttributeValues},
+    render_resource::PrimitiveTopology,
+    view::ViewVisibility,
+    Extract,
+};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::HashMap;
+
+use super::{inflated_clip, shader_flags, stack_z, ExtractedUiNode, ExtractedUiNodes, UiVertex};
+use crate::{
+    BackgroundColor, CalculatedClip, DefaultUiCamera, Node, SharedColor, TargetCamera, UiAntialias,
+    UiClipInflate, UiClipMode, UiMesh,
+};
+
+/// The triangles of the [`Mesh`] of a [`UiMesh`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UiMeshGeometry {
+    /// The positions of the vertices relative to the center of the node, in units of the size of
+    /// the node, with the y axis pointing up.
+    pub positions: Vec<Vec2>,
+    /// Three indices into `positions` for each triangle.
+    pub indices: Vec<u32>,
+}
+
+impl UiMeshGeometry {
+    /// Returns the triangles of `mesh`, or `None` if it isn't a triangle list with 2D or 3D
+    /// positions.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let positions: Vec<Vec2> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+            VertexAttributeValues::Float32x2(positions) => {
+                positions.iter().copied().map(Vec2::from).collect()
+            }
+            VertexAttributeValues::Float32x3(positions) => {
+                positions.iter().map(|&[x, y, _]| Vec2::new(x, y)).collect()
+            }
+            _ => return None,
+        };
+        let indices = match mesh.indices() {
+            Some(indices) => indices.iter().map(|index| index as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        Some(Self { positions, indices })
+    }
+}
+
+/// The geometry of the meshes of the [`UiMesh`]es extracted this frame.
+#[derive(Resource, Default)]
+pub struct ExtractedUiMeshes {
+    pub meshes: HashMap<AssetId<Mesh>, UiMeshGeometry>,
+    /// Whether the geometry of the meshes changed since the previous frame, in which case the
+    /// vertices of the UI are written again.
+    pub changed: bool,
+}
+
+/// Extracts the nodes with a [`UiMesh`], drawn with their [`BackgroundColor`] instead of the
+/// background of the node.
+pub fn extract_uinode_meshes(
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    mut extracted_meshes: ResMut<ExtractedUiMeshes>,
+    default_ui_camera: Extract<DefaultUiCamera>,
+    meshes: Extract<Res<Assets<Mesh>>>,
+    shared_colors: Extract<Res<Assets<SharedColor>>>,
+    uinode_query: Extract<
+        Query<(
+            Entity,
+            &Node,
+            &GlobalTransform,
+            &ViewVisibility,
+            Option<&CalculatedClip>,
+            Option<&UiClipInflate>,
+            Option<&TargetCamera>,
+            &BackgroundColor,
+            Option<&Handle<SharedColor>>,
+            &UiMesh,
+        )>,
+    >,
+) {
+    let mut geometries = HashMap::new();
+    for (
+        entity,
+        uinode,
+        transform,
+        view_visibility,
+        clip,
+        clip_inflate,
+        camera,
+        background_color,
+        shared_color,
+        mesh,
+    ) in &uinode_query
+    {
+        let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
+        else {
+            continue;
+        };
+
+        // Skip invisible meshes
+        let color = SharedColor::resolve(background_color, shared_color, &shared_colors);
+        if !view_visibility.get() || color.is_fully_transparent() || uinode.is_empty() {
+            continue;
+        }
+
+        let mesh = mesh.0.id();
+        if !geometries.contains_key(&mesh) {
+            // Skip meshes that aren't loaded or can't be drawn
+            let Some(geometry) = meshes.get(mesh).and_then(UiMeshGeometry::from_mesh) else {
+                continue;
+            };
+            geometries.insert(mesh, geometry);
+        }
+
+        extracted_uinodes.uinodes.insert(
+            entity,
+            ExtractedUiNode {
+                stack_index: uinode.stack_index,
+                transform: transform.compute_matrix(),
+                color: color.into(),
+                rect: Rect {
+                    min: Vec2::ZERO,
+                    max: uinode.calculated_size,
+                },
+                clip: inflated_clip(clip, clip_inflate),
+                // Only the scissor clips the triangles of a mesh correctly
+                clip_mode: UiClipMode::Scissor,
+                camera_entity,
+                // The edges of the node's rect aren't the edges of the mesh
+                antialias: UiAntialias::Off,
+                mesh: Some(mesh),
+                ..Default::default()
+            },
+        );
+    }
+
+    extracted_meshes.changed = extracted_meshes.meshes != geometries;
+    extracted_meshes.meshes = geometries;
+}
+
+/// Appends the vertices and indices of the triangles of `geometry`, laid out in the rect of
+/// `extracted_uinode`, to the UI geometry, returning `false` if the mesh has no triangles.
+///
+/// This is how [`prepare_uinodes`](super::prepare_uinodes) writes the nodes with a [`UiMesh`],
+/// like [`write_ui_node_geometry`](super::write_ui_node_geometry) does for the other nodes.
+pub fn write_ui_mesh_geometry(
+    extracted_uinode: &ExtractedUiNode,
+    geometry: &UiMeshGeometry,
+    vertices: &mut Vec<UiVertex>,
+    indices: &mut Vec<u32>,
+) -> bool {
+    if geometry.indices.is_empty() {
+        return false;
+    }
+    let first_vertex = vertices.len() as u32;
+
+    let size = extracted_uinode.rect.size();
+    let z = stack_z(extracted_uinode.stack_index);
+    let flags = shader_flags::UNTEXTURED | shader_flags::NO_ANTIALIAS;
+    let color = extracted_uinode.color.to_f32_array();
+    for position in &geometry.positions {
+        // The y axis of the mesh points up, the y axis of the UI points down
+        let point = Vec2::new(position.x, -position.y) * size;
+        let mut vertex_position =
+            (extracted_uinode.transform * point.extend(0.).extend(1.)).xyz() + Vec3::Z * z;
+        if let Some(depth) = extracted_uinode.scene_depth {
+            vertex_position.z = depth;
+        }
+        vertices.push(UiVertex {
+            position: vertex_position.into(),
+            uv: [0.; 2],
+            color,
+            flags,
+            radius: [0.; 4],
+            border: [0.; 4],
+            size: size.into(),
+            point: point.into(),
+            layer: 0,
+        });
+    }
+
+    indices.extend(geometry.indices.iter().map(|index| first_vertex + index));
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::{ColorToComponents, LinearRgba};
+    use bevy_math::{
+        primitives::{Circle, Triangle2d},
+        Mat4, Rect, Vec2, Vec3,
+    };
+    use bevy_render::{
+        mesh::Mesh, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology,
+    };
+
+    use super::{write_ui_mesh_geometry, UiMeshGeometry};
+    use crate::{render::tests::base, ExtractedUiNode};
+
+    #[test]
+    fn meshes_are_laid_out_in_the_rect_of_their_node() {
+        let triangle = Mesh::from(Triangle2d::new(
+            Vec2::new(0., 0.5),
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(0.5, -0.5),
+        ));
+        let geometry = UiMeshGeometry::from_mesh(&triangle).unwrap();
+        assert_eq!(geometry.indices.len(), 3);
+
+        // A 200x100 node centered at (300, 200)
+        let uinode = ExtractedUiNode {
+            transform: Mat4::from_translation(Vec3::new(300., 200., 0.)),
+            color: LinearRgba::RED,
+            rect: Rect::new(0., 0., 200., 100.),
+            ..base()
+        };
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        assert!(write_ui_mesh_geometry(
+            &uinode,
+            &geometry,
+            &mut vertices,
+            &mut indices
+        ));
+
+        // The apex of the triangle is at the top edge of the node, the y axis of the UI points down
+        let positions: Vec<Vec2> = vertices
+            .iter()
+            .map(|vertex| Vec2::new(vertex.position[0], vertex.position[1]))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                Vec2::new(300., 150.),
+                Vec2::new(200., 250.),
+                Vec2::new(400., 250.)
+            ]
+        );
+        assert!(vertices
+            .iter()
+            .all(|vertex| vertex.color == LinearRgba::RED.to_f32_array()));
+        assert_eq!(indices, [0, 1, 2]);
+
+        // Indices are offset by the vertices already written
+        let circle = UiMeshGeometry::from_mesh(&Mesh::from(Circle::new(0.5))).unwrap();
+        assert!(write_ui_mesh_geometry(
+            &uinode,
+            &circle,
+            &mut vertices,
+            &mut indices
+        ));
+        assert_eq!(vertices.len(), 3 + circle.positions.len());
+        assert!(indices[3..].iter().all(|&index| index >= 3));
+        assert!(vertices[3..].iter().all(|vertex| {
+            (200. ..=400.).contains(&vertex.position[0])
+                && (150. ..=250.).contains(&vertex.position[1])
+        }));
+
+        // Other topologies can't be drawn
+        let lines = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default());
+        assert_eq!(UiMeshGeometry::from_mesh(&lines), None);
+    }
+}
@@ -0,0 +1,434 @@
+use std::ops::Range;
+
+use bevy_asset::*;
+use bevy_color::{ColorToComponents, LinearRgba};
+use bevy_core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy_ecs::{entity::EntityHashMap, prelude::*};
+use bevy_math::{FloatOrd, URect, UVec2, Vec4};
+use bevy_render::{
+    camera::{Camera, ExtractedCamera},
+    render_phase::*,
+    render_resource::{
+        binding_types::{sampler, texture_2d, uniform_buffer},
+        *,
+    },
+    renderer::{RenderContext, RenderDevice, RenderQueue},
+    texture::BevyDefault,
+    view::*,
+    Extract, ExtractSchedule, Render, RenderSet,
+};
+
+use crate::*;
+
+pub const UI_MODAL_BACKDROP_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(7364893146727640315);
+
+/// Draws the [`UiModalBackdrop`]s of UI nodes.
+///
+/// Each backdrop adds one item to the [`TransparentUi`] phase, just below its node. The
+/// [`UiPassNode`] splits the UI pass at these items: everything below is rendered, the main
+/// texture is blurred once into the other main texture, and the rest of the UI is rendered on top.
+pub struct UiModalBackdropPlugin;
+
+impl Plugin for UiModalBackdropPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            UI_MODAL_BACKDROP_SHADER_HANDLE,
+            "ui_modal_backdrop.wgsl",
+            Shader::from_wgsl
+        );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<ExtractedUiModalBackdrops>()
+                .init_resource::<UiModalBackdropMeta>()
+                .init_resource::<SpecializedRenderPipelines<UiModalBackdropPipeline>>()
+                .add_systems(ExtractSchedule, extract_ui_modal_backdrops)
+                .add_systems(
+                    Render,
+                    (
+                        queue_ui_modal_backdrops.in_set(RenderSet::Queue),
+                        prepare_ui_modal_backdrops.in_set(RenderSet::PrepareResources),
+                    ),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<UiModalBackdropPipeline>();
+        }
+    }
+}
+
+pub struct ExtractedUiModalBackdrop {
+    pub stack_index: u32,
+    /// Blur radius in physical pixels.
+    pub blur: f32,
+    pub tint: LinearRgba,
+    pub camera_entity: Entity,
+}
+
+#[derive(Resource, Default)]
+pub struct ExtractedUiModalBackdrops {
+    pub backdrops: EntityHashMap<ExtractedUiModalBackdrop>,
+}
+
+pub fn extract_ui_modal_backdrops(
+    mut commands: Commands,
+    mut extracted_backdrops: ResMut<ExtractedUiModalBackdrops>,
+    camera_query: Extract<Query<&Camera>>,
+    default_ui_camera: Extract<DefaultUiCamera>,
+    ui_scale: Extract<Res<UiScale>>,
+    backdrop_query: Extract<
+        Query<(
+            &Node,
+            &ViewVisibility,
+            Option<&TargetCamera>,
+            &UiModalBackdrop,
+        )>,
+    >,
+) {
+    extracted_backdrops.backdrops.clear();
+
+    for (uinode, view_visibility, camera, backdrop) in &backdrop_query {
+        let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
+        else {
+            continue;
+        };
+
+        if !view_visibility.get() {
+            continue;
+        }
+
+        let scale_factor = ui_target_scale_factor(camera_query.get(camera_entity).ok(), &ui_scale);
+
+        extracted_backdrops.backdrops.insert(
+            commands.spawn_empty().id(),
+            ExtractedUiModalBackdrop {
+                stack_index: uinode.stack_index,
+                blur: backdrop.blur.max(0.) * scale_factor,
+                tint: backdrop.tint.into(),
+                camera_entity,
+            },
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct UiModalBackdropPipeline {
+    pub layout: BindGroupLayout,
+    pub sampler: Sampler,
+}
+
+impl FromWorld for UiModalBackdropPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "ui_modal_backdrop_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    uniform_buffer::<UiModalBackdropUniform>(true),
+                ),
+            ),
+        );
+
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        UiModalBackdropPipeline { layout, sampler }
+    }
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+pub struct UiModalBackdropPipelineKey {
+    pub hdr: bool,
+}
+
+impl SpecializedRenderPipeline for UiModalBackdropPipeline {
+    type Key = UiModalBackdropPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("ui_modal_backdrop_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: UI_MODAL_BACKDROP_SHADER_HANDLE,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: if key.hdr {
+                        ViewTarget::TEXTURE_FORMAT_HDR
+                    } else {
+                        TextureFormat::bevy_default()
+                    },
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: Vec::new(),
+        }
+    }
+}
+
+#[derive(ShaderType)]
+pub struct UiModalBackdropUniform {
+    pub tint: Vec4,
+    /// The camera's viewport in physical pixels, as `(min_x, min_y, max_x, max_y)`.
+    ///
+    /// Pixels outside of it are copied unchanged.
+    pub viewport: Vec4,
+    pub blur: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct UiModalBackdropMeta {
+    uniforms: DynamicUniformBuffer<UiModalBackdropUniform>,
+    offsets: EntityHashMap<u32>,
+}
+
+impl UiModalBackdropMeta {
+    /// Returns `true` if the phase item for `entity` is a modal backdrop.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.offsets.contains_key(&entity)
+    }
+}
+
+pub fn queue_ui_modal_backdrops(
+    extracted_backdrops: Res<ExtractedUiModalBackdrops>,
+    backdrop_pipeline: Res<UiModalBackdropPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<UiModalBackdropPipeline>>,
+    mut transparent_render_phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
+    views: Query<&ExtractedView>,
+    pipeline_cache: Res<PipelineCache>,
+    draw_functions: Res<DrawFunctions<TransparentUi>>,
+) {
+    // Backdrop items keep an empty batch range so the phase never draws them,
+    // the draw function is only needed to build the item.
+    let draw_function = draw_functions.read().id::<DrawUi>();
+    for (entity, backdrop) in extracted_backdrops.backdrops.iter() {
+        let Ok(view) = views.get(backdrop.camera_entity) else {
+            continue;
+        };
+
+        let Some(transparent_phase) = transparent_render_phases.get_mut(&backdrop.camera_entity)
+        else {
+            continue;
+        };
+
+        let pipeline = pipelines.specialize(
+            &pipeline_cache,
+            &backdrop_pipeline,
+            UiModalBackdropPipelineKey { hdr: view.hdr },
+        );
+        transparent_phase.add(TransparentUi {
+            draw_function,
+            pipeline,
+            entity: *entity,
+            // Below the node and its box shadow
+            sort_key: (FloatOrd(backdrop.stack_index as f32 - 0.75), entity.index()),
+            batch_range: 0..0,
+            extra_index: PhaseItemExtraIndex::NONE,
+        });
+    }
+}
+
+pub fn prepare_ui_modal_backdrops(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut meta: ResMut<UiModalBackdropMeta>,
+    extracted_backdrops: Res<ExtractedUiModalBackdrops>,
+    cameras: Query<&ExtractedCamera>,
+) {
+    let meta = &mut *meta;
+    meta.uniforms.clear();
+    meta.offsets.clear();
+
+    for (entity, backdrop) in extracted_backdrops.backdrops.iter() {
+        let Ok(camera) = cameras.get(backdrop.camera_entity) else {
+            continue;
+        };
+        let Some(viewport) = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| {
+                URect::from_corners(
+                    viewport.physical_position,
+                    viewport.physical_position + viewport.physical_size,
+                )
+            })
+            .or(camera
+                .physical_target_size
+                .map(|size| URect::from_corners(UVec2::ZERO, size)))
+        else {
+            continue;
+        };
+
+        let offset = meta.uniforms.push(&UiModalBackdropUniform {
+            tint: backdrop.tint.to_vec4(),
+            viewport: Vec4::new(
+                viewport.min.x as f32,
+                viewport.min.y as f32,
+                viewport.max.x as f32,
+                viewport.max.y as f32,
+            ),
+            blur: backdrop.blur,
+        });
+        meta.offsets.insert(*entity, offset);
+    }
+
+    meta.uniforms.write_buffer(&render_device, &render_queue);
+}
+
+/// A part of the [`TransparentUi`] phase of a view, see [`ui_pass_segments`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UiPassSegment {
+    /// Phase items drawn in a single UI render pass.
+    Nodes(Range<usize>),
+    /// The phase item of a modal backdrop, drawn with its own blur pass.
+    ModalBackdrop(usize),
+}
+
+/// Splits the sorted items of a [`TransparentUi`] phase at each modal backdrop.
+///
+/// Every modal layer gets exactly one [`UiPassSegment::ModalBackdrop`], empty runs of nodes are
+/// skipped.
+pub fn ui_pass_segments(
+    items: impl IntoIterator<Item = Entity>,
+    is_modal_backdrop: impl Fn(Entity) -> bool,
+) -> Vec<UiPassSegment> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut len = 0;
+    for (index, entity) in items.into_iter().enumerate() {
+        len = index + 1;
+        if is_modal_backdrop(entity) {
+            if start < index {
+                segments.push(UiPassSegment::Nodes(start..index));
+            }
+            segments.push(UiPassSegment::ModalBackdrop(index));
+            start = index + 1;
+        }
+    }
+    if start < len {
+        segments.push(UiPassSegment::Nodes(start..len));
+    }
+    segments
+}
+
+/// Blurs the main texture of `target` into its other main texture for the modal backdrop `item`.
+pub(crate) fn render_ui_modal_backdrop(
+    render_context: &mut RenderContext,
+    world: &World,
+    target: &ViewTarget,
+    item: &TransparentUi,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let backdrop_pipeline = world.resource::<UiModalBackdropPipeline>();
+    let meta = world.resource::<UiModalBackdropMeta>();
+
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(item.pipeline) else {
+        return;
+    };
+    let (Some(&offset), Some(uniform_binding)) =
+        (meta.offsets.get(&item.entity), meta.uniforms.binding())
+    else {
+        return;
+    };
+
+    let post_process = target.post_process_write();
+    let bind_group = render_context.render_device().create_bind_group(
+        "ui_modal_backdrop_bind_group",
+        &backdrop_pipeline.layout,
+        &BindGroupEntries::sequential((
+            post_process.source,
+            &backdrop_pipeline.sampler,
+            uniform_binding,
+        )),
+    );
+
+    // The whole texture is written, pixels outside of the camera's viewport are copied as is.
+    let mut render_pass =
+        render_context
+            .command_encoder()
+            .begin_render_pass(&RenderPassDescriptor {
+                label: Some("ui_modal_backdrop_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: post_process.destination,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group, &[offset]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::entity::Entity;
+
+    use super::{ui_pass_segments, UiPassSegment};
+
+    #[test]
+    fn blur_pass_runs_once_per_modal_layer() {
+        let items: Vec<Entity> = (0..7).map(Entity::from_raw).collect();
+        let backdrops = [items[2], items[5]];
+
+        let segments =
+            ui_pass_segments(items.iter().copied(), |entity| backdrops.contains(&entity));
+
+        assert_eq!(
+            segments,
+            vec![
+                UiPassSegment::Nodes(0..2),
+                UiPassSegment::ModalBackdrop(2),
+                UiPassSegment::Nodes(3..5),
+                UiPassSegment::ModalBackdrop(5),
+                UiPassSegment::Nodes(6..7),
+            ]
+        );
+        let blur_passes = segments
+            .iter()
+            .filter(|segment| matches!(segment, UiPassSegment::ModalBackdrop(_)))
+            .count();
+        assert_eq!(blur_passes, backdrops.len());
+    }
+
+    #[test]
+    fn modal_layer_without_nodes_below() {
+        let items: Vec<Entity> = (0..2).map(Entity::from_raw).collect();
+
+        let segments = ui_pass_segments(items.iter().copied(), |entity| entity == items[0]);
+
+        assert_eq!(
+            segments,
+            vec![UiPassSegment::ModalBackdrop(0), UiPassSegment::Nodes(1..2)]
+        );
+    }
+
+    #[test]
+    fn no_modal_layers_renders_a_single_pass() {
+        let items: Vec<Entity> = (0..3).map(Entity::from_raw).collect();
+
+        assert_eq!(
+            ui_pass_segments(items, |_| false),
+            vec![UiPassSegment::Nodes(0..3)]
+        );
+    }
+}
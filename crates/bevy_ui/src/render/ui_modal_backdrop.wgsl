@@ -0,0 +1,48 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct UiModalBackdrop {
+    tint: vec4<f32>,
+    // (min_x, min_y, max_x, max_y) in physical pixels
+    viewport: vec4<f32>,
+    blur: f32,
+};
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> backdrop: UiModalBackdrop;
+
+// Number of samples on each side of the center, along each axis
+const TAPS: i32 = 4;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(screen_texture));
+    let point = in.position.xy;
+    let color = textureSampleLevel(screen_texture, screen_sampler, in.uv, 0.0);
+
+    if any(point < backdrop.viewport.xy) || any(backdrop.viewport.zw <= point) {
+        return color;
+    }
+
+    if backdrop.blur <= 0.0 {
+        return vec4(mix(color.rgb, backdrop.tint.rgb, backdrop.tint.a), color.a);
+    }
+
+    // Gaussian blur sampled on a grid that spans the blur radius, kept inside the viewport
+    let spacing = backdrop.blur / f32(TAPS);
+    let sigma = 0.5 * backdrop.blur;
+    var sum = vec4(0.0);
+    var total_weight = 0.0;
+    for (var x = -TAPS; x <= TAPS; x += 1) {
+        for (var y = -TAPS; y <= TAPS; y += 1) {
+            let offset = vec2(f32(x), f32(y)) * spacing;
+            let weight = exp(-dot(offset, offset) / (2.0 * sigma * sigma));
+            let sample_point = clamp(point + offset, backdrop.viewport.xy, backdrop.viewport.zw - 1.0);
+            sum += weight * textureSampleLevel(screen_texture, screen_sampler, sample_point / size, 0.0);
+            total_weight += weight;
+        }
+    }
+    let blurred = sum / total_weight;
+
+    return vec4(mix(blurred.rgb, backdrop.tint.rgb, backdrop.tint.a), blurred.a);
+}
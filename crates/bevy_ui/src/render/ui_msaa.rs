@@ -0,0 +1,146 @@
+use bevy_core_pipeline::blit::BlitPipeline;
+use bevy_ecs::prelude::*;
+use bevy_math::UVec2;
+use bevy_render::{
+    camera::ExtractedCamera,
+    render_phase::ViewSortedRenderPhases,
+    render_resource::*,
+    renderer::RenderDevice,
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    Render, RenderSet,
+};
+
+use super::ui_force_ldr::{ui_composite_pipeline_key, ExtractedUiForceLdr};
+use crate::*;
+
+/// Renders the UI of cameras with [`Msaa`] enabled with the same number of samples.
+///
+/// The main textures of a view are post-processed after its multisampled texture is resolved, so
+/// the UI can't be drawn into the multisampled main texture without losing the post-processing.
+/// Instead it is drawn into an intermediate multisampled texture of the view, resolved into a
+/// [`ViewUiMsaaTexture`] and composited over the main texture after each run of nodes. Cameras
+/// with [`Msaa::Off`] draw their UI into the main texture as before.
+pub struct UiMsaaPlugin;
+
+impl Plugin for UiMsaaPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(
+                Render,
+                prepare_ui_msaa_textures.in_set(RenderSet::PrepareResources),
+            );
+        }
+    }
+}
+
+/// Returns the number of samples the UI pipelines of a view with `msaa` are specialized with.
+pub fn ui_msaa_samples(msaa: Option<&Msaa>) -> u32 {
+    msaa.map_or(1, Msaa::samples)
+}
+
+/// Returns the multisampled texture the UI is drawn in before being resolved into a texture of
+/// `format`, or `None` if `samples` is 1.
+pub(crate) fn ui_sampled_texture(
+    render_device: &RenderDevice,
+    texture_cache: &mut TextureCache,
+    label: &'static str,
+    format: TextureFormat,
+    size: UVec2,
+    samples: u32,
+) -> Option<CachedTexture> {
+    (samples > 1).then(|| {
+        texture_cache.get(
+            render_device,
+            TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: samples,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        )
+    })
+}
+
+/// The texture the multisampled UI of a view with [`Msaa`] is resolved into.
+#[derive(Component)]
+pub struct ViewUiMsaaTexture {
+    pub texture: CachedTexture,
+    /// The multisampled texture the UI is drawn in.
+    pub sampled: CachedTexture,
+    /// Blends `texture` over the main texture of the view.
+    pub composite_pipeline: CachedRenderPipelineId,
+}
+
+pub fn prepare_ui_msaa_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    transparent_render_phases: Res<ViewSortedRenderPhases<TransparentUi>>,
+    // The UI of cameras forcing it to LDR is already drawn into an intermediate texture
+    views: Query<(Entity, &ViewTarget, &ExtractedCamera, &Msaa), Without<ExtractedUiForceLdr>>,
+) {
+    for (entity, target, camera, msaa) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+        if transparent_render_phases
+            .get(&entity)
+            .map_or(true, |phase| phase.items.is_empty())
+        {
+            continue;
+        }
+        let format = target.main_texture_format();
+        let Some(sampled) = ui_sampled_texture(
+            &render_device,
+            &mut texture_cache,
+            "ui_msaa_sampled_texture",
+            format,
+            physical_target_size,
+            msaa.samples(),
+        ) else {
+            continue;
+        };
+
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_msaa_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+
+        let composite_pipeline = blit_pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            ui_composite_pipeline_key(format),
+        );
+
+        commands.entity(entity).insert(ViewUiMsaaTexture {
+            texture,
+            sampled,
+            composite_pipeline,
+        });
+    }
+}
@@ -0,0 +1,219 @@
+use bevy_ecs::prelude::*;
+use bevy_hierarchy::Children;
+use bevy_render::Extract;
+use bevy_utils::HashMap;
+
+use super::{
+    ui_texture_slice_pipeline::ExtractedUiTextureSlices, ExtractedUiMaterialNodes, ExtractedUiNodes,
+};
+use crate::{Node, UiMaterial, UiOpacity};
+
+/// The opacities of the nodes faded by a [`UiOpacity`] on themselves or on their ancestors.
+///
+/// The extracted items of a node share its stack index, which is used to find them regardless of
+/// the entity they were extracted to.
+#[derive(Resource, Default)]
+pub struct ExtractedUiOpacities {
+    /// The opacity of each faded node, by stack index.
+    pub opacities: HashMap<u32, f32>,
+}
+
+impl ExtractedUiOpacities {
+    /// Returns the opacity of the node at `stack_index`, which is 1 for nodes that aren't faded.
+    pub fn get(&self, stack_index: u32) -> f32 {
+        self.opacities.get(&stack_index).copied().unwrap_or(1.)
+    }
+}
+
+/// Returns the opacities of the nodes with a [`UiOpacity`] and of all of their descendants, by
+/// stack index, multiplying the opacities of nested nodes together.
+pub fn ui_opacities(
+    opacity_query: &Query<(Entity, &UiOpacity)>,
+    node_query: &Query<&Node>,
+    children_query: &Query<&Children>,
+) -> HashMap<u32, f32> {
+    let mut opacities = HashMap::new();
+    for (root, opacity) in opacity_query {
+        let opacity = opacity.get();
+        let mut entities = vec![root];
+        while let Some(entity) = entities.pop() {
+            let Ok(node) = node_query.get(entity) else {
+                continue;
+            };
+            *opacities.entry(node.stack_index).or_insert(1.) *= opacity;
+            if let Ok(children) = children_query.get(entity) {
+                entities.extend(children);
+            }
+        }
+    }
+    opacities
+}
+
+/// Extracts the opacities of the nodes faded by a [`UiOpacity`], see [`ui_opacities`].
+pub fn extract_ui_opacities(
+    mut extracted_opacities: ResMut<ExtractedUiOpacities>,
+    opacity_query: Extract<Query<(Entity, &UiOpacity)>>,
+    node_query: Extract<Query<&Node>>,
+    children_query: Extract<Query<&Children>>,
+) {
+    extracted_opacities.opacities = ui_opacities(&opacity_query, &node_query, &children_query);
+}
+
+/// Fades the colors of the [`ExtractedUiNodes`] by their [`UiOpacity`], dropping the nodes that
+/// are fully transparent.
+pub fn fade_extracted_uinodes(
+    opacities: Res<ExtractedUiOpacities>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+) {
+    if opacities.opacities.is_empty() {
+        return;
+    }
+
+    extracted_uinodes.uinodes.retain(|_, uinode| {
+        // The shapes of stencil masks aren't drawn, and must stay balanced
+        if uinode.stencil.writes() {
+            return true;
+        }
+        let opacity = opacities.get(uinode.stack_index);
+        uinode.fade(opacity);
+        opacity > 0.
+    });
+}
+
+/// Fades the colors of the [`ExtractedUiTextureSlices`] by their [`UiOpacity`], dropping the
+/// slices that are fully transparent.
+pub fn fade_extracted_ui_texture_slices(
+    opacities: Res<ExtractedUiOpacities>,
+    mut extracted_slices: ResMut<ExtractedUiTextureSlices>,
+) {
+    if opacities.opacities.is_empty() {
+        return;
+    }
+
+    let mut transparent_slices = Vec::new();
+    for (entity, slice) in extracted_slices.slices.iter_mut() {
+        let opacity = opacities.get(slice.stack_index);
+        slice.color.alpha *= opacity;
+        if opacity == 0. {
+            transparent_slices.push(*entity);
+        }
+    }
+    for entity in transparent_slices {
+        extracted_slices.slices.remove(entity);
+    }
+}
+
+/// Multiplies the opacity of the [`ExtractedUiMaterialNodes`] of type `M` by their [`UiOpacity`],
+/// dropping the nodes that are fully transparent.
+pub fn fade_extracted_ui_material_nodes<M: UiMaterial>(
+    opacities: Res<ExtractedUiOpacities>,
+    mut extracted_uinodes: ResMut<ExtractedUiMaterialNodes<M>>,
+) {
+    if opacities.opacities.is_empty() {
+        return;
+    }
+
+    let mut transparent_uinodes = Vec::new();
+    for (entity, uinode) in extracted_uinodes.uinodes.iter_mut() {
+        let opacity = opacities.get(uinode.stack_index);
+        uinode.opacity *= opacity;
+        if opacity == 0. {
+            transparent_uinodes.push(*entity);
+        }
+    }
+    for entity in transparent_uinodes {
+        extracted_uinodes.uinodes.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::{Alpha, LinearRgba};
+    use bevy_ecs::{
+        entity::Entity,
+        system::{Query, RunSystemOnce},
+        world::World,
+    };
+    use bevy_hierarchy::{BuildChildren, Children};
+    use bevy_utils::HashMap;
+
+    use super::{fade_extracted_uinodes, ui_opacities, ExtractedUiOpacities};
+    use crate::{
+        render::tests::base, ExtractedUiNode, ExtractedUiNodes, Node, NodeType, UiOpacity,
+    };
+
+    fn opacities(world: &mut World) -> HashMap<u32, f32> {
+        world.run_system_once(
+            |opacity_query: Query<(Entity, &UiOpacity)>,
+             node_query: Query<&Node>,
+             children_query: Query<&Children>| {
+                ui_opacities(&opacity_query, &node_query, &children_query)
+            },
+        )
+    }
+
+    #[test]
+    fn nodes_fade_with_their_ancestors() {
+        let mut world = World::new();
+        let node = |stack_index| Node {
+            stack_index,
+            ..Default::default()
+        };
+        // root -> (panel -> (child -> faded grandchild), sibling)
+        let root = world.spawn(node(0)).id();
+        let panel = world.spawn(node(1)).set_parent(root).id();
+        let child = world.spawn(node(2)).set_parent(panel).id();
+        world.spawn((node(3), UiOpacity(0.5))).set_parent(child);
+        world.spawn(node(4)).set_parent(root);
+
+        // Fade the panel out, the nested opacity of the grandchild multiplies the panel's
+        for (panel_opacity, expected) in [(1., 1.), (0.5, 0.5), (0., 0.)] {
+            world.entity_mut(panel).insert(UiOpacity(panel_opacity));
+            let opacities = opacities(&mut world);
+            assert!(!opacities.contains_key(&0));
+            assert_eq!(opacities[&1], expected);
+            assert_eq!(opacities[&2], expected);
+            assert_eq!(opacities[&3], expected * 0.5);
+            assert!(!opacities.contains_key(&4));
+        }
+
+        // Opacities are clamped
+        world.entity_mut(panel).insert(UiOpacity(2.));
+        assert_eq!(opacities(&mut world)[&1], 1.);
+        world.entity_mut(panel).insert(UiOpacity(-1.));
+        assert_eq!(opacities(&mut world)[&1], 0.);
+    }
+
+    #[test]
+    fn fully_transparent_nodes_are_dropped() {
+        let mut world = World::new();
+        let uinode = ExtractedUiNode {
+            color: LinearRgba::RED.with_alpha(0.5),
+            border_side: Some(0),
+            node_type: NodeType::Border,
+            ..base()
+        };
+        let entity = world.spawn_empty().id();
+
+        // Fade the node from 1 to 0
+        for opacity in [1., 0.5, 0.] {
+            let mut extracted_uinodes = ExtractedUiNodes::default();
+            extracted_uinodes
+                .uinodes
+                .insert(entity, ExtractedUiNode { ..uinode });
+            world.insert_resource(extracted_uinodes);
+            world.insert_resource(ExtractedUiOpacities {
+                opacities: HashMap::from_iter([(0, opacity)]),
+            });
+            world.run_system_once(fade_extracted_uinodes);
+
+            let extracted_uinodes = world.resource::<ExtractedUiNodes>();
+            match extracted_uinodes.uinodes.get(&entity) {
+                Some(faded) => {
+                    assert_eq!(faded.color.alpha, 0.5 * opacity);
+                }
+                None => assert_eq!(opacity, 0.),
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_render::{
+    camera::ExtractedCamera,
+    render_resource::*,
+    renderer::RenderDevice,
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    Render, RenderSet,
+};
+
+use super::{
+    ui_force_ldr::ExtractedUiForceLdr, ui_modal_backdrop::ExtractedUiModalBackdrops,
+    ui_msaa::ui_msaa_samples, ui_pass_layer::ExtractedUiSceneDepths,
+    ui_stencil_mask::ExtractedUiStencilMasks,
+};
+use crate::*;
+
+/// Draws the nodes of opaque [`UiMaterial`]s front to back in the [`OpaqueUi`] phase.
+///
+/// Views with such nodes get a [`ViewUiDepthTexture`], shared by every UI pipeline of the view.
+/// Nodes are offset along the z-axis by their stack index, see [`UI_STACK_Z_STEP`], so the
+/// opaque phase writes the depth of the topmost opaque node of each pixel, and fragments of
+/// opaque nodes below it are discarded by the depth test instead of being blended. The
+/// [`TransparentUi`] phase is then drawn back to front as before, testing against that depth
+/// without writing it, so transparent nodes below an opaque one are hidden by it.
+///
+/// Views whose UI is drawn to a multisampled or LDR intermediate texture, views with a
+/// [`UiModalBackdrop`], which blurs the nodes drawn before it, and views with a
+/// [`UiStencilMask`], which are drawn with a stencil texture instead, don't have a depth texture:
+/// their opaque nodes are drawn in the [`TransparentUi`] phase.
+pub struct UiOpaquePlugin;
+
+impl Plugin for UiOpaquePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<UiDepthViews>()
+                .allow_ambiguous_resource::<UiDepthViews>()
+                .add_systems(
+                    Render,
+                    (
+                        resolve_ui_depth_views.in_set(RenderSet::ManageViews),
+                        prepare_ui_depth_textures.in_set(RenderSet::PrepareResources),
+                    ),
+                );
+        }
+    }
+}
+
+/// The cameras whose UI is drawn with a [`ViewUiDepthTexture`] this frame.
+///
+/// Cameras with opaque nodes are requested when the nodes are extracted, and the request is
+/// granted in [`RenderSet::ManageViews`] if the UI of the camera supports a depth texture, see
+/// [`ui_view_supports_depth`]. UI pipelines queued for these cameras must be specialized with a
+/// depth state, see [`ui_depth_stencil_state`].
+#[derive(Resource, Default)]
+pub struct UiDepthViews {
+    requested: EntityHashSet,
+    cameras: EntityHashSet,
+}
+
+impl UiDepthViews {
+    /// Requests a depth texture for the UI of `camera`, because it has opaque nodes.
+    pub fn request(&mut self, camera: Entity) {
+        self.requested.insert(camera);
+    }
+
+    /// Returns `true` if the UI of `camera` is drawn with a depth texture.
+    pub fn contains(&self, camera: Entity) -> bool {
+        self.cameras.contains(&camera)
+    }
+}
+
+/// Returns whether the UI of a camera can be drawn with a [`ViewUiDepthTexture`].
+///
+/// The opaque phase is drawn straight to the main texture, so the UI must not be drawn to a
+/// forced LDR texture or with more than one sample, and a modal backdrop would blur the opaque
+/// nodes above it. The UI of a view with stencil masks is drawn with a stencil texture instead.
+pub fn ui_view_supports_depth(
+    force_ldr: bool,
+    samples: u32,
+    has_modal_backdrop: bool,
+    has_stencil_masks: bool,
+) -> bool {
+    !force_ldr && samples == 1 && !has_modal_backdrop && !has_stencil_masks
+}
+
+/// Grants the depth textures requested for the UI of each camera this frame.
+pub fn resolve_ui_depth_views(
+    mut depth_views: ResMut<UiDepthViews>,
+    backdrops: Res<ExtractedUiModalBackdrops>,
+    stencil_masks: Res<ExtractedUiStencilMasks>,
+    scene_depths: Res<ExtractedUiSceneDepths>,
+    views: Query<(Has<ExtractedUiForceLdr>, Option<&Msaa>), With<ExtractedView>>,
+) {
+    let UiDepthViews { requested, cameras } = &mut *depth_views;
+    cameras.clear();
+    cameras.extend(requested.drain().filter(|camera| {
+        let Ok((force_ldr, msaa)) = views.get(*camera) else {
+            return false;
+        };
+        // The UI of in-scene cameras is tested against the depth of their scene instead
+        if scene_depths.cameras.contains(camera) {
+            return false;
+        }
+        let has_modal_backdrop = backdrops
+            .backdrops
+            .values()
+            .any(|backdrop| backdrop.camera_entity == *camera);
+        ui_view_supports_depth(
+            force_ldr,
+            ui_msaa_samples(msaa),
+            has_modal_backdrop,
+            stencil_masks.cameras.contains(camera),
+        )
+    }));
+}
+
+/// Returns the depth state of a UI pipeline drawn with a [`ViewUiDepthTexture`].
+///
+/// The [`OpaqueUi`] phase writes depth, the [`TransparentUi`] phase only tests against it.
+pub fn ui_depth_stencil_state(
+    depth_write_enabled: bool,
+    depth_compare: CompareFunction,
+) -> DepthStencilState {
+    DepthStencilState {
+        format: UI_PREPASS_DEPTH_FORMAT,
+        depth_write_enabled,
+        depth_compare,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+    }
+}
+
+/// The depth texture the UI of a view in [`UiDepthViews`] is drawn with.
+///
+/// Cleared to `1.0` (the far plane) by the [`UiPassNode`] before drawing the [`OpaqueUi`] phase.
+#[derive(Component)]
+pub struct ViewUiDepthTexture {
+    pub depth: CachedTexture,
+}
+
+pub fn prepare_ui_depth_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    depth_views: Res<UiDepthViews>,
+    views: Query<(Entity, &ExtractedCamera)>,
+) {
+    for (entity, camera) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+        if !depth_views.contains(entity) {
+            commands.entity(entity).remove::<ViewUiDepthTexture>();
+            continue;
+        }
+
+        let depth = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_depth_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: UI_PREPASS_DEPTH_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        );
+
+        commands.entity(entity).insert(ViewUiDepthTexture { depth });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{entity::Entity, world::World};
+    use bevy_math::FloatOrd;
+    use bevy_render::{
+        render_phase::{
+            Draw, DrawError, DrawFunctions, PhaseItemExtraIndex, SortedPhaseItem, TrackedRenderPass,
+        },
+        render_resource::CachedRenderPipelineId,
+    };
+
+    use super::ui_view_supports_depth;
+    use crate::OpaqueUi;
+
+    #[test]
+    fn opaque_phase_is_sorted_front_to_back() {
+        struct NoopDraw;
+        impl Draw<OpaqueUi> for NoopDraw {
+            fn draw<'w>(
+                &mut self,
+                _world: &'w World,
+                _pass: &mut TrackedRenderPass<'w>,
+                _view: Entity,
+                _item: &OpaqueUi,
+            ) -> Result<(), DrawError> {
+                Ok(())
+            }
+        }
+        let draw_function = DrawFunctions::<OpaqueUi>::default().write().add(NoopDraw);
+        let item = |stack_index: u32| OpaqueUi {
+            sort_key: (FloatOrd(stack_index as f32), stack_index),
+            entity: Entity::PLACEHOLDER,
+            pipeline: CachedRenderPipelineId::INVALID,
+            draw_function,
+            batch_range: 0..0,
+            extra_index: PhaseItemExtraIndex::NONE,
+        };
+        let mut items = vec![item(3), item(10), item(0), item(7)];
+        OpaqueUi::sort(&mut items);
+
+        // The topmost node is drawn first, so it hides the nodes below it
+        let order: Vec<_> = items.iter().map(|item| item.sort_key.1).collect();
+        assert_eq!(order, [10, 7, 3, 0]);
+    }
+
+    #[test]
+    fn only_single_sampled_views_without_backdrops_have_a_depth_texture() {
+        assert!(ui_view_supports_depth(false, 1, false, false));
+        assert!(!ui_view_supports_depth(true, 1, false, false));
+        assert!(!ui_view_supports_depth(false, 4, false, false));
+        assert!(!ui_view_supports_depth(false, 1, true, false));
+        assert!(!ui_view_supports_depth(false, 1, false, true));
+    }
+}
@@ -0,0 +1,310 @@
+use bevy_core_pipeline::core_3d::CORE_3D_DEPTH_FORMAT;
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_hierarchy::Children;
+use bevy_render::{
+    camera::Camera,
+    render_graph::{self, NodeRunError, RenderGraphContext},
+    render_resource::*,
+    renderer::RenderContext,
+    view::Msaa,
+    Extract, ExtractSchedule,
+};
+use bevy_utils::{warn_once, HashMap};
+
+use super::{
+    graph::SubGraphUi, ui_msaa::ui_msaa_samples, ui_stencil_mask::stencil_extracted_uinodes,
+    ExtractedUiNodes, RenderUiSystem,
+};
+use crate::*;
+
+/// Draws the UI of [`UiPassLayer::InScene`] cameras between the opaque and transparent passes of
+/// their scene.
+///
+/// The UI sub graph runs from two nodes of the camera's render graph, [`NodeUi::UiPass`] after
+/// the post-processing of the scene and [`NodeUi::UiInScenePass`] after its opaque pass, each one
+/// only for the cameras of its layer. The extracted items of in-scene cameras get the depth of
+/// their [`UiSceneDepth`], the items drawn by the [`UiPipeline`] are recorded in
+/// [`UiSceneDepthItems`] when they're queued, and the [`UiPassNode`] draws each run of them tested
+/// against the [`ViewDepthTexture`](bevy_render::view::ViewDepthTexture) of the scene.
+///
+/// [`NodeUi::UiPass`]: super::graph::NodeUi::UiPass
+/// [`NodeUi::UiInScenePass`]: super::graph::NodeUi::UiInScenePass
+pub struct UiPassLayerPlugin;
+
+impl Plugin for UiPassLayerPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<ExtractedUiSceneDepths>()
+                .init_resource::<UiSceneDepthItems>()
+                .allow_ambiguous_resource::<UiSceneDepthItems>()
+                .add_systems(
+                    ExtractSchedule,
+                    (
+                        clear_ui_scene_depth_items,
+                        extract_ui_scene_depths.in_set(RenderUiSystem::ExtractBackgrounds),
+                        scene_depth_extracted_uinodes
+                            .after(extract_ui_scene_depths)
+                            .after(RenderUiSystem::ExtractText)
+                            .after(stencil_extracted_uinodes),
+                    ),
+                );
+        }
+    }
+}
+
+/// The depth of the near plane of the reverse-z projections of the scene, where the nodes of an
+/// in-scene camera without a [`UiSceneDepth`] are drawn.
+pub const UI_SCENE_NEAR_DEPTH: f32 = 1.0;
+
+/// The depths of the nodes drawn in a scene this frame.
+///
+/// The extracted items of a node share its stack index, which is used to find them regardless of
+/// the entity they were extracted to.
+#[derive(Resource, Default)]
+pub struct ExtractedUiSceneDepths {
+    /// The depth of the nodes with a [`UiSceneDepth`] and of their descendants, by stack index.
+    pub depths: HashMap<u32, f32>,
+    /// The cameras drawing their UI in their scene, see [`UiPassLayer::InScene`].
+    pub cameras: EntityHashSet,
+}
+
+/// Returns whether the UI of a [`UiPassLayer::InScene`] camera can be drawn in its scene.
+///
+/// The in-scene nodes are drawn straight to the main texture, so the UI must not be drawn to a
+/// forced LDR texture or with more than one sample.
+pub fn ui_view_supports_scene_depth(force_ldr: bool, samples: u32) -> bool {
+    !force_ldr && samples == 1
+}
+
+/// Returns the depth of each descendant of the nodes with a [`UiSceneDepth`], by stack index.
+///
+/// Nodes inherit the depth of their closest ancestor with a [`UiSceneDepth`].
+pub fn ui_scene_depths(
+    roots: impl IntoIterator<Item = Entity>,
+    node_query: &Query<(&Node, Option<&UiSceneDepth>)>,
+    children_query: &Query<&Children>,
+) -> HashMap<u32, f32> {
+    let mut depths = HashMap::new();
+    for root in roots {
+        let Ok((_, Some(&UiSceneDepth(depth)))) = node_query.get(root) else {
+            continue;
+        };
+        let mut entities = vec![root];
+        while let Some(entity) = entities.pop() {
+            let Ok((node, scene_depth)) = node_query.get(entity) else {
+                continue;
+            };
+            // Nested depths are walked from their own node
+            if entity != root && scene_depth.is_some() {
+                continue;
+            }
+            depths.insert(node.stack_index, depth);
+            if let Ok(children) = children_query.get(entity) {
+                entities.extend(children);
+            }
+        }
+    }
+    depths
+}
+
+/// Extracts the cameras drawing their UI in their scene, and the depths of the nodes.
+pub fn extract_ui_scene_depths(
+    mut extracted_depths: ResMut<ExtractedUiSceneDepths>,
+    camera_query: Extract<
+        Query<(
+            Entity,
+            &Camera,
+            &UiPassLayer,
+            Option<&Msaa>,
+            Has<UiForceLdr>,
+        )>,
+    >,
+    root_query: Extract<Query<Entity, With<UiSceneDepth>>>,
+    node_query: Extract<Query<(&Node, Option<&UiSceneDepth>)>>,
+    children_query: Extract<Query<&Children>>,
+) {
+    extracted_depths.cameras.clear();
+    extracted_depths.depths.clear();
+
+    for (entity, camera, layer, msaa, force_ldr) in &camera_query {
+        if !camera.is_active || *layer != UiPassLayer::InScene {
+            continue;
+        }
+        // LDR cameras are never forced to an intermediate texture
+        if !ui_view_supports_scene_depth(force_ldr && camera.hdr, ui_msaa_samples(msaa)) {
+            warn_once!(
+                "The UI of cameras with MSAA or a `UiForceLdr` can't be drawn in their scene, it \
+                is drawn as an overlay instead"
+            );
+            continue;
+        }
+        extracted_depths.cameras.insert(entity);
+    }
+
+    if !extracted_depths.cameras.is_empty() {
+        extracted_depths.depths = ui_scene_depths(root_query.iter(), &node_query, &children_query);
+    }
+}
+
+/// Sets the [`ExtractedUiNode::scene_depth`] of the items drawn in their camera's scene.
+///
+/// The shapes of the [`UiStencilMask`] nodes, and the nodes they mask, are drawn over the scene.
+pub fn scene_depth_extracted_uinodes(
+    extracted_depths: Res<ExtractedUiSceneDepths>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+) {
+    if extracted_depths.cameras.is_empty() {
+        return;
+    }
+
+    for uinode in extracted_uinodes.uinodes.values_mut() {
+        if uinode.stencil != UiStencil::None
+            || !extracted_depths.cameras.contains(&uinode.camera_entity)
+        {
+            continue;
+        }
+        uinode.scene_depth = Some(
+            extracted_depths
+                .depths
+                .get(&uinode.stack_index)
+                .copied()
+                .unwrap_or(UI_SCENE_NEAR_DEPTH),
+        );
+    }
+}
+
+/// The [`TransparentUi`] phase items tested against the depth of their scene this frame.
+#[derive(Resource, Default)]
+pub struct UiSceneDepthItems {
+    entities: EntityHashSet,
+}
+
+impl UiSceneDepthItems {
+    pub fn insert(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    /// Returns `true` if the phase item for `entity` is tested against the depth of its scene.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+pub fn clear_ui_scene_depth_items(mut scene_depth_items: ResMut<UiSceneDepthItems>) {
+    scene_depth_items.entities.clear();
+}
+
+/// Returns the depth state of a UI pipeline drawn in the scene of its view.
+///
+/// Nodes are drawn where they're closer than the scene, without writing its depth, so the
+/// transparent objects drawn after them are still tested against the opaque ones.
+pub fn ui_scene_depth_state() -> DepthStencilState {
+    DepthStencilState {
+        format: CORE_3D_DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::GreaterEqual,
+        stencil: StencilState::default(),
+        bias: DepthBiasState::default(),
+    }
+}
+
+/// Runs the UI sub graph for the views whose UI is drawn in `layer`.
+pub struct RunUiGraphNode {
+    layer: UiPassLayer,
+}
+
+impl RunUiGraphNode {
+    pub fn new(layer: UiPassLayer) -> Self {
+        Self { layer }
+    }
+}
+
+impl render_graph::Node for RunUiGraphNode {
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        _render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.view_entity();
+        let in_scene = world
+            .get_resource::<ExtractedUiSceneDepths>()
+            .is_some_and(|depths| depths.cameras.contains(&view_entity));
+        let layer = if in_scene {
+            UiPassLayer::InScene
+        } else {
+            UiPassLayer::Overlay
+        };
+        if layer == self.layer {
+            graph.run_sub_graph(SubGraphUi, vec![], Some(view_entity))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        entity::Entity,
+        query::With,
+        system::{Query, RunSystemOnce},
+        world::World,
+    };
+    use bevy_hierarchy::{BuildChildren, Children};
+    use bevy_render::render_resource::CompareFunction;
+    use bevy_utils::HashMap;
+
+    use super::{ui_scene_depth_state, ui_scene_depths, ui_view_supports_scene_depth};
+    use crate::{Node, UiSceneDepth};
+
+    #[test]
+    fn nodes_inherit_the_scene_depth_of_their_closest_ancestor() {
+        let mut world = World::new();
+        let node = |stack_index| Node {
+            stack_index,
+            ..Default::default()
+        };
+        // root -> (bar -> (fill -> marker -> label), sibling)
+        let root = world.spawn(node(0)).id();
+        let bar = world
+            .spawn((node(1), UiSceneDepth(0.25)))
+            .set_parent(root)
+            .id();
+        let fill = world.spawn(node(2)).set_parent(bar).id();
+        let marker = world
+            .spawn((node(3), UiSceneDepth(0.5)))
+            .set_parent(fill)
+            .id();
+        world.spawn(node(4)).set_parent(marker);
+        world.spawn(node(5)).set_parent(root);
+
+        let depths: HashMap<u32, f32> = world.run_system_once(
+            |root_query: Query<Entity, With<UiSceneDepth>>,
+             node_query: Query<(&Node, Option<&UiSceneDepth>)>,
+             children_query: Query<&Children>| {
+                ui_scene_depths(&root_query, &node_query, &children_query)
+            },
+        );
+
+        assert!(!depths.contains_key(&0));
+        assert_eq!(depths[&1], 0.25);
+        assert_eq!(depths[&2], 0.25);
+        assert_eq!(depths[&3], 0.5);
+        assert_eq!(depths[&4], 0.5);
+        assert!(!depths.contains_key(&5));
+    }
+
+    #[test]
+    fn in_scene_nodes_are_tested_against_the_reverse_z_scene_depth() {
+        assert!(ui_view_supports_scene_depth(false, 1));
+        assert!(!ui_view_supports_scene_depth(true, 1));
+        assert!(!ui_view_supports_scene_depth(false, 4));
+
+        // Closer points have a greater depth, and the scene's depth is left for its transparent
+        // objects
+        let state = ui_scene_depth_state();
+        assert_eq!(state.depth_compare, CompareFunction::GreaterEqual);
+        assert!(!state.depth_write_enabled);
+    }
+}
@@ -0,0 +1,125 @@
+use bevy_asset::*;
+use bevy_math::{Rect, Vec4};
+use bevy_reflect::prelude::*;
+use bevy_render::{
+    render_asset::RenderAssets,
+    render_resource::*,
+    texture::{GpuImage, Image},
+};
+
+use crate::*;
+
+pub const PIXELATE_UI_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(6120945828716408139);
+
+/// Adds the built-in [`PixelateUiMaterial`].
+pub struct PixelateUiMaterialPlugin;
+
+impl Plugin for PixelateUiMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            PIXELATE_UI_MATERIAL_SHADER_HANDLE,
+            "ui_pixelate_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(UiMaterialPlugin::<PixelateUiMaterial>::default())
+            .register_asset_reflect::<PixelateUiMaterial>();
+    }
+}
+
+/// A [`UiMaterial`] drawing a texture as a mosaic of square blocks, for a retro look or to censor
+/// part of an image.
+///
+/// Each block of `block_size` physical pixels of the node is filled with the texture color at its
+/// center. `region` is the part of the texture stretched over the node, in UV coordinates, so a
+/// node placed over part of an image can pixelate just that part.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[uniform(0, PixelateUiMaterialUniform)]
+pub struct PixelateUiMaterial {
+    pub block_size: f32,
+    pub region: Rect,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+}
+
+impl PixelateUiMaterial {
+    /// Pixelates the whole `texture` into blocks of `block_size` physical pixels.
+    pub fn new(texture: Handle<Image>, block_size: f32) -> Self {
+        Self {
+            block_size,
+            texture,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for PixelateUiMaterial {
+    fn default() -> Self {
+        Self {
+            block_size: 8.,
+            region: Rect::new(0., 0., 1., 1.),
+            texture: Handle::default(),
+        }
+    }
+}
+
+/// The GPU representation of the uniform data of a [`PixelateUiMaterial`].
+#[derive(Clone, Default, ShaderType)]
+pub struct PixelateUiMaterialUniform {
+    /// The region of the texture, its min corner in `xy` and max corner in `zw`.
+    pub region: Vec4,
+    pub block_size: f32,
+}
+
+impl AsBindGroupShaderType<PixelateUiMaterialUniform> for PixelateUiMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<GpuImage>,
+    ) -> PixelateUiMaterialUniform {
+        PixelateUiMaterialUniform {
+            region: self
+                .region
+                .min
+                .extend(self.region.max.x)
+                .extend(self.region.max.y),
+            block_size: self.block_size.max(1.),
+        }
+    }
+}
+
+impl UiMaterial for PixelateUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        PIXELATE_UI_MATERIAL_SHADER_HANDLE.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::Handle;
+    use bevy_math::{Rect, Vec4};
+    use bevy_render::{render_asset::RenderAssets, render_resource::AsBindGroupShaderType};
+
+    use super::PixelateUiMaterial;
+
+    #[test]
+    fn block_size_and_texture_region_reach_the_shader() {
+        let material = PixelateUiMaterial {
+            region: Rect::new(0.5, 0., 1., 0.5),
+            ..PixelateUiMaterial::new(Handle::default(), 10.)
+        };
+        let uniform = material.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.block_size, 10.);
+        // The min corner of the region in `xy` and its max corner in `zw`
+        assert_eq!(uniform.region, Vec4::new(0.5, 0., 1., 0.5));
+
+        // Blocks are at least a pixel wide, so the shader never divides by zero
+        let material = PixelateUiMaterial::new(Handle::default(), 0.);
+        let uniform = material.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.block_size, 1.);
+        assert_eq!(uniform.region, Vec4::new(0., 0., 1., 1.));
+    }
+}
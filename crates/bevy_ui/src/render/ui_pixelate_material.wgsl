@@ -0,0 +1,22 @@
+#import bevy_ui::{
+    ui_material_mask::ui_material_mask,
+    ui_vertex_output::UiVertexOutput,
+}
+
+struct PixelateUiMaterial {
+    // The region of the texture, its min corner in `xy` and max corner in `zw`.
+    region: vec4<f32>,
+    block_size: f32,
+}
+
+@group(1) @binding(0) var<uniform> material: PixelateUiMaterial;
+@group(1) @binding(1) var material_texture: texture_2d<f32>;
+@group(1) @binding(2) var material_sampler: sampler;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let block_center = (floor(in.uv * in.size / material.block_size) + 0.5) * material.block_size / in.size;
+    let uv = material.region.xy + min(block_center, vec2(1.0)) * (material.region.zw - material.region.xy);
+    let color = textureSample(material_texture, material_sampler, uv);
+    return vec4(color.rgb, color.a * in.opacity * ui_material_mask(in));
+}
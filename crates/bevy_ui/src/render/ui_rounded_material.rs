@@ -0,0 +1,106 @@
+use bevy_asset::*;
+use bevy_color::{Color, ColorToComponents, LinearRgba};
+use bevy_math::Vec4;
+use bevy_reflect::prelude::*;
+use bevy_render::{render_asset::RenderAssets, render_resource::*, texture::GpuImage};
+
+use crate::*;
+
+pub const ROUNDED_UI_MATERIAL_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(4411735302952681371);
+
+/// Adds the built-in [`RoundedUiMaterial`].
+pub struct RoundedUiMaterialPlugin;
+
+impl Plugin for RoundedUiMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            ROUNDED_UI_MATERIAL_SHADER_HANDLE,
+            "ui_rounded_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(UiMaterialPlugin::<RoundedUiMaterial>::default())
+            .register_asset_reflect::<RoundedUiMaterial>();
+    }
+}
+
+/// A [`UiMaterial`] filling a node with a color, with rounded and anti-aliased corners.
+///
+/// `radius` holds the radius of each corner in pixels, ordered top left, top right, bottom right
+/// and bottom left like [`BorderRadius`]. The shader reads the size of the node from the `size`
+/// every [`UiMaterialVertex`](crate::UiMaterialVertex) carries, so the same material can be shared
+/// by nodes of any size. Each radius is clamped to half the smallest dimension of the node, so a
+/// large radius turns a square node into a circle and the short sides of a rectangular node into
+/// half circles.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[reflect(Default, Debug)]
+#[uniform(0, RoundedUiMaterialUniform)]
+pub struct RoundedUiMaterial {
+    pub color: Color,
+    pub radius: Vec4,
+}
+
+impl RoundedUiMaterial {
+    /// A material of `color` with every corner rounded by `radius`.
+    pub fn new(color: impl Into<Color>, radius: f32) -> Self {
+        Self {
+            color: color.into(),
+            radius: Vec4::splat(radius),
+        }
+    }
+}
+
+impl Default for RoundedUiMaterial {
+    fn default() -> Self {
+        Self::new(Color::WHITE, 0.)
+    }
+}
+
+/// The GPU representation of the uniform data of a [`RoundedUiMaterial`].
+#[derive(Clone, Default, ShaderType)]
+pub struct RoundedUiMaterialUniform {
+    pub color: Vec4,
+    /// The radius of the top left, top right, bottom right and bottom left corners.
+    pub radius: Vec4,
+}
+
+impl AsBindGroupShaderType<RoundedUiMaterialUniform> for RoundedUiMaterial {
+    fn as_bind_group_shader_type(
+        &self,
+        _images: &RenderAssets<GpuImage>,
+    ) -> RoundedUiMaterialUniform {
+        RoundedUiMaterialUniform {
+            color: LinearRgba::from(self.color).to_vec4(),
+            radius: self.radius.max(Vec4::ZERO),
+        }
+    }
+}
+
+impl UiMaterial for RoundedUiMaterial {
+    fn fragment_shader() -> ShaderRef {
+        ROUNDED_UI_MATERIAL_SHADER_HANDLE.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::{palettes::css::ORANGE, ColorToComponents, LinearRgba};
+    use bevy_math::Vec4;
+    use bevy_render::{render_asset::RenderAssets, render_resource::AsBindGroupShaderType};
+
+    use super::RoundedUiMaterial;
+
+    #[test]
+    fn negative_radii_are_clamped_in_the_uniform() {
+        let material = RoundedUiMaterial {
+            radius: Vec4::new(10., 100., -5., 25.),
+            ..RoundedUiMaterial::new(ORANGE, 0.)
+        };
+        let uniform = material.as_bind_group_shader_type(&RenderAssets::default());
+        assert_eq!(uniform.color, LinearRgba::from(ORANGE).to_vec4());
+        // Radii larger than the node are clamped by the shader, which knows the size of each node
+        assert_eq!(uniform.radius, Vec4::new(10., 100., 0., 25.));
+    }
+}
@@ -0,0 +1,24 @@
+#import bevy_ui::{
+    ui_material_mask::ui_material_mask,
+    ui_vertex_output::UiVertexOutput,
+}
+
+struct RoundedUiMaterial {
+    color: vec4<f32>,
+    // The radius of the top left, top right, bottom right and bottom left corners.
+    radius: vec4<f32>,
+}
+
+@group(1) @binding(0) var<uniform> material: RoundedUiMaterial;
+
+@fragment
+fn fragment(in: UiVertexOutput) -> @location(0) vec4<f32> {
+    let point = (in.uv - 0.5) * in.size;
+    let radii = min(material.radius, vec4(0.5 * min(in.size.x, in.size.y)));
+    let rs = select(radii.xy, radii.wz, 0.0 < point.y);
+    let radius = select(rs.x, rs.y, 0.0 < point.x);
+    let q = abs(point) - 0.5 * in.size + radius;
+    let distance = length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    let coverage = saturate(0.5 - distance);
+    return vec4(material.color.rgb, material.color.a * coverage * in.opacity * ui_material_mask(in));
+}
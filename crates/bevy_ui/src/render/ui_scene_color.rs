@@ -0,0 +1,284 @@
+use bevy_core_pipeline::blit::{BlitPipeline, BlitPipelineKey};
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_render::{
+    camera::ExtractedCamera,
+    globals::GlobalsUniform,
+    render_resource::{
+        binding_types::{sampler, texture_2d, uniform_buffer},
+        *,
+    },
+    renderer::{RenderContext, RenderDevice},
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    ExtractSchedule, Render, RenderSet,
+};
+
+use crate::*;
+
+/// Gives the nodes of [`UiMaterial`]s with [`UiMaterial::reads_view_transform`] access to the
+/// view of the scene below the UI.
+///
+/// Views with such nodes are recorded in [`UiSceneColorViews`] when they're queued. Before the
+/// [`UiPassNode`] draws any UI, the main texture of these views is copied to their
+/// [`ViewUiSceneColorTexture`], which the materials sample along with the view uniform of the
+/// camera.
+pub struct UiSceneColorPlugin;
+
+impl Plugin for UiSceneColorPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<UiSceneColorViews>()
+                .allow_ambiguous_resource::<UiSceneColorViews>()
+                .add_systems(ExtractSchedule, clear_ui_scene_color_views)
+                .add_systems(
+                    Render,
+                    prepare_ui_scene_color_textures.in_set(RenderSet::PrepareResources),
+                );
+        }
+    }
+}
+
+/// Links the view drawing the UI of a camera to the camera, whose view uniform is the view of the
+/// scene below the UI, see [`DefaultCameraView`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct UiSceneView(pub Entity);
+
+/// The views with nodes reading the scene below the UI this frame, see
+/// [`UiMaterial::reads_view_transform`].
+#[derive(Resource, Default)]
+pub struct UiSceneColorViews {
+    views: EntityHashSet,
+}
+
+impl UiSceneColorViews {
+    pub fn insert(&mut self, view: Entity) {
+        self.views.insert(view);
+    }
+
+    /// Returns `true` if the UI of `view` reads the scene below it.
+    pub fn contains(&self, view: Entity) -> bool {
+        self.views.contains(&view)
+    }
+}
+
+pub fn clear_ui_scene_color_views(mut scene_color_views: ResMut<UiSceneColorViews>) {
+    scene_color_views.views.clear();
+}
+
+/// A copy of the main texture of a view in [`UiSceneColorViews`], from before the UI was drawn.
+#[derive(Component)]
+pub struct ViewUiSceneColorTexture {
+    pub texture: CachedTexture,
+    pub blit_pipeline: CachedRenderPipelineId,
+}
+
+pub fn prepare_ui_scene_color_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    scene_color_views: Res<UiSceneColorViews>,
+    views: Query<(Entity, &ViewTarget, &ExtractedCamera)>,
+) {
+    for (entity, target, camera) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+        if !scene_color_views.contains(entity) {
+            commands.entity(entity).remove::<ViewUiSceneColorTexture>();
+            continue;
+        }
+
+        let texture_format = target.main_texture_format();
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_scene_color_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: texture_format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        let blit_pipeline = blit_pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            BlitPipelineKey {
+                texture_format,
+                blend_state: None,
+                samples: 1,
+            },
+        );
+
+        commands.entity(entity).insert(ViewUiSceneColorTexture {
+            texture,
+            blit_pipeline,
+        });
+    }
+}
+
+/// The number of bindings in the view bind group of [`UiMaterial`]s reading the scene: the UI
+/// view, the globals, the view of the scene, its color texture and the sampler of the texture.
+pub const UI_SCENE_VIEW_BINDINGS: usize = 5;
+
+/// The layout bound as group 0 by [`UiMaterial`]s with [`UiMaterial::reads_view_transform`], in
+/// place of the view layout of the UI.
+pub struct UiSceneViewLayout {
+    pub layout: BindGroupLayout,
+    pub sampler: Sampler,
+}
+
+impl UiSceneViewLayout {
+    pub fn new(render_device: &RenderDevice) -> Self {
+        Self {
+            layout: render_device
+                .create_bind_group_layout("ui_scene_view_layout", &ui_scene_view_layout_entries()),
+            sampler: render_device.create_sampler(&SamplerDescriptor {
+                label: Some("ui_scene_color_sampler"),
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Creates the bind group of a view whose UI reads the scene, see
+    /// [`ViewUiSceneColorTexture`].
+    pub fn create_bind_group(
+        &self,
+        render_device: &RenderDevice,
+        view: BindingResource,
+        globals: BindingResource,
+        scene_color: &ViewUiSceneColorTexture,
+    ) -> BindGroup {
+        render_device.create_bind_group(
+            "ui_scene_view_bind_group",
+            &self.layout,
+            &ui_scene_view_bind_group_entries(
+                view,
+                globals,
+                &scene_color.texture.default_view,
+                &self.sampler,
+            ),
+        )
+    }
+}
+
+/// Returns the layout entries of the view bind group of [`UiMaterial`]s reading the scene.
+///
+/// The UI view and the globals keep the bindings of the view bind group of the UI.
+pub fn ui_scene_view_layout_entries() -> BindGroupLayoutEntries<UI_SCENE_VIEW_BINDINGS> {
+    BindGroupLayoutEntries::sequential(
+        ShaderStages::VERTEX_FRAGMENT,
+        (
+            uniform_buffer::<ViewUniform>(true),
+            uniform_buffer::<GlobalsUniform>(false),
+            uniform_buffer::<ViewUniform>(true),
+            texture_2d(TextureSampleType::Float { filterable: true }),
+            sampler(SamplerBindingType::Filtering),
+        ),
+    )
+}
+
+/// Returns the resources bound to [`ui_scene_view_layout_entries`], in the same order.
+///
+/// Both the scene view and the UI view are offsets in the view uniform buffer, so `view` is bound
+/// twice.
+pub fn ui_scene_view_bind_group_entries<'a>(
+    view: BindingResource<'a>,
+    globals: BindingResource<'a>,
+    scene_color: &'a TextureView,
+    sampler: &'a Sampler,
+) -> BindGroupEntries<'a, UI_SCENE_VIEW_BINDINGS> {
+    BindGroupEntries::sequential((view.clone(), globals, view, scene_color, sampler))
+}
+
+/// Copies the main texture of `target` to the scene color texture of its view, before any UI is
+/// drawn.
+pub(crate) fn copy_ui_scene_color(
+    render_context: &mut RenderContext,
+    world: &World,
+    target: &ViewTarget,
+    scene_color: &ViewUiSceneColorTexture,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let blit_pipeline = world.resource::<BlitPipeline>();
+
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(scene_color.blit_pipeline) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "ui_scene_color_copy_bind_group",
+        &blit_pipeline.texture_bind_group,
+        &BindGroupEntries::sequential((target.main_texture_view(), &blit_pipeline.sampler)),
+    );
+
+    let mut render_pass =
+        render_context
+            .command_encoder()
+            .begin_render_pass(&RenderPassDescriptor {
+                label: Some("ui_scene_color_copy_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &scene_color.texture.default_view,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_render::render_resource::{BindingType, BufferBindingType};
+
+    use super::{ui_scene_view_layout_entries, UI_SCENE_VIEW_BINDINGS};
+
+    #[test]
+    fn materials_reading_the_view_transform_bind_the_scene_view() {
+        // The bind group is built from `UI_SCENE_VIEW_BINDINGS` sequential resources, so the
+        // layout must have an entry for each of them, in the same order
+        let entries = ui_scene_view_layout_entries();
+        let bindings: Vec<_> = entries.iter().map(|entry| entry.binding).collect();
+        assert_eq!(
+            bindings,
+            (0..UI_SCENE_VIEW_BINDINGS as u32).collect::<Vec<_>>()
+        );
+
+        // The view of the scene and its color texture follow the UI view and the globals
+        let dynamic_uniforms: Vec<_> = entries
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.ty,
+                    BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        ..
+                    }
+                )
+            })
+            .map(|entry| entry.binding)
+            .collect();
+        // Both views are offset in the same view uniform buffer
+        assert_eq!(dynamic_uniforms, [0, 2]);
+        assert_eq!(entries[0].ty, entries[2].ty);
+        assert!(matches!(entries[3].ty, BindingType::Texture { .. }));
+        assert!(matches!(entries[4].ty, BindingType::Sampler(_)));
+    }
+}
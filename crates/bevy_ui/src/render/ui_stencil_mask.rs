@@ -0,0 +1,462 @@
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_hierarchy::Children;
+use bevy_math::{Rect, Vec2};
+use bevy_render::{
+    camera::ExtractedCamera,
+    render_resource::*,
+    renderer::RenderDevice,
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    Extract, ExtractSchedule, Render, RenderSet,
+};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::HashMap;
+
+use super::{
+    inflated_clip, ui_msaa::ui_msaa_samples, ExtractedUiNode, ExtractedUiNodes, RenderUiSystem,
+};
+use crate::*;
+
+/// Clips the descendants of the [`UiStencilMask`] nodes to the shapes of the masks.
+///
+/// The extracted items of a masked node get a [`UiStencil::Test`] for the number of masks above
+/// it. Each mask is pushed to the view's [`ViewUiStencilTexture`] after its node is drawn,
+/// incrementing the stencil values inside of its shape, and popped after its last descendant.
+/// Masked items are recorded in [`UiStencilItems`] when they're queued, and the [`UiPassNode`]
+/// draws each run of them with the stencil texture.
+pub struct UiStencilMaskPlugin;
+
+impl Plugin for UiStencilMaskPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<ExtractedUiStencilMasks>()
+                .init_resource::<UiStencilItems>()
+                .allow_ambiguous_resource::<UiStencilItems>()
+                .add_systems(
+                    ExtractSchedule,
+                    (
+                        clear_ui_stencil_items,
+                        extract_ui_stencil_masks.in_set(RenderUiSystem::ExtractBackgrounds),
+                        stencil_extracted_uinodes
+                            .after(extract_ui_stencil_masks)
+                            .after(RenderUiSystem::ExtractText),
+                    ),
+                )
+                .add_systems(
+                    Render,
+                    prepare_ui_stencil_textures.in_set(RenderSet::PrepareResources),
+                );
+        }
+    }
+}
+
+/// The format of a [`ViewUiStencilTexture`].
+pub const UI_STENCIL_FORMAT: TextureFormat = TextureFormat::Stencil8;
+
+/// How an [`ExtractedUiNode`] uses the stencil texture of its view, see [`UiStencilMask`].
+///
+/// Each variant holds the number of masks above the node, the stencil value inside of all of
+/// their shapes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UiStencil {
+    /// The node isn't masked.
+    #[default]
+    None,
+    /// The node is only drawn where the stencil value is equal to the level.
+    Test(u32),
+    /// The shape of a mask, incrementing the stencil value where it is equal to the level.
+    Push(u32),
+    /// The shape of a mask, decrementing the stencil value where its push incremented it.
+    Pop(u32),
+}
+
+impl UiStencil {
+    /// Returns the stencil value the node is tested against.
+    pub fn reference(self) -> u32 {
+        match self {
+            UiStencil::None => 0,
+            UiStencil::Test(level) | UiStencil::Push(level) => level,
+            UiStencil::Pop(level) => level + 1,
+        }
+    }
+
+    /// Returns `true` for the shapes of masks, which write to the stencil texture instead of
+    /// being drawn.
+    pub fn writes(self) -> bool {
+        self.mode().writes()
+    }
+
+    /// Returns the stencil state the node is drawn with.
+    pub fn mode(self) -> UiStencilMode {
+        match self {
+            UiStencil::None => UiStencilMode::Disabled,
+            UiStencil::Test(_) => UiStencilMode::Test,
+            UiStencil::Push(_) => UiStencilMode::Push,
+            UiStencil::Pop(_) => UiStencilMode::Pop,
+        }
+    }
+}
+
+/// The stencil state of a UI pipeline, see [`UiStencil`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UiStencilMode {
+    /// Drawn without a stencil texture.
+    #[default]
+    Disabled,
+    Test,
+    Push,
+    Pop,
+}
+
+impl UiStencilMode {
+    /// Returns `true` if the pipeline writes to the stencil texture instead of drawing.
+    pub fn writes(self) -> bool {
+        matches!(self, UiStencilMode::Push | UiStencilMode::Pop)
+    }
+}
+
+/// Returns the depth stencil state of a UI pipeline drawn with `mode`, or `None` if it is
+/// drawn without a stencil texture.
+pub fn ui_stencil_state(mode: UiStencilMode) -> Option<DepthStencilState> {
+    let pass_op = match mode {
+        UiStencilMode::Disabled => return None,
+        UiStencilMode::Test => StencilOperation::Keep,
+        UiStencilMode::Push => StencilOperation::IncrementClamp,
+        UiStencilMode::Pop => StencilOperation::DecrementClamp,
+    };
+    let face = StencilFaceState {
+        compare: CompareFunction::Equal,
+        fail_op: StencilOperation::Keep,
+        depth_fail_op: StencilOperation::Keep,
+        pass_op,
+    };
+    Some(DepthStencilState {
+        format: UI_STENCIL_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::Always,
+        stencil: StencilState {
+            front: face,
+            back: face,
+            read_mask: !0,
+            write_mask: !0,
+        },
+        bias: DepthBiasState::default(),
+    })
+}
+
+/// The masks extracted this frame.
+///
+/// The extracted items of a node share its stack index, which is used to find them regardless of
+/// the entity they were extracted to.
+#[derive(Resource, Default)]
+pub struct ExtractedUiStencilMasks {
+    /// The number of masks above each masked node, by stack index.
+    pub levels: HashMap<u32, u32>,
+    /// The cameras drawing masks, which get a [`ViewUiStencilTexture`].
+    pub cameras: EntityHashSet,
+}
+
+/// Returns the number of masks above each descendant of the `masks`, by stack index.
+pub fn ui_stencil_levels(
+    masks: impl IntoIterator<Item = Entity>,
+    node_query: &Query<&Node>,
+    children_query: &Query<&Children>,
+) -> HashMap<u32, u32> {
+    let mut levels = HashMap::new();
+    for mask in masks {
+        let mut entities: Vec<Entity> = children_query
+            .get(mask)
+            .map(|children| children.to_vec())
+            .unwrap_or_default();
+        while let Some(entity) = entities.pop() {
+            let Ok(node) = node_query.get(entity) else {
+                continue;
+            };
+            *levels.entry(node.stack_index).or_insert(0) += 1;
+            if let Ok(children) = children_query.get(entity) {
+                entities.extend(children);
+            }
+        }
+    }
+    levels
+}
+
+/// Returns the highest stack index of `root` and of its descendants, after which the mask of
+/// `root` is popped.
+pub fn ui_subtree_last_stack_index(
+    root: Entity,
+    node_query: &Query<&Node>,
+    children_query: &Query<&Children>,
+) -> Option<u32> {
+    let mut last = None;
+    let mut entities = vec![root];
+    while let Some(entity) = entities.pop() {
+        let Ok(node) = node_query.get(entity) else {
+            continue;
+        };
+        last = last.max(Some(node.stack_index));
+        if let Ok(children) = children_query.get(entity) {
+            entities.extend(children);
+        }
+    }
+    last
+}
+
+/// Extracts the levels of the masked nodes, and pushes and pops the shape of each
+/// [`UiStencilMask`] around its descendants.
+pub fn extract_ui_stencil_masks(
+    mut commands: Commands,
+    mut extracted_masks: ResMut<ExtractedUiStencilMasks>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    default_ui_camera: Extract<DefaultUiCamera>,
+    mask_query: Extract<
+        Query<
+            (
+                Entity,
+                &Node,
+                &GlobalTransform,
+                Option<&CalculatedClip>,
+                (Option<&UiClipInflate>, Option<&UiClipMode>),
+                Option<&TargetCamera>,
+            ),
+            With<UiStencilMask>,
+        >,
+    >,
+    node_query: Extract<Query<&Node>>,
+    children_query: Extract<Query<&Children>>,
+) {
+    extracted_masks.cameras.clear();
+    extracted_masks.levels = ui_stencil_levels(
+        mask_query.iter().map(|(entity, ..)| entity),
+        &node_query,
+        &children_query,
+    );
+
+    for (entity, uinode, transform, clip, (clip_inflate, clip_mode), camera) in &mask_query {
+        let Some(camera_entity) = camera.map(TargetCamera::entity).or(default_ui_camera.get())
+        else {
+            continue;
+        };
+
+        // Masks without descendants don't mask anything, empty masks hide their descendants
+        // because their shape is never pushed
+        let Some(last) = ui_subtree_last_stack_index(entity, &node_query, &children_query)
+            .filter(|last| *last != uinode.stack_index)
+        else {
+            continue;
+        };
+        if uinode.is_empty() {
+            continue;
+        }
+
+        let level = extracted_masks
+            .levels
+            .get(&uinode.stack_index)
+            .copied()
+            .unwrap_or(0);
+        let shape = |stack_index, stencil| ExtractedUiNode {
+            stack_index,
+            transform: transform.compute_matrix(),
+            rect: Rect {
+                min: Vec2::ZERO,
+                max: uinode.calculated_size,
+            },
+            antialias: UiAntialias::Off,
+            stencil,
+            clip: inflated_clip(clip, clip_inflate),
+            clip_mode: clip_mode.copied().unwrap_or_default(),
+            camera_entity,
+            border_radius: [
+                uinode.border_radius.top_left,
+                uinode.border_radius.top_right,
+                uinode.border_radius.bottom_right,
+                uinode.border_radius.bottom_left,
+            ],
+            ..Default::default()
+        };
+        extracted_uinodes.push(
+            &mut commands,
+            shape(uinode.stack_index, UiStencil::Push(level)),
+        );
+        extracted_uinodes.push(&mut commands, shape(last, UiStencil::Pop(level)));
+        extracted_masks.cameras.insert(camera_entity);
+    }
+}
+
+/// Sets the [`UiStencil::Test`] of the extracted items of the masked nodes.
+pub fn stencil_extracted_uinodes(
+    extracted_masks: Res<ExtractedUiStencilMasks>,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+) {
+    if extracted_masks.levels.is_empty() {
+        return;
+    }
+
+    for uinode in extracted_uinodes.uinodes.values_mut() {
+        if uinode.stencil != UiStencil::None {
+            continue;
+        }
+        if let Some(&level) = extracted_masks.levels.get(&uinode.stack_index) {
+            uinode.stencil = UiStencil::Test(level);
+        }
+    }
+}
+
+/// The [`TransparentUi`] phase items drawn with a stencil texture this frame.
+#[derive(Resource, Default)]
+pub struct UiStencilItems {
+    entities: EntityHashSet,
+}
+
+impl UiStencilItems {
+    pub fn insert(&mut self, entity: Entity) {
+        self.entities.insert(entity);
+    }
+
+    /// Returns `true` if the phase item for `entity` is drawn with a stencil texture.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+}
+
+pub fn clear_ui_stencil_items(mut stencil_items: ResMut<UiStencilItems>) {
+    stencil_items.entities.clear();
+}
+
+/// The stencil texture the masked nodes of a view are drawn with.
+///
+/// Cleared to 0 by the [`UiPassNode`] before the first masked nodes are drawn.
+#[derive(Component)]
+pub struct ViewUiStencilTexture {
+    pub texture: CachedTexture,
+}
+
+pub fn prepare_ui_stencil_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    extracted_masks: Res<ExtractedUiStencilMasks>,
+    views: Query<(Entity, &ExtractedCamera, Option<&Msaa>)>,
+) {
+    for (entity, camera, msaa) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+        if !extracted_masks.cameras.contains(&entity) {
+            commands.entity(entity).remove::<ViewUiStencilTexture>();
+            continue;
+        }
+
+        let texture = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_stencil_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                // Masked nodes are drawn with as many samples as the rest of the UI
+                sample_count: ui_msaa_samples(msaa),
+                dimension: TextureDimension::D2,
+                format: UI_STENCIL_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        );
+
+        commands
+            .entity(entity)
+            .insert(ViewUiStencilTexture { texture });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        entity::Entity,
+        query::With,
+        system::{Query, RunSystemOnce},
+        world::World,
+    };
+    use bevy_hierarchy::{BuildChildren, Children};
+    use bevy_render::render_resource::{CompareFunction, StencilOperation};
+    use bevy_utils::HashMap;
+
+    use super::{
+        ui_stencil_levels, ui_stencil_state, ui_subtree_last_stack_index, UiStencil, UiStencilMode,
+        UI_STENCIL_FORMAT,
+    };
+    use crate::{Node, UiStencilMask};
+
+    #[test]
+    fn nested_masks_increment_the_level_of_their_descendants() {
+        let mut world = World::new();
+        let node = |stack_index| Node {
+            stack_index,
+            ..Default::default()
+        };
+        // root -> (mask -> (child -> inner mask -> grandchild), sibling)
+        let root = world.spawn(node(0)).id();
+        let mask = world.spawn((node(1), UiStencilMask)).set_parent(root).id();
+        let child = world.spawn(node(2)).set_parent(mask).id();
+        let inner = world.spawn((node(3), UiStencilMask)).set_parent(child).id();
+        world.spawn(node(4)).set_parent(inner);
+        world.spawn(node(5)).set_parent(root);
+
+        let (levels, last): (HashMap<u32, u32>, [Option<u32>; 3]) = world.run_system_once(
+            move |mask_query: Query<Entity, With<UiStencilMask>>,
+                  node_query: Query<&Node>,
+                  children_query: Query<&Children>| {
+                (
+                    ui_stencil_levels(&mask_query, &node_query, &children_query),
+                    [mask, inner, root].map(|entity| {
+                        ui_subtree_last_stack_index(entity, &node_query, &children_query)
+                    }),
+                )
+            },
+        );
+
+        // Masks aren't masked by themselves, only by the masks above them
+        assert!(!levels.contains_key(&0));
+        assert!(!levels.contains_key(&1));
+        assert_eq!(levels[&2], 1);
+        assert_eq!(levels[&3], 1);
+        assert_eq!(levels[&4], 2);
+        assert!(!levels.contains_key(&5));
+
+        // Masks are popped after their last descendant
+        assert_eq!(last, [Some(4), Some(4), Some(5)]);
+    }
+
+    #[test]
+    fn masks_push_and_pop_the_level_of_their_descendants() {
+        // The inner mask is pushed where the outer one was, and its descendants tested inside both
+        let outer = 0;
+        assert_eq!(UiStencil::Push(outer).reference(), 0);
+        assert_eq!(UiStencil::Test(outer + 1).reference(), 1);
+        assert_eq!(UiStencil::Push(outer + 1).reference(), 1);
+        assert_eq!(UiStencil::Test(outer + 2).reference(), 2);
+        assert_eq!(UiStencil::Pop(outer + 1).reference(), 2);
+        assert_eq!(UiStencil::Pop(outer).reference(), 1);
+
+        assert_eq!(ui_stencil_state(UiStencilMode::Disabled), None);
+        for (mode, pass_op) in [
+            (UiStencilMode::Test, StencilOperation::Keep),
+            (UiStencilMode::Push, StencilOperation::IncrementClamp),
+            (UiStencilMode::Pop, StencilOperation::DecrementClamp),
+        ] {
+            let state = ui_stencil_state(mode).unwrap();
+            assert_eq!(state.format, UI_STENCIL_FORMAT);
+            assert!(!state.depth_write_enabled);
+            assert_eq!(state.stencil.front.compare, CompareFunction::Equal);
+            assert_eq!(state.stencil.front.pass_op, pass_op);
+            assert_eq!(state.stencil.back, state.stencil.front);
+            assert_eq!(mode.writes(), mode != UiStencilMode::Test);
+        }
+        assert!(!UiStencil::None.writes());
+        assert!(!UiStencil::Test(1).writes());
+    }
+}
@@ -10,7 +10,7 @@ use bevy_ecs::{
         *,
     },
 };
-use bevy_math::{FloatOrd, Mat4, Rect, Vec2, Vec4Swizzles};
+use bevy_math::{FloatOrd, Mat4, Rect, Vec2, Vec3, Vec4Swizzles};
 use bevy_render::{
     render_asset::RenderAssets,
     render_phase::*,
@@ -25,10 +25,16 @@ use bevy_sprite::{
     TextureSlicer,
 };
 use bevy_transform::prelude::GlobalTransform;
-use bevy_utils::HashMap;
+use bevy_utils::{warn_once, HashMap};
 use binding_types::{sampler, texture_2d};
 use bytemuck::{Pod, Zeroable};
 
+use super::{
+    ui_force_ldr::{ui_target_is_hdr, ExtractedUiForceLdr},
+    ui_msaa::ui_msaa_samples,
+    ui_opacity::{extract_ui_opacities, fade_extracted_ui_texture_slices},
+    ui_opaque::{ui_depth_stencil_state, UiDepthViews},
+};
 use crate::*;
 
 pub const UI_SLICER_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(11156288772117983964);
@@ -53,13 +59,20 @@ impl Plugin for UiTextureSlicerPlugin {
                 .init_resource::<SpecializedRenderPipelines<UiTextureSlicePipeline>>()
                 .add_systems(
                     ExtractSchedule,
-                    extract_ui_texture_slices.after(extract_uinode_images),
+                    (
+                        extract_ui_texture_slices.after(extract_uinode_images),
+                        fade_extracted_ui_texture_slices
+                            .after(extract_ui_opacities)
+                            .after(extract_ui_texture_slices),
+                    ),
                 )
                 .add_systems(
                     Render,
                     (
                         queue_ui_slices.in_set(RenderSet::Queue),
-                        prepare_ui_slices.in_set(RenderSet::PrepareBindGroups),
+                        prepare_ui_slices
+                            .in_set(RenderSet::PrepareBindGroups)
+                            .before(RenderUiSystem::ModifyBatches),
                     ),
                 );
         }
@@ -117,6 +130,7 @@ pub struct UiTextureSliceImageBindGroups {
 pub struct UiTextureSlicePipeline {
     pub view_layout: BindGroupLayout,
     pub image_layout: BindGroupLayout,
+    pub customizer: UiPipelineCustomizer,
 }
 
 impl FromWorld for UiTextureSlicePipeline {
@@ -145,6 +159,7 @@ impl FromWorld for UiTextureSlicePipeline {
         UiTextureSlicePipeline {
             view_layout,
             image_layout,
+            customizer: UiPipelineCustomizer::from_render_world(world),
         }
     }
 }
@@ -152,6 +167,11 @@ impl FromWorld for UiTextureSlicePipeline {
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct UiTextureSlicePipelineKey {
     pub hdr: bool,
+    /// The number of MSAA samples of the UI pass, see [`ui_msaa_samples`].
+    pub samples: u32,
+    /// Whether the UI of the view is drawn with a
+    /// [`ViewUiDepthTexture`](super::ui_opaque::ViewUiDepthTexture).
+    pub depth: bool,
 }
 
 impl SpecializedRenderPipeline for UiTextureSlicePipeline {
@@ -179,7 +199,7 @@ impl SpecializedRenderPipeline for UiTextureSlicePipeline {
         );
         let shader_defs = Vec::new();
 
-        RenderPipelineDescriptor {
+        let mut descriptor = RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: UI_SLICER_SHADER_HANDLE,
                 entry_point: "vertex".into(),
@@ -211,14 +231,19 @@ impl SpecializedRenderPipeline for UiTextureSlicePipeline {
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
             },
-            depth_stencil: None,
+            // Hidden by the opaque nodes above, see `ViewUiDepthTexture`
+            depth_stencil: key
+                .depth
+                .then(|| ui_depth_stencil_state(false, CompareFunction::Less)),
             multisample: MultisampleState {
-                count: 1,
+                count: key.samples,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             label: Some("ui_texture_slice_pipeline".into()),
-        }
+        };
+        self.customizer.customize(&mut descriptor);
+        descriptor
     }
 }
 
@@ -312,12 +337,16 @@ pub fn extract_ui_texture_slices(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn queue_ui_slices(
     extracted_ui_slicers: ResMut<ExtractedUiTextureSlices>,
     ui_slicer_pipeline: Res<UiTextureSlicePipeline>,
     mut pipelines: ResMut<SpecializedRenderPipelines<UiTextureSlicePipeline>>,
     mut transparent_render_phases: ResMut<ViewSortedRenderPhases<TransparentUi>>,
     mut views: Query<(Entity, &ExtractedView)>,
+    force_ldr_views: Query<(), With<ExtractedUiForceLdr>>,
+    msaa_views: Query<&Msaa>,
+    depth_views: Res<UiDepthViews>,
     pipeline_cache: Res<PipelineCache>,
     draw_functions: Res<DrawFunctions<TransparentUi>>,
 ) {
@@ -334,7 +363,11 @@ pub fn queue_ui_slices(
         let pipeline = pipelines.specialize(
             &pipeline_cache,
             &ui_slicer_pipeline,
-            UiTextureSlicePipelineKey { hdr: view.hdr },
+            UiTextureSlicePipelineKey {
+                hdr: ui_target_is_hdr(view, force_ldr_views.contains(view_entity)),
+                samples: ui_msaa_samples(msaa_views.get(view_entity).ok()),
+                depth: depth_views.contains(view_entity),
+            },
         );
 
         transparent_phase.add(TransparentUi {
@@ -473,9 +506,12 @@ pub fn prepare_ui_slices(
 
                     let rect_size = uinode_rect.size().extend(1.0);
 
-                    // Specify the corners of the node
-                    let positions = QUAD_VERTEX_POSITIONS
-                        .map(|pos| (texture_slices.transform * (pos * rect_size).extend(1.)).xyz());
+                    // Specify the corners of the node, offset along the z-axis by its stack index
+                    let z = stack_z(texture_slices.stack_index);
+                    let positions = QUAD_VERTEX_POSITIONS.map(|pos| {
+                        (texture_slices.transform * (pos * rect_size).extend(1.)).xyz()
+                            + Vec3::Z * z
+                    });
 
                     // Calculate the effect of clipping
                     // Note: this won't work with rotation/scaling, but that's much more complex (may need more that 2 quads)
@@ -625,6 +661,7 @@ pub type DrawUiTextureSlices = (
     SetItemPipeline,
     SetSlicerViewBindGroup<0>,
     SetSlicerTextureBindGroup<1>,
+    SetUiScissorRect,
     DrawSlicer,
 );
 
@@ -641,9 +678,11 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSlicerViewBindGroup<I
         ui_meta: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        let Some(view_bind_group) = ui_meta.into_inner().view_bind_group.as_ref() else {
-            return RenderCommandResult::Failure("view_bind_group not available");
-        };
+        let view_bind_group =
+            match ui_view_bind_group(ui_meta.into_inner().view_bind_group.as_ref()) {
+                Ok(view_bind_group) => view_bind_group,
+                Err(result) => return result,
+            };
         pass.set_bind_group(I, view_bind_group, &[view_uniform.offset]);
         RenderCommandResult::Success
     }
@@ -667,7 +706,14 @@ impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetSlicerTextureBindGrou
             return RenderCommandResult::Skip;
         };
 
-        pass.set_bind_group(I, image_bind_groups.values.get(&batch.image).unwrap(), &[]);
+        let Some(image_bind_group) = image_bind_groups.values.get(&batch.image) else {
+            warn_once!(
+                "The bind group of UI texture slice image {:?} is missing, nodes using it were not drawn",
+                batch.image
+            );
+            return RenderCommandResult::Failure("missing ui texture slice image bind group");
+        };
+        pass.set_bind_group(I, image_bind_group, &[]);
         RenderCommandResult::Success
     }
 }
@@ -7,5 +7,9 @@ struct UiVertexOutput {
     @location(1) border_widths: vec4<f32>,
     // The size of the node in pixels. Order is width, height.
     @location(2) @interpolate(flat) size: vec2<f32>,
+    // The opacity to multiply the alpha of the output by, see `UiMaterialOpacity`.
+    @location(3) @interpolate(flat) opacity: f32,
+    // The UV of the glyph atlas of text nodes, see `ui_material_mask`.
+    @location(4) mask_uv: vec2<f32>,
     @builtin(position) position: vec4<f32>,
 };
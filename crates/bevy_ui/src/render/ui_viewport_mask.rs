@@ -0,0 +1,425 @@
+use bevy_asset::*;
+use bevy_core_pipeline::{
+    blit::{BlitPipeline, BlitPipelineKey},
+    fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+};
+use bevy_ecs::prelude::*;
+use bevy_math::{UVec2, Vec4};
+use bevy_render::{
+    camera::{Camera, ExtractedCamera, Viewport},
+    render_resource::{
+        binding_types::{texture_2d, uniform_buffer},
+        *,
+    },
+    renderer::{RenderContext, RenderDevice, RenderQueue},
+    texture::{CachedTexture, TextureCache},
+    view::*,
+    Extract, ExtractSchedule, Render, RenderSet,
+};
+
+use crate::*;
+
+pub const UI_VIEWPORT_MASK_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(11786251573926153170);
+
+/// Applies the [`UiViewportMask`]s of cameras to their UI.
+///
+/// Before the [`UiPassNode`] draws any UI, the main texture is copied to a texture of the view.
+/// Once all of the UI is drawn, that copy is blended back over the main texture wherever the
+/// mask doesn't cover the viewport, so only the UI is masked and not the scene below it.
+pub struct UiViewportMaskPlugin;
+
+impl Plugin for UiViewportMaskPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            UI_VIEWPORT_MASK_SHADER_HANDLE,
+            "ui_viewport_mask.wgsl",
+            Shader::from_wgsl
+        );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<UiViewportMaskMeta>()
+                .init_resource::<SpecializedRenderPipelines<UiViewportMaskPipeline>>()
+                .add_systems(ExtractSchedule, extract_ui_viewport_masks)
+                .add_systems(
+                    Render,
+                    prepare_ui_viewport_masks.in_set(RenderSet::PrepareResources),
+                );
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<UiViewportMaskPipeline>();
+        }
+    }
+}
+
+/// The [`UiViewportMask`] of a camera, with its lengths in physical pixels.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ExtractedUiViewportMask(pub UiViewportMask);
+
+pub fn extract_ui_viewport_masks(
+    mut commands: Commands,
+    camera_query: Extract<Query<(Entity, &Camera, &UiViewportMask)>>,
+) {
+    for (entity, camera, mask) in &camera_query {
+        if !camera.is_active {
+            continue;
+        }
+
+        let scale_factor = camera.target_scaling_factor().unwrap_or(1.);
+        let shape = match mask.shape {
+            UiViewportMaskShape::RoundedRect { radius } => UiViewportMaskShape::RoundedRect {
+                radius: radius.max(0.) * scale_factor,
+            },
+            UiViewportMaskShape::Ellipse => UiViewportMaskShape::Ellipse,
+        };
+        commands
+            .get_or_spawn(entity)
+            .insert(ExtractedUiViewportMask(UiViewportMask {
+                shape,
+                feather: mask.feather.max(0.) * scale_factor,
+            }));
+    }
+}
+
+#[derive(Resource)]
+pub struct UiViewportMaskPipeline {
+    pub layout: BindGroupLayout,
+}
+
+impl FromWorld for UiViewportMaskPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "ui_viewport_mask_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    uniform_buffer::<UiViewportMaskUniform>(true),
+                ),
+            ),
+        );
+
+        UiViewportMaskPipeline { layout }
+    }
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+pub struct UiViewportMaskPipelineKey {
+    pub texture_format: TextureFormat,
+}
+
+/// Blends the copy of the scene over the UI with the inverse of the mask's coverage as alpha.
+///
+/// The alpha of the main texture is left as the UI wrote it.
+pub const UI_VIEWPORT_MASK_BLEND_STATE: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::SrcAlpha,
+        dst_factor: BlendFactor::OneMinusSrcAlpha,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::Zero,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// Returns the target of the mask pass for `key`, blending over the main texture.
+pub fn ui_viewport_mask_color_target(key: UiViewportMaskPipelineKey) -> ColorTargetState {
+    ColorTargetState {
+        format: key.texture_format,
+        blend: Some(UI_VIEWPORT_MASK_BLEND_STATE),
+        write_mask: ColorWrites::ALL,
+    }
+}
+
+impl SpecializedRenderPipeline for UiViewportMaskPipeline {
+    type Key = UiViewportMaskPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("ui_viewport_mask_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: UI_VIEWPORT_MASK_SHADER_HANDLE,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ui_viewport_mask_color_target(key))],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: Vec::new(),
+        }
+    }
+}
+
+#[derive(ShaderType)]
+pub struct UiViewportMaskUniform {
+    /// The camera's viewport in physical pixels, as `(min_x, min_y, max_x, max_y)`.
+    pub viewport: Vec4,
+    /// `0` for [`UiViewportMaskShape::RoundedRect`], `1` for [`UiViewportMaskShape::Ellipse`].
+    pub shape: u32,
+    pub radius: f32,
+    pub feather: f32,
+}
+
+/// Returns the uniform of `mask` for a camera with the given viewport and physical target size.
+pub fn ui_viewport_mask_uniform(
+    mask: &UiViewportMask,
+    viewport: Option<&Viewport>,
+    physical_target_size: UVec2,
+) -> UiViewportMaskUniform {
+    let (min, max) = match viewport {
+        Some(viewport) => (
+            viewport.physical_position,
+            viewport.physical_position + viewport.physical_size,
+        ),
+        None => (UVec2::ZERO, physical_target_size),
+    };
+    let (shape, radius) = match mask.shape {
+        UiViewportMaskShape::RoundedRect { radius } => (0, radius),
+        UiViewportMaskShape::Ellipse => (1, 0.),
+    };
+    UiViewportMaskUniform {
+        viewport: Vec4::new(min.x as f32, min.y as f32, max.x as f32, max.y as f32),
+        shape,
+        radius,
+        feather: mask.feather,
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct UiViewportMaskMeta {
+    uniforms: DynamicUniformBuffer<UiViewportMaskUniform>,
+}
+
+/// The resources used to mask the UI of a view with an [`ExtractedUiViewportMask`].
+#[derive(Component)]
+pub struct ViewUiViewportMask {
+    /// Copy of the main texture from before the UI was drawn.
+    pub scene: CachedTexture,
+    pub blit_pipeline: CachedRenderPipelineId,
+    pub mask_pipeline: CachedRenderPipelineId,
+    pub uniform_offset: u32,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_ui_viewport_masks(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut texture_cache: ResMut<TextureCache>,
+    mut meta: ResMut<UiViewportMaskMeta>,
+    pipeline_cache: Res<PipelineCache>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut blit_pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    mask_pipeline: Res<UiViewportMaskPipeline>,
+    mut mask_pipelines: ResMut<SpecializedRenderPipelines<UiViewportMaskPipeline>>,
+    views: Query<(
+        Entity,
+        &ViewTarget,
+        &ExtractedCamera,
+        &ExtractedUiViewportMask,
+    )>,
+) {
+    meta.uniforms.clear();
+
+    for (entity, target, camera, mask) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let texture_format = target.main_texture_format();
+        let scene = texture_cache.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("ui_viewport_mask_scene_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: texture_format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+
+        let uniform_offset = meta.uniforms.push(&ui_viewport_mask_uniform(
+            &mask.0,
+            camera.viewport.as_ref(),
+            physical_target_size,
+        ));
+
+        let blit_pipeline = blit_pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            BlitPipelineKey {
+                texture_format,
+                blend_state: None,
+                samples: 1,
+            },
+        );
+        let mask_pipeline = mask_pipelines.specialize(
+            &pipeline_cache,
+            &mask_pipeline,
+            UiViewportMaskPipelineKey { texture_format },
+        );
+
+        commands.entity(entity).insert(ViewUiViewportMask {
+            scene,
+            blit_pipeline,
+            mask_pipeline,
+            uniform_offset,
+        });
+    }
+
+    meta.uniforms.write_buffer(&render_device, &render_queue);
+}
+
+/// Copies the main texture of `target` to the scene texture of `mask`, before any UI is drawn.
+pub(crate) fn copy_ui_viewport_mask_scene(
+    render_context: &mut RenderContext,
+    world: &World,
+    target: &ViewTarget,
+    mask: &ViewUiViewportMask,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let blit_pipeline = world.resource::<BlitPipeline>();
+
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(mask.blit_pipeline) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "ui_viewport_mask_copy_bind_group",
+        &blit_pipeline.texture_bind_group,
+        &BindGroupEntries::sequential((target.main_texture_view(), &blit_pipeline.sampler)),
+    );
+
+    let mut render_pass =
+        render_context
+            .command_encoder()
+            .begin_render_pass(&RenderPassDescriptor {
+                label: Some("ui_viewport_mask_copy_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &mask.scene.default_view,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+/// Blends the scene texture of `mask` back over the main texture of `target`, after all of the
+/// UI is drawn.
+pub(crate) fn render_ui_viewport_mask(
+    render_context: &mut RenderContext,
+    world: &World,
+    target: &ViewTarget,
+    camera: &ExtractedCamera,
+    mask: &ViewUiViewportMask,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+    let mask_pipeline = world.resource::<UiViewportMaskPipeline>();
+    let meta = world.resource::<UiViewportMaskMeta>();
+
+    let (Some(pipeline), Some(uniform_binding)) = (
+        pipeline_cache.get_render_pipeline(mask.mask_pipeline),
+        meta.uniforms.binding(),
+    ) else {
+        return;
+    };
+
+    let bind_group = render_context.render_device().create_bind_group(
+        "ui_viewport_mask_bind_group",
+        &mask_pipeline.layout,
+        &BindGroupEntries::sequential((&mask.scene.default_view, uniform_binding)),
+    );
+
+    let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some("ui_viewport_mask_pass"),
+        color_attachments: &[Some(target.get_unsampled_color_attachment())],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    if let Some(viewport) = camera.viewport.as_ref() {
+        render_pass.set_camera_viewport(viewport);
+    }
+    render_pass.set_render_pipeline(pipeline);
+    render_pass.set_bind_group(0, &bind_group, &[mask.uniform_offset]);
+    render_pass.draw(0..3, 0..1);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{UVec2, Vec4};
+    use bevy_render::{
+        camera::Viewport,
+        render_resource::{BlendFactor, TextureFormat},
+    };
+
+    use super::{
+        ui_viewport_mask_color_target, ui_viewport_mask_uniform, UiViewportMaskPipelineKey,
+    };
+    use crate::UiViewportMask;
+
+    #[test]
+    fn uniform_is_fitted_to_the_camera_viewport() {
+        let target_size = UVec2::new(800, 600);
+        let mask = UiViewportMask::rounded(20.).with_feather(4.);
+
+        let uniform = ui_viewport_mask_uniform(&mask, None, target_size);
+        assert_eq!(uniform.viewport, Vec4::new(0., 0., 800., 600.));
+        assert_eq!(uniform.shape, 0);
+        assert_eq!(uniform.radius, 20.);
+        assert_eq!(uniform.feather, 4.);
+
+        let viewport = Viewport {
+            physical_position: UVec2::new(100, 50),
+            physical_size: UVec2::new(200, 100),
+            ..Default::default()
+        };
+        let uniform = ui_viewport_mask_uniform(&mask, Some(&viewport), target_size);
+        assert_eq!(uniform.viewport, Vec4::new(100., 50., 300., 150.));
+    }
+
+    #[test]
+    fn ellipse_uniform_has_no_radius() {
+        let mask = UiViewportMask::ellipse().with_feather(10.);
+        let uniform = ui_viewport_mask_uniform(&mask, None, UVec2::new(100, 100));
+        assert_eq!(uniform.shape, 1);
+        assert_eq!(uniform.radius, 0.);
+        assert_eq!(uniform.feather, 10.);
+    }
+
+    #[test]
+    fn mask_pass_targets_the_view_format_and_keeps_the_ui_alpha() {
+        let target = ui_viewport_mask_color_target(UiViewportMaskPipelineKey {
+            texture_format: TextureFormat::Rgba16Float,
+        });
+        assert_eq!(target.format, TextureFormat::Rgba16Float);
+
+        let alpha = target.blend.unwrap().alpha;
+        assert_eq!(alpha.src_factor, BlendFactor::Zero);
+        assert_eq!(alpha.dst_factor, BlendFactor::One);
+    }
+}
@@ -0,0 +1,38 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct UiViewportMask {
+    // (min_x, min_y, max_x, max_y) in physical pixels
+    viewport: vec4<f32>,
+    // 0 for a rounded rect, 1 for an ellipse
+    shape: u32,
+    radius: f32,
+    feather: f32,
+};
+
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> mask: UiViewportMask;
+
+const ELLIPSE: u32 = 1u;
+
+// Signed distance to the edge of the mask's shape, negative inside of it
+fn mask_distance(point: vec2<f32>) -> f32 {
+    let half_size = 0.5 * (mask.viewport.zw - mask.viewport.xy);
+    let p = point - mask.viewport.xy - half_size;
+
+    if mask.shape == ELLIPSE {
+        return (length(p / max(half_size, vec2(1e-6))) - 1.0) * min(half_size.x, half_size.y);
+    }
+
+    let radius = clamp(mask.radius, 0.0, min(half_size.x, half_size.y));
+    let q = abs(p) - half_size + radius;
+    return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureLoad(scene_texture, vec2<i32>(in.position.xy), 0);
+    let coverage = saturate(0.5 - mask_distance(in.position.xy) / max(mask.feather, 1.0));
+
+    // Blended over the UI with the scene's color where the mask doesn't cover the viewport
+    return vec4(scene.rgb, 1.0 - coverage);
+}
@@ -0,0 +1,292 @@
+//! Renders static UI screens into a texture once and displays the texture afterwards.
+
+use bevy_asset::{Assets, Handle};
+use bevy_color::Color;
+use bevy_core_pipeline::core_2d::Camera2dBundle;
+use bevy_ecs::prelude::*;
+use bevy_math::UVec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::{Camera, ClearColorConfig, RenderTarget},
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    texture::Image,
+    view::RenderLayers,
+};
+
+use crate::{
+    node_bundles::ImageBundle, DefaultUiCamera, PositionType, Style, TargetCamera, UiImage, Val,
+    ZIndex,
+};
+
+/// Renders a root UI node and its descendants into a texture once, then only displays that
+/// texture each frame until the cache is [invalidated](UiScreenCache::invalidate).
+///
+/// Meant for screens that don't change once built, like a splash or credits screen. The screen is
+/// rendered by its own camera at the logical size of the camera it is displayed on, so it is
+/// upscaled on displays with a scale factor above 1. Images and fonts used by the screen should be
+/// loaded before it is cached, or the cache invalidated once they are.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct UiScreenCache {
+    invalidated: bool,
+}
+
+impl UiScreenCache {
+    /// Renders the screen into its texture again next frame.
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+}
+
+/// The texture a [`UiScreenCache`] is rendered to, and the entities rendering and displaying it.
+///
+/// Added to the cached node by [`update_ui_screen_caches`].
+#[derive(Component, Clone, Debug)]
+pub struct CachedUiScreen {
+    pub image: Handle<Image>,
+    /// The camera rendering the screen into `image`, only active on the frames it is rendered.
+    pub camera: Entity,
+    /// The image node displaying `image` in place of the screen.
+    pub display: Entity,
+    /// The camera the screen is displayed on.
+    pub target_camera: Entity,
+    size: UVec2,
+}
+
+/// Marks the camera and display node of a [`CachedUiScreen`], holding the cached node.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UiScreenCachePart(pub Entity);
+
+/// Creates the texture, camera and display node of new [`UiScreenCache`]s, and activates their
+/// camera for a single frame whenever they need to be rendered.
+#[allow(clippy::type_complexity)]
+pub fn update_ui_screen_caches(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut screen_query: Query<(
+        Entity,
+        &mut UiScreenCache,
+        Option<&mut CachedUiScreen>,
+        Option<&TargetCamera>,
+        Option<&ZIndex>,
+    )>,
+    part_query: Query<(Entity, &UiScreenCachePart)>,
+    mut cameras: ParamSet<(DefaultUiCamera, Query<&mut Camera>)>,
+) {
+    for (part_entity, part) in &part_query {
+        if !screen_query.contains(part.0) {
+            commands.entity(part_entity).despawn();
+        }
+    }
+
+    let default_camera = cameras.p0().get();
+    let mut camera_query = cameras.p1();
+
+    for (entity, mut screen_cache, cached, target_camera, z_index) in &mut screen_query {
+        let target_camera = match &cached {
+            Some(cached) => cached.target_camera,
+            None => match target_camera.map(TargetCamera::entity).or(default_camera) {
+                Some(camera) => camera,
+                None => continue,
+            },
+        };
+        let Some(size) = camera_query
+            .get(target_camera)
+            .ok()
+            .and_then(Camera::logical_viewport_size)
+            .map(|size| size.as_uvec2())
+            .filter(|size| size.cmpgt(UVec2::ZERO).all())
+        else {
+            continue;
+        };
+
+        let Some(mut cached) = cached else {
+            let image = images.add(ui_screen_cache_image(size));
+            let order = camera_query
+                .get(target_camera)
+                .map_or(0, |camera| camera.order);
+            let camera = commands
+                .spawn((
+                    Camera2dBundle {
+                        camera: Camera {
+                            // Rendered before the camera displaying it
+                            order: order - 1,
+                            target: RenderTarget::Image(image.clone()),
+                            clear_color: ClearColorConfig::Custom(Color::NONE),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    // Only the UI is rendered into the cache
+                    RenderLayers::none(),
+                    UiScreenCachePart(entity),
+                ))
+                .id();
+            let display = commands
+                .spawn((
+                    ImageBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(0.),
+                            top: Val::Px(0.),
+                            width: Val::Percent(100.),
+                            height: Val::Percent(100.),
+                            ..Default::default()
+                        },
+                        image: UiImage::new(image.clone()),
+                        z_index: z_index.copied().unwrap_or_default(),
+                        ..Default::default()
+                    },
+                    TargetCamera(target_camera),
+                    UiScreenCachePart(entity),
+                ))
+                .id();
+            commands.entity(entity).insert((
+                TargetCamera(camera),
+                CachedUiScreen {
+                    image,
+                    camera,
+                    display,
+                    target_camera,
+                    size,
+                },
+            ));
+            screen_cache.invalidated = false;
+            continue;
+        };
+
+        if cached.size != size {
+            if let Some(image) = images.get_mut(&cached.image) {
+                image.resize(Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    ..Default::default()
+                });
+            }
+            cached.size = size;
+            screen_cache.invalidate();
+        }
+
+        let Ok(mut camera) = camera_query.get_mut(cached.camera) else {
+            continue;
+        };
+        // The screen is only rendered on the frames its cache was invalidated
+        let is_active = screen_cache.invalidated;
+        if is_active {
+            screen_cache.invalidated = false;
+        }
+        if camera.is_active != is_active {
+            camera.is_active = is_active;
+        }
+    }
+}
+
+/// Returns an empty texture of `size` that the UI can be rendered to and sampled from.
+fn ui_screen_cache_image(size: UVec2) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            ..Default::default()
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::{AssetEvent, Assets};
+    use bevy_ecs::{event::Events, prelude::*, schedule::Schedule};
+    use bevy_render::{
+        camera::{Camera, ManualTextureViews, OrthographicProjection},
+        texture::Image,
+    };
+    use bevy_window::{
+        PrimaryWindow, Window, WindowCreated, WindowResized, WindowResolution,
+        WindowScaleFactorChanged,
+    };
+
+    use super::{update_ui_screen_caches, CachedUiScreen, UiScreenCache};
+    use crate::{node_bundles::NodeBundle, TargetCamera, UiImage};
+
+    #[test]
+    fn cached_screen_is_rendered_once_then_only_displayed() {
+        let mut world = World::new();
+        world.init_resource::<Events<WindowScaleFactorChanged>>();
+        world.init_resource::<Events<WindowResized>>();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<ManualTextureViews>();
+        world.spawn((
+            Window {
+                resolution: WindowResolution::new(800., 600.),
+                ..Default::default()
+            },
+            PrimaryWindow,
+        ));
+        let window_camera = world
+            .spawn(bevy_core_pipeline::core_2d::Camera2dBundle::default())
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                bevy_render::camera::camera_system::<OrthographicProjection>,
+                update_ui_screen_caches,
+            )
+                .chain(),
+        );
+
+        let screen = world
+            .spawn((NodeBundle::default(), UiScreenCache::default()))
+            .with_children(|parent| {
+                parent.spawn(NodeBundle::default());
+            })
+            .id();
+
+        // Returns whether the screen is rendered into its texture this frame
+        let mut update = |world: &mut World| {
+            schedule.run(world);
+            let cached = world.get::<CachedUiScreen>(screen).unwrap().clone();
+            world.get::<Camera>(cached.camera).unwrap().is_active
+        };
+
+        assert!(update(&mut world));
+        let cached = world.get::<CachedUiScreen>(screen).unwrap().clone();
+        assert_eq!(
+            world.get::<TargetCamera>(screen),
+            Some(&TargetCamera(cached.camera))
+        );
+        assert_eq!(cached.target_camera, window_camera);
+        assert_eq!(
+            world.get::<UiImage>(cached.display).unwrap().texture,
+            cached.image
+        );
+        assert_eq!(
+            world.get::<TargetCamera>(cached.display),
+            Some(&TargetCamera(window_camera))
+        );
+
+        // Following frames only display the texture
+        assert!(!update(&mut world));
+        assert!(!update(&mut world));
+
+        world.get_mut::<UiScreenCache>(screen).unwrap().invalidate();
+        assert!(update(&mut world));
+        assert!(!update(&mut world));
+
+        // Removing the screen removes its camera and display node
+        world.despawn(screen);
+        schedule.run(&mut world);
+        assert!(world.get_entity(cached.camera).is_none());
+        assert!(world.get_entity(cached.display).is_none());
+    }
+}
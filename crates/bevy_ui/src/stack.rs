@@ -3,7 +3,7 @@
 use bevy_ecs::prelude::*;
 use bevy_hierarchy::prelude::*;
 
-use crate::{Node, ZIndex};
+use crate::{Node, UiTooltip, ZIndex};
 
 /// The current UI stack, which contains all UI nodes ordered by their depth (back-to-front).
 ///
@@ -53,7 +53,7 @@ pub(crate) fn ui_stack_system(
     mut cache: Local<StackingContextCache>,
     mut ui_stack: ResMut<UiStack>,
     root_node_query: Query<Entity, (With<Node>, Without<Parent>)>,
-    zindex_query: Query<&ZIndex, With<Node>>,
+    zindex_query: Query<(Option<&ZIndex>, Has<UiTooltip>), With<Node>>,
     children_query: Query<&Children>,
     mut update_query: Query<&mut Node>,
 ) {
@@ -89,7 +89,7 @@ pub(crate) fn ui_stack_system(
 /// Generate z-index based UI node tree
 fn insert_context_hierarchy(
     cache: &mut StackingContextCache,
-    zindex_query: &Query<&ZIndex, With<Node>>,
+    zindex_query: &Query<(Option<&ZIndex>, Has<UiTooltip>), With<Node>>,
     children_query: &Query<&Children>,
     entity: Entity,
     global_context: &mut StackingContext,
@@ -117,10 +117,14 @@ fn insert_context_hierarchy(
     }
 
     // The node will be added either to global/parent based on its z-index type: global/local.
-    let z_index = zindex_query.get(entity).unwrap_or(&ZIndex::Local(0));
+    let z_index = match zindex_query.get(entity) {
+        Ok((_, true)) => UiTooltip::Z_INDEX,
+        Ok((Some(z_index), false)) => *z_index,
+        _ => ZIndex::Local(0),
+    };
     let (entity_context, z_index) = match z_index {
-        ZIndex::Local(value) => (parent_context.unwrap_or(global_context), *value),
-        ZIndex::Global(value) => (global_context, *value),
+        ZIndex::Local(value) => (parent_context.unwrap_or(global_context), value),
+        ZIndex::Global(value) => (global_context, value),
     };
 
     *total_entry_count += 1;
@@ -264,4 +268,73 @@ mod tests {
         ];
         assert_eq!(actual_result, expected_result);
     }
+
+    #[test]
+    fn tooltip_in_a_clipped_low_z_container_renders_last_and_unclipped() {
+        use bevy_ecs::schedule::IntoSystemConfigs;
+
+        use crate::{
+            node_bundles::NodeBundle, update::update_clipping_system, CalculatedClip, Overflow,
+            Style, UiTooltip,
+        };
+
+        let mut world = World::default();
+        world.init_resource::<UiStack>();
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut tooltip = None;
+        let mut tooltip_child = None;
+        let mut clipped = None;
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        overflow: Overflow::clip(),
+                        ..Default::default()
+                    },
+                    z_index: ZIndex::Global(-5),
+                    ..Default::default()
+                },
+                Label("container"),
+            ))
+            .with_children(|parent| {
+                clipped = Some(parent.spawn(NodeBundle::default()).id());
+                tooltip = Some(
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                z_index: ZIndex::Local(-1),
+                                ..Default::default()
+                            },
+                            UiTooltip,
+                        ))
+                        .with_children(|parent| {
+                            tooltip_child = Some(parent.spawn(NodeBundle::default()).id());
+                        })
+                        .id(),
+                );
+            });
+        commands.spawn((
+            NodeBundle {
+                z_index: ZIndex::Global(10),
+                ..Default::default()
+            },
+            Label("above"),
+        ));
+        queue.apply(&mut world);
+        let (tooltip, tooltip_child, clipped) =
+            (tooltip.unwrap(), tooltip_child.unwrap(), clipped.unwrap());
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((ui_stack_system, update_clipping_system).chain());
+        schedule.run(&mut world);
+
+        let ui_stack = &world.resource::<UiStack>().uinodes;
+        assert_eq!(ui_stack[ui_stack.len() - 2..], [tooltip, tooltip_child]);
+
+        assert!(world.get::<CalculatedClip>(clipped).is_some());
+        assert!(world.get::<CalculatedClip>(tooltip).is_none());
+        assert!(world.get::<CalculatedClip>(tooltip_child).is_none());
+    }
 }
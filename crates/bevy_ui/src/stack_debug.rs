@@ -0,0 +1,225 @@
+//! A debug overlay that labels every UI node with its stack index, to diagnose layering issues.
+
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::view::{InheritedVisibility, Visibility};
+use bevy_text::{Text, TextStyle};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::HashSet;
+
+use crate::{
+    node_bundles::TextBundle, render::stack_z, Node, PositionType, Style, TargetCamera, Val, ZIndex,
+};
+
+/// Draws the stack index and depth of every UI node as a small text label at the node's top left
+/// corner.
+///
+/// Labels are regular text nodes on top of all other UI, so they appear one frame after a node
+/// moves. Disabled by default.
+#[derive(Resource, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Resource, Default, Debug, PartialEq)]
+pub struct UiStackDebugOverlay {
+    pub enabled: bool,
+    /// The font size of the labels, in logical pixels.
+    pub font_size: f32,
+    pub color: Color,
+    pub background_color: Color,
+}
+
+impl Default for UiStackDebugOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            font_size: 10.,
+            color: Color::WHITE,
+            background_color: Color::srgba(0., 0., 0., 0.6),
+        }
+    }
+}
+
+/// Marks a label of the [`UiStackDebugOverlay`], holding the node it is labelling.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UiStackDebugLabel(pub Entity);
+
+/// Returns the text of the [`UiStackDebugOverlay`] label of `node`.
+pub fn ui_stack_debug_label_text(node: &Node) -> String {
+    format!("{} z:{:.3}", node.stack_index, stack_z(node.stack_index))
+}
+
+/// Spawns, updates and despawns the labels of the [`UiStackDebugOverlay`].
+pub fn update_ui_stack_debug_labels(
+    mut commands: Commands,
+    overlay: Res<UiStackDebugOverlay>,
+    node_query: Query<
+        (
+            Entity,
+            &Node,
+            &GlobalTransform,
+            Option<&InheritedVisibility>,
+            Option<&TargetCamera>,
+        ),
+        Without<UiStackDebugLabel>,
+    >,
+    mut label_query: Query<(
+        Entity,
+        &UiStackDebugLabel,
+        &mut Text,
+        &mut Style,
+        &mut Visibility,
+    )>,
+    mut labelled: Local<HashSet<Entity>>,
+) {
+    labelled.clear();
+
+    for (label_entity, label, mut text, mut style, mut visibility) in &mut label_query {
+        let Ok((_, node, global_transform, inherited_visibility, _)) = node_query.get(label.0)
+        else {
+            commands.entity(label_entity).despawn();
+            continue;
+        };
+        if !overlay.enabled {
+            commands.entity(label_entity).despawn();
+            continue;
+        }
+
+        labelled.insert(label.0);
+
+        let value = ui_stack_debug_label_text(node);
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+        let (left, top) = label_position(node, global_transform);
+        if style.left != left || style.top != top {
+            style.left = left;
+            style.top = top;
+        }
+        let target_visibility = label_visibility(inherited_visibility);
+        if *visibility != target_visibility {
+            *visibility = target_visibility;
+        }
+    }
+
+    if !overlay.enabled {
+        return;
+    }
+
+    for (entity, node, global_transform, inherited_visibility, target_camera) in &node_query {
+        if labelled.contains(&entity) {
+            continue;
+        }
+
+        let (left, top) = label_position(node, global_transform);
+        let mut label = commands.spawn((
+            TextBundle::from_section(
+                ui_stack_debug_label_text(node),
+                TextStyle {
+                    font_size: overlay.font_size,
+                    color: overlay.color,
+                    ..Default::default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                left,
+                top,
+                ..Default::default()
+            })
+            .with_background_color(overlay.background_color),
+            ZIndex::Global(i32::MAX),
+            label_visibility(inherited_visibility),
+            UiStackDebugLabel(entity),
+        ));
+        if let Some(target_camera) = target_camera {
+            label.insert(target_camera.clone());
+        }
+    }
+}
+
+fn label_position(node: &Node, global_transform: &GlobalTransform) -> (Val, Val) {
+    let top_left = global_transform.translation().truncate() - 0.5 * node.size();
+    (Val::Px(top_left.x), Val::Px(top_left.y))
+}
+
+fn label_visibility(inherited_visibility: Option<&InheritedVisibility>) -> Visibility {
+    if inherited_visibility.map_or(true, |visibility| visibility.get()) {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::{App, Update};
+    use bevy_ecs::prelude::*;
+    use bevy_math::Vec2;
+    use bevy_text::Text;
+    use bevy_transform::components::GlobalTransform;
+
+    use super::{update_ui_stack_debug_labels, UiStackDebugLabel, UiStackDebugOverlay};
+    use crate::Node;
+
+    fn node(stack_index: u32) -> (Node, GlobalTransform) {
+        (
+            Node {
+                stack_index,
+                calculated_size: Vec2::new(20., 10.),
+                ..Default::default()
+            },
+            GlobalTransform::from_xyz(10. * stack_index as f32, 0., 0.),
+        )
+    }
+
+    #[test]
+    fn one_label_per_node_with_its_stack_index() {
+        let mut app = App::new();
+        app.init_resource::<UiStackDebugOverlay>()
+            .add_systems(Update, update_ui_stack_debug_labels);
+        let nodes: Vec<Entity> = (0..3)
+            .map(|stack_index| app.world_mut().spawn(node(stack_index)).id())
+            .collect();
+
+        app.update();
+        let label_count = app
+            .world_mut()
+            .query::<&UiStackDebugLabel>()
+            .iter(app.world())
+            .count();
+        assert_eq!(label_count, 0);
+
+        app.world_mut()
+            .resource_mut::<UiStackDebugOverlay>()
+            .enabled = true;
+        app.update();
+        // A second update must neither label the labels nor duplicate them
+        app.update();
+
+        let mut labels: Vec<(Entity, String)> = app
+            .world_mut()
+            .query::<(&UiStackDebugLabel, &Text)>()
+            .iter(app.world())
+            .map(|(label, text)| (label.0, text.sections[0].value.clone()))
+            .collect();
+        labels.sort_by_key(|(entity, _)| *entity);
+        assert_eq!(
+            labels,
+            vec![
+                (nodes[0], "0 z:0.000".to_string()),
+                (nodes[1], "1 z:0.001".to_string()),
+                (nodes[2], "2 z:0.002".to_string()),
+            ]
+        );
+
+        app.world_mut()
+            .resource_mut::<UiStackDebugOverlay>()
+            .enabled = false;
+        app.update();
+        let label_count = app
+            .world_mut()
+            .query::<&UiStackDebugLabel>()
+            .iter(app.world())
+            .count();
+        assert_eq!(label_count, 0);
+    }
+}
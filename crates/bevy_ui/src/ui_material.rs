@@ -1,7 +1,22 @@
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash, Hasher};
 
-use bevy_asset::Asset;
-use bevy_render::render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef};
+use bevy_asset::{Asset, Handle};
+use bevy_color::Color;
+use bevy_ecs::{
+    component::Component,
+    reflect::{ReflectComponent, ReflectResource},
+    system::Resource,
+    world::EntityRef,
+};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    extract_resource::ExtractResource,
+    render_resource::{
+        AsBindGroup, BlendState, CompareFunction, FrontFace, RenderPipelineDescriptor, Shader,
+        ShaderDefVal, ShaderRef, Source, VertexBufferLayout,
+    },
+};
+use bevy_utils::FixedState;
 
 /// Materials are used alongside [`UiMaterialPlugin`](crate::UiMaterialPlugin) and [`MaterialNodeBundle`](crate::prelude::MaterialNodeBundle)
 /// to spawn entities that are rendered with a specific [`UiMaterial`] type. They serve as an easy to use high level
@@ -12,6 +27,10 @@ use bevy_render::render_resource::{AsBindGroup, RenderPipelineDescriptor, Shader
 ///
 /// Materials must also implement [`Asset`] so they can be treated as such.
 ///
+/// A material can bind several textures, each with its own `#[texture]` and `#[sampler]`
+/// bindings. Fields of type `Option<Handle<Image>>` that are `None` are bound to the
+/// [`FallbackImage`](bevy_render::texture::FallbackImage).
+///
 /// If you are only using the fragment shader, make sure your shader imports the `UiVertexOutput`
 /// from `bevy_ui::ui_vertex_output` and uses it as the input of your fragment shader like the
 /// example below does.
@@ -90,6 +109,38 @@ use bevy_render::render_resource::{AsBindGroup, RenderPipelineDescriptor, Shader
 ///
 /// }
 /// ```
+///
+/// Besides uniforms and textures, materials can bind read-only storage buffers with
+/// `#[storage(0, read_only)]` on a `Handle<ShaderStorageBuffer>`, for data exceeding the size
+/// limits of uniforms such as a large palette. The
+/// [`ShaderStorageBuffer`](bevy_render::storage::ShaderStorageBuffer) is prepared before the
+/// materials of the frame, and nodes whose material binds a buffer that isn't prepared yet aren't
+/// drawn. Storage buffers aren't available on WebGL2.
+///
+/// Fragment shaders should multiply the alpha of their output by `in.opacity`, so the node can
+/// be faded with [`UiMaterialOpacity`], [`UiMaterialCrossfade`] and
+/// [`UiOpacity`](crate::UiOpacity).
+///
+/// Text nodes can be drawn with a material too, see [`UiMaterialText`]. The glyph atlas is bound
+/// to bind group 2, after the material's own bind group, so the material's textures and samplers
+/// don't collide with it. Fragment shaders should also multiply their alpha by the coverage of the
+/// glyph returned by `ui_material_mask(in)`, imported from `bevy_ui::ui_material_mask`, which is 1
+/// on other nodes.
+///
+/// # Color spaces
+///
+/// Fragment shaders output linear colors by default, like the rest of the UI: node and text
+/// colors are converted to linear when they're extracted. The 8-bit
+/// [`TextureFormat::bevy_default`](bevy_render::render_resource::TextureFormat) target of LDR
+/// views encodes them to sRGB when they're written, while HDR views and materials blended in a
+/// linear texture (see [`UiMaterial::target_color_space`]) store them unchanged. A mid gray of
+/// `0.5` is thus stored as the byte 188 on LDR views, and as `0.5` on HDR views.
+///
+/// Materials computing sRGB-encoded colors, e.g. sampled from a texture with a non-sRGB format,
+/// have to decode them in their fragment shader. Returning [`UiColorSpace::Srgb`] from
+/// [`UiMaterial::color_space`] only sets the `UI_OUTPUT_SRGB` shader def: the output isn't
+/// converted unless the shader passes it through `ui_output_color`, imported from
+/// `bevy_ui::ui_color_space`, which decodes it to the linear color the target expects.
 pub trait UiMaterial: AsBindGroup + Asset + Clone + Sized {
     /// Returns this materials vertex shader. If [`ShaderRef::Default`] is returned, the default UI
     /// vertex shader will be used.
@@ -97,19 +148,353 @@ pub trait UiMaterial: AsBindGroup + Asset + Clone + Sized {
         ShaderRef::Default
     }
 
+    /// Returns the layout of the vertex buffer of nodes using this material, or `None` to use
+    /// [`ui_material_vertex_layout`](crate::ui_material_vertex_layout).
+    ///
+    /// Custom layouts add per node data to the vertices, e.g. a corner radius or a gradient
+    /// direction. They must start with the attributes of the default layout, at locations 0 to 5,
+    /// followed by the custom attributes whose bytes are written by
+    /// [`UiMaterial::extract_vertex_data`]. Build them with
+    /// [`ui_material_vertex_layout_with`](crate::ui_material_vertex_layout_with), which packs the
+    /// custom attributes right after the 56 bytes of the default ones.
+    ///
+    /// Every vertex attribute offset and the array stride must be multiples of 4 bytes, so custom
+    /// attributes of fewer bytes, like `Uint8x2`, have to be padded. Use a
+    /// [`UiMaterial::vertex_shader`] reading the custom attributes.
+    fn vertex_buffer_layout() -> Option<VertexBufferLayout> {
+        None
+    }
+
+    /// Appends the bytes of the custom vertex attributes of the node `entity` to `data`, when
+    /// [`UiMaterial::vertex_buffer_layout`] isn't `None`. They're copied after the default
+    /// attributes of each vertex of the node.
+    ///
+    /// Runs during extraction, so `entity` is read from the main world. The number of bytes must
+    /// be the array stride of the layout minus the 56 bytes of the default attributes, for every
+    /// node.
+    #[allow(unused_variables)]
+    fn extract_vertex_data(&self, entity: EntityRef, data: &mut Vec<u8>) {}
+
+    /// Returns the size in bytes of the push constants of nodes using this material, written by
+    /// [`UiMaterial::extract_push_constants`], or 0 for none.
+    ///
+    /// Push constants suit small parameters changing every frame, like the highlight of a hovered
+    /// button, which would otherwise need a material per node or a modified material asset. Shaders
+    /// declare them as `var<push_constant>` when the `UI_PUSH_CONSTANTS` shader def is set, or
+    /// as `@group(3) @binding(0) var<uniform>` when `UI_PUSH_CONSTANTS_UNIFORM` is set instead,
+    /// see [`UiPushConstantMode`](crate::UiPushConstantMode).
+    ///
+    /// The size must be a multiple of 4 bytes. Devices only guarantee 128 bytes of push
+    /// constants, the size falls back to a uniform on the devices supporting fewer, and on those
+    /// without push constants, like WebGL2.
+    ///
+    /// Defaults to 0.
+    fn push_constant_size() -> u32 {
+        0
+    }
+
+    /// Appends the bytes of the push constants of the node `entity` to `data`, when
+    /// [`UiMaterial::push_constant_size`] isn't 0.
+    ///
+    /// Runs during extraction, so `entity` is read from the main world. The bytes are padded
+    /// with zeros or truncated to the size of the push constants. Consecutive nodes with the same
+    /// material are only drawn together when their push constants are the same.
+    #[allow(unused_variables)]
+    fn extract_push_constants(&self, entity: EntityRef, data: &mut Vec<u8>) {}
+
     /// Returns this materials fragment shader. If [`ShaderRef::Default`] is returned, the default
     /// UI fragment shader will be used.
     fn fragment_shader() -> ShaderRef {
         ShaderRef::Default
     }
 
+    /// Returns the fragment shader used while the pipeline using [`UiMaterial::fragment_shader`]
+    /// is still being compiled.
+    ///
+    /// Pipelines are compiled in the background by the
+    /// [`PipelineCache`](bevy_render::render_resource::PipelineCache) where supported, so a large
+    /// shader doesn't stall rendering. Nodes using this material are drawn with the fallback until
+    /// their pipeline is ready, which should be a cheap shader that compiles quickly. If
+    /// [`ShaderRef::Default`] is returned, the default UI fragment shader is used.
+    ///
+    /// Defaults to `None`: nodes using this material aren't drawn until their pipeline is ready.
+    fn fallback_fragment_shader() -> Option<ShaderRef> {
+        None
+    }
+
+    /// Returns whether nodes using this material are also drawn into the UI prepass.
+    ///
+    /// The UI prepass runs before the main UI pass and writes the depth of each opted-in node into
+    /// [`ViewUiPrepassTextures`](crate::ViewUiPrepassTextures), where nodes higher in the
+    /// [`UiStack`](crate::UiStack) are closer to the camera. Later effects (outline detection,
+    /// ambient occlusion between UI layers, ...) can read this texture.
+    ///
+    /// Defaults to `false`.
+    fn prepass_enabled() -> bool {
+        false
+    }
+
+    /// Returns the depth comparison of the pipeline specialized for `key`, when it has a depth
+    /// attachment (see [`UiMaterial::prepass_enabled`] and [`UiMaterial::alpha_mode`]).
+    ///
+    /// Nodes failing the comparison against the depth already written are discarded, e.g.
+    /// [`CompareFunction::Always`] draws an overlay regardless of depth while still writing its own.
+    ///
+    /// Defaults to [`CompareFunction::LessEqual`].
+    #[allow(unused_variables)]
+    fn depth_compare(key: UiMaterialKey<Self>) -> CompareFunction {
+        CompareFunction::LessEqual
+    }
+
+    /// Returns the blend state of the pipeline specialized for `key`, combining the output of the
+    /// fragment shader with the nodes below.
+    ///
+    /// E.g. an additive blend brightens the nodes below a glow, and
+    /// [`BlendState::PREMULTIPLIED_ALPHA_BLENDING`] blends a shader outputting premultiplied
+    /// colors. Materials can return different blends depending on `key.bind_group_data`, which
+    /// are specialized into different pipelines. Ignored for nodes drawn with
+    /// [`UiAlphaMode::Opaque`], which aren't blended.
+    ///
+    /// Defaults to [`BlendState::ALPHA_BLENDING`].
+    #[allow(unused_variables)]
+    fn blend_state(key: UiMaterialKey<Self>) -> Option<BlendState> {
+        Some(BlendState::ALPHA_BLENDING)
+    }
+
+    /// Returns whether nodes using this material cover every pixel of their quad with an opaque
+    /// color, see [`UiAlphaMode`].
+    ///
+    /// Defaults to [`UiAlphaMode::Blend`].
+    fn alpha_mode() -> UiAlphaMode {
+        UiAlphaMode::Blend
+    }
+
+    /// Returns the color space of the texture nodes using this material are blended in.
+    ///
+    /// Defaults to [`UiTargetColorSpace::Srgb`].
+    fn target_color_space() -> UiTargetColorSpace {
+        UiTargetColorSpace::Srgb
+    }
+
+    /// Returns the color space of the colors output by the fragment shader of this material.
+    ///
+    /// This is only a shader def toggle: [`UiColorSpace::Srgb`] sets the `UI_OUTPUT_SRGB` shader
+    /// def, which makes `ui_output_color` from `bevy_ui::ui_color_space` decode the colors passed
+    /// to it. The pipeline doesn't convert the output of shaders that don't call it. Unlike
+    /// [`UiMaterial::target_color_space`], this doesn't change the texture the nodes are blended
+    /// in.
+    ///
+    /// Defaults to [`UiColorSpace::Linear`].
+    fn color_space() -> UiColorSpace {
+        UiColorSpace::Linear
+    }
+
+    /// Returns whether the shaders of this material read the view of the scene below the UI.
+    ///
+    /// If `true`, the `UI_SCENE_VIEW` shader def is set and the view bind group gains:
+    /// - `@group(0) @binding(2) var<uniform> scene_view: View`, the view uniform of the camera the
+    ///   UI is drawn over, with its world and view matrices.
+    /// - `@group(0) @binding(3) var scene_color: texture_2d<f32>`, a copy of the main texture of
+    ///   the camera taken before any UI is drawn.
+    /// - `@group(0) @binding(4) var scene_sampler: sampler`.
+    ///
+    /// `scene_color` covers the whole render target, so it's sampled at
+    /// `in.position.xy / vec2<f32>(textureDimensions(scene_color))`. The main texture is only
+    /// copied for the views drawing nodes of such materials.
+    ///
+    /// Defaults to `false`.
+    fn reads_view_transform() -> bool {
+        false
+    }
+
+    /// Returns whether this material takes values from the [`UiTheme`], see
+    /// [`UiMaterial::apply_theme`].
+    ///
+    /// Defaults to `false`.
+    fn uses_theme() -> bool {
+        false
+    }
+
+    /// Copies values of the `theme` into this material, e.g. its accent color into a uniform.
+    ///
+    /// Only called if [`UiMaterial::uses_theme`] returns `true`, on a copy of the material each
+    /// time it's prepared for the GPU, so the asset keeps its own values. Every material of a
+    /// theme-aware type is prepared again when the [`UiTheme`] changes.
+    #[allow(unused_variables)]
+    fn apply_theme(&mut self, theme: &UiTheme) {}
+
+    /// Returns the shader defs of the pipeline specialized for `key`, added to both its vertex and
+    /// fragment shader so they can branch with `#ifdef` at compile time.
+    ///
+    /// Materials usually derive them from `key.bind_group_data`, set with
+    /// `#[bind_group_data(...)]`, so materials with different values get different pipelines.
+    ///
+    /// Defaults to none.
+    #[allow(unused_variables)]
+    fn shader_defs(key: &UiMaterialKey<Self>) -> Vec<ShaderDefVal> {
+        Vec::new()
+    }
+
+    /// Customizes the pipeline specialized for `key`, after the [`UiMaterial::shader_defs`] have
+    /// been added to its shaders.
     #[allow(unused_variables)]
     #[inline]
     fn specialize(descriptor: &mut RenderPipelineDescriptor, key: UiMaterialKey<Self>) {}
 }
 
+/// Values shared by the [`UiMaterial`]s of the whole UI, to re-theme it at once.
+///
+/// Materials returning `true` from [`UiMaterial::uses_theme`] take the values they need in
+/// [`UiMaterial::apply_theme`] when their bind group is prepared. Changing this resource prepares
+/// all of them again, without editing their assets or the nodes using them.
+#[derive(Resource, ExtractResource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource, Debug, Default, PartialEq)]
+pub struct UiTheme {
+    /// The color highlighting interactive or selected elements.
+    pub accent: Color,
+    /// The color of panels and other surfaces.
+    pub background: Color,
+    /// The color of text and icons drawn over the background.
+    pub foreground: Color,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            accent: Color::srgb(0.25, 0.5, 1.),
+            background: Color::srgb(0.15, 0.15, 0.15),
+            foreground: Color::WHITE,
+        }
+    }
+}
+
+/// How the nodes of a [`UiMaterial`] are combined with the UI below them, see
+/// [`UiMaterial::alpha_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UiAlphaMode {
+    /// Blended over the nodes below them, in stack order.
+    #[default]
+    Blend,
+    /// Drawn front to back in the [`OpaqueUi`](crate::OpaqueUi) phase, without blending, writing
+    /// the depth of the node to the [`ViewUiDepthTexture`](crate::ui_opaque::ViewUiDepthTexture)
+    /// of the view. Only the topmost opaque node of each pixel is shaded, which saves the cost of
+    /// blending the panels hidden below it.
+    ///
+    /// The alpha output by the fragment shader is ignored, so the material must cover its whole
+    /// quad. Nodes drawn with a [`UiMaterialOpacity`] or [`UiOpacity`](crate::UiOpacity) below 1,
+    /// the glyphs of [`UiMaterialText`], nodes of materials with [`UiMaterial::prepass_enabled`] or
+    /// blended in a linear texture, and nodes of views whose UI can't have a depth texture are
+    /// still blended.
+    Opaque,
+}
+
+/// The color space of the texture the nodes of a [`UiMaterial`] are blended in, see
+/// [`UiMaterial::target_color_space`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UiTargetColorSpace {
+    /// Blended in the target of the view like the rest of the UI, which stores colors in 8-bit
+    /// sRGB on LDR views.
+    #[default]
+    Srgb,
+    /// Blended in a linear floating point texture, which is composited over the target once the
+    /// consecutive nodes of linear materials are drawn.
+    ///
+    /// The material's pipeline always outputs to a linear format, so overlapping nodes keep their
+    /// precision and out of range values until they're composited, e.g. for data visualization.
+    /// This is how every material renders on HDR views.
+    Linear,
+}
+
+/// The color space of the colors output by the fragment shader of a [`UiMaterial`], see
+/// [`UiMaterial::color_space`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UiColorSpace {
+    /// The shader outputs linear colors, which are encoded to sRGB by the target of LDR views and
+    /// stored unchanged by linear targets.
+    #[default]
+    Linear,
+    /// The shader outputs sRGB-encoded colors, and decodes them to linear with `ui_output_color`
+    /// before they're written, so they're stored unchanged by the target of LDR views.
+    Srgb,
+}
+
+/// Multiplies the alpha of the [`UiMaterial`] a node is drawn with, passed to its fragment shader
+/// as `in.opacity`.
+///
+/// Nodes with a [`UiMaterialCrossfade`] get this component kept in sync with the blend factor.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+pub struct UiMaterialOpacity(pub f32);
+
+impl Default for UiMaterialOpacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Crossfades a node from its own [`UiMaterial`] to `material`, e.g. to animate the style of a
+/// button on hover.
+///
+/// Both materials are drawn, the node's own one with an opacity of `1 - blend` and `material`
+/// over it with an opacity of `blend`. The node doesn't need a `Handle<M>` of its own, in which
+/// case only `material` is drawn. Requires the [`UiMaterialPlugin`](crate::UiMaterialPlugin) of
+/// `M`, which keeps the [`UiMaterialOpacity`] of the node in sync with `blend`.
+#[derive(Component, Debug)]
+pub struct UiMaterialCrossfade<M: UiMaterial> {
+    /// The material the node fades to.
+    pub material: Handle<M>,
+    /// How far the node has faded to `material`, from 0 to 1.
+    pub blend: f32,
+}
+
+impl<M: UiMaterial> UiMaterialCrossfade<M> {
+    pub fn new(material: Handle<M>, blend: f32) -> Self {
+        Self { material, blend }
+    }
+
+    /// Returns the opacities of the node's own material and of `material`, which add up to 1.
+    pub fn opacities(&self) -> [f32; 2] {
+        let blend = self.blend.clamp(0., 1.);
+        [1. - blend, blend]
+    }
+}
+
+impl<M: UiMaterial> Clone for UiMaterialCrossfade<M> {
+    fn clone(&self) -> Self {
+        Self {
+            material: self.material.clone(),
+            blend: self.blend,
+        }
+    }
+}
+
+/// Marks a text node drawn with its [`UiMaterial`] instead of the colors of its sections.
+///
+/// Inserted on text nodes with a `Handle<M>` by the [`UiMaterialPlugin`](crate::UiMaterialPlugin)
+/// of `M`. Each glyph is drawn as a quad with the material, whose UVs and size span the whole node
+/// so e.g. a gradient runs across the text, masked by the coverage of the glyph in its atlas.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component, Debug, Default)]
+pub struct UiMaterialText;
+
 pub struct UiMaterialKey<M: UiMaterial> {
     pub hdr: bool,
+    /// The winding of front-facing triangles, see [`ui_front_face`](crate::ui_front_face).
+    pub front_face: FrontFace,
+    /// Whether the pipeline is specialized for the UI prepass instead of the main UI pass.
+    pub prepass: bool,
+    /// Whether the pipeline draws opaque nodes in the [`OpaqueUi`](crate::OpaqueUi) phase, see
+    /// [`UiAlphaMode::Opaque`].
+    pub opaque: bool,
+    /// Whether the UI of the view is drawn with a
+    /// [`ViewUiDepthTexture`](crate::ui_opaque::ViewUiDepthTexture).
+    pub depth: bool,
+    /// Whether the pipeline uses [`UiMaterial::fallback_fragment_shader`] instead of
+    /// [`UiMaterial::fragment_shader`].
+    pub fallback: bool,
+    /// The number of MSAA samples of the UI pass, see
+    /// [`ui_msaa_samples`](crate::ui_msaa::ui_msaa_samples). Always 1 for the prepass.
+    pub samples: u32,
     pub bind_group_data: M::Data,
 }
 
@@ -120,7 +505,14 @@ where
     M::Data: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.hdr == other.hdr && self.bind_group_data == other.bind_group_data
+        self.hdr == other.hdr
+            && self.front_face == other.front_face
+            && self.prepass == other.prepass
+            && self.opaque == other.opaque
+            && self.depth == other.depth
+            && self.fallback == other.fallback
+            && self.samples == other.samples
+            && self.bind_group_data == other.bind_group_data
     }
 }
 
@@ -131,6 +523,12 @@ where
     fn clone(&self) -> Self {
         Self {
             hdr: self.hdr,
+            front_face: self.front_face,
+            prepass: self.prepass,
+            opaque: self.opaque,
+            depth: self.depth,
+            fallback: self.fallback,
+            samples: self.samples,
             bind_group_data: self.bind_group_data.clone(),
         }
     }
@@ -142,6 +540,80 @@ where
 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.hdr.hash(state);
+        self.front_face.hash(state);
+        self.prepass.hash(state);
+        self.opaque.hash(state);
+        self.depth.hash(state);
+        self.fallback.hash(state);
+        self.samples.hash(state);
         self.bind_group_data.hash(state);
     }
 }
+
+impl<M: UiMaterial> UiMaterialKey<M>
+where
+    M::Data: Hash,
+{
+    /// Returns a hash of this key and of the sources of the `shaders` it is specialized with,
+    /// which is the same across runs of the app.
+    ///
+    /// Unlike the [`Hash`] used by the
+    /// [`PipelineCache`](bevy_render::render_resource::PipelineCache), which is seeded randomly,
+    /// it can identify the compiled pipeline in a cache persisted to disk. It changes whenever a
+    /// shader is edited. `M::Data` must hash deterministically too, e.g. not contain pointers.
+    pub fn stable_hash<'a>(&self, shaders: impl IntoIterator<Item = &'a Shader>) -> u64 {
+        let mut hasher = FixedState.build_hasher();
+        self.hash(&mut hasher);
+        for shader in shaders {
+            shader.path.hash(&mut hasher);
+            match &shader.source {
+                Source::Wgsl(source) | Source::Glsl(source, _) => source.hash(&mut hasher),
+                Source::SpirV(source) => source.hash(&mut hasher),
+            }
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::Asset;
+    use bevy_reflect::TypePath;
+    use bevy_render::render_resource::{AsBindGroup, FrontFace, Shader};
+
+    use super::{UiMaterial, UiMaterialKey};
+
+    #[derive(AsBindGroup, Asset, TypePath, Clone)]
+    struct TestMaterial {}
+
+    impl UiMaterial for TestMaterial {}
+
+    #[test]
+    fn material_key_hashes_the_same_across_runs() {
+        let key = |hdr| UiMaterialKey::<TestMaterial> {
+            hdr,
+            front_face: FrontFace::Ccw,
+            prepass: false,
+            opaque: false,
+            depth: false,
+            fallback: false,
+            samples: 1,
+            bind_group_data: (),
+        };
+        let shader = |source: &'static str| Shader::from_wgsl(source, "test_material.wgsl");
+        let v1 = shader("@fragment fn fragment() -> @location(0) vec4<f32> { return vec4(1.0); }");
+        let v2 = shader("@fragment fn fragment() -> @location(0) vec4<f32> { return vec4(0.5); }");
+
+        // Every run hashes with the same seed, unlike the `RandomState` of the pipeline cache
+        assert_eq!(key(false).stable_hash([&v1]), key(false).stable_hash([&v1]));
+        assert_eq!(
+            key(false).stable_hash([&v1]),
+            key(false).stable_hash([&shader(
+                "@fragment fn fragment() -> @location(0) vec4<f32> { return vec4(1.0); }"
+            )])
+        );
+        // Changing the key or editing the shader changes the hash
+        assert_ne!(key(false).stable_hash([&v1]), key(true).stable_hash([&v1]));
+        assert_ne!(key(false).stable_hash([&v1]), key(false).stable_hash([&v2]));
+    }
+}
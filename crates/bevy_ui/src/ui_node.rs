@@ -1,12 +1,13 @@
 use crate::{UiRect, Val};
-use bevy_asset::Handle;
-use bevy_color::Color;
+use bevy_asset::{Asset, Assets, Handle};
+use bevy_color::{Color, ColorToComponents, LinearRgba, Oklaba};
 use bevy_ecs::{prelude::*, system::SystemParam};
-use bevy_math::{Rect, Vec2};
+use bevy_math::{Rect, URect, Vec2, Vec4};
 use bevy_reflect::prelude::*;
 use bevy_render::{
-    camera::{Camera, RenderTarget},
-    texture::{Image, TRANSPARENT_IMAGE_HANDLE},
+    camera::{Camera, RenderTarget, Viewport},
+    mesh::Mesh,
+    texture::{Image, ImageSamplerDescriptor, TRANSPARENT_IMAGE_HANDLE},
 };
 use bevy_transform::prelude::GlobalTransform;
 use bevy_utils::warn_once;
@@ -1709,6 +1710,200 @@ impl<T: Into<Color>> From<T> for BackgroundColor {
     }
 }
 
+/// Fills the background of a node with a gradient between a color at each of its corners.
+///
+/// Replaces the node's [`BackgroundColor`]. The colors are interpolated across the node's quad by
+/// the GPU, add [`UiGradientTessellation`] to interpolate them at more points on large nodes.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiCornerColors {
+    pub top_left: Color,
+    pub top_right: Color,
+    pub bottom_right: Color,
+    pub bottom_left: Color,
+}
+
+impl UiCornerColors {
+    pub const DEFAULT: Self = Self::all(Color::NONE);
+
+    /// Uses `color` at every corner.
+    pub const fn all(color: Color) -> Self {
+        Self {
+            top_left: color,
+            top_right: color,
+            bottom_right: color,
+            bottom_left: color,
+        }
+    }
+
+    /// A gradient from `left` to `right`.
+    pub const fn horizontal(left: Color, right: Color) -> Self {
+        Self {
+            top_left: left,
+            top_right: right,
+            bottom_right: right,
+            bottom_left: left,
+        }
+    }
+
+    /// A gradient from `top` to `bottom`.
+    pub const fn vertical(top: Color, bottom: Color) -> Self {
+        Self {
+            top_left: top,
+            top_right: top,
+            bottom_right: bottom,
+            bottom_left: bottom,
+        }
+    }
+
+    /// Returns the colors ordered top left, top right, bottom right, bottom left.
+    pub fn to_array(&self) -> [Color; 4] {
+        [
+            self.top_left,
+            self.top_right,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+    }
+}
+
+impl Default for UiCornerColors {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Splits the background quad of a node with [`UiCornerColors`] into a grid of smaller quads.
+///
+/// Each grid vertex gets its own interpolated color, which reduces the banding of smooth
+/// gradients across large nodes at the cost of more vertices.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiGradientTessellation {
+    /// The largest width and height of a grid cell, in logical pixels.
+    pub max_cell_size: f32,
+}
+
+impl UiGradientTessellation {
+    pub const DEFAULT: Self = Self { max_cell_size: 32. };
+}
+
+impl Default for UiGradientTessellation {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The color space the [`UiCornerColors`] of a node are interpolated in.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiGradientInterpolation {
+    /// Interpolates the linear RGB components, which is cheapest but makes gradients between
+    /// saturated colors pass through dull midtones.
+    #[default]
+    Linear,
+    /// Interpolates in the perceptual [Oklab](https://bottosson.github.io/posts/oklab/) color
+    /// space, keeping the lightness and saturation of the midtones even.
+    Oklab,
+}
+
+impl UiGradientInterpolation {
+    /// Returns the color at `st` of a gradient between `corners`, ordered top left, top right,
+    /// bottom right, bottom left, where `st` is `(0, 0)` at the top left and `(1, 1)` at the
+    /// bottom right corner.
+    ///
+    /// This matches the colors interpolated by the UI shader.
+    pub fn mix(self, corners: [LinearRgba; 4], st: Vec2) -> LinearRgba {
+        let bilerp = |corners: [Vec4; 4]| {
+            let top = corners[0].lerp(corners[1], st.x);
+            let bottom = corners[3].lerp(corners[2], st.x);
+            top.lerp(bottom, st.y)
+        };
+        match self {
+            UiGradientInterpolation::Linear => {
+                LinearRgba::from_vec4(bilerp(corners.map(|color| color.to_vec4())))
+            }
+            UiGradientInterpolation::Oklab => {
+                let [lightness, a, b, alpha] = bilerp(
+                    corners.map(|color| Vec4::from_array(Oklaba::from(color).to_f32_array())),
+                )
+                .to_array();
+                Oklaba::new(lightness, a, b, alpha).into()
+            }
+        }
+    }
+}
+
+/// Whether the edges of a node's rounded corners and borders are antialiased.
+///
+/// Pixel-art UIs can turn it off, so that each pixel along an edge is either fully covered by the
+/// node or not at all. The UI pipelines are never multisampled, so this only changes how the UI
+/// shader smooths edges.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiAntialias {
+    /// Edges fade out over half a pixel on either side.
+    #[default]
+    On,
+    /// Pixels whose center is inside the edge are fully covered, the others aren't.
+    Off,
+}
+
+/// A background color shared by a group of nodes.
+///
+/// Nodes with a [`Handle<SharedColor>`] are filled with the shared color instead of their
+/// [`BackgroundColor`], which is only used until the asset is loaded. Changing the asset recolors
+/// every node of the group, without a write to each node's components.
+#[derive(Asset, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub struct SharedColor(pub Color);
+
+impl SharedColor {
+    /// Returns the color a node should be filled with, given its `background_color` and the
+    /// handle of its shared color, if any.
+    pub fn resolve(
+        background_color: &BackgroundColor,
+        shared_color: Option<&Handle<SharedColor>>,
+        shared_colors: &Assets<SharedColor>,
+    ) -> Color {
+        shared_color
+            .and_then(|handle| shared_colors.get(handle))
+            .map_or(background_color.0, |shared_color| shared_color.0)
+    }
+}
+
+impl Default for SharedColor {
+    fn default() -> Self {
+        Self(Color::NONE)
+    }
+}
+
+impl<T: Into<Color>> From<T> for SharedColor {
+    fn from(color: T) -> Self {
+        Self(color.into())
+    }
+}
+
 /// The border color of the UI node.
 #[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
 #[reflect(Component, Default, Debug, PartialEq)]
@@ -1736,6 +1931,55 @@ impl Default for BorderColor {
     }
 }
 
+/// A color for each side of the border of a UI node, replacing its [`BorderColor`].
+///
+/// Sides of different colors meet along the diagonals of the corners, from the outer to the inner
+/// corner of the border, so sides with different widths are mitered like in CSS.
+///
+/// Each side with a color and a width is drawn as its own quad covering the whole node, and the
+/// shader discards the fragments of the other sides. A node with four colored sides therefore
+/// costs four times the fill of a single [`BorderColor`], so prefer [`BorderColor`] for large
+/// nodes whose sides share a color. The miter between two sides is a hard edge: it is not
+/// anti-aliased like the outer and inner edges of the border.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct BorderSideColors {
+    pub left: Color,
+    pub top: Color,
+    pub right: Color,
+    pub bottom: Color,
+}
+
+impl BorderSideColors {
+    pub const DEFAULT: Self = Self::all(Color::NONE);
+
+    /// Uses `color` for every side.
+    pub const fn all(color: Color) -> Self {
+        Self {
+            left: color,
+            top: color,
+            right: color,
+            bottom: color,
+        }
+    }
+
+    /// Returns the colors ordered left, top, right, bottom, like the border widths of a node.
+    pub fn to_array(&self) -> [Color; 4] {
+        [self.left, self.top, self.right, self.bottom]
+    }
+}
+
+impl Default for BorderSideColors {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 #[derive(Component, Copy, Clone, Default, Debug, PartialEq, Reflect)]
 #[reflect(Component, Default, Debug, PartialEq)]
 #[cfg_attr(
@@ -1820,6 +2064,272 @@ impl Outline {
     }
 }
 
+/// Adds a drop shadow behind a UI node.
+///
+/// The shadow is a copy of the node's rounded rectangle, grown by `spread_radius`, moved by
+/// `x_offset` and `y_offset` and blurred by `blur_radius`. It is drawn just below its node.
+///
+/// Percentage `Val` values are resolved based on the width of the node.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct BoxShadow {
+    /// The color of the shadow.
+    pub color: Color,
+    /// Horizontal offset of the shadow.
+    pub x_offset: Val,
+    /// Vertical offset of the shadow.
+    pub y_offset: Val,
+    /// How much the shadow is grown (or shrunk, if negative) before it is blurred.
+    pub spread_radius: Val,
+    /// The distance over which the edge of the shadow fades out.
+    pub blur_radius: Val,
+    /// If `true`, the shadow is not clipped by the [`CalculatedClip`] of its node.
+    ///
+    /// This lets the shadows of nodes inside a scrolling container extend past the edges of the
+    /// container, so they keep looking elevated at its boundaries.
+    pub escape_clip: bool,
+}
+
+impl Default for BoxShadow {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            x_offset: Val::Percent(20.),
+            y_offset: Val::Percent(20.),
+            spread_radius: Val::ZERO,
+            blur_radius: Val::Percent(10.),
+            escape_clip: false,
+        }
+    }
+}
+
+/// Blurs everything rendered below this node before the node and its descendants are drawn.
+///
+/// Meant for the root node of a modal dialog: the scene and the UI behind the dialog are
+/// snapshotted, blurred and tinted in a single full screen pass, which is much cheaper than
+/// blurring behind each node of the dialog.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiModalBackdrop {
+    /// The blur radius in logical pixels.
+    pub blur: f32,
+    /// The color blended over the blurred backdrop, using its alpha as the blend factor.
+    pub tint: Color,
+}
+
+impl UiModalBackdrop {
+    pub const DEFAULT: Self = Self {
+        blur: 8.,
+        tint: Color::srgba(0., 0., 0., 0.3),
+    };
+}
+
+impl Default for UiModalBackdrop {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Masks the UI rendered by this camera to a non-rectangular shape, such as a screen with
+/// rounded corners.
+///
+/// Add it to a camera entity. The shape is fitted to the camera's viewport: the UI is kept
+/// inside of it, and outside of it whatever was rendered before the UI shows through.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiViewportMask {
+    pub shape: UiViewportMaskShape,
+    /// Width in logical pixels over which the mask fades out at the shape's edge.
+    ///
+    /// Edges are always antialiased over at least one physical pixel.
+    pub feather: f32,
+}
+
+impl UiViewportMask {
+    pub const DEFAULT: Self = Self {
+        shape: UiViewportMaskShape::RoundedRect { radius: 0. },
+        feather: 0.,
+    };
+
+    /// Masks the UI to the viewport with its corners rounded by `radius` logical pixels.
+    pub const fn rounded(radius: f32) -> Self {
+        Self {
+            shape: UiViewportMaskShape::RoundedRect { radius },
+            feather: 0.,
+        }
+    }
+
+    /// Masks the UI to the ellipse inscribed in the viewport.
+    pub const fn ellipse() -> Self {
+        Self {
+            shape: UiViewportMaskShape::Ellipse,
+            feather: 0.,
+        }
+    }
+
+    pub const fn with_feather(mut self, feather: f32) -> Self {
+        self.feather = feather;
+        self
+    }
+}
+
+impl Default for UiViewportMask {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The shape of a [`UiViewportMask`], fitted to the camera's viewport.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiViewportMaskShape {
+    /// The viewport with rounded corners, `radius` is in logical pixels.
+    RoundedRect { radius: f32 },
+    /// The ellipse inscribed in the viewport.
+    Ellipse,
+}
+
+impl Default for UiViewportMaskShape {
+    fn default() -> Self {
+        Self::RoundedRect { radius: 0. }
+    }
+}
+
+/// Renders the UI of this camera in LDR, even if the camera has `hdr` enabled.
+///
+/// Add it to a camera entity. Its UI is drawn to an intermediate LDR texture, which is then
+/// composited over the camera's HDR output, so UI colors are clamped and blended the same way they
+/// would be on an LDR camera, regardless of the tonemapping or bloom applied to the scene.
+/// Has no effect on LDR cameras.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiForceLdr;
+
+/// Culls the UI nodes of this camera lying entirely outside of its viewport.
+///
+/// Add it to a camera entity. The nodes rendered to the camera whose bounds, grown by `margin`
+/// logical pixels on each side, don't intersect the viewport of its UI have their
+/// [`ViewVisibility`](bevy_render::view::ViewVisibility) hidden, so they're neither extracted nor
+/// drawn. Each node is tested on its own: the children overflowing a culled node are still drawn,
+/// but not the box shadows, outlines or glyphs drawn beyond its bounds unless `margin` covers them.
+/// Disabled by default, so layouts drawing past the bounds of their nodes aren't affected.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiViewportCulling {
+    pub margin: f32,
+}
+
+/// Where the UI of a camera is drawn in its render graph.
+///
+/// Add it to a camera entity, the UI of cameras without it is drawn as an [`UiPassLayer::Overlay`].
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiPassLayer {
+    /// The UI is drawn over the whole scene once it is post-processed, including its transparent
+    /// objects.
+    #[default]
+    Overlay,
+    /// The UI is drawn after the opaque objects of the scene and before its transparent ones,
+    /// tested against the depth of the scene: nodes with a [`UiSceneDepth`], and their
+    /// descendants, are hidden behind the opaque objects closer to the camera. The UI is
+    /// tonemapped and post-processed along with the scene.
+    ///
+    /// Only the nodes drawn by the built-in UI pipeline are occluded, [`UiMaterial`](crate::UiMaterial)
+    /// and sliced image nodes are drawn over the scene. The UI of cameras with [`Msaa`] samples or
+    /// a [`UiForceLdr`] is drawn as an [`UiPassLayer::Overlay`] instead, since it is drawn to an
+    /// intermediate texture composited over the main texture, which the multisampled scene would
+    /// then be resolved over.
+    ///
+    /// [`Msaa`]: bevy_render::view::Msaa
+    InScene,
+}
+
+/// The depth of a node in the 3D scene of a [`UiPassLayer::InScene`] camera, inherited by its
+/// descendants.
+///
+/// This is the depth in normalized device coordinates, the `z` returned by
+/// [`Camera::world_to_ndc`] for the point of the scene the node follows, closer points having a
+/// greater depth. The node is hidden wherever an opaque object of the scene is in front of that
+/// depth. Nodes of an in-scene camera without one are drawn on the near plane, over the whole
+/// scene.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiSceneDepth(pub f32);
+
+/// The native viewport of a camera that renders its scene at a dynamically scaled resolution.
+///
+/// Dynamic resolution shrinks the camera's [`Viewport`] to render the scene with fewer pixels,
+/// before upscaling it to the output. Add this to such a camera with the unscaled viewport: the
+/// UI is then laid out, projected and rendered at that native resolution instead, so text and
+/// edges stay sharp whatever resolution the scene is rendered at.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct UiNativeViewport(pub Viewport);
+
+/// Returns the viewport the UI of `camera` is rendered to in physical pixels, and its size in
+/// logical pixels.
+///
+/// This is the [`UiNativeViewport`] of the camera if it has one, or else the camera's viewport.
+pub fn ui_viewport(
+    camera: &Camera,
+    native_viewport: Option<&UiNativeViewport>,
+) -> Option<(URect, Vec2)> {
+    match native_viewport {
+        Some(UiNativeViewport(viewport)) => {
+            let scale_factor = camera.target_scaling_factor()?;
+            let rect = URect::from_corners(
+                viewport.physical_position,
+                viewport.physical_position + viewport.physical_size,
+            );
+            Some((rect, viewport.physical_size.as_vec2() / scale_factor))
+        }
+        None => Some((
+            camera.physical_viewport_rect()?,
+            camera.logical_viewport_size()?,
+        )),
+    }
+}
+
 /// The 2D texture displayed for this UI node
 #[derive(Component, Clone, Debug, Reflect)]
 #[reflect(Component, Default, Debug)]
@@ -1842,7 +2352,27 @@ pub struct UiImage {
     ///
     /// When used with a [`TextureAtlas`](bevy_sprite::TextureAtlas), the rect
     /// is offset by the atlas's minimal (top-left) corner position.
+    ///
+    /// The rect is in texels, and clamped to the bounds of the image, or of the frame of the
+    /// atlas. An image whose rect is entirely outside of these bounds isn't drawn. Images only
+    /// kept in the render world (see [`RenderAssetUsages`](bevy_render::render_asset::RenderAssetUsages))
+    /// aren't clamped without an atlas, as their size isn't known when they're extracted.
     pub rect: Option<Rect>,
+    /// The sampler used to draw the image instead of the sampler of the texture, e.g.
+    /// [`ImageSamplerDescriptor::nearest`] to keep pixel art sharp when it's scaled up.
+    ///
+    /// Images sliced with an [`ImageScaleMode`](bevy_sprite::ImageScaleMode) always use the
+    /// sampler of their texture.
+    #[reflect(ignore)]
+    pub sampler: Option<ImageSamplerDescriptor>,
+    /// Whether the colors of the texture are premultiplied by their alpha, e.g. images exported
+    /// premultiplied by an image editor. The image is then blended with
+    /// [`BlendState::PREMULTIPLIED_ALPHA_BLENDING`](bevy_render::render_resource::BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+    /// instead of showing dark fringes around its transparent edges.
+    ///
+    /// Images sliced with an [`ImageScaleMode`](bevy_sprite::ImageScaleMode) are always blended
+    /// as straight alpha.
+    pub premultiplied_alpha: bool,
 }
 
 impl Default for UiImage {
@@ -1863,6 +2393,8 @@ impl Default for UiImage {
             flip_x: false,
             flip_y: false,
             rect: None,
+            sampler: None,
+            premultiplied_alpha: false,
         }
     }
 }
@@ -1887,6 +2419,8 @@ impl UiImage {
             flip_x: false,
             flip_y: false,
             rect: None,
+            sampler: None,
+            premultiplied_alpha: false,
         }
     }
 
@@ -1916,6 +2450,20 @@ impl UiImage {
         self.rect = Some(rect);
         self
     }
+
+    /// Sample the image with `sampler` instead of the sampler of its texture
+    #[must_use]
+    pub fn with_sampler(mut self, sampler: ImageSamplerDescriptor) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Blend the image as premultiplied alpha, see [`UiImage::premultiplied_alpha`]
+    #[must_use]
+    pub const fn with_premultiplied_alpha(mut self) -> Self {
+        self.premultiplied_alpha = true;
+        self
+    }
 }
 
 impl From<Handle<Image>> for UiImage {
@@ -1924,6 +2472,107 @@ impl From<Handle<Image>> for UiImage {
     }
 }
 
+/// Displays a single array layer of the texture of a [`UiImage`].
+///
+/// Without it, a [`UiImage`] needs a plain 2D texture. With it, the image can show one layer of a
+/// 2D array texture or one face of a cube map, such as a skybox preview. Cube map faces are
+/// ordered `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`. Layers past the last one show the last layer.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiImageLayer(pub u32);
+
+/// Draws this node as a 2D [`Mesh`] instead of a rectangle, e.g. for radial menus, progress arcs
+/// or other custom shapes.
+///
+/// The mesh replaces the background of the node and is filled with its [`BackgroundColor`]. Its
+/// positions are laid out in the rect of the node: `(0, 0)` is the center of the node, `(-0.5,
+/// -0.5)` its bottom left corner and `(0.5, 0.5)` its top right corner, with the y axis pointing up
+/// like the meshes of 2D primitives, so a mesh of `Circle::new(0.5)` fills the node. Only the x
+/// and y coordinates of the positions are used.
+///
+/// The mesh must be a triangle list and stay loaded in the main world. Mesh nodes are always
+/// clipped with a scissor rect, see [`UiClipMode::Scissor`], and their borders, outlines and
+/// shadows are still rectangular.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct UiMesh(pub Handle<Mesh>);
+
+/// How the sampled texture color of a [`UiImage`] is combined with its tint color.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiColorBlend {
+    /// The texture color is multiplied by the tint color.
+    #[default]
+    Multiply,
+    /// The texture color is used as is, the tint color is ignored.
+    Replace,
+    /// The tint color is overlaid on the texture color: dark parts of the texture are multiplied
+    /// by the tint, bright parts are screened with it.
+    Overlay,
+    /// The texture only provides the brightness, the color comes from the tint.
+    Tint,
+}
+
+/// Multiplies the alpha of a node and of all of its descendants, e.g. to fade a whole panel in
+/// or out.
+///
+/// The opacities of nested nodes are multiplied together. Values are clamped to `0.0..=1.0`, and
+/// nodes with an opacity of 0 aren't drawn. The backgrounds, borders, outlines, shadows, images
+/// and text of the nodes have their colors faded, while nodes drawn with a
+/// [`UiMaterial`](crate::UiMaterial) get their `in.opacity` multiplied, like a
+/// [`UiMaterialOpacity`](crate::UiMaterialOpacity).
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiOpacity(pub f32);
+
+impl UiOpacity {
+    /// Returns the opacity, clamped to `0.0..=1.0`.
+    pub fn get(&self) -> f32 {
+        self.0.clamp(0., 1.)
+    }
+}
+
+impl Default for UiOpacity {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+/// Clips the descendants of a node to its shape, e.g. to crop an image to a circle with a
+/// [`BorderRadius`] of 50%.
+///
+/// The rounded rect of the node is written to a stencil texture after the node is drawn, and the
+/// fragments of its descendants outside of it are discarded. Masks can be nested, the descendants
+/// of an inner mask are only drawn inside of both masks.
+///
+/// Only the backgrounds, borders, outlines, shadows, images and text of the descendants are
+/// masked, not the nodes drawn with a [`UiMaterial`](crate::UiMaterial) or a sliced image.
+/// Descendants with a [`ZIndex::Global`] drawn after the last descendant of the mask are hidden.
+/// Opaque materials of the cameras drawing masks are drawn without a depth texture.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiStencilMask;
+
 /// The calculated clip of the node
 #[derive(Component, Default, Copy, Clone, Debug, Reflect)]
 #[reflect(Component, Default, Debug)]
@@ -1932,6 +2581,55 @@ pub struct CalculatedClip {
     pub clip: Rect,
 }
 
+/// Grows the [`CalculatedClip`] of this node, and only this node, by the given amount of
+/// logical pixels on every side.
+///
+/// This lets a node's own shadow or glow render slightly beyond the clip of its parents without
+/// disabling clipping entirely. Has no effect on nodes that aren't clipped.
+#[derive(Component, Default, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiClipInflate(pub f32);
+
+/// Keeps this node drawn when the [`UiVertexBudget`](crate::UiVertexBudget) is exceeded.
+///
+/// The vertices of the nodes with this component are reserved first, in stack order, and the
+/// other nodes only get the rest of the budget. Useful for the parts of the UI that must stay
+/// visible, like a pause menu or a warning, in a UI crowded with nodes.
+#[derive(Component, Default, Copy, Clone, Debug, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct UiVertexPriority;
+
+/// How the [`CalculatedClip`] of this node is applied when it's rendered.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum UiClipMode {
+    /// The node is drawn whole, and only the pixels inside the clip are kept with a scissor rect.
+    ///
+    /// This clips rotated and scaled nodes correctly, to the nearest pixel.
+    #[default]
+    Scissor,
+    /// The vertices of the node are moved to the edges of the clip.
+    ///
+    /// Clipped edges are placed with sub-pixel precision, but this is only correct for nodes that
+    /// aren't rotated.
+    Vertices,
+}
+
 /// Indicates that this [`Node`] entity's front-to-back ordering is not controlled solely
 /// by its location in the UI hierarchy. A node with a higher z-index will appear on top
 /// of other nodes with a lower z-index.
@@ -1961,6 +2659,31 @@ impl Default for ZIndex {
     }
 }
 
+/// Marks a node as a tooltip, drawn above all other UI and never clipped by its ancestors.
+///
+/// The tooltip and its descendants are stacked as if the tooltip had [`UiTooltip::Z_INDEX`],
+/// replacing its own [`ZIndex`], and ignore the clipping rect of the tooltip's ancestors, so a
+/// tooltip can be spawned as the child of the node it describes wherever that node is. It is still
+/// laid out relative to its parent, use [`PositionType::Absolute`] to take it out of the flow.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct UiTooltip;
+
+impl UiTooltip {
+    /// The z-index of tooltips, above every other global z-index.
+    pub const Z_INDEX: ZIndex = ZIndex::Global(i32::MAX);
+}
+
+/// Stops a node with visible overflow from passing the clip of its ancestors to its children.
+///
+/// Without it, the children of a node with [`OverflowAxis::Visible`] are not clipped by the node
+/// itself but are still clipped by its ancestors. With it, they aren't clipped on the visible axes
+/// at all, while the axes the node clips keep clipping to the node and its ancestors.
+/// The node itself is still clipped by its ancestors.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct UiUnclippedOverflow;
+
 /// Used to add rounded corners to a UI node. You can set a UI node to have uniformly
 /// rounded corners or specify different radii for each corner. If a given radius exceeds half
 /// the length of the smallest dimension between the node's height or width, the radius will
@@ -2268,7 +2991,10 @@ impl ResolvedBorderRadius {
 
 #[cfg(test)]
 mod tests {
-    use crate::GridPlacement;
+    use bevy_asset::Assets;
+    use bevy_color::Color;
+
+    use crate::{BackgroundColor, GridPlacement, SharedColor};
 
     #[test]
     fn invalid_grid_placement_values() {
@@ -2286,6 +3012,34 @@ mod tests {
         assert!(std::panic::catch_unwind(|| GridPlacement::default().set_span(0)).is_err());
     }
 
+    #[test]
+    fn changing_a_shared_color_recolors_every_node() {
+        let mut shared_colors = Assets::<SharedColor>::default();
+        let handle = shared_colors.add(SharedColor(Color::BLACK));
+        let nodes = [
+            (BackgroundColor(Color::WHITE), Some(handle.clone())),
+            (BackgroundColor(Color::WHITE), Some(handle.clone())),
+            (BackgroundColor(Color::WHITE), None),
+        ];
+
+        shared_colors.get_mut(&handle).unwrap().0 = Color::srgb(1., 0., 0.);
+
+        let colors: Vec<Color> = nodes
+            .iter()
+            .map(|(background_color, shared_color)| {
+                SharedColor::resolve(background_color, shared_color.as_ref(), &shared_colors)
+            })
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                Color::srgb(1., 0., 0.),
+                Color::srgb(1., 0., 0.),
+                Color::WHITE
+            ]
+        );
+    }
+
     #[test]
     fn grid_placement_accessors() {
         assert_eq!(GridPlacement::start(5).get_start(), Some(5));
@@ -2295,6 +3049,82 @@ mod tests {
         assert_eq!(GridPlacement::start_span(3, 5).get_end(), None);
         assert_eq!(GridPlacement::end_span(-4, 12).get_start(), None);
     }
+
+    #[test]
+    fn ui_keeps_the_native_viewport_with_a_scaled_scene_resolution() {
+        use bevy_asset::AssetEvent;
+        use bevy_ecs::{event::Events, prelude::*, schedule::Schedule};
+        use bevy_math::{URect, UVec2, Vec2};
+        use bevy_render::{
+            camera::{Camera, ManualTextureViews, OrthographicProjection, Viewport},
+            texture::Image,
+        };
+        use bevy_window::{
+            PrimaryWindow, Window, WindowCreated, WindowResized, WindowResolution,
+            WindowScaleFactorChanged,
+        };
+
+        use crate::{ui_viewport, UiNativeViewport};
+
+        let mut world = World::new();
+        world.init_resource::<Events<WindowScaleFactorChanged>>();
+        world.init_resource::<Events<WindowResized>>();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<ManualTextureViews>();
+        world.spawn((
+            Window {
+                resolution: WindowResolution::new(800., 600.).with_scale_factor_override(2.),
+                ..Default::default()
+            },
+            PrimaryWindow,
+        ));
+        let native_size = UVec2::new(1600, 1200);
+        // The scene is rendered at half of the native resolution
+        let camera = world
+            .spawn((
+                bevy_core_pipeline::core_2d::Camera2dBundle {
+                    camera: Camera {
+                        viewport: Some(Viewport {
+                            physical_size: native_size / 2,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                UiNativeViewport(Viewport {
+                    physical_size: native_size,
+                    ..Default::default()
+                }),
+            ))
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(bevy_render::camera::camera_system::<OrthographicProjection>);
+        schedule.run(&mut world);
+
+        let camera_ref = world.entity(camera);
+        let scene_camera = camera_ref.get::<Camera>().unwrap();
+        let native_viewport = camera_ref.get::<UiNativeViewport>();
+        assert_eq!(scene_camera.physical_viewport_size(), Some(native_size / 2));
+        assert_eq!(
+            ui_viewport(scene_camera, native_viewport),
+            Some((
+                URect::from_corners(UVec2::ZERO, native_size),
+                Vec2::new(800., 600.)
+            ))
+        );
+        // Without a native viewport the UI follows the camera's viewport
+        assert_eq!(
+            ui_viewport(scene_camera, None),
+            Some((
+                URect::from_corners(UVec2::ZERO, native_size / 2),
+                Vec2::new(400., 300.)
+            ))
+        );
+    }
 }
 
 /// Indicates that this root [`Node`] entity should be rendered to a specific camera.
@@ -2305,6 +3135,10 @@ mod tests {
 /// by the root node's component.
 ///
 /// Optional if there is only one camera in the world. Required otherwise.
+///
+/// The UI isn't shared between cameras: root nodes without this component are only drawn by the
+/// [`DefaultUiCamera`]. In split-screen, give each camera its own roots targeting it, and each
+/// viewport shows its own UI laid out for its size.
 #[derive(Component, Clone, Debug, Reflect, Eq, PartialEq)]
 #[reflect(Component, Debug, PartialEq)]
 pub struct TargetCamera(pub Entity);
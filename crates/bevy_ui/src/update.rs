@@ -1,15 +1,22 @@
 //! This module contains systems that update the UI when something changes
 
-use crate::{CalculatedClip, Display, OverflowAxis, Style, TargetCamera};
+use crate::{
+    ui_viewport, CalculatedClip, DefaultUiCamera, Display, OverflowAxis, Style, TargetCamera,
+    UiNativeViewport, UiScale, UiTooltip, UiUnclippedOverflow, UiViewportCulling,
+};
 
 use super::Node;
 use bevy_ecs::{
     entity::Entity,
-    query::{Changed, With, Without},
-    system::{Commands, Query},
+    query::{Changed, Has, With, Without},
+    system::{Commands, Query, Res},
 };
 use bevy_hierarchy::{Children, Parent};
-use bevy_math::Rect;
+use bevy_math::{Rect, Vec2};
+use bevy_render::{
+    camera::Camera,
+    view::{RenderLayers, ViewVisibility},
+};
 use bevy_transform::components::GlobalTransform;
 use bevy_utils::HashSet;
 
@@ -17,7 +24,14 @@ use bevy_utils::HashSet;
 pub fn update_clipping_system(
     mut commands: Commands,
     root_node_query: Query<Entity, (With<Node>, Without<Parent>)>,
-    mut node_query: Query<(&Node, &GlobalTransform, &Style, Option<&mut CalculatedClip>)>,
+    mut node_query: Query<(
+        &Node,
+        &GlobalTransform,
+        &Style,
+        Option<&mut CalculatedClip>,
+        Has<UiTooltip>,
+        Has<UiUnclippedOverflow>,
+    )>,
     children_query: Query<&Children>,
 ) {
     for root_node in &root_node_query {
@@ -34,15 +48,28 @@ pub fn update_clipping_system(
 fn update_clipping(
     commands: &mut Commands,
     children_query: &Query<&Children>,
-    node_query: &mut Query<(&Node, &GlobalTransform, &Style, Option<&mut CalculatedClip>)>,
+    node_query: &mut Query<(
+        &Node,
+        &GlobalTransform,
+        &Style,
+        Option<&mut CalculatedClip>,
+        Has<UiTooltip>,
+        Has<UiUnclippedOverflow>,
+    )>,
     entity: Entity,
     mut maybe_inherited_clip: Option<Rect>,
 ) {
-    let Ok((node, global_transform, style, maybe_calculated_clip)) = node_query.get_mut(entity)
+    let Ok((node, global_transform, style, maybe_calculated_clip, is_tooltip, is_unclipped)) =
+        node_query.get_mut(entity)
     else {
         return;
     };
 
+    // Tooltips escape the clipping of their ancestors
+    if is_tooltip {
+        maybe_inherited_clip = None;
+    }
+
     // If `display` is None, clip the entire node and all its descendants by replacing the inherited clip with a default rect (which is empty)
     if style.display == Display::None {
         maybe_inherited_clip = Some(Rect::default());
@@ -74,7 +101,8 @@ fn update_clipping(
         // the current node's boundaries. In this case they inherit the current
         // node's parent clip. If an ancestor is set as `Hidden`, that clip will
         // be used; otherwise this will be `None`.
-        maybe_inherited_clip
+        // With `UiUnclippedOverflow`, the ancestors' clip isn't passed through either.
+        maybe_inherited_clip.filter(|_| !is_unclipped)
     } else {
         // If `maybe_inherited_clip` is `Some`, use the intersection between
         // current node's clip and the inherited clip. This handles the case
@@ -89,7 +117,19 @@ fn update_clipping(
             node_rect.min.y = -f32::INFINITY;
             node_rect.max.y = f32::INFINITY;
         }
-        Some(maybe_inherited_clip.map_or(node_rect, |c| c.intersect(node_rect)))
+        let mut clip = maybe_inherited_clip.map_or(node_rect, |c| c.intersect(node_rect));
+        if is_unclipped {
+            // Only the clipping axes keep the ancestors' clip
+            if style.overflow.x == OverflowAxis::Visible {
+                clip.min.x = -f32::INFINITY;
+                clip.max.x = f32::INFINITY;
+            }
+            if style.overflow.y == OverflowAxis::Visible {
+                clip.min.y = -f32::INFINITY;
+                clip.max.y = f32::INFINITY;
+            }
+        }
+        Some(clip)
     };
 
     if let Ok(children) = children_query.get(entity) {
@@ -181,3 +221,256 @@ fn update_children_target_camera(
         );
     }
 }
+
+/// Hides the nodes with [`RenderLayers`] that don't intersect the layers of the camera they're
+/// rendered to, their [`TargetCamera`] or the [`DefaultUiCamera`].
+///
+/// [`check_visibility`](bevy_render::view::check_visibility) shows a node seen by any camera, so
+/// this keeps a node off the cameras rendering other layers of the UI. Cameras without
+/// [`RenderLayers`] are on the default layer `0`. Nodes without [`RenderLayers`] are drawn by
+/// their camera whatever its layers, and like for other entities, the layers of a node aren't
+/// inherited by its children.
+pub fn hide_nodes_outside_camera_layers(
+    default_ui_camera: DefaultUiCamera,
+    camera_query: Query<Option<&RenderLayers>, With<Camera>>,
+    mut node_query: Query<(&mut ViewVisibility, &RenderLayers, Option<&TargetCamera>), With<Node>>,
+) {
+    let default_camera = default_ui_camera.get();
+    for (mut view_visibility, layers, target_camera) in &mut node_query {
+        if !view_visibility.get() {
+            continue;
+        }
+        let Some(camera_layers) = target_camera
+            .map(TargetCamera::entity)
+            .or(default_camera)
+            .and_then(|camera| camera_query.get(camera).ok())
+        else {
+            continue;
+        };
+        if !layers.intersects(camera_layers.unwrap_or(&RenderLayers::default())) {
+            *view_visibility = ViewVisibility::HIDDEN;
+        }
+    }
+}
+
+/// Hides the nodes lying entirely outside of the viewport of their camera, for the cameras with a
+/// [`UiViewportCulling`].
+pub fn hide_nodes_outside_camera_viewport(
+    default_ui_camera: DefaultUiCamera,
+    ui_scale: Res<UiScale>,
+    camera_query: Query<(&Camera, &UiViewportCulling, Option<&UiNativeViewport>)>,
+    mut node_query: Query<(
+        &mut ViewVisibility,
+        &Node,
+        &GlobalTransform,
+        Option<&TargetCamera>,
+    )>,
+) {
+    if camera_query.is_empty() {
+        return;
+    }
+    let default_camera = default_ui_camera.get();
+    for (mut view_visibility, node, transform, target_camera) in &mut node_query {
+        if !view_visibility.get() {
+            continue;
+        }
+        let Some((camera, culling, native_viewport)) = target_camera
+            .map(TargetCamera::entity)
+            .or(default_camera)
+            .and_then(|camera| camera_query.get(camera).ok())
+        else {
+            continue;
+        };
+        let Some((_, logical_size)) = ui_viewport(camera, native_viewport) else {
+            continue;
+        };
+        // The nodes are laid out in logical pixels divided by the `UiScale`
+        let viewport = Rect::from_corners(Vec2::ZERO, logical_size / ui_scale.0);
+        if ui_node_outside_viewport(transform, node.size(), viewport.inflate(culling.margin)) {
+            *view_visibility = ViewVisibility::HIDDEN;
+        }
+    }
+}
+
+/// Returns `true` if the bounds of a node of `size` with `transform` lie entirely outside of
+/// `viewport`.
+///
+/// Rotated and scaled nodes are tested with the axis-aligned bounds of their corners. Nodes
+/// touching an edge of the viewport aren't outside of it.
+pub fn ui_node_outside_viewport(transform: &GlobalTransform, size: Vec2, viewport: Rect) -> bool {
+    let corners = [
+        Vec2::new(-0.5, -0.5),
+        Vec2::new(0.5, -0.5),
+        Vec2::new(0.5, 0.5),
+        Vec2::new(-0.5, 0.5),
+    ]
+    .map(|corner| {
+        transform
+            .transform_point((corner * size).extend(0.))
+            .truncate()
+    });
+    let min = corners.into_iter().reduce(Vec2::min).unwrap();
+    let max = corners.into_iter().reduce(Vec2::max).unwrap();
+    max.x < viewport.min.x
+        || max.y < viewport.min.y
+        || viewport.max.x < min.x
+        || viewport.max.y < min.y
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        schedule::Schedule,
+        system::Commands,
+        world::{CommandQueue, World},
+    };
+    use bevy_hierarchy::BuildChildren;
+    use bevy_math::{Rect, Vec2, Vec3};
+    use bevy_transform::components::GlobalTransform;
+
+    use super::update_clipping_system;
+    use crate::{CalculatedClip, Node, Overflow, Style, UiUnclippedOverflow};
+
+    fn node(size: f32, center: f32, overflow: Overflow) -> (Node, GlobalTransform, Style) {
+        (
+            Node {
+                calculated_size: Vec2::splat(size),
+                ..Default::default()
+            },
+            GlobalTransform::from_translation(Vec3::new(center, center, 0.)),
+            Style {
+                overflow,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn unclipped_overflow_container_does_not_clip_its_oversized_child() {
+        let mut world = World::default();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut container = None;
+        let mut oversized = None;
+        let mut passed_through = None;
+        commands
+            .spawn(node(100., 50., Overflow::clip()))
+            .with_children(|parent| {
+                container = Some(
+                    parent
+                        .spawn((node(10., 5., Overflow::visible()), UiUnclippedOverflow))
+                        .with_children(|parent| {
+                            oversized =
+                                Some(parent.spawn(node(500., 250., Overflow::DEFAULT)).id());
+                        })
+                        .id(),
+                );
+                parent
+                    .spawn(node(10., 5., Overflow::visible()))
+                    .with_children(|parent| {
+                        passed_through =
+                            Some(parent.spawn(node(500., 250., Overflow::DEFAULT)).id());
+                    });
+            });
+        queue.apply(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_clipping_system);
+        schedule.run(&mut world);
+
+        let root_clip = Rect::new(0., 0., 100., 100.);
+        // The container itself is still clipped by its ancestors
+        assert_eq!(
+            world
+                .get::<CalculatedClip>(container.unwrap())
+                .unwrap()
+                .clip,
+            root_clip
+        );
+        assert!(world.get::<CalculatedClip>(oversized.unwrap()).is_none());
+        // A visible container without the marker passes the ancestors' clip through
+        assert_eq!(
+            world
+                .get::<CalculatedClip>(passed_through.unwrap())
+                .unwrap()
+                .clip,
+            root_clip
+        );
+    }
+
+    #[test]
+    fn nodes_are_only_shown_on_cameras_rendering_their_layers() {
+        use bevy_render::{
+            camera::Camera,
+            view::{RenderLayers, ViewVisibility},
+        };
+
+        use super::hide_nodes_outside_camera_layers;
+        use crate::TargetCamera;
+
+        let mut world = World::default();
+        let camera = world
+            .spawn((Camera::default(), RenderLayers::layer(1)))
+            .id();
+        world.spawn((Camera::default(), RenderLayers::layer(2)));
+
+        let mut visible = ViewVisibility::HIDDEN;
+        // Visible to one of the cameras, as set by `check_visibility`
+        visible.set();
+        let mut spawn_node = |layers: Option<RenderLayers>| {
+            let mut node = world.spawn((Node::default(), visible, TargetCamera(camera)));
+            if let Some(layers) = layers {
+                node.insert(layers);
+            }
+            node.id()
+        };
+        let on_camera_layer = spawn_node(Some(RenderLayers::layer(1)));
+        let on_other_layer = spawn_node(Some(RenderLayers::layer(2)));
+        let on_both_layers = spawn_node(Some(RenderLayers::from_layers(&[1, 2])));
+        let without_layers = spawn_node(None);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(hide_nodes_outside_camera_layers);
+        schedule.run(&mut world);
+
+        let is_visible = |entity| world.get::<ViewVisibility>(entity).unwrap().get();
+        assert!(is_visible(on_camera_layer));
+        assert!(!is_visible(on_other_layer));
+        assert!(is_visible(on_both_layers));
+        assert!(is_visible(without_layers));
+    }
+
+    #[test]
+    fn nodes_outside_the_viewport_are_culled() {
+        use bevy_math::Quat;
+        use bevy_transform::components::Transform;
+
+        use super::ui_node_outside_viewport;
+
+        let viewport = Rect::new(0., 0., 800., 600.);
+        let node = |x: f32, y: f32| GlobalTransform::from_translation(Vec3::new(x, y, 0.));
+        let size = Vec2::new(100., 40.);
+
+        assert!(!ui_node_outside_viewport(&node(400., 300.), size, viewport));
+        // Partially inside, or touching an edge
+        assert!(!ui_node_outside_viewport(&node(-40., 300.), size, viewport));
+        assert!(!ui_node_outside_viewport(&node(400., 620.), size, viewport));
+        // Below the viewport, like the rows of a long list
+        assert!(ui_node_outside_viewport(&node(400., 700.), size, viewport));
+        assert!(ui_node_outside_viewport(&node(-60., 300.), size, viewport));
+        // Kept by a margin
+        assert!(!ui_node_outside_viewport(
+            &node(-60., 300.),
+            size,
+            viewport.inflate(20.)
+        ));
+
+        // Rotated nodes are tested with their bounds
+        let tall = Vec2::new(40., 100.);
+        let rotated = GlobalTransform::from(
+            Transform::from_xyz(-30., 300., 0.).with_rotation(Quat::from_rotation_z(1.)),
+        );
+        assert!(ui_node_outside_viewport(&node(-30., 300.), tall, viewport));
+        assert!(!ui_node_outside_viewport(&rotated, tall, viewport));
+    }
+}
@@ -11,7 +11,7 @@ use bevy_ecs::{
     system::{Local, Query, Res, ResMut},
     world::{Mut, Ref},
 };
-use bevy_math::Vec2;
+use bevy_math::{Rect, Vec2};
 use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_render::{camera::Camera, texture::Image};
 use bevy_sprite::TextureAtlasLayout;
@@ -43,6 +43,106 @@ impl Default for TextFlags {
     }
 }
 
+/// Per-section opacity multipliers for a UI [`Text`] node.
+///
+/// The alpha of each section's color is multiplied by the entry at the section's index when the
+/// text is extracted for rendering, so individual sections can be faded without changing their
+/// base color. Sections without an entry are drawn fully opaque. Values are clamped to `0.0..=1.0`.
+#[derive(Component, Debug, Clone, Default, Reflect)]
+#[reflect(Component, Default, Debug)]
+pub struct TextSectionOpacity(pub Vec<f32>);
+
+impl TextSectionOpacity {
+    /// Returns the opacity multiplier of the section at `section_index`.
+    pub fn get(&self, section_index: usize) -> f32 {
+        self.0
+            .get(section_index)
+            .map_or(1.0, |opacity| opacity.clamp(0.0, 1.0))
+    }
+
+    /// Sets the opacity multiplier of the section at `section_index`, padding any preceding
+    /// sections with `1.0`.
+    pub fn set(&mut self, section_index: usize, opacity: f32) {
+        if self.0.len() <= section_index {
+            self.0.resize(section_index + 1, 1.0);
+        }
+        self.0[section_index] = opacity;
+    }
+}
+
+/// Fades out the glyphs of a UI [`Text`] node near the edges of its clip that the text overflows.
+///
+/// Instead of glyphs cut in half at the clip edge, the last visible glyphs fade out for a soft
+/// truncation. Only edges the text extends past are faded, and the layout is unchanged.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextOverflowFade {
+    /// The distance from the clip edge, in logical pixels, over which glyphs fade out.
+    pub width: f32,
+}
+
+impl TextOverflowFade {
+    pub const DEFAULT: Self = Self { width: 24. };
+
+    /// Returns the opacity multiplier of a glyph centered at `glyph_center`, for text covering
+    /// `text_bounds` drawn inside of `clip`.
+    pub fn alpha(&self, glyph_center: Vec2, text_bounds: Rect, clip: Rect) -> f32 {
+        let width = self.width.max(f32::EPSILON);
+        let mut alpha: f32 = 1.;
+        if clip.max.x < text_bounds.max.x {
+            alpha = alpha.min((clip.max.x - glyph_center.x) / width);
+        }
+        if text_bounds.min.x < clip.min.x {
+            alpha = alpha.min((glyph_center.x - clip.min.x) / width);
+        }
+        if clip.max.y < text_bounds.max.y {
+            alpha = alpha.min((clip.max.y - glyph_center.y) / width);
+        }
+        if text_bounds.min.y < clip.min.y {
+            alpha = alpha.min((glyph_center.y - clip.min.y) / width);
+        }
+        alpha.clamp(0., 1.)
+    }
+}
+
+impl Default for TextOverflowFade {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Mirrors the glyphs of a UI [`Text`] node, e.g. for mirrored overlays.
+///
+/// The glyphs are mirrored along with their positions in the node, so the whole text reads
+/// mirrored. The layout is unchanged.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextFlip {
+    /// Mirrors the text horizontally.
+    pub flip_x: bool,
+    /// Mirrors the text vertically.
+    pub flip_y: bool,
+}
+
+impl TextFlip {
+    /// Returns the offset of a glyph at `offset` from the top left corner of a node of `size`,
+    /// once mirrored.
+    pub fn mirror(&self, offset: Vec2, size: Vec2) -> Vec2 {
+        Vec2::new(
+            if self.flip_x {
+                size.x - offset.x
+            } else {
+                offset.x
+            },
+            if self.flip_y {
+                size.y - offset.y
+            } else {
+                offset.y
+            },
+        )
+    }
+}
+
 pub struct TextMeasure {
     pub info: TextMeasureInfo,
 }
@@ -0,0 +1,112 @@
+//! Lays out a long list overflowing the window, most of its items below the bottom edge, to
+//! measure the cost of extracting off-screen UI nodes.
+//!
+//! The list isn't clipped, so every item is extracted and prepared each frame. Run with `--cull`
+//! to add a [`UiViewportCulling`] to the camera, which hides the items outside of the window
+//! before they're extracted, and compare the frame times.
+
+use argh::FromArgs;
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    window::{PresentMode, WindowResolution},
+    winit::{UpdateMode, WinitSettings},
+};
+
+#[derive(FromArgs, Resource)]
+/// `many_list_items` lays out a long unclipped list to measure the extraction of off-screen nodes
+struct Args {
+    /// how many items to add to the list
+    #[argh(option, default = "5000")]
+    items: usize,
+
+    /// cull the items outside of the window
+    #[argh(switch)]
+    cull: bool,
+
+    /// don't add a label to each item
+    #[argh(switch)]
+    no_text: bool,
+}
+
+fn main() {
+    // `from_env` panics on the web
+    #[cfg(not(target_arch = "wasm32"))]
+    let args: Args = argh::from_env();
+    #[cfg(target_arch = "wasm32")]
+    let args = Args::from_args(&[], &[]).unwrap();
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::AutoNoVsync,
+                    resolution: WindowResolution::new(1920.0, 1080.0)
+                        .with_scale_factor_override(1.0),
+                    ..default()
+                }),
+                ..default()
+            }),
+            FrameTimeDiagnosticsPlugin,
+            LogDiagnosticsPlugin::default(),
+        ))
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::Continuous,
+            unfocused_mode: UpdateMode::Continuous,
+        })
+        .insert_resource(args)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, args: Res<Args>) {
+    warn!(include_str!("warning_string.txt"));
+
+    let mut camera = commands.spawn(Camera2dBundle::default());
+    if args.cull {
+        camera.insert(UiViewportCulling::default());
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(2.),
+                padding: UiRect::all(Val::Px(4.)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for i in 0..args.items {
+                let t = i as f32 / args.items.max(1) as f32;
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.),
+                            height: Val::Px(24.),
+                            flex_shrink: 0.,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::horizontal(Val::Px(8.)),
+                            border: UiRect::all(Val::Px(1.)),
+                            ..default()
+                        },
+                        background_color: Color::hsl(t * 360., 0.4, 0.25).into(),
+                        border_color: Color::WHITE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        if !args.no_text {
+                            parent.spawn(TextBundle::from_section(
+                                format!("Item {i}"),
+                                TextStyle {
+                                    font_size: 16.,
+                                    ..default()
+                                },
+                            ));
+                        }
+                    });
+            }
+        });
+}
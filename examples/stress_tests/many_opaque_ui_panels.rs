@@ -0,0 +1,154 @@
+//! Stacks many overlapping, fully-opaque UI material panels to measure UI overdraw.
+//!
+//! The panels are drawn with an opaque [`UiMaterial`], so they are drawn front to back in the
+//! opaque UI phase and the fragments hidden by the panels above them are discarded by the depth
+//! test. Run with `--blend` to draw the same panels with a blended material, back to front, and
+//! compare the frame times.
+
+use argh::FromArgs;
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    window::{PresentMode, WindowResolution},
+    winit::{UpdateMode, WinitSettings},
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/solid_ui_material.wgsl";
+
+#[derive(FromArgs, Resource)]
+/// `many_opaque_ui_panels` stacks many overlapping UI material panels to measure UI overdraw
+struct Args {
+    /// how many panels to stack
+    #[argh(option, default = "1000")]
+    panels: usize,
+
+    /// draw the panels with a blended material instead of an opaque one
+    #[argh(switch)]
+    blend: bool,
+}
+
+fn main() {
+    // `from_env` panics on the web
+    #[cfg(not(target_arch = "wasm32"))]
+    let args: Args = argh::from_env();
+    #[cfg(target_arch = "wasm32")]
+    let args = Args::from_args(&[], &[]).unwrap();
+
+    let mut app = App::new();
+
+    app.add_plugins((
+        DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                present_mode: PresentMode::AutoNoVsync,
+                resolution: WindowResolution::new(1920.0, 1080.0).with_scale_factor_override(1.0),
+                ..default()
+            }),
+            ..default()
+        }),
+        UiMaterialPlugin::<OpaquePanelMaterial>::default(),
+        UiMaterialPlugin::<BlendedPanelMaterial>::default(),
+        FrameTimeDiagnosticsPlugin,
+        LogDiagnosticsPlugin::default(),
+    ))
+    .insert_resource(WinitSettings {
+        focused_mode: UpdateMode::Continuous,
+        unfocused_mode: UpdateMode::Continuous,
+    });
+
+    if args.blend {
+        app.add_systems(Startup, setup::<BlendedPanelMaterial>);
+    } else {
+        app.add_systems(Startup, setup::<OpaquePanelMaterial>);
+    }
+
+    app.insert_resource(args).run();
+}
+
+/// A flat color drawn in the opaque UI phase.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct OpaquePanelMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+}
+
+impl UiMaterial for OpaquePanelMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn alpha_mode() -> UiAlphaMode {
+        UiAlphaMode::Opaque
+    }
+}
+
+/// The same flat color, blended in the transparent UI phase.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct BlendedPanelMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+}
+
+impl UiMaterial for BlendedPanelMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+}
+
+trait PanelMaterial: UiMaterial {
+    fn new(color: LinearRgba) -> Self;
+}
+
+impl PanelMaterial for OpaquePanelMaterial {
+    fn new(color: LinearRgba) -> Self {
+        Self { color }
+    }
+}
+
+impl PanelMaterial for BlendedPanelMaterial {
+    fn new(color: LinearRgba) -> Self {
+        Self { color }
+    }
+}
+
+fn setup<M: PanelMaterial>(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<M>>,
+    args: Res<Args>,
+) {
+    warn!(include_str!("warning_string.txt"));
+
+    commands.spawn(Camera2dBundle::default());
+
+    let panels_f = args.panels.max(1) as f32;
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            // Each panel covers most of the window and is shifted a little from the one below it,
+            // so every pixel is covered by hundreds of panels
+            for i in 0..args.panels {
+                let t = i as f32 / panels_f;
+                let color = Color::hsl(t * 360., 0.8, 0.5);
+                parent.spawn(MaterialNodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(20. * t),
+                        top: Val::Percent(20. * (1. - t)),
+                        width: Val::Percent(80.),
+                        height: Val::Percent(80.),
+                        ..default()
+                    },
+                    material: materials.add(M::new(color.into())),
+                    ..default()
+                });
+            }
+        });
+}
@@ -0,0 +1,146 @@
+//! Fills the window with a grid of icons, each one a different layer of a 2D array texture, to
+//! measure the cost of drawing many distinct images.
+//!
+//! Each layer displayed with a [`UiImageLayer`] gets its own bind group, so every icon is drawn
+//! with its own draw call. Run with `--array` to enable [`UiImageArrayBatching`], which binds the
+//! whole texture as an array so the grid is drawn in a single batch, and compare the frame times
+//! and the batch counts logged every second.
+
+use argh::FromArgs;
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    ui::{ui_batch_diagnostics::UiBatchDiagnostics, UiImageArrayBatching},
+    window::{PresentMode, WindowResolution},
+    winit::{UpdateMode, WinitSettings},
+};
+
+const ICON_SIZE: u32 = 16;
+
+#[derive(FromArgs, Resource)]
+/// `many_ui_icons` fills the window with icons showing the layers of an array texture
+struct Args {
+    /// how many icons to lay out
+    #[argh(option, default = "4096")]
+    icons: usize,
+
+    /// how many layers the array texture has
+    #[argh(option, default = "64")]
+    layers: u32,
+
+    /// draw the icons from a texture array with `UiImageArrayBatching`
+    #[argh(switch)]
+    array: bool,
+}
+
+fn main() {
+    // `from_env` panics on the web
+    #[cfg(not(target_arch = "wasm32"))]
+    let args: Args = argh::from_env();
+    #[cfg(target_arch = "wasm32")]
+    let args = Args::from_args(&[], &[]).unwrap();
+
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::AutoNoVsync,
+                    resolution: WindowResolution::new(1920.0, 1080.0)
+                        .with_scale_factor_override(1.0),
+                    ..default()
+                }),
+                ..default()
+            }),
+            FrameTimeDiagnosticsPlugin,
+            LogDiagnosticsPlugin::default(),
+        ))
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::Continuous,
+            unfocused_mode: UpdateMode::Continuous,
+        })
+        .insert_resource(UiImageArrayBatching {
+            enabled: args.array,
+        })
+        .insert_resource(args)
+        .add_systems(Startup, setup)
+        .add_systems(Update, log_batches)
+        .run();
+}
+
+/// Returns an array texture with `layers` layers, each one filled with its own color.
+fn icon_array(layers: u32) -> Image {
+    let layers = layers.max(1);
+    let mut data = Vec::new();
+    for layer in 0..layers {
+        let color = Color::hsl(layer as f32 / layers as f32 * 360., 0.7, 0.6).to_srgba();
+        for y in 0..ICON_SIZE {
+            for x in 0..ICON_SIZE {
+                // A lighter frame around each icon
+                let edge = x == 0 || y == 0 || x == ICON_SIZE - 1 || y == ICON_SIZE - 1;
+                let color = if edge { color.lighter(0.2) } else { color };
+                data.extend_from_slice(&color.to_u8_array());
+            }
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: ICON_SIZE,
+            height: ICON_SIZE,
+            depth_or_array_layers: layers,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>, args: Res<Args>) {
+    warn!(include_str!("warning_string.txt"));
+
+    commands.spawn(Camera2dBundle::default());
+
+    let icons = images.add(icon_array(args.layers));
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                flex_wrap: FlexWrap::Wrap,
+                align_content: AlignContent::FlexStart,
+                row_gap: Val::Px(1.),
+                column_gap: Val::Px(1.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for i in 0..args.icons {
+                parent.spawn((
+                    ImageBundle {
+                        style: Style {
+                            width: Val::Px(ICON_SIZE as f32),
+                            height: Val::Px(ICON_SIZE as f32),
+                            ..default()
+                        },
+                        image: UiImage::new(icons.clone()),
+                        ..default()
+                    },
+                    UiImageLayer(i as u32 % args.layers.max(1)),
+                ));
+            }
+        });
+}
+
+fn log_batches(time: Res<Time>, mut timer: Local<Timer>, diagnostics: Res<UiBatchDiagnostics>) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(1., TimerMode::Repeating);
+    }
+    if timer.tick(time.delta()).just_finished() {
+        let counts = diagnostics.counts();
+        info!("{} icons drawn in {} batches", counts.quads, counts.batches);
+    }
+}
@@ -0,0 +1,121 @@
+//! Demonstrates blurring everything behind a modal dialog with [`UiModalBackdrop`].
+//!
+//! Press space to open and close the dialog.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, toggle_modal)
+        .run();
+}
+
+#[derive(Component)]
+struct Modal;
+
+fn setup(mut commands: Commands) {
+    // Camera so we can see UI
+    commands.spawn(Camera2dBundle::default());
+
+    // A colorful grid to have something to blur
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_wrap: FlexWrap::Wrap,
+                align_content: AlignContent::FlexStart,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            let colors = [CRIMSON, GOLD, SEA_GREEN, ROYAL_BLUE, ORCHID];
+            for i in 0..60 {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(10.),
+                            height: Val::Percent(16.),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: colors[i % colors.len()].into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            format!("{i}"),
+                            TextStyle {
+                                font_size: 30.,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+
+    // The modal root covers the screen, everything below it gets blurred
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            UiModalBackdrop {
+                blur: 12.,
+                tint: Color::srgba(0., 0., 0., 0.4),
+            },
+            Modal,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(400.),
+                        height: Val::Px(200.),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                    border_radius: BorderRadius::all(Val::Px(20.)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Press space to close",
+                        TextStyle {
+                            font_size: 30.,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn toggle_modal(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut modal_query: Query<&mut Visibility, With<Modal>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+
+    for mut visibility in &mut modal_query {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
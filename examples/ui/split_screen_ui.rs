@@ -0,0 +1,141 @@
+//! Shows a different UI in each half of a split-screen window.
+//!
+//! Each camera renders to its own viewport, and each UI root targets one of the cameras with a
+//! [`TargetCamera`]. The roots are laid out for the size of their camera's viewport, so the panels
+//! fill their half of the window, and clicking a button only updates the score of its side.
+
+use bevy::{prelude::*, render::camera::Viewport, window::WindowResized};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (set_camera_viewports, button_system))
+        .run();
+}
+
+/// The half of the window a camera renders to.
+#[derive(Component)]
+struct Side(u32);
+
+/// The number of times the button of a side was pressed.
+#[derive(Component, Default)]
+struct Score(u32);
+
+const PLAYERS: [(&str, Color); 2] = [
+    ("Player 1", Color::srgb(0.2, 0.25, 0.45)),
+    ("Player 2", Color::srgb(0.45, 0.2, 0.25)),
+];
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+
+    for (index, (name, color)) in PLAYERS.into_iter().enumerate() {
+        let camera = commands
+            .spawn((
+                Camera2dBundle {
+                    camera: Camera {
+                        order: index as isize,
+                        // Clearing the render target clears the whole window, don't clear the
+                        // first camera's half
+                        clear_color: if index == 0 {
+                            ClearColorConfig::Default
+                        } else {
+                            ClearColorConfig::None
+                        },
+                        ..default()
+                    },
+                    ..default()
+                },
+                Side(index as u32),
+            ))
+            .id();
+
+        // The root of each side fills the viewport of its camera
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::SpaceBetween,
+                        padding: UiRect::all(Val::Px(20.)),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                },
+                TargetCamera(camera),
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    name,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 40.,
+                        ..default()
+                    },
+                ));
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(24.), Val::Px(12.)),
+                            ..default()
+                        },
+                        border_radius: BorderRadius::all(Val::Px(8.)),
+                        background_color: Color::BLACK.with_alpha(0.4).into(),
+                        ..default()
+                    })
+                    .with_child((
+                        TextBundle::from_section(
+                            "Score: 0",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 30.,
+                                ..default()
+                            },
+                        ),
+                        Score::default(),
+                    ));
+            });
+    }
+}
+
+fn set_camera_viewports(
+    windows: Query<&Window>,
+    mut resize_events: EventReader<WindowResized>,
+    mut query: Query<(&Side, &mut Camera)>,
+) {
+    // Each camera takes up half the window, side by side. A resize event is sent when the window
+    // is first created, which sets up the viewports.
+    for resize_event in resize_events.read() {
+        let window = windows.get(resize_event.window).unwrap();
+        let size = window.physical_size() / UVec2::new(2, 1);
+
+        for (side, mut camera) in &mut query {
+            camera.viewport = Some(Viewport {
+                physical_position: UVec2::new(side.0 * size.x, 0),
+                physical_size: size,
+                ..default()
+            });
+        }
+    }
+}
+
+fn button_system(
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<Button>)>,
+    mut score_query: Query<(&mut Score, &mut Text)>,
+) {
+    for (interaction, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        // The score is the child of the button, so only the side of the button is updated
+        if let Ok((mut score, mut text)) = score_query.get_mut(children[0]) {
+            score.0 += 1;
+            text.sections[0].value = format!("Score: {}", score.0);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Draws nodes with the built-in [`ColorUiMaterial`], which needs no shader of its own.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorUiMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let icon = asset_server.load("branding/icon.png");
+    let materials = [
+        // A solid color
+        ColorUiMaterial::from_color(CRIMSON),
+        // A texture
+        ColorUiMaterial::from(icon.clone()),
+        // A texture tinted by a color
+        ColorUiMaterial {
+            color: GOLD.into(),
+            texture: Some(icon),
+        },
+    ]
+    .map(|material| materials.add(material));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(40.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for material in materials {
+                parent.spawn(MaterialNodeBundle {
+                    style: Style {
+                        width: Val::Px(200.),
+                        height: Val::Px(200.),
+                        ..default()
+                    },
+                    material,
+                    ..default()
+                });
+            }
+        });
+}
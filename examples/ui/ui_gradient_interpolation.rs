@@ -0,0 +1,86 @@
+//! Compares UI gradients interpolated in linear RGB with gradients interpolated in Oklab, using
+//! [`UiCornerColors`] and [`UiGradientInterpolation`].
+//!
+//! Gradients between saturated colors pass through dull midtones in linear RGB, while Oklab keeps
+//! their lightness and saturation even.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+
+    let gradients: [(Color, Color); 3] = [
+        (BLUE.into(), YELLOW.into()),
+        (RED.into(), LIME.into()),
+        (BLACK.into(), WHITE.into()),
+    ];
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (left, right) in gradients {
+                for interpolation in [
+                    UiGradientInterpolation::Linear,
+                    UiGradientInterpolation::Oklab,
+                ] {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                column_gap: Val::Px(20.),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(
+                                TextBundle::from_section(
+                                    format!("{interpolation:?}"),
+                                    TextStyle {
+                                        font_size: 24.,
+                                        ..default()
+                                    },
+                                )
+                                .with_style(Style {
+                                    width: Val::Px(100.),
+                                    ..default()
+                                }),
+                            );
+                            parent.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Px(600.),
+                                        height: Val::Px(60.),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                UiCornerColors::horizontal(left, right),
+                                // Interpolate at more points than the corners, so the gradient
+                                // follows the chosen color space along its whole width
+                                UiGradientTessellation::default(),
+                                interpolation,
+                            ));
+                        });
+                }
+            }
+        });
+}
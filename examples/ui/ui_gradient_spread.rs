@@ -0,0 +1,77 @@
+//! Fills nodes with a diagonal [`GradientUiMaterial`], padded, repeated and reflected beyond its
+//! stops with [`UiGradientSpread`].
+//!
+//! The stops only cover a small part of each node, so the spread fills the rest of it.
+
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    ui::ui_gradient_material::{GradientUiMaterial, UiGradientSpread},
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<GradientUiMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for spread in [
+                UiGradientSpread::Pad,
+                UiGradientSpread::Repeat,
+                UiGradientSpread::Reflect,
+            ] {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(10.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(MaterialNodeBundle {
+                            style: Style {
+                                width: Val::Px(300.),
+                                height: Val::Px(200.),
+                                ..default()
+                            },
+                            // A diagonal gradient over the first tenth of the node
+                            material: materials.add(GradientUiMaterial {
+                                start: Vec2::ZERO,
+                                end: Vec2::splat(0.1),
+                                start_color: NAVY.into(),
+                                end_color: GOLD.into(),
+                                spread,
+                            }),
+                            ..default()
+                        });
+                        parent.spawn(TextBundle::from_section(
+                            format!("{spread:?}"),
+                            TextStyle {
+                                font_size: 24.,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
@@ -0,0 +1,144 @@
+//! Draws the health bar of a character in the 3D scene with [`UiPassLayer::InScene`], so it is
+//! hidden behind the wall the character walks behind.
+//!
+//! The health bar follows the character's head, and its [`UiSceneDepth`] is the depth of the
+//! head in the scene. The text at the top has no depth, and is drawn over the whole scene.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_character, follow_character).chain())
+        .run();
+}
+
+#[derive(Component)]
+struct Character;
+
+#[derive(Component)]
+struct HealthBar;
+
+const HEALTH_BAR_SIZE: Vec2 = Vec2::new(80., 10.);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // The UI of in-scene cameras can't be multisampled
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0., 4., 9.).looking_at(Vec3::new(0., 1., 0.), Vec3::Y),
+            msaa: Msaa::Off,
+            ..default()
+        },
+        UiPassLayer::InScene,
+    ));
+
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(4., 8., 4.).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+
+    // Ground
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Plane3d::default().mesh().size(20., 20.)),
+        material: materials.add(Color::from(DARK_OLIVEGREEN)),
+        ..default()
+    });
+
+    // Wall
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::new(3., 3., 0.5)),
+        material: materials.add(Color::from(DIM_GRAY)),
+        transform: Transform::from_xyz(0., 1.5, 0.),
+        ..default()
+    });
+
+    // Character, walking behind the wall
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Capsule3d::new(0.4, 1.)),
+            material: materials.add(Color::from(STEEL_BLUE)),
+            transform: Transform::from_xyz(0., 0.9, -2.),
+            ..default()
+        },
+        Character,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Px(HEALTH_BAR_SIZE.x),
+                    height: Val::Px(HEALTH_BAR_SIZE.y),
+                    padding: UiRect::all(Val::Px(2.)),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                ..default()
+            },
+            HealthBar,
+            UiSceneDepth::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(70.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: Color::from(CRIMSON).into(),
+                ..default()
+            });
+        });
+
+    commands.spawn(
+        TextBundle::from_section(
+            "The health bar is hidden behind the wall",
+            TextStyle::default(),
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.),
+            left: Val::Px(12.),
+            ..default()
+        }),
+    );
+}
+
+fn move_character(time: Res<Time>, mut characters: Query<&mut Transform, With<Character>>) {
+    for mut transform in &mut characters {
+        transform.translation.x = 4. * time.elapsed_seconds().sin();
+    }
+}
+
+fn follow_character(
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    characters: Query<&GlobalTransform, With<Character>>,
+    mut health_bars: Query<(&mut Style, &mut UiSceneDepth), With<HealthBar>>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Ok(character_transform) = characters.get_single() else {
+        return;
+    };
+    let Ok((mut style, mut depth)) = health_bars.get_single_mut() else {
+        return;
+    };
+
+    let head = character_transform.translation() + Vec3::Y * 1.2;
+    let (Ok(position), Some(ndc)) = (
+        camera.world_to_viewport(camera_transform, head),
+        camera.world_to_ndc(camera_transform, head),
+    ) else {
+        return;
+    };
+    style.left = Val::Px(position.x - HEALTH_BAR_SIZE.x / 2.);
+    style.top = Val::Px(position.y - HEALTH_BAR_SIZE.y / 2.);
+    depth.0 = ndc.z;
+}
@@ -0,0 +1,121 @@
+//! Draws overlapping glows with a [`UiMaterial`] that chooses an additive blend state.
+//!
+//! Where the glows overlap their colors add up, brightening towards white, while the same
+//! material with the default alpha blending only covers the glows below.
+
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    render::render_resource::{
+        AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, ShaderRef,
+    },
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_glow_material.wgsl";
+
+/// Adds the color of the glow, weighted by its alpha, to the color below it.
+const ADDITIVE_BLENDING: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::SrcAlpha,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent::OVER,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, UiMaterialPlugin::<GlowMaterial>::default()))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<GlowMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for additive in [false, true] {
+                // Three overlapping glows, blended with the default or the additive blend state
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(400.),
+                            height: Val::Px(400.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for (color, left, top) in
+                            [(RED, 0., 50.), (LIME, 150., 50.), (BLUE, 75., 175.)]
+                        {
+                            parent.spawn(MaterialNodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(left),
+                                    top: Val::Px(top),
+                                    width: Val::Px(250.),
+                                    height: Val::Px(250.),
+                                    ..default()
+                                },
+                                material: materials.add(GlowMaterial {
+                                    color: color.into(),
+                                    additive,
+                                }),
+                                ..default()
+                            });
+                        }
+                    });
+            }
+        });
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(GlowMaterialKey)]
+struct GlowMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+    additive: bool,
+}
+
+// The blend state is part of the pipeline, so materials blending differently are drawn with
+// different pipelines, specialized from this key.
+#[derive(Eq, PartialEq, Hash, Clone)]
+struct GlowMaterialKey {
+    additive: bool,
+}
+
+impl From<&GlowMaterial> for GlowMaterialKey {
+    fn from(material: &GlowMaterial) -> Self {
+        Self {
+            additive: material.additive,
+        }
+    }
+}
+
+impl UiMaterial for GlowMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn blend_state(key: UiMaterialKey<Self>) -> Option<BlendState> {
+        Some(if key.bind_group_data.additive {
+            ADDITIVE_BLENDING
+        } else {
+            BlendState::ALPHA_BLENDING
+        })
+    }
+}
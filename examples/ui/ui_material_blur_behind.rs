@@ -0,0 +1,114 @@
+//! Draws a frosted glass panel blurring the scene behind it with a [`UiMaterial`].
+//!
+//! The material returns `true` from [`UiMaterial::reads_view_transform`], so its shader can sample
+//! a copy of the scene taken before the UI is drawn, along with the view uniform of the camera.
+//! The shader projects each fragment back to the world with the camera's matrices to draw a grid
+//! that stays in place as the camera moves.
+
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_material_blur_behind.wgsl";
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            UiMaterialPlugin::<BlurBehindMaterial>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_shapes, move_camera))
+        .run();
+}
+
+/// A shape moving around the scene below the panel.
+#[derive(Component)]
+struct Shape {
+    phase: f32,
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<BlurBehindMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    for (index, color) in [RED, ORANGE, YELLOW, LIME, AQUA, BLUE, FUCHSIA]
+        .into_iter()
+        .enumerate()
+    {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: color.into(),
+                    custom_size: Some(Vec2::splat(120.)),
+                    ..default()
+                },
+                ..default()
+            },
+            Shape {
+                phase: index as f32,
+            },
+        ));
+    }
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_child(MaterialNodeBundle {
+            style: Style {
+                width: Val::Px(500.),
+                height: Val::Px(300.),
+                ..default()
+            },
+            material: materials.add(BlurBehindMaterial {
+                tint: Color::WHITE.with_alpha(0.2).into(),
+                blur_radius: 24.,
+            }),
+            ..default()
+        });
+}
+
+fn move_shapes(time: Res<Time>, mut shapes: Query<(&Shape, &mut Transform)>) {
+    for (shape, mut transform) in &mut shapes {
+        let t = time.elapsed_seconds() * 0.5 + shape.phase;
+        transform.translation.x = 350. * t.cos();
+        transform.translation.y = 200. * (2. * t).sin();
+        transform.rotation = Quat::from_rotation_z(t);
+    }
+}
+
+fn move_camera(time: Res<Time>, mut cameras: Query<&mut Transform, With<Camera>>) {
+    for mut transform in &mut cameras {
+        transform.translation.x = 60. * (time.elapsed_seconds() * 0.3).sin();
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct BlurBehindMaterial {
+    /// The color mixed into the blurred scene, weighted by its alpha.
+    #[uniform(0)]
+    tint: LinearRgba,
+    /// The radius of the blur, in pixels.
+    #[uniform(1)]
+    blur_radius: f32,
+}
+
+impl UiMaterial for BlurBehindMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn reads_view_transform() -> bool {
+        true
+    }
+}
@@ -0,0 +1,82 @@
+//! Crossfades a button from a flat [`GradientUiMaterial`] to a diagonal one on hover with a
+//! [`UiMaterialCrossfade`].
+//!
+//! Both materials are drawn while the button fades, each with the opacity the other one lacks.
+
+use bevy::{color::palettes::css::*, prelude::*, ui::ui_gradient_material::GradientUiMaterial};
+
+/// How long the button takes to fade to the other material, in seconds.
+const FADE_DURATION: f32 = 0.25;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, fade_hovered_buttons)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<GradientUiMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    MaterialNodeBundle {
+                        style: Style {
+                            width: Val::Px(250.),
+                            height: Val::Px(80.),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        material: materials.add(GradientUiMaterial::diagonal(NAVY, NAVY)),
+                        ..default()
+                    },
+                    Button,
+                    Interaction::default(),
+                    UiMaterialCrossfade::new(
+                        materials.add(GradientUiMaterial::diagonal(ROYAL_BLUE, GOLD)),
+                        0.,
+                    ),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Hover me",
+                        TextStyle {
+                            font_size: 32.,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn fade_hovered_buttons(
+    time: Res<Time>,
+    mut buttons: Query<(&Interaction, &mut UiMaterialCrossfade<GradientUiMaterial>), With<Button>>,
+) {
+    let step = time.delta_seconds() / FADE_DURATION;
+    for (interaction, mut crossfade) in &mut buttons {
+        let target = match interaction {
+            Interaction::None => 0.,
+            Interaction::Hovered | Interaction::Pressed => 1.,
+        };
+        let blend = crossfade.blend + (target - crossfade.blend).clamp(-step, step);
+        // Only touch the component while fading, so its opacity isn't updated every frame
+        if blend != crossfade.blend {
+            crossfade.blend = blend;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Passes the hover intensity of each button to a UI material shader as a push constant.
+//!
+//! The buttons share a single material. Its push constants are written for each node from the
+//! node's `HoverIntensity`, so animating them doesn't modify the material asset. On backends
+//! without push constants, like WebGL2, they fall back to a uniform.
+
+use bevy::{
+    color::palettes::css::*,
+    ecs::world::EntityRef,
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_material_push_constants.wgsl";
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, UiMaterialPlugin::<HoverMaterial>::default()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, animate_hover)
+        .run();
+}
+
+/// How much a button is highlighted, from 0 to 1.
+#[derive(Component, Default)]
+struct HoverIntensity(f32);
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<HoverMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    let material = materials.add(HoverMaterial {
+        color: STEEL_BLUE.into(),
+    });
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for _ in 0..5 {
+                parent.spawn((
+                    MaterialNodeBundle {
+                        style: Style {
+                            width: Val::Px(120.),
+                            height: Val::Px(60.),
+                            ..default()
+                        },
+                        material: material.clone(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    HoverIntensity::default(),
+                ));
+            }
+        });
+}
+
+fn animate_hover(time: Res<Time>, mut buttons: Query<(&Interaction, &mut HoverIntensity)>) {
+    for (interaction, mut hover) in &mut buttons {
+        let target = match interaction {
+            Interaction::Pressed | Interaction::Hovered => 1.,
+            Interaction::None => 0.,
+        };
+        hover.0 = hover.0.lerp(target, (8. * time.delta_seconds()).min(1.));
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct HoverMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+}
+
+impl UiMaterial for HoverMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn push_constant_size() -> u32 {
+        // The `f32` intensity
+        4
+    }
+
+    fn extract_push_constants(&self, entity: EntityRef, data: &mut Vec<u8>) {
+        if let Some(hover) = entity.get::<HoverIntensity>() {
+            data.extend_from_slice(&hover.0.to_le_bytes());
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Enables a branch of a UI material shader at compile time with a shader def.
+//!
+//! The `rounded` field of the material is part of its bind group data, so materials with and
+//! without it are drawn with two pipelines, one of them compiled with the `ROUNDED` shader def.
+
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderDefVal, ShaderRef},
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_material_shader_defs.wgsl";
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            UiMaterialPlugin::<FeatureMaterial>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<FeatureMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(40.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (color, rounded) in [(TEAL, false), (ORANGE_RED, true)] {
+                parent.spawn(MaterialNodeBundle {
+                    style: Style {
+                        width: Val::Px(200.),
+                        height: Val::Px(200.),
+                        ..default()
+                    },
+                    material: materials.add(FeatureMaterial {
+                        color: color.into(),
+                        rounded,
+                    }),
+                    ..default()
+                });
+            }
+        });
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(FeatureMaterialKey)]
+struct FeatureMaterial {
+    #[uniform(0)]
+    color: LinearRgba,
+    rounded: bool,
+}
+
+// This key is used to identify a specific permutation of the material's pipeline, so it should be
+// kept small and cheap to hash.
+#[derive(Eq, PartialEq, Hash, Clone)]
+struct FeatureMaterialKey {
+    rounded: bool,
+}
+
+impl From<&FeatureMaterial> for FeatureMaterialKey {
+    fn from(material: &FeatureMaterial) -> Self {
+        Self {
+            rounded: material.rounded,
+        }
+    }
+}
+
+impl UiMaterial for FeatureMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn shader_defs(key: &UiMaterialKey<Self>) -> Vec<ShaderDefVal> {
+        if key.bind_group_data.rounded {
+            vec!["ROUNDED".into()]
+        } else {
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Colors a node from a palette bound to a [`UiMaterial`] as a storage buffer.
+//!
+//! The palette holds 4096 colors, more than fit in a uniform buffer on some platforms. Storage
+//! buffers aren't available on WebGL2.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{AsBindGroup, ShaderRef},
+        storage::ShaderStorageBuffer,
+    },
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_material_storage_buffer.wgsl";
+
+/// The number of colors in the palette.
+const PALETTE_LEN: usize = 4096;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            UiMaterialPlugin::<PaletteMaterial>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut materials: ResMut<Assets<PaletteMaterial>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    // A hue ramp, one color per column of the node
+    let palette: Vec<Vec4> = (0..PALETTE_LEN)
+        .map(|i| {
+            let hue = i as f32 / PALETTE_LEN as f32 * 360.;
+            LinearRgba::from(Color::hsl(hue, 0.9, 0.5)).to_vec4()
+        })
+        .collect();
+    let palette = buffers.add(ShaderStorageBuffer::from(palette));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(MaterialNodeBundle {
+                style: Style {
+                    width: Val::Percent(80.),
+                    height: Val::Px(200.),
+                    ..default()
+                },
+                material: materials.add(PaletteMaterial { palette }),
+                ..default()
+            });
+        });
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct PaletteMaterial {
+    #[storage(0, read_only)]
+    palette: Handle<ShaderStorageBuffer>,
+}
+
+impl UiMaterial for PaletteMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+}
@@ -0,0 +1,63 @@
+//! Fills text with a [`GradientUiMaterial`] instead of a flat color.
+//!
+//! Text nodes with a material handle are drawn glyph by glyph with the material, which spans the
+//! whole node, masked by the coverage of each glyph.
+
+use bevy::{color::palettes::css::*, prelude::*, ui::ui_gradient_material::GradientUiMaterial};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<GradientUiMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Gradient text",
+                    TextStyle {
+                        font_size: 96.,
+                        ..default()
+                    },
+                ),
+                materials.add(GradientUiMaterial {
+                    start_color: ORANGE_RED.into(),
+                    end_color: GOLD.into(),
+                    ..default()
+                }),
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Top to bottom, over every glyph",
+                    TextStyle {
+                        font_size: 48.,
+                        ..default()
+                    },
+                ),
+                materials.add(GradientUiMaterial {
+                    start: Vec2::new(0.5, 0.),
+                    end: Vec2::new(0.5, 1.),
+                    start_color: DEEP_SKY_BLUE.into(),
+                    end_color: MEDIUM_PURPLE.into(),
+                    ..default()
+                }),
+            ));
+        });
+}
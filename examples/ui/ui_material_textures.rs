@@ -0,0 +1,86 @@
+//! Draws nodes with a UI material that binds several textures: a base color, a mask cropping it
+//! to a shape and an optional detail texture.
+//!
+//! The detail texture of the second node isn't set, so it's bound to the white `FallbackImage`
+//! and leaves the base color unchanged.
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+/// This example uses a shader source file from the assets subdirectory
+const SHADER_ASSET_PATH: &str = "shaders/ui_material_textures.wgsl";
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            UiMaterialPlugin::<MaskedMaterial>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<MaskedMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let base = asset_server.load("textures/slice_square_2.png");
+    let mask = asset_server.load("branding/icon.png");
+    let detail = asset_server.load("textures/slice_square.png");
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(40.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for detail in [Some(detail), None] {
+                parent.spawn(MaterialNodeBundle {
+                    style: Style {
+                        width: Val::Px(256.),
+                        height: Val::Px(256.),
+                        ..default()
+                    },
+                    material: materials.add(MaskedMaterial {
+                        base: base.clone(),
+                        mask: mask.clone(),
+                        detail,
+                    }),
+                    ..default()
+                });
+            }
+        });
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+struct MaskedMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    base: Handle<Image>,
+    /// Only the alpha of the mask is used
+    #[texture(2)]
+    #[sampler(3)]
+    mask: Handle<Image>,
+    /// Bound to the fallback image when unset
+    #[texture(4)]
+    #[sampler(5)]
+    detail: Option<Handle<Image>>,
+}
+
+impl UiMaterial for MaskedMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+}
@@ -0,0 +1,81 @@
+//! Draws UI nodes as meshes with [`UiMesh`].
+//!
+//! The positions of a UI mesh are in units of the size of its node, centered on the node, so a
+//! circle with a radius of 0.5 fills the node it's drawn in.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    let triangle = meshes.add(Triangle2d::new(
+        Vec2::new(0., 0.5),
+        Vec2::new(-0.5, -0.5),
+        Vec2::new(0.5, -0.5),
+    ));
+    let circle = meshes.add(Circle::new(0.5));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(50.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (mesh, color) in [(triangle, ORANGE_RED), (circle.clone(), DEEP_SKY_BLUE)] {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(200.),
+                            height: Val::Px(200.),
+                            ..default()
+                        },
+                        background_color: color.into(),
+                        ..default()
+                    },
+                    UiMesh(mesh),
+                ));
+            }
+
+            // The mesh is clipped by its parent, like any other node
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(100.),
+                        height: Val::Px(200.),
+                        overflow: Overflow::clip(),
+                        ..default()
+                    },
+                    background_color: GRAY.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                height: Val::Px(200.),
+                                flex_shrink: 0.,
+                                ..default()
+                            },
+                            background_color: LIME.into(),
+                            ..default()
+                        },
+                        UiMesh(circle.clone()),
+                    ));
+                });
+        });
+}
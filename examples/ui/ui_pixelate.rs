@@ -0,0 +1,66 @@
+//! Censors part of an image with a [`PixelateUiMaterial`].
+//!
+//! A node placed over the image draws the same part of the texture as a mosaic of large blocks.
+
+use bevy::{prelude::*, ui::ui_pixelate_material::PixelateUiMaterial};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<PixelateUiMaterial>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let image = asset_server.load("branding/icon.png");
+    // The censored part of the image, relative to its size
+    let region = Rect::new(0.25, 0.3, 0.75, 0.6);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(400.),
+                        height: Val::Px(400.),
+                        ..default()
+                    },
+                    image: UiImage::new(image.clone()),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(MaterialNodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(region.min.x * 100.),
+                            top: Val::Percent(region.min.y * 100.),
+                            width: Val::Percent(region.width() * 100.),
+                            height: Val::Percent(region.height() * 100.),
+                            ..default()
+                        },
+                        material: materials.add(PixelateUiMaterial {
+                            block_size: 20.,
+                            region,
+                            texture: image,
+                        }),
+                        ..default()
+                    });
+                });
+        });
+}
@@ -0,0 +1,110 @@
+//! Draws an image whose colors are premultiplied by their alpha, as exported by many image editors
+//! and texture packers, with and without [`UiImage::premultiplied_alpha`].
+//!
+//! Blended as straight alpha, the soft edges of the image are darkened twice and show a dark
+//! fringe over the light background.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    let glow = images.add(premultiplied_glow());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                column_gap: Val::Px(50.),
+                ..default()
+            },
+            background_color: Color::srgb(0.9, 0.9, 0.85).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (label, premultiplied_alpha) in
+                [("Straight alpha", false), ("Premultiplied alpha", true)]
+            {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            row_gap: Val::Px(10.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(ImageBundle {
+                            style: Style {
+                                width: Val::Px(256.),
+                                height: Val::Px(256.),
+                                ..default()
+                            },
+                            image: UiImage {
+                                premultiplied_alpha,
+                                ..UiImage::new(glow.clone())
+                            },
+                            ..default()
+                        });
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                color: Color::BLACK,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// A disc of light fading out from its center, its colors premultiplied by their alpha.
+fn premultiplied_glow() -> Image {
+    const TEXTURE_SIZE: usize = 64;
+
+    let color = LinearRgba::rgb(1., 0.4, 0.05);
+    let mut texture_data = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE * 4);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let point = (Vec2::new(x as f32, y as f32) + 0.5) / TEXTURE_SIZE as f32 - 0.5;
+            let alpha = (1. - 2. * point.length()).clamp(0., 1.).powf(1.5);
+            let premultiplied = [color.red, color.green, color.blue].map(|c| c * alpha);
+            texture_data.extend(
+                premultiplied
+                    .into_iter()
+                    .chain([alpha])
+                    .map(|c| (c * 255.).round() as u8),
+            );
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: TEXTURE_SIZE as u32,
+            height: TEXTURE_SIZE as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        texture_data,
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
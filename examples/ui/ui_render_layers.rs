@@ -0,0 +1,150 @@
+//! Shows UI nodes on some cameras only with [`RenderLayers`].
+//!
+//! Each half of the window is rendered by its own camera with its own UI. The debug panel of both
+//! UIs is on layer 1, which only the left camera renders. Press space to toggle layer 1 on the
+//! right camera.
+
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    window::WindowResized,
+};
+
+/// The layer of the debug panels.
+const DEBUG_LAYER: usize = 1;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (set_camera_viewports, toggle_debug_layer))
+        .run();
+}
+
+/// The half of the window a camera renders, 0 for the left one and 1 for the right one.
+#[derive(Component)]
+struct CameraPosition(u32);
+
+fn setup(mut commands: Commands) {
+    for (position, layers, background) in [
+        (
+            0,
+            RenderLayers::from_layers(&[0, DEBUG_LAYER]),
+            MIDNIGHT_BLUE,
+        ),
+        (1, RenderLayers::layer(0), DARK_SLATE_GRAY),
+    ] {
+        let camera = commands
+            .spawn((
+                Camera2dBundle {
+                    camera: Camera {
+                        order: position as isize,
+                        // Clearing would also clear the half of the camera rendered before
+                        clear_color: if position == 0 {
+                            ClearColorConfig::Default
+                        } else {
+                            ClearColorConfig::None
+                        },
+                        ..default()
+                    },
+                    ..default()
+                },
+                CameraPosition(position),
+                layers,
+            ))
+            .id();
+
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(20.)),
+                        row_gap: Val::Px(20.),
+                        ..default()
+                    },
+                    background_color: background.into(),
+                    ..default()
+                },
+                TargetCamera(camera),
+            ))
+            .with_children(|parent| {
+                // Nodes without render layers are drawn by their camera whatever its layers
+                parent.spawn(TextBundle::from_section(
+                    format!("Player {}", position + 1),
+                    TextStyle {
+                        font_size: 40.,
+                        ..default()
+                    },
+                ));
+
+                // Render layers aren't inherited, so the text of the panel needs them too
+                parent
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.)),
+                                ..default()
+                            },
+                            background_color: CRIMSON.into(),
+                            ..default()
+                        },
+                        RenderLayers::layer(DEBUG_LAYER),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "Debug panel on layer 1",
+                                TextStyle {
+                                    font_size: 24.,
+                                    ..default()
+                                },
+                            ),
+                            RenderLayers::layer(DEBUG_LAYER),
+                        ));
+                    });
+            });
+    }
+}
+
+fn set_camera_viewports(
+    windows: Query<&Window>,
+    mut resize_events: EventReader<WindowResized>,
+    mut cameras: Query<(&CameraPosition, &mut Camera)>,
+) {
+    // A resize event is sent when the window is first created, so this also sets the initial
+    // viewports
+    for resize_event in resize_events.read() {
+        let window = windows.get(resize_event.window).unwrap();
+        let size = window.physical_size() * UVec2::new(1, 2) / 2;
+
+        for (CameraPosition(position), mut camera) in &mut cameras {
+            camera.viewport = Some(Viewport {
+                physical_position: UVec2::new(position * size.x, 0),
+                physical_size: size,
+                ..default()
+            });
+        }
+    }
+}
+
+fn toggle_debug_layer(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut cameras: Query<(&CameraPosition, &mut RenderLayers)>,
+) {
+    if !keyboard.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for (CameraPosition(position), mut layers) in &mut cameras {
+        if *position == 1 {
+            *layers = if layers.intersects(&RenderLayers::layer(DEBUG_LAYER)) {
+                RenderLayers::layer(0)
+            } else {
+                RenderLayers::from_layers(&[0, DEBUG_LAYER])
+            };
+        }
+    }
+}
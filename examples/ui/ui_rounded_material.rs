@@ -0,0 +1,57 @@
+//! Rounds the corners of nodes with the built-in [`RoundedUiMaterial`].
+//!
+//! The same material is shared by nodes of different sizes, and the radius of each corner is
+//! clamped to half the smallest dimension of the node.
+
+use bevy::{color::palettes::css::*, prelude::*, ui::ui_rounded_material::RoundedUiMaterial};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<RoundedUiMaterial>>) {
+    commands.spawn(Camera2dBundle::default());
+
+    let shared = materials.add(RoundedUiMaterial::new(TEAL, 20.));
+    // Clamped to a circle on square nodes and to half circles on the short sides of the others
+    let pill = materials.add(RoundedUiMaterial::new(ORANGE_RED, 1000.));
+    let leaf = materials.add(RoundedUiMaterial {
+        color: SEA_GREEN.into(),
+        radius: Vec4::new(60., 0., 60., 0.),
+    });
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(20.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (material, width, height) in [
+                (&shared, 100., 100.),
+                (&shared, 250., 120.),
+                (&pill, 120., 120.),
+                (&pill, 250., 60.),
+                (&leaf, 150., 150.),
+            ] {
+                parent.spawn(MaterialNodeBundle {
+                    style: Style {
+                        width: Val::Px(width),
+                        height: Val::Px(height),
+                        ..default()
+                    },
+                    material: material.clone(),
+                    ..default()
+                });
+            }
+        });
+}
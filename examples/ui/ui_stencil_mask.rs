@@ -0,0 +1,106 @@
+//! Crops an image to a circle with a [`UiStencilMask`].
+//!
+//! The descendants of a mask are clipped to its rounded rect, so a node with a [`BorderRadius`] of
+//! 50% crops them to a circle. Masks can be nested, here the label is cropped to both masks.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, pan)
+        .run();
+}
+
+/// Pans the image inside of its mask.
+#[derive(Component)]
+struct Pan;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn(Camera2dBundle::default());
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(300.),
+                            height: Val::Px(300.),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: DARK_SLATE_GRAY.into(),
+                        border_radius: BorderRadius::all(Val::Percent(50.)),
+                        ..default()
+                    },
+                    UiStencilMask,
+                ))
+                .with_children(|parent| {
+                    // The image is larger than its mask, only the circle inside of it is drawn
+                    parent.spawn((
+                        ImageBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                width: Val::Px(400.),
+                                height: Val::Px(400.),
+                                ..default()
+                            },
+                            image: UiImage::new(asset_server.load("branding/icon.png")),
+                            ..default()
+                        },
+                        Pan,
+                    ));
+
+                    // A nested mask, its label is cropped to both masks
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    bottom: Val::Px(20.),
+                                    width: Val::Px(240.),
+                                    height: Val::Px(60.),
+                                    align_items: AlignItems::Center,
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                background_color: Color::BLACK.with_alpha(0.6).into(),
+                                border_radius: BorderRadius::all(Val::Px(20.)),
+                                ..default()
+                            },
+                            UiStencilMask,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Masked",
+                                TextStyle {
+                                    font_size: 60.,
+                                    color: GOLD.into(),
+                                    ..default()
+                                },
+                            ));
+                        });
+                });
+        });
+}
+
+fn pan(time: Res<Time>, mut query: Query<&mut Style, With<Pan>>) {
+    let t = time.elapsed_seconds();
+    for mut style in &mut query {
+        style.left = Val::Px(-50. + 50. * t.cos());
+        style.top = Val::Px(-50. + 50. * t.sin());
+    }
+}
@@ -0,0 +1,89 @@
+//! Animates UI icons through the frames of a sprite sheet with a [`TextureAtlas`].
+//!
+//! Each icon is an image node whose atlas index advances on a timer, at a different speed.
+
+use bevy::prelude::*;
+
+fn main() {
+    App::new()
+        // Keeps the pixel art sharp when it's scaled up
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, animate)
+        .run();
+}
+
+/// The frames of the run cycle of the sprite sheet, and the timer advancing them.
+#[derive(Component)]
+struct FrameAnimation {
+    first: usize,
+    last: usize,
+    timer: Timer,
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let texture = asset_server.load("textures/rpg/chars/gabe/gabe-idle-run.png");
+    let layout = layouts.add(TextureAtlasLayout::from_grid(
+        UVec2::splat(24),
+        7,
+        1,
+        None,
+        None,
+    ));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(40.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (size, seconds_per_frame) in [(48., 0.2), (96., 0.1), (192., 0.05)] {
+                parent.spawn((
+                    ImageBundle {
+                        style: Style {
+                            width: Val::Px(size),
+                            height: Val::Px(size),
+                            ..default()
+                        },
+                        image: UiImage::new(texture.clone()),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: layout.clone(),
+                        index: 1,
+                    },
+                    FrameAnimation {
+                        first: 1,
+                        last: 6,
+                        timer: Timer::from_seconds(seconds_per_frame, TimerMode::Repeating),
+                    },
+                ));
+            }
+        });
+}
+
+fn animate(time: Res<Time>, mut query: Query<(&mut FrameAnimation, &mut TextureAtlas)>) {
+    for (mut animation, mut atlas) in &mut query {
+        animation.timer.tick(time.delta());
+        if animation.timer.just_finished() {
+            atlas.index = if atlas.index == animation.last {
+                animation.first
+            } else {
+                atlas.index + 1
+            };
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Demonstrates masking the UI of a camera to a rounded screen shape with [`UiViewportMask`].
+//!
+//! Only the UI is masked, the sprites rendered by the camera below the UI are not.
+//! Press the up and down arrows to change the corner radius, and E to switch to an ellipse.
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update_mask)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), UiViewportMask::rounded(60.)));
+
+    // Sprites show through wherever the mask hides the UI
+    for (i, color) in [CRIMSON, GOLD, SEA_GREEN, ROYAL_BLUE]
+        .into_iter()
+        .enumerate()
+    {
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: color.into(),
+                custom_size: Some(Vec2::splat(300.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(-450. + 300. * i as f32, 0., 0.),
+            ..default()
+        });
+    }
+
+    // A semi-transparent panel covering the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                border: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            background_color: Color::srgba(0.1, 0.1, 0.2, 0.8).into(),
+            border_color: WHITE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Up / Down: corner radius\nE: toggle ellipse",
+                TextStyle {
+                    font_size: 40.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn update_mask(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut mask_query: Query<&mut UiViewportMask>,
+) {
+    for mut mask in &mut mask_query {
+        if keyboard_input.just_pressed(KeyCode::KeyE) {
+            mask.shape = match mask.shape {
+                UiViewportMaskShape::Ellipse => UiViewportMaskShape::RoundedRect { radius: 60. },
+                UiViewportMaskShape::RoundedRect { .. } => UiViewportMaskShape::Ellipse,
+            };
+        }
+
+        if let UiViewportMaskShape::RoundedRect { radius } = &mut mask.shape {
+            let speed = 200. * time.delta_seconds();
+            if keyboard_input.pressed(KeyCode::ArrowUp) {
+                *radius += speed;
+            }
+            if keyboard_input.pressed(KeyCode::ArrowDown) {
+                *radius = (*radius - speed).max(0.);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! A test to confirm that the UI pass records its elapsed time as render diagnostics.
+//! This is run in CI to ensure that this doesn't regress again.
+//!
+//! The GPU time is only checked on backends supporting timestamp queries inside encoders.
+use bevy::{
+    diagnostic::{DiagnosticPath, DiagnosticsStore},
+    prelude::*,
+    render::{
+        diagnostic::RenderDiagnosticsPlugin, render_resource::WgpuFeatures, renderer::RenderDevice,
+    },
+};
+
+const ELAPSED_CPU: DiagnosticPath = DiagnosticPath::const_new("render/ui_pass/elapsed_cpu");
+const ELAPSED_GPU: DiagnosticPath = DiagnosticPath::const_new("render/ui_pass/elapsed_gpu");
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, RenderDiagnosticsPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, check_ui_pass_diagnostics)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+    commands.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(100.),
+            height: Val::Px(100.),
+            ..default()
+        },
+        background_color: Color::srgb(0.25, 0.25, 0.75).into(),
+        ..default()
+    });
+}
+
+fn check_ui_pass_diagnostics(
+    diagnostics: Res<DiagnosticsStore>,
+    render_device: Res<RenderDevice>,
+    mut app_exit: EventWriter<AppExit>,
+    mut frames: Local<u32>,
+) {
+    // The GPU timings are read back a few frames after they are recorded
+    *frames += 1;
+    if *frames < 60 {
+        return;
+    }
+
+    let elapsed_cpu = diagnostics.get(&ELAPSED_CPU).and_then(|d| d.value());
+    assert!(
+        elapsed_cpu.is_some_and(|elapsed| elapsed >= 0.),
+        "`{ELAPSED_CPU}` wasn't recorded: {elapsed_cpu:?}"
+    );
+
+    let timestamps = WgpuFeatures::TIMESTAMP_QUERY | WgpuFeatures::TIMESTAMP_QUERY_INSIDE_ENCODERS;
+    if render_device.features().contains(timestamps) {
+        let elapsed_gpu = diagnostics.get(&ELAPSED_GPU).and_then(|d| d.value());
+        assert!(
+            elapsed_gpu.is_some_and(|elapsed| elapsed >= 0.),
+            "`{ELAPSED_GPU}` wasn't recorded: {elapsed_gpu:?}"
+        );
+    }
+
+    app_exit.send(AppExit::Success);
+}